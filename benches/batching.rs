@@ -0,0 +1,26 @@
+//! Benchmarks of computing which draws of a frame can be merged, which the OpenGL renderer
+//! does every frame when draw batching is enabled.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use inox2d::render::batching::merge_draws;
+
+/// Draw keys of 1024 parts, in runs of 16 sharing a texture,
+/// with every 50th part masked so that it can't be merged.
+fn draw_keys() -> Vec<Option<u32>> {
+    (0..1024)
+        .map(|i| (i % 50 != 49).then_some(i / 16 % 4))
+        .collect()
+}
+
+fn bench_merge_draws(c: &mut Criterion) {
+    let keys = draw_keys();
+    println!(
+        "merge_draws: {} parts in {} draw calls",
+        keys.len(),
+        merge_draws(&keys).len()
+    );
+    c.bench_function("merge_draws", |b| b.iter(|| merge_draws(black_box(&keys))));
+}
+
+criterion_group!(benches, bench_merge_draws);
+criterion_main!(benches);
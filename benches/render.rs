@@ -0,0 +1,208 @@
+//! Benchmarks of the OpenGL renderer, rendering a dense generated puppet with headless OpenGL.
+//!
+//! Besides the timings, each benchmark prints what a frame of each variant drew, as counted by
+//! `OpenglRenderer::render_with_stats`. These counts don't depend on the machine.
+//! The benchmarks are skipped when no headless GL context can be created. Mesa's software
+//! renderer is enough to run them, but its timings include rasterizing on the CPU.
+
+use std::io::Cursor;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use glam::{uvec2, UVec2};
+use glow::HasContext;
+use image::{ImageFormat, ImageOutputFormat, Rgba, RgbaImage};
+use inox2d::model::{Model, ModelTexture};
+use inox2d::render::opengl::headless::{HeadlessError, HeadlessRenderer};
+use inox2d::render::opengl::RendererConfig;
+use inox2d::render::Renderer;
+
+const SIZE: UVec2 = uvec2(256, 256);
+
+/// Parts per side of the grid of parts making up the puppet.
+const GRID: u32 = 32;
+
+/// Textures of the puppet, each used by runs of consecutive parts.
+const TEXTURES: u32 = 4;
+
+/// Generates a puppet made of a grid of square parts, drawn in rows,
+/// where runs of `texture_run` consecutive parts share a texture.
+///
+/// Parts are placed by their vertices rather than their transforms, so that they share
+/// all their draw state besides their texture.
+fn dense_model(texture_run: u32) -> Model {
+    let parts = (0..GRID * GRID)
+        .map(|i| {
+            let (x, y) = ((i % GRID) as f32 * 25.0, (i / GRID) as f32 * 25.0);
+            let (x0, y0, x1, y1) = (x - 10.0, y - 10.0, x + 10.0, y + 10.0);
+            format!(
+                r#"{{"uuid": {uuid}, "name": "Part{i}", "type": "Part", "enabled": true, "zsort": 0,
+                "lockToRoot": false, "transform": {{"trans": [0, 0, 0], "rot": [0, 0, 0], "scale": [1, 1]}},
+                "blend_mode": "Normal", "tint": [1, 1, 1], "screenTint": [0, 0, 0], "mask_threshold": 0.5,
+                "opacity": 1, "masks": [], "textures": [{texture}, 4294967295, 4294967295],
+                "mesh": {{"verts": [{x0}, {y0}, {x1}, {y0}, {x1}, {y1}, {x0}, {y1}], "uvs": [0, 0, 1, 0, 1, 1, 0, 1],
+                "indices": [0, 1, 2, 0, 2, 3], "origin": [0, 0]}}}}"#,
+                uuid = i + 2,
+                texture = i / texture_run % TEXTURES,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    let json = format!(
+        r#"{{"meta": {{"name": "Dense", "version": "1.0-alpha", "rigger": null, "artist": null,
+        "copyright": null, "licenseURL": null, "contact": null, "reference": null, "preservePixels": false}},
+        "physics": {{"pixelsPerMeter": 1000, "gravity": 9.8}},
+        "nodes": {{"uuid": 1, "name": "Root", "type": "Node", "enabled": true, "zsort": 0, "lockToRoot": false,
+        "transform": {{"trans": [0, 0, 0], "rot": [0, 0, 0], "scale": [1, 1]}}, "children": [{parts}]}},
+        "param": []}}"#
+    );
+
+    let mut model = Model::from_bytes(json.as_bytes()).unwrap();
+    model.textures = (0..TEXTURES).map(|i| texture(i, 64)).collect();
+    model
+}
+
+/// Encodes a square gradient texture as a PNG, different for each `seed`.
+fn texture(seed: u32, size: u32) -> ModelTexture {
+    let image = RgbaImage::from_fn(size, size, |x, y| {
+        Rgba([
+            (x * 255 / size) as u8,
+            (y * 255 / size) as u8,
+            (seed * 60) as u8,
+            255,
+        ])
+    });
+    let mut data = Cursor::new(Vec::new());
+    image.write_to(&mut data, ImageOutputFormat::Png).unwrap();
+    ModelTexture {
+        format: ImageFormat::Png,
+        data: data.into_inner(),
+    }
+}
+
+/// Creates a renderer framing the model's puppet in its rest pose,
+/// or `None` if there is no way to render headless here.
+fn renderer(model: &mut Model, config: RendererConfig) -> Option<HeadlessRenderer> {
+    let mut renderer = match HeadlessRenderer::from_model(model, SIZE, config) {
+        Ok(renderer) => renderer,
+        Err(e @ (HeadlessError::Context(_) | HeadlessError::NoDevice)) => {
+            eprintln!("Skipping benchmark: {e}");
+            return None;
+        }
+        Err(e) => panic!("{e}"),
+    };
+    renderer.renderer.frame_puppet(&model.puppet, 0.1);
+
+    model.puppet.begin_set_params();
+    model.puppet.end_set_params();
+    Some(renderer)
+}
+
+/// Prints what a frame drew, rendering it into the renderer's framebuffer.
+fn print_stats(name: &str, renderer: &mut HeadlessRenderer, model: &Model) {
+    // Binds the framebuffer, which `render_with_stats` draws into
+    renderer.render(&model.puppet);
+    let stats = renderer.renderer.render_with_stats(&model.puppet);
+    println!("{name}: {stats:?}");
+}
+
+/// Renders the puppet zoomed in on its center, where about a tenth of the parts are visible.
+fn bench_culling(c: &mut Criterion) {
+    let mut model = dense_model(16);
+    let Some(mut renderer) = renderer(&mut model, RendererConfig::default()) else {
+        return;
+    };
+    renderer.renderer.camera_mut().scale *= 4.0;
+
+    let mut group = c.benchmark_group("culling");
+    for (name, culling) in [("off", false), ("on", true)] {
+        renderer.renderer.set_culling(culling);
+        print_stats(&format!("culling/{name}"), &mut renderer, &model);
+        group.bench_function(name, |b| b.iter(|| renderer.render(&model.puppet)));
+    }
+    group.finish();
+}
+
+/// Renders the puppet with each part using another texture than the previous one,
+/// with separate textures and with a texture array.
+fn bench_texture_array(c: &mut Criterion) {
+    let mut group = c.benchmark_group("texture_array");
+    for (name, texture_array) in [("off", false), ("on", true)] {
+        let mut model = dense_model(1);
+        let config = RendererConfig {
+            texture_array,
+            ..RendererConfig::default()
+        };
+        let Some(mut renderer) = renderer(&mut model, config) else {
+            return;
+        };
+
+        print_stats(&format!("texture_array/{name}"), &mut renderer, &model);
+        group.bench_function(name, |b| b.iter(|| renderer.render(&model.puppet)));
+    }
+    group.finish();
+}
+
+/// Renders the puppet with and without merging the draw calls of parts sharing their state.
+fn bench_draw_batching(c: &mut Criterion) {
+    let mut model = dense_model(16);
+    let Some(mut renderer) = renderer(&mut model, RendererConfig::default()) else {
+        return;
+    };
+
+    let mut group = c.benchmark_group("draw_batching");
+    for (name, batching) in [("off", false), ("on", true)] {
+        renderer.renderer.set_draw_batching(batching);
+        print_stats(&format!("draw_batching/{name}"), &mut renderer, &model);
+        group.bench_function(name, |b| b.iter(|| renderer.render(&model.puppet)));
+    }
+    group.finish();
+}
+
+/// Renders the whole puppet into a tiny image, so that the time goes to the work done for each
+/// part on the CPU, such as updating the GL state cache, rather than to rasterizing.
+fn bench_frame(c: &mut Criterion) {
+    let mut model = dense_model(16);
+    let Some(mut renderer) = renderer(&mut model, RendererConfig::default()) else {
+        return;
+    };
+    renderer.resize(uvec2(16, 16)).unwrap();
+    // The camera still frames the puppet for the larger size
+    renderer.renderer.set_culling(false);
+
+    print_stats("frame", &mut renderer, &model);
+    c.bench_function("frame", |b| b.iter(|| renderer.render(&model.puppet)));
+}
+
+/// Decodes and uploads the textures of a puppet with 48 textures of 512x512 pixels.
+fn bench_upload(c: &mut Criterion) {
+    let mut model = dense_model(16);
+    model.textures = (0..48).map(|i| texture(i, 512)).collect();
+    let Some(mut renderer) = renderer(&mut model, RendererConfig::default()) else {
+        return;
+    };
+
+    let mut group = c.benchmark_group("upload");
+    group.sample_size(10);
+    group.bench_function("model_textures", |b| {
+        b.iter(|| {
+            renderer.renderer.unload_textures();
+            renderer
+                .renderer
+                .upload_model_textures(&model.textures)
+                .unwrap();
+            // Waits for the uploads to complete
+            unsafe { renderer.renderer.gl().finish() };
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_culling,
+    bench_texture_array,
+    bench_draw_batching,
+    bench_frame,
+    bench_upload
+);
+criterion_main!(benches);
@@ -0,0 +1,156 @@
+//! Window showing a puppet, with the camera panned by dragging and zoomed with the mouse wheel.
+
+use std::error::Error;
+use std::fs;
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::time::Instant;
+
+use glam::{uvec2, vec2, Vec2};
+use glutin::context::PossiblyCurrentContext;
+use glutin::display::Display;
+use glutin::surface::{GlSurface, Surface, WindowSurface};
+use inox2d::puppet::Puppet;
+use inox2d::render::opengl::OpenglRenderer;
+use tracing::info;
+use winit::event::{ElementState, Event, MouseButton, MouseScrollDelta, WindowEvent};
+use winit::event_loop::EventLoop;
+use winit::window::Window;
+
+use super::opengl::{launch_opengl_window, App};
+
+pub struct PuppetApp {
+    pub renderer: OpenglRenderer,
+    pub puppet: Puppet,
+    gl_ctx: PossiblyCurrentContext,
+    gl_surface: Surface<WindowSurface>,
+    gl_display: Display,
+    window: Window,
+    events: EventLoop<()>,
+}
+
+impl PuppetApp {
+    /// Opens a window and loads the puppet of the `.inp` file at `inp_path` in it,
+    /// framed by the camera.
+    pub fn load(inp_path: &Path) -> Result<Self, Box<dyn Error>> {
+        info!("Setting up windowing and OpenGL");
+        let App {
+            gl,
+            gl_ctx,
+            gl_surface,
+            gl_display,
+            events,
+            window,
+        } = launch_opengl_window()?;
+
+        info!("Loading puppet and initializing Inox2D renderer");
+        let window_size = window.inner_size();
+        let viewport = uvec2(window_size.width, window_size.height);
+        let data = fs::read(inp_path)?;
+        let (mut renderer, puppet) = OpenglRenderer::from_inp(gl, viewport, &data)?;
+        info!(
+            "Successfully loaded puppet: {}",
+            (puppet.meta.name.as_deref()).unwrap_or("<no puppet name specified in file>")
+        );
+
+        if let Some(bounds) = puppet.nodes.model_bounds() {
+            renderer.camera.fit_bounds(bounds, viewport.as_vec2(), 0.1);
+        }
+
+        Ok(Self {
+            renderer,
+            puppet,
+            gl_ctx,
+            gl_surface,
+            gl_display,
+            window,
+            events,
+        })
+    }
+
+    /// Draws the puppet continuously until the window is closed.
+    ///
+    /// Before each frame, `update` is called with the puppet, between `begin_set_params`
+    /// and `end_set_params`, and the time elapsed since the start in seconds.
+    pub fn run(self, mut update: impl FnMut(&mut Puppet, f32) + 'static) -> ! {
+        let Self {
+            mut renderer,
+            mut puppet,
+            gl_ctx,
+            gl_surface,
+            gl_display,
+            window,
+            events,
+        } = self;
+
+        let start = Instant::now();
+        let mut cursor = Vec2::ZERO;
+        let mut dragging = false;
+
+        events.run(move |event, _, control_flow| {
+            // It needs to be present
+            let _gl_display = &gl_display;
+
+            control_flow.set_poll();
+
+            let size = window.inner_size();
+            let viewport = vec2(size.width as f32, size.height as f32);
+
+            match event {
+                Event::RedrawRequested(_) => {
+                    renderer.clear();
+
+                    puppet.begin_set_params();
+                    update(&mut puppet, start.elapsed().as_secs_f32());
+                    puppet.end_set_params();
+
+                    renderer.render(&puppet);
+                    gl_surface.swap_buffers(&gl_ctx).unwrap();
+                }
+                Event::WindowEvent { event, .. } => match event {
+                    WindowEvent::Resized(size) => {
+                        renderer.resize(size.width, size.height);
+                        if let (Some(width), Some(height)) =
+                            (NonZeroU32::new(size.width), NonZeroU32::new(size.height))
+                        {
+                            gl_surface.resize(&gl_ctx, width, height);
+                        }
+                    }
+                    WindowEvent::CloseRequested => control_flow.set_exit(),
+                    WindowEvent::MouseInput {
+                        state,
+                        button: MouseButton::Left,
+                        ..
+                    } => dragging = state == ElementState::Pressed,
+                    WindowEvent::CursorMoved { position, .. } => {
+                        let new_cursor = vec2(position.x as f32, position.y as f32);
+                        if dragging {
+                            // The point of the model under the cursor follows it
+                            let camera = &mut renderer.camera;
+                            let moved = camera.screen_to_world(new_cursor, viewport)
+                                - camera.screen_to_world(cursor, viewport);
+                            camera.position += camera.to_native(moved);
+                        }
+                        cursor = new_cursor;
+                    }
+                    WindowEvent::MouseWheel { delta, .. } => {
+                        let lines = match delta {
+                            MouseScrollDelta::LineDelta(_, y) => y,
+                            MouseScrollDelta::PixelDelta(position) => position.y as f32 / 40.0,
+                        };
+
+                        // Zoom toward the cursor, keeping the point of the model under it in place
+                        let camera = &mut renderer.camera;
+                        let before = camera.screen_to_world(cursor, viewport);
+                        camera.scale *= 1.1_f32.powf(lines);
+                        let after = camera.screen_to_world(cursor, viewport);
+                        camera.position += camera.to_native(after - before);
+                    }
+                    _ => (),
+                },
+                Event::MainEventsCleared => window.request_redraw(),
+                _ => (),
+            }
+        })
+    }
+}
@@ -0,0 +1,127 @@
+use std::ffi::CString;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::{error::Error, fs, num::NonZeroU32};
+
+use inox2d::render::opengl::egui::PuppetView;
+use inox2d::render::opengl::OpenglRenderer;
+
+use clap::Parser;
+use glam::{uvec2, vec4, Vec2};
+use glow::HasContext;
+use glutin::prelude::GlDisplay;
+use glutin::surface::GlSurface;
+use tracing::info;
+use tracing_subscriber::{filter::LevelFilter, fmt, prelude::*};
+use winit::event::{Event, WindowEvent};
+
+use crate::opengl::{launch_opengl_window, App};
+
+#[path = "./common/opengl.rs"]
+mod opengl;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    #[arg(help = "Path to the .inp file. .inx files don't work!")]
+    inp_path: PathBuf,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+
+    tracing_subscriber::registry()
+        .with(fmt::layer())
+        .with(LevelFilter::INFO)
+        .init();
+
+    info!("Setting up windowing and OpenGL");
+    let App {
+        gl,
+        gl_ctx,
+        gl_surface,
+        gl_display,
+        events,
+        window,
+    } = launch_opengl_window()?;
+
+    // egui paints with its own handle on the same GL context
+    let egui_gl = Arc::new(unsafe {
+        glow::Context::from_loader_function(|symbol| {
+            gl_display.get_proc_address(&CString::new(symbol).unwrap()) as *const _
+        })
+    });
+    let mut egui_glow = egui_glow::EguiGlow::new(&events, egui_gl.clone(), None);
+    egui_glow
+        .egui_winit
+        .set_pixels_per_point(window.scale_factor() as f32);
+
+    info!("Loading puppet and initializing Inox2D renderer");
+    let data = fs::read(cli.inp_path)?;
+    let (mut renderer, puppet) = OpenglRenderer::from_inp(gl, uvec2(1, 1), &data)?;
+    renderer.camera.scale = Vec2::splat(0.15);
+    renderer.set_clear_color(vec4(0.2, 0.2, 0.2, 1.0));
+    let view = PuppetView::new(renderer, puppet);
+
+    let mut yaw = 0.0;
+    let mut pitch = 0.0;
+
+    events.run(move |event, _, control_flow| {
+        // They need to be present
+        let _gl_display = &gl_display;
+
+        control_flow.set_wait();
+
+        match event {
+            Event::RedrawRequested(_) => {
+                egui_glow.run(&window, |ctx| {
+                    egui::SidePanel::left("params").show(ctx, |ui| {
+                        ui.heading("Head");
+                        ui.add(egui::Slider::new(&mut yaw, -1.0..=1.0).text("Yaw"));
+                        ui.add(egui::Slider::new(&mut pitch, -1.0..=1.0).text("Pitch"));
+                    });
+                    egui::CentralPanel::default().show(ctx, |ui| {
+                        let rect = ui.available_rect_before_wrap();
+                        ui.painter().add(view.paint_callback(rect));
+                    });
+                });
+
+                {
+                    let mut state = view.lock();
+                    state.puppet.begin_set_params();
+                    state
+                        .puppet
+                        .set_param("Head:: Yaw-Pitch", Vec2::new(yaw, pitch));
+                    state.puppet.end_set_params();
+                }
+
+                unsafe {
+                    egui_gl.clear_color(0.0, 0.0, 0.0, 1.0);
+                    egui_gl.clear(glow::COLOR_BUFFER_BIT);
+                }
+                egui_glow.paint(&window);
+
+                gl_surface.swap_buffers(&gl_ctx).unwrap();
+            }
+            Event::WindowEvent { ref event, .. } => {
+                match event {
+                    WindowEvent::Resized(physical_size) => {
+                        gl_surface.resize(
+                            &gl_ctx,
+                            NonZeroU32::new(physical_size.width.max(1)).unwrap(),
+                            NonZeroU32::new(physical_size.height.max(1)).unwrap(),
+                        );
+                    }
+                    WindowEvent::CloseRequested => control_flow.set_exit(),
+                    _ => (),
+                }
+
+                if egui_glow.on_event(event).repaint {
+                    window.request_redraw();
+                }
+            }
+            Event::LoopDestroyed => egui_glow.destroy(),
+            _ => (),
+        }
+    })
+}
@@ -1,7 +1,7 @@
 use std::path::PathBuf;
 use std::{error::Error, fs, num::NonZeroU32};
 
-use inox2d::{formats::inp::parse_inp, render::opengl::OpenglRenderer};
+use inox2d::render::opengl::OpenglRenderer;
 
 use clap::Parser;
 use glam::{uvec2, Vec2};
@@ -34,16 +34,6 @@ fn main() -> Result<(), Box<dyn Error>> {
         .with(LevelFilter::INFO)
         .init();
 
-    info!("Parsing puppet");
-
-    let data = fs::read(cli.inp_path).unwrap();
-    let model = parse_inp(data.as_slice()).unwrap();
-    let puppet = model.puppet;
-    info!(
-        "Successfully parsed puppet: {}",
-        (puppet.meta.name.as_deref()).unwrap_or("<no puppet name specified in file>")
-    );
-
     info!("Setting up windowing and OpenGL");
     let App {
         gl,
@@ -54,16 +44,18 @@ fn main() -> Result<(), Box<dyn Error>> {
         window,
     } = launch_opengl_window()?;
 
-    info!("Initializing Inox2D renderer");
+    info!("Loading puppet and initializing Inox2D renderer");
     let window_size = window.inner_size();
     let viewport = uvec2(window_size.width, window_size.height);
-    let mut renderer = OpenglRenderer::new(gl, viewport, &puppet)?;
-    renderer.upload_model_textures(&model.textures)?;
+    let data = fs::read(cli.inp_path)?;
+    let (mut renderer, mut puppet) = OpenglRenderer::from_inp(gl, viewport, &data)?;
     renderer.camera.scale = Vec2::splat(0.15);
-    info!("Inox2D renderer initialized");
+    info!(
+        "Successfully loaded puppet: {}",
+        (puppet.meta.name.as_deref()).unwrap_or("<no puppet name specified in file>")
+    );
 
     let mut scene_ctrl = ExampleSceneController::new(&renderer.camera, 0.5);
-    let mut puppet = puppet;
 
     // Event loop
     events.run(move |event, _, control_flow| {
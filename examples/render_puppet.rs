@@ -0,0 +1,36 @@
+use std::error::Error;
+use std::path::PathBuf;
+
+use clap::Parser;
+use glam::Vec2;
+use tracing_subscriber::{filter::LevelFilter, fmt, prelude::*};
+
+use crate::puppet_app::PuppetApp;
+
+#[path = "./common/opengl.rs"]
+mod opengl;
+
+#[path = "./common/puppet_app.rs"]
+mod puppet_app;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    #[arg(help = "Path to the .inp file. .inx files don't work!")]
+    inp_path: PathBuf,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+
+    tracing_subscriber::registry()
+        .with(fmt::layer())
+        .with(LevelFilter::INFO)
+        .init();
+
+    // Drag to pan, scroll to zoom
+    let app = PuppetApp::load(&cli.inp_path)?;
+    app.run(|puppet, t| {
+        puppet.set_param("Head:: Yaw-Pitch", Vec2::new(t.cos(), t.sin()));
+    })
+}
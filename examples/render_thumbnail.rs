@@ -0,0 +1,38 @@
+use std::path::PathBuf;
+use std::{error::Error, fs};
+
+use inox2d::render::opengl::headless::render_inp;
+
+use clap::Parser;
+use glam::uvec2;
+use tracing::info;
+use tracing_subscriber::{filter::LevelFilter, fmt, prelude::*};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    #[arg(help = "Path to the .inp file. .inx files don't work!")]
+    inp_path: PathBuf,
+    #[arg(help = "Path of the PNG image to write")]
+    png_path: PathBuf,
+    #[arg(long, default_value_t = 512, help = "Width of the image")]
+    width: u32,
+    #[arg(long, default_value_t = 512, help = "Height of the image")]
+    height: u32,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+
+    tracing_subscriber::registry()
+        .with(fmt::layer())
+        .with(LevelFilter::INFO)
+        .init();
+
+    let data = fs::read(&cli.inp_path)?;
+    let image = render_inp(&data, uvec2(cli.width, cli.height))?;
+    image.save_with_format(&cli.png_path, image::ImageFormat::Png)?;
+    info!("Wrote {}", cli.png_path.display());
+
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use std::io::{self, Read};
+use std::io::{self, Read, Write};
 use std::str::Utf8Error;
 use std::string::FromUtf8Error;
 
@@ -8,7 +8,9 @@ use crate::model::{Model, ModelTexture, VendorData};
 use crate::{read_be_u32, read_n, read_u8, read_vec};
 
 use super::json::JsonError;
-use super::serialize::{deserialize_puppet, InoxParseError};
+use super::serialize::{
+    deserialize_puppet, serialize_puppet, serialize_puppet_ext, InoxParseError,
+};
 
 #[derive(Debug, thiserror::Error)]
 #[error("Could not parse INP file\n  - {0}")]
@@ -29,6 +31,14 @@ pub enum ParseInpError {
     Json(#[from] JsonError),
 }
 
+#[derive(Debug, thiserror::Error)]
+#[error("Could not write INP file: {0}")]
+pub enum WriteInpError {
+    #[error("textures in the {0:?} format can't be stored in INP files")]
+    UnsupportedTexFormat(ImageFormat),
+    Io(#[from] io::Error),
+}
+
 /// Trans rights!
 const MAGIC: &[u8] = b"TRNSRTS\0";
 /// Texture section header
@@ -36,6 +46,11 @@ const TEX_SECT: &[u8] = b"TEX_SECT";
 /// Optional EXTended Vendor Data section for app provided settings for the puppet
 const EXT_SECT: &[u8] = b"EXT_SECT";
 
+/// Checks whether `data` starts with the INP magic bytes.
+pub fn is_inp(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
 pub fn parse_inp<R: Read>(mut data: R) -> Result<Model, ParseInpError> {
     // check magic bytes
     let magic = read_n::<_, 8>(&mut data)?;
@@ -94,9 +109,164 @@ pub fn parse_inp<R: Read>(mut data: R) -> Result<Model, ParseInpError> {
         _ => Vec::new(),
     };
 
-    Ok(Model {
+    let model = Model {
         puppet,
         textures,
         vendors,
-    })
+    };
+    model.warn_validation_issues();
+    Ok(model)
+}
+
+/// Writes a model in the INP format, as read by `parse_inp`.
+///
+/// Nodes of unknown types are written as plain nodes, see `serialize_puppet`.
+pub fn write_inp<W: Write>(model: &Model, data: W) -> Result<(), WriteInpError> {
+    write_inp_payload(model, &serialize_puppet(&model.puppet), data)
+}
+
+/// Writes a model with custom node data in the INP format, see `serialize_puppet_ext`.
+pub fn write_inp_ext<T, W: Write>(
+    model: &Model<T>,
+    data: W,
+    serialize_node_custom: &impl Fn(&T, &mut json::JsonValue) -> String,
+) -> Result<(), WriteInpError> {
+    let payload = serialize_puppet_ext(&model.puppet, serialize_node_custom);
+    write_inp_payload(model, &payload, data)
+}
+
+fn write_inp_payload<T, W: Write>(
+    model: &Model<T>,
+    payload: &json::JsonValue,
+    mut data: W,
+) -> Result<(), WriteInpError> {
+    data.write_all(MAGIC)?;
+    write_chunk(&mut data, json::stringify(payload.clone()).as_bytes())?;
+
+    data.write_all(TEX_SECT)?;
+    data.write_all(&(model.textures.len() as u32).to_be_bytes())?;
+    for texture in &model.textures {
+        let tex_encoding: u8 = match texture.format {
+            ImageFormat::Png => 0,
+            ImageFormat::Tga => 1,
+            format => return Err(WriteInpError::UnsupportedTexFormat(format)),
+        };
+        data.write_all(&(texture.data.len() as u32).to_be_bytes())?;
+        data.write_all(&[tex_encoding])?;
+        data.write_all(&texture.data)?;
+    }
+
+    if !model.vendors.is_empty() {
+        data.write_all(EXT_SECT)?;
+        data.write_all(&(model.vendors.len() as u32).to_be_bytes())?;
+        for vendor in &model.vendors {
+            write_chunk(&mut data, vendor.name.as_bytes())?;
+            write_chunk(
+                &mut data,
+                json::stringify(vendor.payload.clone()).as_bytes(),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes `bytes` prefixed by their length.
+fn write_chunk<W: Write>(data: &mut W, bytes: &[u8]) -> io::Result<()> {
+    data.write_all(&(bytes.len() as u32).to_be_bytes())?;
+    data.write_all(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{Model, ModelTexture, VendorData};
+    use crate::nodes::node::InoxNodeUuid;
+    use crate::testing::{part_json, puppet_json};
+
+    use super::*;
+
+    /// Puppet with extra fields, a node of unknown type and a parameter with bindings.
+    fn puppet() -> String {
+        let mask = r#"{ "source": 3, "mode": "DodgeMask" }"#;
+        let mut part = json::parse(&part_json(2, "0, 0, 1, 0, 0, 1", 0.75, mask)).unwrap();
+        part["zsort"] = 0.5.into();
+        part["transform"] =
+            json::parse(r#"{ "trans": [1.5, -2, 0], "rot": [0, 0, 0.25], "scale": [1, 1] }"#)
+                .unwrap();
+        part["vendor"] = json::parse(r#"{ "locked": true, "color": [1, 0, 0] }"#).unwrap();
+        part["blend_mode"] = "Multiply".into();
+        part["tint"] = json::array![1, 0.5, 1];
+        let children = format!(
+            r#"{}, {{
+                "uuid": 3, "name": "Unknown", "type": "Camera", "enabled": false, "zsort": 0,
+                "lockToRoot": true, "dynamic": true,
+                "transform": {{ "trans": [0, 0, 0], "rot": [0, 0, 0], "scale": [2, 2] }}
+            }}"#,
+            part.dump()
+        );
+        let param = r#"[{
+            "uuid": 4, "name": "Angle", "is_vec2": false,
+            "min": [-1, 0], "max": [1, 0], "defaults": [0, 0],
+            "axis_points": [[0, 0.5, 1], [0]],
+            "bindings": [{
+                "node": 2, "param_name": "transform.t.x", "interpolate_mode": "Linear",
+                "values": [[-1], [0], [1]], "isSet": [[true], [false], [true]]
+            }, {
+                "node": 2, "param_name": "deform", "interpolate_mode": "Linear",
+                "values": [[[[0, 0], [1, 0], [0, 1]]], [[[0, 0], [0, 0], [0, 0]]],
+                    [[[0, 0], [-1, 0], [0, -1]]]],
+                "isSet": [[true], [true], [true]]
+            }]
+        }]"#;
+
+        let mut puppet = json::parse(&puppet_json(&children)).unwrap();
+        puppet["meta"]["name"] = "test".into();
+        puppet["meta"]["vendor"] = json::parse(r#"{ "tool": "editor" }"#).unwrap();
+        puppet["param"] = json::parse(param).unwrap();
+        puppet.dump()
+    }
+
+    #[test]
+    fn test_write_inp_round_trip() {
+        let mut model = Model::from_bytes(puppet().as_bytes()).unwrap();
+        model.textures.push(ModelTexture {
+            format: ImageFormat::Png,
+            data: vec![1, 2, 3],
+        });
+        model.vendors.push(VendorData {
+            name: "vendor".to_owned(),
+            payload: json::parse(r#"{ "setting": [1, "two"] }"#).unwrap(),
+        });
+
+        let mut written = Vec::new();
+        model.write_inp(&mut written).unwrap();
+        let reloaded = Model::from_bytes(&written).unwrap();
+
+        let nodes = &reloaded.puppet.nodes;
+        assert_eq!(nodes.all_node_ids().len(), 3);
+        assert!(nodes.get_node(InoxNodeUuid(2)).unwrap().is_part());
+        // The node of unknown type is kept, as a plain node
+        let unknown = nodes.get_node(InoxNodeUuid(3)).unwrap();
+        assert!(unknown.data.is_node() && unknown.lock_to_root && !unknown.enabled);
+        assert_eq!(reloaded.textures[0].data, vec![1, 2, 3]);
+        assert_eq!(reloaded.vendors[0].payload, model.vendors[0].payload);
+
+        // Fields that aren't understood are kept
+        let extra = |uuid| &nodes.get_node(InoxNodeUuid(uuid)).unwrap().extra;
+        assert_eq!(
+            extra(2)["vendor"],
+            json::parse(r#"{ "locked": true, "color": [1, 0, 0] }"#).unwrap()
+        );
+        assert_eq!(extra(3)["dynamic"], true);
+        assert_eq!(reloaded.puppet.meta.extra["vendor"]["tool"], "editor");
+
+        let param = &reloaded.puppet.parameters["Angle"];
+        assert_eq!(param.axis_points.x, vec![0.0, 0.5, 1.0]);
+        assert_eq!(param.bindings.len(), 2);
+
+        // Everything that was loaded is written back the same way
+        let mut rewritten = Vec::new();
+        reloaded.write_inp(&mut rewritten).unwrap();
+        assert_eq!(rewritten, written);
+    }
 }
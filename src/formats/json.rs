@@ -66,7 +66,7 @@ impl<'a> JsonObject<'a> {
         }
     }
 
-    pub fn get_object(&self, key: &str) -> JsonResult<JsonObject> {
+    pub fn get_object(&self, key: &str) -> JsonResult<JsonObject<'_>> {
         match self.get(key)?.as_object() {
             Some(obj) => Ok(JsonObject(obj)),
             None => Err(JsonError::ValueIsNotObject(key.to_owned())),
@@ -1,21 +1,23 @@
 use std::collections::{BTreeMap, HashMap};
 
-use glam::{vec2, Vec2};
+use glam::{vec2, Vec2, Vec3};
 use indextree::Arena;
 use json::JsonValue;
+use tracing::warn;
 
 use crate::math::interp::{InterpolateMode, UnknownInterpolateModeError};
 use crate::math::matrix::{Matrix2d, Matrix2dFromSliceVecsError};
 use crate::math::transform::TransformOffset;
-use crate::mesh::{f32s_as_vec2s, Mesh};
+use crate::mesh::{f32s_as_vec2s, Mesh, MeshError};
 use crate::nodes::node::{InoxNode, InoxNodeUuid};
 use crate::nodes::node_data::{
-    BlendMode, Composite, Drawable, InoxData, Mask, MaskMode, Part, UnknownBlendModeError,
-    UnknownMaskModeError,
+    BlendMode, Composite, Drawable, InoxData, Mask, MaskMode, MeshGroup, Part,
+    UnknownBlendModeError, UnknownMaskModeError,
 };
 use crate::nodes::node_tree::InoxNodeTree;
-use crate::nodes::physics::SimplePhysics;
+use crate::nodes::physics::{ParamMapMode, PhysicsModel, SimplePhysics};
 use crate::params::{AxisPoints, Binding, BindingValues, Param};
+use crate::physics::PhysicsCtx;
 use crate::puppet::{
     Puppet, PuppetAllowedModification, PuppetAllowedRedistribution, PuppetAllowedUsers, PuppetMeta,
     PuppetPhysics, PuppetUsageRights, UnknownPuppetAllowedModificationError,
@@ -55,6 +57,8 @@ pub enum InoxParseError {
     OddNumberOfFloatsInList(usize),
     #[error("Expected 2 floats in list, got {0}")]
     Not2FloatsInList(usize),
+    #[error(transparent)]
+    InvalidMesh(#[from] MeshError),
 }
 
 impl InoxParseError {
@@ -74,8 +78,68 @@ pub fn deserialize_node(obj: &JsonObject) -> InoxParseResult<InoxNode> {
     deserialize_node_ext(obj, &default_deserialize_custom)
 }
 
-fn default_deserialize_custom<T>(node_type: &str, _obj: &JsonObject) -> InoxParseResult<T> {
-    Err(InoxParseError::UnknownNodeType(node_type.to_owned()))
+/// Keeps nodes of unknown types as passthrough `InoxData::Custom(())` nodes,
+/// so that their transforms and children are still used.
+fn default_deserialize_custom(node_type: &str, _obj: &JsonObject) -> InoxParseResult<()> {
+    warn!("Unknown node type {node_type:?}, treating it as a plain node");
+    Ok(())
+}
+
+/// Keys of node objects that are read into every `InoxNode`.
+const NODE_KEYS: &[&str] = &[
+    "uuid",
+    "name",
+    "type",
+    "enabled",
+    "zsort",
+    "transform",
+    "lockToRoot",
+    "children",
+];
+const DRAWABLE_KEYS: &[&str] = &[
+    "blend_mode",
+    "tint",
+    "screenTint",
+    "mask_threshold",
+    "masks",
+    "opacity",
+];
+const PART_KEYS: &[&str] = &["textures", "mesh"];
+const MESH_GROUP_KEYS: &[&str] = &["mesh", "dynamic_deformation", "translate_children"];
+const SIMPLE_PHYSICS_KEYS: &[&str] = &[
+    "param",
+    "model_type",
+    "map_mode",
+    "gravity",
+    "length",
+    "frequency",
+    "angle_damping",
+    "length_damping",
+    "output_scale",
+];
+const META_KEYS: &[&str] = &[
+    "name",
+    "version",
+    "rigger",
+    "artist",
+    "rights",
+    "copyright",
+    "licenseURL",
+    "contact",
+    "reference",
+    "thumbnailId",
+    "preservePixels",
+];
+
+/// Gets the fields of an object whose keys are in none of the `known` lists.
+fn extra_fields(obj: &JsonObject, known: &[&[&str]]) -> json::object::Object {
+    let mut extra = json::object::Object::new();
+    for (key, value) in obj.0.iter() {
+        if !known.iter().any(|keys| keys.contains(&key)) {
+            extra.insert(key, value.clone());
+        }
+    }
+    extra
 }
 
 pub fn deserialize_node_ext<T>(
@@ -83,6 +147,14 @@ pub fn deserialize_node_ext<T>(
     deserialize_node_custom: &impl Fn(&str, &JsonObject) -> InoxParseResult<T>,
 ) -> InoxParseResult<InoxNode<T>> {
     let node_type = obj.get_str("type")?;
+    let known_keys: &[&[&str]] = match node_type {
+        "Part" => &[NODE_KEYS, DRAWABLE_KEYS, PART_KEYS],
+        "Composite" => &[NODE_KEYS, DRAWABLE_KEYS],
+        "SimplePhysics" => &[NODE_KEYS, SIMPLE_PHYSICS_KEYS],
+        "MeshGroup" => &[NODE_KEYS, MESH_GROUP_KEYS],
+        _ => &[NODE_KEYS],
+    };
+
     Ok(InoxNode {
         uuid: InoxNodeUuid(obj.get_u32("uuid")?),
         name: obj.get_str("name")?.to_owned(),
@@ -97,6 +169,7 @@ pub fn deserialize_node_ext<T>(
             "data",
             deserialize_node_data(node_type, obj, deserialize_node_custom),
         )?,
+        extra: extra_fields(obj, known_keys),
     })
 }
 
@@ -110,6 +183,7 @@ fn deserialize_node_data<T>(
         "Part" => InoxData::Part(deserialize_part(obj)?),
         "Composite" => InoxData::Composite(deserialize_composite(obj)?),
         "SimplePhysics" => InoxData::SimplePhysics(deserialize_simple_physics(obj)?),
+        "MeshGroup" => InoxData::MeshGroup(deserialize_mesh_group(obj)?),
         node_type => InoxData::Custom((deserialize_custom)(node_type, obj)?),
     })
 }
@@ -119,7 +193,7 @@ fn deserialize_part(obj: &JsonObject) -> InoxParseResult<Part> {
         let textures = obj.get_list("textures")?;
 
         let tex_albedo = match textures
-            .get(0)
+            .first()
             .ok_or(InoxParseError::NoAlbedoTexture)?
             .as_number()
         {
@@ -134,25 +208,25 @@ fn deserialize_part(obj: &JsonObject) -> InoxParseResult<Part> {
         let tex_emissive = match textures.get(1).and_then(JsonValue::as_number) {
             Some(val) => val.try_into()
                 // Map u32::MAX to nothing
-                .map(|val| if val == u32::MAX as usize { 0 } else { val })
+                .map(|val| (val != u32::MAX as usize).then_some(val))
                 .map_err(|_| {
                     InoxParseError::JsonError(
                         JsonError::ParseIntError("1".to_owned()).nested("textures"),
                     )
                 })?,
-            None => 0,
+            None => None,
         };
 
         let tex_bumpmap = match textures.get(2).and_then(JsonValue::as_number) {
             Some(val) => val.try_into()
                 // Map u32::MAX to nothing
-                .map(|val| if val == u32::MAX as usize { 0 } else { val })
+                .map(|val| (val != u32::MAX as usize).then_some(val))
                 .map_err(|_| {
                     InoxParseError::JsonError(
                         JsonError::ParseIntError("2".to_owned()).nested("textures"),
                     )
                 })?,
-            None => 0,
+            None => None,
         };
 
         (tex_albedo, tex_emissive, tex_bumpmap)
@@ -172,11 +246,26 @@ fn deserialize_composite(obj: &JsonObject) -> InoxParseResult<Composite> {
     Ok(Composite { draw_state })
 }
 
+fn deserialize_mesh_group(obj: &JsonObject) -> InoxParseResult<MeshGroup> {
+    Ok(MeshGroup {
+        mesh: vals("mesh", deserialize_mesh(&obj.get_object("mesh")?))?,
+        dynamic_deformation: obj.get_bool("dynamic_deformation").unwrap_or_default(),
+        translate_children: obj.get_bool("translate_children").unwrap_or(true),
+    })
+}
+
 fn deserialize_simple_physics(obj: &JsonObject) -> InoxParseResult<SimplePhysics> {
     Ok(SimplePhysics {
         param: obj.get_u32("param")?,
-        model_type: obj.get_str("model_type")?.to_owned(),
-        map_mode: obj.get_str("map_mode")?.to_owned(),
+        // Newer versions of Inochi2D may add models and modes, which shouldn't prevent loading
+        model_type: PhysicsModel::try_from(obj.get_str("model_type")?).unwrap_or_else(|e| {
+            warn!("{e}, simulating a rigid pendulum instead");
+            PhysicsModel::Pendulum
+        }),
+        map_mode: ParamMapMode::try_from(obj.get_str("map_mode")?).unwrap_or_else(|e| {
+            warn!("{e}, mapping the angle and length instead");
+            ParamMapMode::AngleLength
+        }),
         gravity: obj.get_f32("gravity")?,
         length: obj.get_f32("length")?,
         frequency: obj.get_f32("frequency")?,
@@ -207,16 +296,24 @@ fn deserialize_drawable(obj: &JsonObject) -> InoxParseResult<Drawable> {
 }
 
 fn deserialize_mesh(obj: &JsonObject) -> InoxParseResult<Mesh> {
-    Ok(Mesh {
-        vertices: vals("verts", deserialize_vec2s_flat(obj.get_list("verts")?))?,
-        uvs: vals("uvs", deserialize_vec2s_flat(obj.get_list("uvs")?))?,
-        indices: obj
-            .get_list("indices")?
-            .iter()
-            .map_while(JsonValue::as_u16)
-            .collect::<Vec<_>>(),
-        origin: obj.get_vec2("origin")?,
-    })
+    let indices = obj
+        .get_list("indices")?
+        .iter()
+        .enumerate()
+        .map(|(index, val)| {
+            val.as_u16().ok_or_else(|| JsonError::ErrorInList {
+                index,
+                inner: Box::new(JsonError::ParseIntError("indices".to_owned())),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut mesh = Mesh::new(
+        vals("verts", deserialize_vec2s_flat(obj.get_list("verts")?))?,
+        vals("uvs", deserialize_vec2s_flat(obj.get_list("uvs")?))?,
+        indices,
+    )?;
+    mesh.origin = obj.get_vec2("origin")?;
+    Ok(mesh)
 }
 
 fn deserialize_mask(obj: &JsonObject) -> InoxParseResult<Mask> {
@@ -293,6 +390,7 @@ pub fn deserialize_puppet_ext<T>(
         deserialize_nodes(&obj.get_object("nodes")?, deserialize_node_custom),
     )?;
     let render_ctx = RenderCtx::new(&nodes);
+    let physics_ctx = PhysicsCtx::default();
 
     Ok(Puppet {
         meta: vals("meta", deserialize_puppet_meta(&obj.get_object("meta")?))?,
@@ -302,7 +400,9 @@ pub fn deserialize_puppet_ext<T>(
         )?,
         nodes,
         parameters: deserialize_params(obj.get_list("param")?),
+        param_groups: HashMap::new(),
         render_ctx,
+        physics_ctx,
     })
 }
 
@@ -366,6 +466,7 @@ fn deserialize_binding_values(
         "transform.r.x" => BindingValues::TransformRX(deserialize_inner_binding_values(values)?),
         "transform.r.y" => BindingValues::TransformRY(deserialize_inner_binding_values(values)?),
         "transform.r.z" => BindingValues::TransformRZ(deserialize_inner_binding_values(values)?),
+        "opacity" => BindingValues::Opacity(deserialize_inner_binding_values(values)?),
         "deform" => {
             let mut parsed = Vec::with_capacity(values.len());
             for (j, vals) in values.iter().enumerate() {
@@ -422,7 +523,12 @@ fn deserialize_nodes<T>(
     let root = arena.new_node(root_node);
     uuids.insert(root_uuid, root);
 
-    let mut node_tree = InoxNodeTree { root, arena, uuids };
+    let mut node_tree = InoxNodeTree {
+        root,
+        arena,
+        uuids,
+        names: HashMap::new(),
+    };
 
     for (i, child) in obj.get_list("children").unwrap_or(&[]).iter().enumerate() {
         let Some(child) = child.as_object() else {
@@ -436,6 +542,8 @@ fn deserialize_nodes<T>(
         root.append(child_id, &mut node_tree.arena);
     }
 
+    node_tree.rebuild_name_index();
+
     Ok(node_tree)
 }
 
@@ -486,6 +594,7 @@ fn deserialize_puppet_meta(obj: &JsonObject) -> InoxParseResult<PuppetMeta> {
         reference: obj.get_nullable_str("reference")?.map(str::to_owned),
         thumbnail_id: obj.get_u32("thumbnailId").ok(),
         preserve_pixels: obj.get_bool("preservePixels")?,
+        extra: extra_fields(obj, &[META_KEYS]),
     })
 }
 
@@ -504,3 +613,335 @@ fn deserialize_puppet_usage_rights(obj: &JsonObject) -> InoxParseResult<PuppetUs
         require_attribution: obj.get_bool("require_attribution")?,
     })
 }
+
+// Puppet serialization
+
+/// Serializes a puppet into the JSON payload of an INP file.
+///
+/// Nodes of unknown types, loaded as `InoxData::Custom(())`, are written as plain nodes.
+/// Fields that weren't understood when loading, including those of unknown node types, are kept.
+pub fn serialize_puppet(puppet: &Puppet) -> JsonValue {
+    serialize_puppet_ext(puppet, &default_serialize_custom)
+}
+
+fn default_serialize_custom(_data: &(), _obj: &mut JsonValue) -> String {
+    "Node".to_owned()
+}
+
+/// Serializes a puppet into the JSON payload of an INP file.
+///
+/// `serialize_node_custom` adds the fields of custom node data to the node's object
+/// and returns the node's type, as the inverse of the function given to `deserialize_puppet_ext`.
+pub fn serialize_puppet_ext<T>(
+    puppet: &Puppet<T>,
+    serialize_node_custom: &impl Fn(&T, &mut JsonValue) -> String,
+) -> JsonValue {
+    let mut obj = JsonValue::new_object();
+    obj["meta"] = serialize_puppet_meta(&puppet.meta);
+    obj["physics"] = serialize_puppet_physics(&puppet.physics);
+    obj["nodes"] = serialize_nodes(&puppet.nodes, puppet.nodes.root, serialize_node_custom);
+
+    // Sort parameters so that saving the same puppet twice gives the same file
+    let mut params = puppet.parameters.values().collect::<Vec<_>>();
+    params.sort_by_key(|param| param.uuid);
+    obj["param"] = params
+        .into_iter()
+        .map(serialize_param)
+        .collect::<Vec<_>>()
+        .into();
+    obj
+}
+
+fn serialize_nodes<T>(
+    node_tree: &InoxNodeTree<T>,
+    node_id: indextree::NodeId,
+    serialize_node_custom: &impl Fn(&T, &mut JsonValue) -> String,
+) -> JsonValue {
+    let mut obj = serialize_node_ext(node_tree.arena[node_id].get(), serialize_node_custom);
+    obj["children"] = node_id
+        .children(&node_tree.arena)
+        .map(|child_id| serialize_nodes(node_tree, child_id, serialize_node_custom))
+        .collect::<Vec<_>>()
+        .into();
+    obj
+}
+
+/// Serializes a node without its children.
+pub fn serialize_node_ext<T>(
+    node: &InoxNode<T>,
+    serialize_node_custom: &impl Fn(&T, &mut JsonValue) -> String,
+) -> JsonValue {
+    let mut obj = JsonValue::Object(node.extra.clone());
+    obj["uuid"] = node.uuid.0.into();
+    obj["name"] = node.name.as_str().into();
+    obj["enabled"] = node.enabled.into();
+    obj["zsort"] = node.zsort.into();
+    obj["transform"] = serialize_transform(&node.trans_offset);
+    obj["lockToRoot"] = node.lock_to_root.into();
+
+    let node_type = match &node.data {
+        InoxData::Node => "Node".to_owned(),
+        InoxData::Part(part) => {
+            serialize_part(part, &mut obj);
+            "Part".to_owned()
+        }
+        InoxData::Composite(composite) => {
+            serialize_drawable(&composite.draw_state, &mut obj);
+            "Composite".to_owned()
+        }
+        InoxData::SimplePhysics(physics) => {
+            serialize_simple_physics(physics, &mut obj);
+            "SimplePhysics".to_owned()
+        }
+        InoxData::MeshGroup(mesh_group) => {
+            obj["mesh"] = serialize_mesh(&mesh_group.mesh);
+            obj["dynamic_deformation"] = mesh_group.dynamic_deformation.into();
+            obj["translate_children"] = mesh_group.translate_children.into();
+            "MeshGroup".to_owned()
+        }
+        InoxData::Custom(custom) => (serialize_node_custom)(custom, &mut obj),
+    };
+    obj["type"] = node_type.into();
+    obj
+}
+
+fn serialize_part(part: &Part, obj: &mut JsonValue) {
+    serialize_drawable(&part.draw_state, obj);
+    // Missing textures are written as u32::MAX, like Inochi2D does
+    let no_texture = u32::MAX as usize;
+    obj["textures"] = vec![
+        part.tex_albedo,
+        part.tex_emissive.unwrap_or(no_texture),
+        part.tex_bumpmap.unwrap_or(no_texture),
+    ]
+    .into();
+    obj["mesh"] = serialize_mesh(&part.mesh);
+}
+
+fn serialize_simple_physics(physics: &SimplePhysics, obj: &mut JsonValue) {
+    obj["param"] = physics.param.into();
+    obj["model_type"] = physics_model_name(physics.model_type).into();
+    obj["map_mode"] = param_map_mode_name(physics.map_mode).into();
+    obj["gravity"] = physics.gravity.into();
+    obj["length"] = physics.length.into();
+    obj["frequency"] = physics.frequency.into();
+    obj["angle_damping"] = physics.angle_damping.into();
+    obj["length_damping"] = physics.length_damping.into();
+    obj["output_scale"] = serialize_vec2(physics.output_scale);
+}
+
+fn serialize_drawable(drawable: &Drawable, obj: &mut JsonValue) {
+    obj["blend_mode"] = blend_mode_name(drawable.blend_mode).into();
+    obj["tint"] = serialize_vec3(drawable.tint);
+    obj["screenTint"] = serialize_vec3(drawable.screen_tint);
+    obj["mask_threshold"] = drawable.mask_threshold.into();
+    obj["masks"] = drawable
+        .masks
+        .iter()
+        .map(serialize_mask)
+        .collect::<Vec<_>>()
+        .into();
+    obj["opacity"] = drawable.opacity.into();
+}
+
+fn serialize_mesh(mesh: &Mesh) -> JsonValue {
+    let mut obj = JsonValue::new_object();
+    obj["verts"] = serialize_vec2s_flat(&mesh.vertices);
+    obj["uvs"] = serialize_vec2s_flat(&mesh.uvs);
+    obj["indices"] = mesh.indices.clone().into();
+    obj["origin"] = serialize_vec2(mesh.origin);
+    obj
+}
+
+fn serialize_mask(mask: &Mask) -> JsonValue {
+    let mut obj = JsonValue::new_object();
+    obj["source"] = mask.source.0.into();
+    obj["mode"] = mask_mode_name(mask.mode).into();
+    obj
+}
+
+fn serialize_transform(trans: &TransformOffset) -> JsonValue {
+    let mut obj = JsonValue::new_object();
+    obj["trans"] = serialize_vec3(trans.translation);
+    obj["rot"] = serialize_vec3(trans.rotation);
+    obj["scale"] = serialize_vec2(trans.scale);
+    obj["pixel_snap"] = trans.pixel_snap.into();
+    obj
+}
+
+fn serialize_vec2(val: Vec2) -> JsonValue {
+    val.to_array().to_vec().into()
+}
+
+fn serialize_vec3(val: Vec3) -> JsonValue {
+    val.to_array().to_vec().into()
+}
+
+fn serialize_vec2s_flat(vals: &[Vec2]) -> JsonValue {
+    vals.iter()
+        .flat_map(|val| val.to_array())
+        .collect::<Vec<_>>()
+        .into()
+}
+
+fn serialize_param(param: &Param) -> JsonValue {
+    let mut obj = JsonValue::new_object();
+    obj["uuid"] = param.uuid.into();
+    obj["name"] = param.name.as_str().into();
+    obj["is_vec2"] = param.is_vec2.into();
+    obj["min"] = serialize_vec2(param.min);
+    obj["max"] = serialize_vec2(param.max);
+    obj["defaults"] = serialize_vec2(param.defaults);
+    obj["axis_points"] = vec![param.axis_points.x.clone(), param.axis_points.y.clone()].into();
+    obj["bindings"] = param
+        .bindings
+        .iter()
+        .map(serialize_binding)
+        .collect::<Vec<_>>()
+        .into();
+    obj
+}
+
+fn serialize_binding(binding: &Binding) -> JsonValue {
+    let (param_name, values) = serialize_binding_values(&binding.values);
+
+    let mut obj = JsonValue::new_object();
+    obj["node"] = binding.node.0.into();
+    obj["param_name"] = param_name.into();
+    obj["values"] = values;
+    obj["isSet"] = binding.is_set.to_slice_vecs().into();
+    obj["interpolate_mode"] = interpolate_mode_name(binding.interpolate_mode).into();
+    obj
+}
+
+fn serialize_binding_values(values: &BindingValues) -> (&'static str, JsonValue) {
+    match values {
+        BindingValues::ZSort(values) => ("zSort", values.to_slice_vecs().into()),
+        BindingValues::TransformTX(values) => ("transform.t.x", values.to_slice_vecs().into()),
+        BindingValues::TransformTY(values) => ("transform.t.y", values.to_slice_vecs().into()),
+        BindingValues::TransformSX(values) => ("transform.s.x", values.to_slice_vecs().into()),
+        BindingValues::TransformSY(values) => ("transform.s.y", values.to_slice_vecs().into()),
+        BindingValues::TransformRX(values) => ("transform.r.x", values.to_slice_vecs().into()),
+        BindingValues::TransformRY(values) => ("transform.r.y", values.to_slice_vecs().into()),
+        BindingValues::TransformRZ(values) => ("transform.r.z", values.to_slice_vecs().into()),
+        BindingValues::Opacity(values) => ("opacity", values.to_slice_vecs().into()),
+        BindingValues::Deform(values) => {
+            let values = values
+                .to_slice_vecs()
+                .into_iter()
+                .map(|line| {
+                    line.into_iter()
+                        .map(|deforms| deforms.into_iter().map(serialize_vec2).collect())
+                        .collect::<Vec<Vec<_>>>()
+                })
+                .collect::<Vec<_>>();
+            ("deform", values.into())
+        }
+    }
+}
+
+fn serialize_puppet_physics(physics: &PuppetPhysics) -> JsonValue {
+    let mut obj = JsonValue::new_object();
+    obj["pixelsPerMeter"] = physics.pixels_per_meter.into();
+    obj["gravity"] = physics.gravity.into();
+    obj
+}
+
+fn serialize_puppet_meta(meta: &PuppetMeta) -> JsonValue {
+    let mut obj = JsonValue::Object(meta.extra.clone());
+    obj["name"] = meta.name.clone().into();
+    obj["version"] = meta.version.as_str().into();
+    obj["rigger"] = meta.rigger.clone().into();
+    obj["artist"] = meta.artist.clone().into();
+    if let Some(rights) = &meta.rights {
+        obj["rights"] = serialize_puppet_usage_rights(rights);
+    }
+    obj["copyright"] = meta.copyright.clone().into();
+    obj["licenseURL"] = meta.license_url.clone().into();
+    obj["contact"] = meta.contact.clone().into();
+    obj["reference"] = meta.reference.clone().into();
+    if let Some(thumbnail_id) = meta.thumbnail_id {
+        obj["thumbnailId"] = thumbnail_id.into();
+    }
+    obj["preservePixels"] = meta.preserve_pixels.into();
+    obj
+}
+
+fn serialize_puppet_usage_rights(rights: &PuppetUsageRights) -> JsonValue {
+    let mut obj = JsonValue::new_object();
+    obj["allowed_users"] = match rights.allowed_users {
+        PuppetAllowedUsers::OnlyAuthor => "OnlyAuthor",
+        PuppetAllowedUsers::OnlyLicensee => "OnlyLicensee",
+        PuppetAllowedUsers::Everyone => "Everyone",
+    }
+    .into();
+    obj["allow_violence"] = rights.allow_violence.into();
+    obj["allow_sexual"] = rights.allow_sexual.into();
+    obj["allow_commercial"] = rights.allow_commercial.into();
+    obj["allow_redistribution"] = match rights.allow_redistribution {
+        PuppetAllowedRedistribution::Prohibited => "Prohibited",
+        PuppetAllowedRedistribution::ViralLicense => "ViralLicense",
+        PuppetAllowedRedistribution::CopyleftLicense => "CopyleftLicense",
+    }
+    .into();
+    obj["allow_modification"] = match rights.allow_modification {
+        PuppetAllowedModification::Prohibited => "Prohibited",
+        PuppetAllowedModification::AllowPersonal => "AllowPersonal",
+        PuppetAllowedModification::AllowRedistribute => "AllowRedistribute",
+    }
+    .into();
+    obj["require_attribution"] = rights.require_attribution.into();
+    obj
+}
+
+fn blend_mode_name(blend_mode: BlendMode) -> &'static str {
+    match blend_mode {
+        BlendMode::Normal => "Normal",
+        BlendMode::Multiply => "Multiply",
+        BlendMode::ColorDodge => "ColorDodge",
+        BlendMode::LinearDodge => "LinearDodge",
+        BlendMode::Screen => "Screen",
+        BlendMode::ClipToLower => "ClipToLower",
+        BlendMode::SliceFromLower => "SliceFromLower",
+        BlendMode::Overlay => "Overlay",
+        BlendMode::SoftLight => "SoftLight",
+        BlendMode::HardLight => "HardLight",
+        BlendMode::Darken => "Darken",
+        BlendMode::Lighten => "Lighten",
+        BlendMode::ColorBurn => "ColorBurn",
+        BlendMode::LinearBurn => "LinearBurn",
+        BlendMode::Subtract => "Subtract",
+        BlendMode::Difference => "Difference",
+        BlendMode::Exclusion => "Exclusion",
+    }
+}
+
+fn mask_mode_name(mask_mode: MaskMode) -> &'static str {
+    match mask_mode {
+        MaskMode::Mask => "Mask",
+        MaskMode::Dodge => "DodgeMask",
+    }
+}
+
+fn physics_model_name(model: PhysicsModel) -> &'static str {
+    match model {
+        PhysicsModel::Pendulum => "Pendulum",
+        PhysicsModel::SpringPendulum => "SpringPendulum",
+    }
+}
+
+fn param_map_mode_name(map_mode: ParamMapMode) -> &'static str {
+    match map_mode {
+        ParamMapMode::AngleLength => "AngleLength",
+        ParamMapMode::XY => "XY",
+        ParamMapMode::LengthAngle => "LengthAngle",
+        ParamMapMode::YX => "YX",
+    }
+}
+
+fn interpolate_mode_name(interpolate_mode: InterpolateMode) -> &'static str {
+    match interpolate_mode {
+        InterpolateMode::Nearest => "Nearest",
+        InterpolateMode::Linear => "Linear",
+    }
+}
@@ -40,6 +40,8 @@ pub mod mesh;
 pub mod model;
 pub mod nodes;
 pub mod params;
+pub mod physics;
 pub mod puppet;
 pub mod render;
+pub mod testing;
 pub mod texture;
@@ -0,0 +1,79 @@
+use glam::{Mat4, Vec2};
+
+/// Axis-aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    /// Corner with the smallest coordinates.
+    pub min: Vec2,
+    /// Corner with the largest coordinates.
+    pub max: Vec2,
+}
+
+impl Aabb {
+    pub fn new(min: Vec2, max: Vec2) -> Self {
+        Self { min, max }
+    }
+
+    /// Gets the smallest box containing all the points, or `None` if there are none.
+    pub fn from_points(points: impl IntoIterator<Item = Vec2>) -> Option<Self> {
+        points.into_iter().fold(None, |aabb: Option<Self>, point| {
+            Some(match aabb {
+                Some(aabb) => Self::new(aabb.min.min(point), aabb.max.max(point)),
+                None => Self::new(point, point),
+            })
+        })
+    }
+
+    /// Gets the smallest box containing both boxes.
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    /// Gets the box containing this one once transformed by `matrix`.
+    pub fn transformed(&self, matrix: Mat4) -> Self {
+        let corners = [
+            self.min,
+            Vec2::new(self.max.x, self.min.y),
+            self.max,
+            Vec2::new(self.min.x, self.max.y),
+        ];
+        let corners = corners.map(|c| matrix.transform_point3(c.extend(0.0)).truncate());
+        Self::from_points(corners).unwrap()
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.max - self.min
+    }
+
+    pub fn center(&self) -> Vec2 {
+        (self.min + self.max) / 2.0
+    }
+
+    /// Checks whether the boxes overlap, touching edges included.
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.max).all() && other.min.cmple(self.max).all()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec2;
+
+    use super::*;
+
+    #[test]
+    fn test_from_points_and_union() {
+        assert_eq!(Aabb::from_points([]), None);
+
+        let aabb = Aabb::from_points([vec2(1.0, -2.0), vec2(-3.0, 4.0), vec2(0.0, 0.0)]).unwrap();
+        assert_eq!(aabb, Aabb::new(vec2(-3.0, -2.0), vec2(1.0, 4.0)));
+        assert_eq!(aabb.center(), vec2(-1.0, 1.0));
+
+        let other = Aabb::new(vec2(5.0, 5.0), vec2(6.0, 6.0));
+        assert!(!aabb.intersects(&other));
+        assert_eq!(
+            aabb.union(&other),
+            Aabb::new(vec2(-3.0, -2.0), vec2(6.0, 6.0))
+        );
+    }
+}
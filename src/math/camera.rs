@@ -1,10 +1,54 @@
-use glam::{Mat4, Vec2};
+use std::f32::consts::TAU;
 
-#[derive(Clone)]
+use glam::{vec2, vec3, Mat4, Vec2, Vec3};
+
+use super::aabb::Aabb;
+
+/// Ratio between the distance of the camera to the model plane and its near and far planes,
+/// with a perspective projection.
+const PERSPECTIVE_RANGE: f32 = 100.0;
+
+/// Direction of the Y axis in application space.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CoordinateSystem {
+    /// Y goes down, like in Inochi2D models and on screen.
+    #[default]
+    YDown,
+    /// Y goes up, like in most game engines.
+    YUp,
+}
+
+/// How a camera projects the puppet onto the viewport.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Projection {
+    /// Flat projection, where everything keeps its size whatever its depth.
+    #[default]
+    Orthographic,
+    /// Projection with a vanishing point at the center of the viewport, for tilt and depth effects.
+    ///
+    /// The camera is placed so that the plane of the model is framed like with an orthographic
+    /// projection. Nodes are moved away from it along the view axis according to their zsort,
+    /// see `Camera::zsort_translation`.
+    Perspective {
+        /// Vertical field of view, in radians.
+        fov: f32,
+        /// Distance along the view axis, in model units, between nodes whose zsorts differ by 1.
+        depth: f32,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq)]
 pub struct Camera {
     pub position: Vec2,
     pub rotation: f32,
     pub scale: Vec2,
+    /// Convention that `position` and `rotation` are expressed in.
+    ///
+    /// With `CoordinateSystem::YUp`, the Y coordinate of `position` is negated and `rotation`
+    /// is counter-clockwise instead of clockwise on screen. Models themselves are always Y-down,
+    /// so the puppet still renders upright; only the app-facing values are converted.
+    pub coordinate_system: CoordinateSystem,
+    pub projection: Projection,
 }
 
 impl Default for Camera {
@@ -13,11 +57,41 @@ impl Default for Camera {
             position: Vec2::ZERO,
             rotation: 0.0,
             scale: Vec2::ONE,
+            coordinate_system: CoordinateSystem::default(),
+            projection: Projection::default(),
         }
     }
 }
 
 impl Camera {
+    /// Creates a default camera whose position and rotation are expressed in `coordinate_system`.
+    pub fn with_coordinate_system(coordinate_system: CoordinateSystem) -> Self {
+        Self {
+            coordinate_system,
+            ..Default::default()
+        }
+    }
+
+    /// Converts a point or vector from the app's convention to the models' Y-down convention.
+    ///
+    /// This is its own inverse.
+    pub fn to_native(&self, v: Vec2) -> Vec2 {
+        match self.coordinate_system {
+            CoordinateSystem::YDown => v,
+            CoordinateSystem::YUp => vec2(v.x, -v.y),
+        }
+    }
+
+    /// Converts an angle from the app's convention to the models' Y-down convention.
+    ///
+    /// This is its own inverse.
+    pub fn to_native_angle(&self, angle: f32) -> f32 {
+        match self.coordinate_system {
+            CoordinateSystem::YDown => angle,
+            CoordinateSystem::YUp => -angle,
+        }
+    }
+
     /// Gets the real size of the viewport
     pub fn real_size(&self, viewport: Vec2) -> Vec2 {
         Vec2 {
@@ -35,14 +109,311 @@ impl Camera {
     pub fn matrix(&self, viewport: Vec2) -> Mat4 {
         let real_size = self.real_size(viewport);
 
+        if let Projection::Perspective { fov, .. } = self.projection {
+            // Far enough for the model plane to span the same area as with an orthographic projection
+            let distance = real_size.y / 2.0 / (fov / 2.0).tan();
+            let pos = self.to_native(self.position).extend(distance);
+
+            return Mat4::perspective_lh(
+                fov,
+                real_size.x / real_size.y,
+                distance / PERSPECTIVE_RANGE,
+                distance * PERSPECTIVE_RANGE,
+            ) * Mat4::from_scale(vec3(1.0, -1.0, 1.0))
+                * Mat4::from_rotation_z(self.to_native_angle(self.rotation))
+                * Mat4::from_translation(pos);
+        }
+
         // Faster to reuse real_size, so do that instead of calling get_center_offset
         let origin = real_size / 2.0;
-        let pos = self.position.extend(-(u16::MAX as f32 / 2.0));
+        let pos = self
+            .to_native(self.position)
+            .extend(-(u16::MAX as f32 / 2.0));
 
         // Return camera ortho matrix
         Mat4::orthographic_lh(0.0, real_size.x, real_size.y, 0.0, 0.0, u16::MAX as f32)
             * Mat4::from_translation(origin.extend(0.0))
-            * Mat4::from_rotation_z(self.rotation)
+            * Mat4::from_rotation_z(self.to_native_angle(self.rotation))
             * Mat4::from_translation(pos)
     }
+
+    /// Gets the translation that moves a node with the absolute zsort `zsort` along the view axis,
+    /// to apply to its transform before `matrix`. Nodes drawn first end up further away.
+    ///
+    /// This is the identity with an orthographic projection.
+    pub fn zsort_translation(&self, zsort: f32) -> Mat4 {
+        match self.projection {
+            Projection::Orthographic => Mat4::IDENTITY,
+            Projection::Perspective { depth, .. } => {
+                Mat4::from_translation(vec3(0.0, 0.0, zsort * depth))
+            }
+        }
+    }
+
+    /// Moves and zooms the camera so that `bounds`, in model space, are centered and fill the viewport.
+    ///
+    /// `padding` is the fraction of the viewport left empty around the bounds along their tightest
+    /// axis, e.g. 0.1 for a 5% margin on each side. The scale is the same on both axes, so the bounds
+    /// keep their aspect ratio and fit entirely. The camera's rotation is kept and accounted for.
+    pub fn fit_bounds(&mut self, bounds: Aabb, viewport: Vec2, padding: f32) {
+        self.position = self.to_native(-bounds.center());
+
+        // Size of the bounds once rotated with the view
+        let (sin, cos) = self.to_native_angle(self.rotation).sin_cos();
+        let size = bounds.size();
+        let extent = vec2(
+            cos.abs() * size.x + sin.abs() * size.y,
+            sin.abs() * size.x + cos.abs() * size.y,
+        );
+        if extent.x <= 0.0 || extent.y <= 0.0 {
+            return;
+        }
+
+        let available = viewport * (1.0 - padding.clamp(0.0, 0.99));
+        let scale = (available / extent).min_element();
+        self.scale = Vec2::splat(scale);
+    }
+
+    /// Converts a position in pixels on the viewport, with the origin at the top-left,
+    /// to a position in model space, where nodes' transforms and meshes are expressed.
+    ///
+    /// Model space is always Y-down, whatever the camera's coordinate system is.
+    pub fn screen_to_world(&self, screen: Vec2, viewport: Vec2) -> Vec2 {
+        let matrix = self.matrix(viewport);
+        // All of model space is at the same depth
+        let ndc_z = matrix.project_point3(Vec3::ZERO).z;
+
+        let ndc = vec2(
+            screen.x / viewport.x * 2.0 - 1.0,
+            1.0 - screen.y / viewport.y * 2.0,
+        );
+        matrix
+            .inverse()
+            .project_point3(ndc.extend(ndc_z))
+            .truncate()
+    }
+
+    /// Converts a position in model space to a position in pixels on the viewport,
+    /// with the origin at the top-left. This is the inverse of `screen_to_world`.
+    pub fn world_to_screen(&self, world: Vec2, viewport: Vec2) -> Vec2 {
+        let ndc = self.matrix(viewport).project_point3(world.extend(0.0));
+        vec2(
+            (ndc.x + 1.0) / 2.0 * viewport.x,
+            (1.0 - ndc.y) / 2.0 * viewport.y,
+        )
+    }
+
+    /// Moves the camera a fraction `t` of the way to `target`, where 0 keeps it as is and 1 snaps to it.
+    ///
+    /// Position and scale are interpolated linearly, and rotation along the shortest arc.
+    /// The target may use another coordinate system, the camera keeps its own, as well as its projection.
+    pub fn lerp_to(&mut self, target: &Camera, t: f32) {
+        let target_position = self.to_native(target.to_native(target.position));
+        let target_rotation = self.to_native_angle(target.to_native_angle(target.rotation));
+        // Same angle as the target's, less than half a turn away from the current one
+        let target_rotation =
+            target_rotation + TAU * ((self.rotation - target_rotation) / TAU).round();
+
+        if t >= 1.0 {
+            // Exactly on the target, so that snapping again doesn't change anything
+            self.position = target_position;
+            self.rotation = target_rotation;
+            self.scale = target.scale;
+        } else {
+            self.position = self.position.lerp(target_position, t);
+            self.rotation += (target_rotation - self.rotation) * t;
+            self.scale = self.scale.lerp(target.scale, t);
+        }
+    }
+}
+
+/// Eases a camera toward a target camera, one frame at a time, for framing that follows
+/// something without snapping to it.
+///
+/// Once the camera is close enough to the target, it is set to it exactly and stops changing,
+/// so renderers don't recompute their matrices for a camera at rest.
+#[derive(Clone)]
+pub struct CameraController {
+    /// Camera that the controlled camera moves toward.
+    pub target: Camera,
+    /// How fast the camera catches up with the target, in inverse seconds.
+    ///
+    /// Each second, the remaining distance shrinks by a factor of `e^stiffness`,
+    /// whatever the frame rate. Infinite stiffness snaps to the target.
+    pub stiffness: f32,
+}
+
+impl CameraController {
+    /// Position, in model units, and rotation and scale differences below which the camera snaps to the target.
+    const SETTLE_EPSILON: f32 = 1e-4;
+
+    pub fn new(target: Camera, stiffness: f32) -> Self {
+        Self { target, stiffness }
+    }
+
+    /// Moves `camera` toward the target, `dt` seconds after the last update.
+    ///
+    /// Returns whether the camera changed.
+    pub fn update(&self, camera: &mut Camera, dt: f32) -> bool {
+        let previous = *camera;
+
+        let t = 1.0 - (-self.stiffness * dt.max(0.0)).exp();
+        camera.lerp_to(&self.target, if t.is_nan() { 1.0 } else { t });
+
+        let mut snapped = *camera;
+        snapped.lerp_to(&self.target, 1.0);
+        if camera
+            .position
+            .abs_diff_eq(snapped.position, Self::SETTLE_EPSILON)
+            && camera
+                .scale
+                .abs_diff_eq(snapped.scale, Self::SETTLE_EPSILON)
+            && (camera.rotation - snapped.rotation).abs() <= Self::SETTLE_EPSILON
+        {
+            *camera = snapped;
+        }
+
+        *camera != previous
+    }
+
+    /// Whether the camera has reached the target, after which updates leave it unchanged.
+    pub fn is_settled(&self, camera: &Camera) -> bool {
+        let mut snapped = *camera;
+        snapped.lerp_to(&self.target, 1.0);
+        snapped == *camera
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_screen_world_round_trip() {
+        let viewport = vec2(800.0, 600.0);
+        let camera = Camera {
+            position: vec2(120.0, -40.0),
+            rotation: 0.3,
+            scale: vec2(2.0, 2.0),
+            ..Default::default()
+        };
+
+        // The center of the screen is where the camera looks at
+        let center = camera.screen_to_world(viewport / 2.0, viewport);
+        assert!(center.abs_diff_eq(-camera.position, 1e-3));
+
+        for screen in [vec2(0.0, 0.0), vec2(400.0, 300.0), vec2(799.0, 17.0)] {
+            let world = camera.screen_to_world(screen, viewport);
+            assert!(camera
+                .world_to_screen(world, viewport)
+                .abs_diff_eq(screen, 1e-3));
+        }
+
+        for world in [vec2(0.0, 0.0), vec2(-350.0, 1200.0)] {
+            let screen = camera.world_to_screen(world, viewport);
+            assert!(camera
+                .screen_to_world(screen, viewport)
+                .abs_diff_eq(world, 1e-3));
+        }
+    }
+
+    #[test]
+    fn test_fit_bounds() {
+        let viewport = vec2(800.0, 600.0);
+        let bounds = Aabb::new(vec2(100.0, -500.0), vec2(300.0, 1500.0));
+
+        let mut camera = Camera::with_coordinate_system(CoordinateSystem::YUp);
+        camera.fit_bounds(bounds, viewport, 0.1);
+
+        // The bounds are centered, and fill 90% of the height, which is the tightest axis
+        let min = camera.world_to_screen(bounds.min, viewport);
+        let max = camera.world_to_screen(bounds.max, viewport);
+        assert!(((min + max) / 2.0).abs_diff_eq(viewport / 2.0, 1e-3));
+        assert!((max.y - min.y - 540.0).abs() < 1e-3);
+        assert!((max.x - min.x) < 800.0);
+    }
+
+    #[test]
+    fn test_projections() {
+        let viewport = vec2(800.0, 600.0);
+        let mut camera = Camera {
+            position: vec2(120.0, -40.0),
+            rotation: 0.3,
+            scale: vec2(2.0, 1.5),
+            coordinate_system: CoordinateSystem::YUp,
+            ..Default::default()
+        };
+
+        // The orthographic matrix is the one computed before perspective was supported
+        let real_size = camera.real_size(viewport);
+        let pos = camera
+            .to_native(camera.position)
+            .extend(-(u16::MAX as f32 / 2.0));
+        let ortho = Mat4::orthographic_lh(0.0, real_size.x, real_size.y, 0.0, 0.0, u16::MAX as f32)
+            * Mat4::from_translation((real_size / 2.0).extend(0.0))
+            * Mat4::from_rotation_z(camera.to_native_angle(camera.rotation))
+            * Mat4::from_translation(pos);
+        assert_eq!(
+            camera.matrix(viewport).to_cols_array(),
+            ortho.to_cols_array()
+        );
+        assert_eq!(camera.zsort_translation(3.0), Mat4::IDENTITY);
+
+        // With perspective, the model plane is framed the same
+        let ortho_camera = camera;
+        camera.projection = Projection::Perspective {
+            fov: 0.8,
+            depth: 10.0,
+        };
+        for world in [vec2(0.0, 0.0), vec2(-350.0, 120.0), vec2(60.0, 90.0)] {
+            let expected = ortho_camera.world_to_screen(world, viewport);
+            assert!(camera
+                .world_to_screen(world, viewport)
+                .abs_diff_eq(expected, 1e-2));
+            let screen = camera.world_to_screen(world, viewport);
+            assert!(camera
+                .screen_to_world(screen, viewport)
+                .abs_diff_eq(world, 1e-2));
+        }
+
+        // Nodes with a higher zsort are drawn first, and look further away and smaller
+        let matrix = camera.matrix(viewport);
+        let size_at = |zsort| {
+            let mvp = matrix * camera.zsort_translation(zsort);
+            (mvp.project_point3(vec3(100.0, 0.0, 0.0)) - mvp.project_point3(Vec3::ZERO)).length()
+        };
+        assert!(size_at(1.0) < size_at(0.0));
+        assert!(size_at(-1.0) > size_at(0.0));
+    }
+
+    #[test]
+    fn test_camera_controller() {
+        let mut camera = Camera {
+            rotation: 3.0,
+            ..Default::default()
+        };
+        let target = Camera {
+            position: vec2(100.0, -50.0),
+            rotation: -3.0,
+            scale: vec2(3.0, 3.0),
+            ..Default::default()
+        };
+
+        // Halfway, turning through PI rather than back through 0
+        let mut half = camera;
+        half.lerp_to(&target, 0.5);
+        assert!(half.position.abs_diff_eq(vec2(50.0, -25.0), 1e-4));
+        assert!(half.scale.abs_diff_eq(vec2(2.0, 2.0), 1e-4));
+        assert!((half.rotation - (3.0 + (TAU - 6.0) / 2.0)).abs() < 1e-4);
+
+        let controller = CameraController::new(target, 10.0);
+        let mut frames = 0;
+        while controller.update(&mut camera, 1.0 / 60.0) {
+            frames += 1;
+            assert!(frames < 1000, "the camera never settled");
+        }
+        assert!(controller.is_settled(&camera));
+        assert!(camera.position.abs_diff_eq(target.position, 1e-4));
+        assert!(!controller.update(&mut camera, 1.0 / 60.0));
+    }
 }
@@ -58,7 +58,7 @@ impl InterpRange<Vec2> {
 #[inline]
 fn interpolate_nearest(t: f32, range_in: InterpRange<f32>, range_out: InterpRange<f32>) -> f32 {
     debug_assert!(
-        range_in.beg <= t && t <= range_in.end,
+        range_in.beg.min(range_in.end) <= t && t <= range_in.beg.max(range_in.end),
         "{} <= {} <= {}",
         range_in.beg,
         t,
@@ -75,7 +75,7 @@ fn interpolate_nearest(t: f32, range_in: InterpRange<f32>, range_out: InterpRang
 #[inline]
 fn interpolate_linear(t: f32, range_in: InterpRange<f32>, range_out: InterpRange<f32>) -> f32 {
     debug_assert!(
-        range_in.beg <= t && t <= range_in.end,
+        range_in.beg.min(range_in.end) <= t && t <= range_in.beg.max(range_in.end),
         "{} <= {} <= {}",
         range_in.beg,
         t,
@@ -93,4 +93,12 @@ impl<T: Default + Clone> Matrix2d<T> {
             })
         }
     }
+
+    /// Gets back the lines the matrix was built from with `from_slice_vecs`.
+    pub fn to_slice_vecs(&self) -> Vec<Vec<T>> {
+        if self.width == 0 {
+            return vec![Vec::new(); self.height];
+        }
+        self.data.chunks(self.width).map(<[T]>::to_vec).collect()
+    }
 }
@@ -50,12 +50,144 @@ impl TransformOffset {
 
     pub fn to_matrix(&self) -> Mat4 {
         Mat4::from_translation(self.translation)
-            * Mat4::from_quat(Quat::from_euler(
-                EulerRot::XYZ,
-                self.rotation.x,
-                self.rotation.y,
-                self.rotation.z,
-            ))
+            * Mat4::from_quat(self.rotation_quat())
             * Mat4::from_scale(Vec3::new(self.scale.x, self.scale.y, 1.))
     }
+
+    /// Decomposes a matrix into a transform, keeping the `pixel_snap` of `self`.
+    ///
+    /// Matrices with shear, which nested non-uniform scales and rotations can produce,
+    /// can't be represented exactly and are approximated.
+    fn with_matrix(&self, matrix: Mat4) -> Self {
+        let (scale, rotation, translation) = matrix.to_scale_rotation_translation();
+        let (x, y, z) = rotation.to_euler(EulerRot::XYZ);
+        Self {
+            translation,
+            rotation: Vec3::new(x, y, z),
+            scale: scale.truncate(),
+            pixel_snap: self.pixel_snap,
+        }
+    }
+
+    /// Gets the transform that applies `self` in the space of `parent`,
+    /// like the absolute transform of a node from its relative transform and its parent's.
+    pub fn combine(&self, parent: &TransformOffset) -> TransformOffset {
+        self.with_matrix(parent.to_matrix() * self.to_matrix())
+    }
+
+    /// Gets the transform that undoes `self`.
+    ///
+    /// Undoing a rotation with a non-uniform scale takes a scale along rotated axes, which
+    /// a transform can't represent: unless the rotation is by quarter turns, the inverse is
+    /// approximated, and only its translation is exact. Use `to_matrix().inverse()` to undo
+    /// such transforms exactly.
+    pub fn inverse(&self) -> TransformOffset {
+        self.with_matrix(self.to_matrix().inverse())
+    }
+
+    /// Interpolates between two transforms, `t` going from 0 at `self` to 1 at `other`.
+    ///
+    /// Translations and scales are interpolated linearly, rotations spherically
+    /// so that they take the shortest path.
+    pub fn lerp(&self, other: &TransformOffset, t: f32) -> TransformOffset {
+        let rotation = self.rotation_quat().slerp(other.rotation_quat(), t);
+        let (x, y, z) = rotation.to_euler(EulerRot::XYZ);
+        Self {
+            translation: self.translation.lerp(other.translation, t),
+            rotation: Vec3::new(x, y, z),
+            scale: self.scale.lerp(other.scale, t),
+            pixel_snap: self.pixel_snap,
+        }
+    }
+
+    fn rotation_quat(&self) -> Quat {
+        Quat::from_euler(
+            EulerRot::XYZ,
+            self.rotation.x,
+            self.rotation.y,
+            self.rotation.z,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use glam::{vec2, vec3};
+
+    use super::*;
+
+    fn assert_same(a: &TransformOffset, b: &TransformOffset) {
+        assert!(
+            a.to_matrix().abs_diff_eq(b.to_matrix(), 1e-5),
+            "{a:?} != {b:?}"
+        );
+    }
+
+    #[test]
+    fn test_combine_and_inverse() {
+        let trans = TransformOffset::new()
+            .with_translation(vec3(1.0, -2.0, 0.0))
+            .with_rotation(vec3(0.0, 0.0, 0.3))
+            .with_scale(vec2(1.5, 0.5));
+        let parent = TransformOffset::new()
+            .with_translation(vec3(4.0, 3.0, 0.0))
+            .with_rotation(vec3(0.0, 0.0, FRAC_PI_2))
+            .with_scale(vec2(2.0, 2.0));
+
+        // combining with the identity changes nothing
+        assert_same(&trans.combine(&TransformOffset::new()), &trans);
+        assert_same(&TransformOffset::new().combine(&trans), &trans);
+
+        let combined = trans.combine(&parent);
+        assert_same(
+            &combined,
+            &TransformOffset::new().with_matrix(parent.to_matrix() * trans.to_matrix()),
+        );
+        assert_same(&combined.combine(&parent.inverse()), &trans);
+        assert_same(&parent.combine(&parent.inverse()), &TransformOffset::new());
+    }
+
+    #[test]
+    fn test_inverse_with_non_uniform_scale() {
+        let scale = vec2(3.0, 0.5);
+        let undo_exactly = |trans: TransformOffset| {
+            assert_same(&trans.combine(&trans.inverse()), &TransformOffset::new());
+            assert_same(&trans.inverse().combine(&trans), &TransformOffset::new());
+        };
+
+        // Without rotation, or with rotations by quarter turns, the inverse is exact
+        let trans = TransformOffset::new()
+            .with_translation(vec3(1.0, -2.0, 0.0))
+            .with_scale(scale);
+        undo_exactly(trans);
+        undo_exactly(trans.with_rotation(vec3(0.0, 0.0, FRAC_PI_2)));
+
+        // Otherwise it is approximated, keeping only the translation of the exact inverse
+        let rotated = trans.with_rotation(vec3(0.0, 0.0, 0.3));
+        let exact = rotated.to_matrix().inverse();
+        assert!(!(rotated.inverse().to_matrix()).abs_diff_eq(exact, 1e-3));
+        assert!(rotated
+            .inverse()
+            .translation
+            .abs_diff_eq(exact.w_axis.truncate(), 1e-5));
+    }
+
+    #[test]
+    fn test_lerp() {
+        let a = TransformOffset::new().with_rotation(vec3(0.0, 0.0, 0.2));
+        let b = TransformOffset::new()
+            .with_translation(vec3(2.0, 4.0, 0.0))
+            .with_rotation(vec3(0.0, 0.0, 1.0))
+            .with_scale(vec2(3.0, 1.0));
+
+        assert_same(&a.lerp(&b, 0.0), &a);
+        assert_same(&a.lerp(&b, 1.0), &b);
+
+        let mid = a.lerp(&b, 0.5);
+        assert!(mid.translation.abs_diff_eq(vec3(1.0, 2.0, 0.0), 1e-5));
+        assert!((mid.rotation.z - 0.6).abs() < 1e-5);
+        assert!(mid.scale.abs_diff_eq(vec2(2.0, 1.0), 1e-5));
+    }
 }
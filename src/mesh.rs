@@ -1,7 +1,9 @@
 use std::collections::BTreeMap;
 use std::slice;
 
-use glam::{vec2, vec3, IVec2, Vec2, Vec4};
+use glam::{vec2, vec3, IVec2, Vec2, Vec3, Vec4};
+
+use crate::math::aabb::Aabb;
 
 /// Mesh
 #[derive(Clone, Debug, Default)]
@@ -16,7 +18,80 @@ pub struct Mesh {
     pub origin: Vec2,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum MeshError {
+    #[error("Mesh has {vertices} vertices but {uvs} UVs")]
+    LengthMismatch { vertices: usize, uvs: usize },
+    #[error("Mesh has {0} vertices, more than 16-bit indices can address")]
+    TooManyVertices(usize),
+    #[error("Mesh has {0} indices, which is not a whole number of triangles")]
+    NotTriangles(usize),
+    #[error("Mesh index {index} is out of range for {vertices} vertices")]
+    IndexOutOfRange { index: u16, vertices: usize },
+}
+
 impl Mesh {
+    /// Creates a mesh, checking that it can be drawn.
+    ///
+    /// Each vertex must have an UV, indices must form whole triangles
+    /// and they must all refer to existing vertices.
+    pub fn new(vertices: Vec<Vec2>, uvs: Vec<Vec2>, indices: Vec<u16>) -> Result<Self, MeshError> {
+        let mesh = Self {
+            vertices,
+            uvs,
+            indices,
+            origin: Vec2::ZERO,
+        };
+        mesh.validate()?;
+        Ok(mesh)
+    }
+
+    /// Checks that the mesh can be drawn, as `Mesh::new` does,
+    /// e.g. after editing its vertices or indices.
+    pub fn validate(&self) -> Result<(), MeshError> {
+        let vertices = self.vertices.len();
+        if vertices != self.uvs.len() {
+            return Err(MeshError::LengthMismatch {
+                vertices,
+                uvs: self.uvs.len(),
+            });
+        }
+        if vertices > u16::MAX as usize + 1 {
+            return Err(MeshError::TooManyVertices(vertices));
+        }
+        if self.indices.len() % 3 != 0 {
+            return Err(MeshError::NotTriangles(self.indices.len()));
+        }
+        if let Some(&index) = self.indices.iter().find(|&&i| i as usize >= vertices) {
+            return Err(MeshError::IndexOutOfRange { index, vertices });
+        }
+        Ok(())
+    }
+
+    /// Number of triangles in the mesh.
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// Gets the mesh's bounding box, or `None` if the mesh has no vertices.
+    pub fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_points(self.vertices.iter().copied())
+    }
+
+    /// Moves the vertices so that the mesh's bounding box is centered on (0, 0),
+    /// and returns the offset that was applied to them.
+    pub fn recenter(&mut self) -> Vec2 {
+        let Some(bounds) = self.bounds() else {
+            return Vec2::ZERO;
+        };
+
+        let offset = -bounds.center();
+        for vertex in &mut self.vertices {
+            *vertex += offset;
+        }
+        offset
+    }
+
     /// Add a new vertex.
     pub fn add(&mut self, vertex: Vec2, uv: Vec2) {
         self.vertices.push(vertex);
@@ -129,6 +204,86 @@ pub(crate) fn f32s_as_vec2s(vec: &[f32]) -> &'_ [Vec2] {
     unsafe { slice::from_raw_parts(vec.as_ptr() as *const Vec2, vec.len() / 2) }
 }
 
+/// Checks whether `point` is inside any of the triangles that `indices` make out of `vertices`.
+///
+/// Points on an edge count as inside, and triangles can be wound either way.
+pub fn triangles_contain(vertices: &[Vec2], indices: &[u16], point: Vec2) -> bool {
+    indices.chunks_exact(3).any(|triangle| {
+        let [a, b, c] = [0, 1, 2].map(|i| vertices[triangle[i] as usize]);
+        let ab = (b - a).perp_dot(point - a);
+        let bc = (c - b).perp_dot(point - b);
+        let ca = (a - c).perp_dot(point - c);
+        (ab >= 0.0 && bc >= 0.0 && ca >= 0.0) || (ab <= 0.0 && bc <= 0.0 && ca <= 0.0)
+    })
+}
+
+/// Position of a point relative to a triangle of a mesh, as barycentric weights of its vertices.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TriangleWeights {
+    /// Indices of the triangle's vertices.
+    pub indices: [u16; 3],
+    /// Weights of the triangle's vertices, which sum to 1.
+    pub weights: Vec3,
+}
+
+impl TriangleWeights {
+    /// Combines values given for each vertex of the mesh with the weights.
+    pub fn interpolate(&self, values: &[Vec2]) -> Vec2 {
+        (0..3)
+            .map(|i| values[self.indices[i] as usize] * self.weights[i])
+            .sum()
+    }
+}
+
+/// Gets the weights of `point` against the triangle that contains it,
+/// among the triangles that `indices` make out of `vertices`.
+///
+/// If no triangle contains the point, the nearest triangle is used instead,
+/// with weights extrapolated outside of it, some of them being negative.
+/// Returns `None` if there are no triangles, or if they are all degenerate.
+pub fn triangle_weights(
+    vertices: &[Vec2],
+    indices: &[u16],
+    point: Vec2,
+) -> Option<TriangleWeights> {
+    let mut nearest: Option<(f32, TriangleWeights)> = None;
+
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| vertices[triangle[i] as usize]);
+        let area = (b - a).perp_dot(c - a);
+        if area == 0.0 {
+            continue;
+        }
+
+        let wb = (point - a).perp_dot(c - a) / area;
+        let wc = (b - a).perp_dot(point - a) / area;
+        let weights = TriangleWeights {
+            indices: [triangle[0], triangle[1], triangle[2]],
+            weights: vec3(1.0 - wb - wc, wb, wc),
+        };
+        if weights.weights.min_element() >= 0.0 {
+            return Some(weights);
+        }
+
+        let distance = [(a, b), (b, c), (c, a)]
+            .into_iter()
+            .map(|(start, end)| segment_distance(start, end, point))
+            .fold(f32::MAX, f32::min);
+        if nearest.map_or(true, |(nearest, _)| distance < nearest) {
+            nearest = Some((distance, weights));
+        }
+    }
+
+    nearest.map(|(_, weights)| weights)
+}
+
+/// Distance from `point` to the segment between `start` and `end`.
+fn segment_distance(start: Vec2, end: Vec2, point: Vec2) -> f32 {
+    let segment = end - start;
+    let t = ((point - start).dot(segment) / segment.length_squared()).clamp(0.0, 1.0);
+    point.distance(start + segment * t)
+}
+
 #[derive(Clone, Debug)]
 pub struct QuadBuilder {
     size: IVec2,
@@ -243,3 +398,112 @@ impl QuadBuilder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_rejects_mismatched_lengths() {
+        let result = Mesh::new(vec![Vec2::ZERO; 3], vec![Vec2::ZERO; 2], vec![0, 1, 2]);
+        assert!(matches!(
+            result,
+            Err(MeshError::LengthMismatch {
+                vertices: 3,
+                uvs: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn test_new_rejects_out_of_range_indices() {
+        let result = Mesh::new(vec![Vec2::ZERO; 3], vec![Vec2::ZERO; 3], vec![0, 1, 3]);
+        assert!(matches!(
+            result,
+            Err(MeshError::IndexOutOfRange {
+                index: 3,
+                vertices: 3
+            })
+        ));
+
+        let result = Mesh::new(vec![Vec2::ZERO; 3], vec![Vec2::ZERO; 3], vec![0, 1]);
+        assert!(matches!(result, Err(MeshError::NotTriangles(2))));
+    }
+
+    #[test]
+    fn test_validate_edited_mesh() {
+        let mut mesh = Mesh::new(vec![Vec2::ZERO; 3], vec![Vec2::ZERO; 3], vec![0, 1, 2]).unwrap();
+        assert_eq!(mesh.validate(), Ok(()));
+
+        mesh.connect(0, 1);
+        assert_eq!(mesh.validate(), Err(MeshError::NotTriangles(5)));
+
+        mesh.indices.push(3);
+        assert_eq!(
+            mesh.validate(),
+            Err(MeshError::IndexOutOfRange {
+                index: 3,
+                vertices: 3
+            })
+        );
+
+        mesh.add(Vec2::ONE, Vec2::ONE);
+        assert_eq!(mesh.validate(), Ok(()));
+        assert_eq!(mesh.triangle_count(), 2);
+    }
+
+    #[test]
+    fn test_triangle_weights() {
+        let vertices = [
+            vec2(0.0, 0.0),
+            vec2(2.0, 0.0),
+            vec2(0.0, 2.0),
+            vec2(2.0, 2.0),
+        ];
+        let indices = [0, 1, 2, 1, 3, 2];
+
+        let weights = triangle_weights(&vertices, &indices, vec2(0.5, 0.5)).unwrap();
+        assert_eq!(weights.indices, [0, 1, 2]);
+        assert_eq!(weights.weights, vec3(0.5, 0.25, 0.25));
+        assert_eq!(weights.interpolate(&vertices), vec2(0.5, 0.5));
+
+        // outside of the mesh, the nearest triangle is extrapolated
+        let point = vec2(3.0, 2.5);
+        let weights = triangle_weights(&vertices, &indices, point).unwrap();
+        assert_eq!(weights.indices, [1, 3, 2]);
+        assert!(weights.weights.min_element() < 0.0);
+        assert!(weights.interpolate(&vertices).distance(point) < 1e-5);
+
+        assert_eq!(triangle_weights(&vertices, &[], point), None);
+    }
+
+    #[test]
+    fn test_triangles_contain() {
+        let vertices = [
+            vec2(0.0, 0.0),
+            vec2(2.0, 0.0),
+            vec2(0.0, 2.0),
+            vec2(2.0, 2.0),
+        ];
+        // second triangle is wound the other way
+        let indices = [0, 1, 2, 1, 3, 2];
+
+        assert!(triangles_contain(&vertices, &indices, vec2(0.5, 0.5)));
+        assert!(triangles_contain(&vertices, &indices, vec2(1.5, 1.5)));
+        assert!(!triangles_contain(&vertices, &indices, vec2(2.5, 1.0)));
+        assert!(!triangles_contain(&vertices, &indices[..3], vec2(1.5, 1.5)));
+    }
+
+    #[test]
+    fn test_recenter() {
+        let vertices = vec![vec2(0.0, 0.0), vec2(4.0, 0.0), vec2(4.0, 2.0)];
+        let mut mesh = Mesh::new(vertices, vec![Vec2::ZERO; 3], vec![0, 1, 2]).unwrap();
+
+        assert_eq!(mesh.triangle_count(), 1);
+        assert_eq!(mesh.recenter(), vec2(-2.0, -1.0));
+        assert_eq!(
+            mesh.bounds(),
+            Some(Aabb::new(vec2(-2.0, -1.0), vec2(2.0, 1.0)))
+        );
+    }
+}
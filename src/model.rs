@@ -1,5 +1,16 @@
-use std::fmt;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::Utf8Error;
+use std::{fmt, fs};
 
+use tracing::warn;
+
+use crate::formats::inp::{is_inp, parse_inp, write_inp, ParseInpError, WriteInpError};
+use crate::formats::serialize::{deserialize_puppet, InoxParseError};
+use crate::mesh::MeshError;
+use crate::nodes::node::InoxNodeUuid;
+use crate::nodes::node_data::InoxData;
 use crate::puppet::Puppet;
 
 #[derive(Debug)]
@@ -30,6 +41,50 @@ impl fmt::Display for VendorData {
     }
 }
 
+#[derive(Debug, thiserror::Error)]
+#[error("Could not load model: {0}")]
+pub enum LoadModelError {
+    Io(#[from] io::Error),
+    Inp(#[from] ParseInpError),
+    Utf8(#[from] Utf8Error),
+    JsonParse(#[from] json::Error),
+    InoxParse(#[from] InoxParseError),
+}
+
+/// Structural problem in a model's data, found by `Model::validate`.
+///
+/// Models with these problems load, but are likely to render wrong or make renderers panic.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ValidationIssue {
+    #[error("{count} nodes have the uuid {uuid:?}")]
+    DuplicateUuid { uuid: InoxNodeUuid, count: usize },
+    #[error(
+        "Mask {index} of node {node:?} is drawn from node {mask_source:?}, which doesn't exist"
+    )]
+    MissingMaskSource {
+        node: InoxNodeUuid,
+        index: usize,
+        mask_source: InoxNodeUuid,
+    },
+    #[error(
+        "Part {part:?} uses texture {index} as its {slot}, but the model has {count} textures"
+    )]
+    TextureOutOfRange {
+        part: InoxNodeUuid,
+        /// Which texture of the part: "albedo", "emissive" or "bumpmap".
+        slot: &'static str,
+        index: usize,
+        count: usize,
+    },
+    #[error("Composite {composite:?} has a descendant with its own uuid, so it would draw itself")]
+    CompositeContainsItself { composite: InoxNodeUuid },
+    #[error("Mesh of node {node:?} can't be drawn: {error}")]
+    InvalidMesh {
+        node: InoxNodeUuid,
+        error: MeshError,
+    },
+}
+
 /// Inochi2D model.
 #[derive(Debug)]
 pub struct Model<T = ()> {
@@ -37,3 +92,226 @@ pub struct Model<T = ()> {
     pub textures: Vec<ModelTexture>,
     pub vendors: Vec<VendorData>,
 }
+
+impl<T> Model<T> {
+    /// Checks the model for structural problems that would make it render wrong or panic,
+    /// and returns them, in an order that is stable for a given model. No issues means the model is sound.
+    ///
+    /// The checks are:
+    /// - every node has its own uuid,
+    /// - masks are drawn from nodes that exist,
+    /// - parts only use textures of the model,
+    /// - composites don't contain themselves, which is only possible with duplicate uuids,
+    /// - meshes of parts and mesh groups form whole triangles of existing vertices,
+    ///   which matters after editing them.
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let nodes = &self.puppet.nodes;
+        let mut issues = Vec::new();
+
+        let mut uuid_counts = BTreeMap::new();
+        for id in nodes.root.descendants(&nodes.arena) {
+            *uuid_counts.entry(nodes.arena[id].get().uuid).or_insert(0) += 1;
+        }
+        issues.extend(
+            (uuid_counts.iter())
+                .filter(|(_, &count)| count > 1)
+                .map(|(&uuid, &count)| ValidationIssue::DuplicateUuid { uuid, count }),
+        );
+
+        for id in nodes.root.descendants(&nodes.arena) {
+            let node = nodes.arena[id].get();
+            let draw_state = match node.data {
+                InoxData::Part(ref part) => {
+                    let count = self.textures.len();
+                    for (slot, index) in [
+                        ("albedo", Some(part.tex_albedo)),
+                        ("emissive", part.tex_emissive),
+                        ("bumpmap", part.tex_bumpmap),
+                    ] {
+                        let Some(index) = index else {
+                            continue;
+                        };
+                        if index >= count {
+                            issues.push(ValidationIssue::TextureOutOfRange {
+                                part: node.uuid,
+                                slot,
+                                index,
+                                count,
+                            });
+                        }
+                    }
+                    if let Err(error) = part.mesh.validate() {
+                        issues.push(ValidationIssue::InvalidMesh {
+                            node: node.uuid,
+                            error,
+                        });
+                    }
+                    &part.draw_state
+                }
+                InoxData::Composite(ref composite) => {
+                    if (id.descendants(&nodes.arena).skip(1))
+                        .any(|descendant| nodes.arena[descendant].get().uuid == node.uuid)
+                    {
+                        issues.push(ValidationIssue::CompositeContainsItself {
+                            composite: node.uuid,
+                        });
+                    }
+                    &composite.draw_state
+                }
+                InoxData::MeshGroup(ref mesh_group) => {
+                    if let Err(error) = mesh_group.mesh.validate() {
+                        issues.push(ValidationIssue::InvalidMesh {
+                            node: node.uuid,
+                            error,
+                        });
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+
+            for (index, mask) in draw_state.masks.iter().enumerate() {
+                if !uuid_counts.contains_key(&mask.source) {
+                    issues.push(ValidationIssue::MissingMaskSource {
+                        node: node.uuid,
+                        index,
+                        mask_source: mask.source,
+                    });
+                }
+            }
+        }
+
+        issues
+    }
+
+    /// Logs the issues found by `validate`, when loading a model.
+    pub(crate) fn warn_validation_issues(&self) {
+        for issue in self.validate() {
+            warn!("Model {:?}: {issue}", self.puppet.meta.name);
+        }
+    }
+}
+
+impl Model {
+    /// Loads a model from either an INP file or a bare JSON puppet, depending on its magic bytes.
+    ///
+    /// A JSON puppet has no textures nor vendor data. Nodes of unknown types are kept
+    /// as `InoxData::Custom(())`: to deserialize them into your own node data `T`,
+    /// parse the payload with `deserialize_puppet_ext` instead.
+    ///
+    /// Structural problems found by `validate` are logged as warnings.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, LoadModelError> {
+        if is_inp(data) {
+            return Ok(parse_inp(data)?);
+        }
+
+        let payload = json::parse(std::str::from_utf8(data)?)?;
+        let model = Model {
+            puppet: deserialize_puppet(&payload)?,
+            textures: Vec::new(),
+            vendors: Vec::new(),
+        };
+        model.warn_validation_issues();
+        Ok(model)
+    }
+
+    /// Reads and loads a model from a file. See `Model::from_bytes`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, LoadModelError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// Saves the model in the INP format, e.g. after editing its puppet.
+    ///
+    /// Loading the written file gives back an equivalent model, with its textures and vendor data.
+    /// The runtime state of the puppet, like its current parameter values, is not saved.
+    /// Nodes of unknown types are saved as plain nodes: see `write_inp_ext` for custom node data.
+    pub fn write_inp<W: Write>(&self, w: &mut W) -> Result<(), WriteInpError> {
+        write_inp(self, w)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{part_json, puppet_json};
+
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let mask = r#"{ "source": 7, "mode": "Mask" }"#;
+        let mut part = json::parse(&part_json(2, "0, 0, 1, 0, 0, 1", 1.0, mask)).unwrap();
+        part["textures"] = json::array![1, 4294967295_u32, 4294967295_u32];
+        let children = format!(
+            r#"{{
+                "uuid": 2, "name": "Composite", "type": "Composite", "enabled": true, "zsort": 0,
+                "lockToRoot": false,
+                "transform": {{ "trans": [0, 0, 0], "rot": [0, 0, 0], "scale": [1, 1] }},
+                "blend_mode": "Normal", "tint": [1, 1, 1], "screenTint": [0, 0, 0],
+                "mask_threshold": 0.5, "opacity": 1,
+                "children": [{}]
+            }}"#,
+            part.dump()
+        );
+        let mut model = Model::from_bytes(puppet_json(&children).as_bytes()).unwrap();
+        model.textures.push(ModelTexture {
+            format: image::ImageFormat::Png,
+            data: Vec::new(),
+        });
+        for node in model.puppet.nodes.arena.iter_mut() {
+            if let InoxData::Part(ref mut part) = node.get_mut().data {
+                part.mesh.indices.pop();
+            }
+        }
+
+        assert_eq!(
+            model.validate(),
+            [
+                ValidationIssue::DuplicateUuid {
+                    uuid: InoxNodeUuid(2),
+                    count: 2
+                },
+                ValidationIssue::CompositeContainsItself {
+                    composite: InoxNodeUuid(2)
+                },
+                ValidationIssue::TextureOutOfRange {
+                    part: InoxNodeUuid(2),
+                    slot: "albedo",
+                    index: 1,
+                    count: 1
+                },
+                ValidationIssue::InvalidMesh {
+                    node: InoxNodeUuid(2),
+                    error: MeshError::NotTriangles(2)
+                },
+                ValidationIssue::MissingMaskSource {
+                    node: InoxNodeUuid(2),
+                    index: 0,
+                    mask_source: InoxNodeUuid(7)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_out_of_range_indices_fail_to_load() {
+        let part = |indices: &str| {
+            let mut part = json::parse(&part_json(2, "0, 0, 1, 0, 0, 1, 1, 1", 1.0, "")).unwrap();
+            part["mesh"]["indices"] = json::parse(&format!("[{indices}]")).unwrap();
+            part.dump()
+        };
+        assert!(Model::from_bytes(puppet_json(&part("0, 1, 2, 1, 3, 2")).as_bytes()).is_ok());
+
+        // Indices that aren't u16 are errors, instead of cutting the mesh short
+        for indices in [
+            "0, 1, 2, 65539, 3, 2",
+            "0, 1, 2, -1, 3, 2",
+            "0, 1, 2, \"1\", 3, 2",
+        ] {
+            let err = Model::from_bytes(puppet_json(&part(indices)).as_bytes()).unwrap_err();
+            assert!(
+                matches!(err, LoadModelError::InoxParse(InoxParseError::JsonError(_))),
+                "{indices}: {err}"
+            );
+        }
+    }
+}
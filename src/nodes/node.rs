@@ -8,7 +8,7 @@ use super::node_data::InoxData;
 #[repr(transparent)]
 pub struct InoxNodeUuid(pub(crate) u32);
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct InoxNode<T = ()> {
     pub uuid: InoxNodeUuid,
     pub name: String,
@@ -17,6 +17,9 @@ pub struct InoxNode<T = ()> {
     pub trans_offset: TransformOffset,
     pub lock_to_root: bool,
     pub data: InoxData<T>,
+    /// JSON fields of the node that aren't understood, like extension data from other tools.
+    /// They are written back when saving the model.
+    pub extra: json::object::Object,
 }
 
 impl<T> InoxNode<T> {
@@ -36,6 +39,10 @@ impl<T> InoxNode<T> {
         self.data.is_simple_physics()
     }
 
+    pub fn is_mesh_group(&self) -> bool {
+        self.data.is_mesh_group()
+    }
+
     pub fn is_custom(&self) -> bool {
         self.data.is_custom()
     }
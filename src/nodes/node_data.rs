@@ -32,10 +32,30 @@ pub enum BlendMode {
     /// via a lower rendered area.
     /// (Basically inverse ClipToLower.)
     SliceFromLower,
+    /// Overlay.
+    Overlay,
+    /// Soft Light.
+    SoftLight,
+    /// Hard Light.
+    HardLight,
+    /// Darken.
+    Darken,
+    /// Lighten.
+    Lighten,
+    /// Color Burn.
+    ColorBurn,
+    /// Linear Burn.
+    LinearBurn,
+    /// Subtract.
+    Subtract,
+    /// Difference.
+    Difference,
+    /// Exclusion.
+    Exclusion,
 }
 
 impl BlendMode {
-    pub const VALUES: [BlendMode; 7] = [
+    pub const VALUES: [BlendMode; 17] = [
         BlendMode::Normal,
         BlendMode::Multiply,
         BlendMode::ColorDodge,
@@ -43,7 +63,32 @@ impl BlendMode {
         BlendMode::Screen,
         BlendMode::ClipToLower,
         BlendMode::SliceFromLower,
+        BlendMode::Overlay,
+        BlendMode::SoftLight,
+        BlendMode::HardLight,
+        BlendMode::Darken,
+        BlendMode::Lighten,
+        BlendMode::ColorBurn,
+        BlendMode::LinearBurn,
+        BlendMode::Subtract,
+        BlendMode::Difference,
+        BlendMode::Exclusion,
     ];
+
+    /// Whether the blend mode can't be expressed with fixed-function blending,
+    /// and needs a shader reading what was drawn below instead.
+    pub fn needs_backdrop(self) -> bool {
+        matches!(
+            self,
+            BlendMode::Overlay
+                | BlendMode::SoftLight
+                | BlendMode::HardLight
+                | BlendMode::Darken
+                | BlendMode::ColorBurn
+                | BlendMode::LinearBurn
+                | BlendMode::Difference
+        )
+    }
 }
 
 #[derive(Debug, Clone, thiserror::Error)]
@@ -62,6 +107,16 @@ impl TryFrom<&str> for BlendMode {
             "Screen" => Ok(BlendMode::Screen),
             "ClipToLower" => Ok(BlendMode::ClipToLower),
             "SliceFromLower" => Ok(BlendMode::SliceFromLower),
+            "Overlay" => Ok(BlendMode::Overlay),
+            "SoftLight" => Ok(BlendMode::SoftLight),
+            "HardLight" => Ok(BlendMode::HardLight),
+            "Darken" => Ok(BlendMode::Darken),
+            "Lighten" => Ok(BlendMode::Lighten),
+            "ColorBurn" => Ok(BlendMode::ColorBurn),
+            "LinearBurn" => Ok(BlendMode::LinearBurn),
+            "Subtract" => Ok(BlendMode::Subtract),
+            "Difference" => Ok(BlendMode::Difference),
+            "Exclusion" => Ok(BlendMode::Exclusion),
             unknown => Err(UnknownBlendModeError(unknown.to_owned())),
         }
     }
@@ -124,8 +179,24 @@ pub struct Part {
     pub draw_state: Drawable,
     pub mesh: Mesh,
     pub tex_albedo: usize,
-    pub tex_emissive: usize,
-    pub tex_bumpmap: usize,
+    /// Emissive map, making the part glow with bloom. `None` if the part doesn't emit light.
+    pub tex_emissive: Option<usize>,
+    /// Bump map, `None` if the part has none.
+    pub tex_bumpmap: Option<usize>,
+}
+
+/// Node that deforms the parts below it with its own mesh.
+///
+/// The mesh isn't drawn: deforming it, e.g. through parameters, deforms the parts instead.
+#[derive(Debug, Clone)]
+pub struct MeshGroup {
+    pub mesh: Mesh,
+    /// Whether the weights of the parts' vertices should be recomputed every frame.
+    /// Not supported yet, they are computed once in the rest pose.
+    pub dynamic_deformation: bool,
+    /// Whether the transforms of the children should follow the deformation too.
+    /// Not supported yet, only the parts' vertices are deformed.
+    pub translate_children: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -134,6 +205,7 @@ pub enum InoxData<T> {
     Part(Part),
     Composite(Composite),
     SimplePhysics(SimplePhysics),
+    MeshGroup(MeshGroup),
     Custom(T),
 }
 
@@ -154,6 +226,10 @@ impl<T> InoxData<T> {
         matches!(self, InoxData::SimplePhysics(_))
     }
 
+    pub fn is_mesh_group(&self) -> bool {
+        matches!(self, InoxData::MeshGroup(_))
+    }
+
     pub fn is_custom(&self) -> bool {
         matches!(self, InoxData::Custom(_))
     }
@@ -164,6 +240,7 @@ impl<T> InoxData<T> {
             InoxData::Part(_) => "Part",
             InoxData::Composite(_) => "Composite",
             InoxData::SimplePhysics(_) => "SimplePhysics",
+            InoxData::MeshGroup(_) => "MeshGroup",
             InoxData::Custom(_) => "Custom",
         }
     }
@@ -1,18 +1,180 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Display;
 
+use glam::Mat4;
 use indextree::{Arena, NodeId};
 
+use crate::math::aabb::Aabb;
+
 use super::node::{InoxNode, InoxNodeUuid};
+use super::node_data::InoxData;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct InoxNodeTree<T = ()> {
     pub root: indextree::NodeId,
     pub arena: Arena<InoxNode<T>>,
     pub uuids: BTreeMap<InoxNodeUuid, indextree::NodeId>,
+    /// Uuids of the nodes with each name, in depth-first order. See `find_by_name`.
+    pub names: HashMap<String, Vec<InoxNodeUuid>>,
 }
 
 impl<T> InoxNodeTree<T> {
+    /// Creates a tree of the nodes of `arena` descending from `root`, indexing their uuids and names.
+    pub fn new(root: NodeId, arena: Arena<InoxNode<T>>) -> Self {
+        let uuids = (root.descendants(&arena))
+            .map(|id| (arena[id].get().uuid, id))
+            .collect();
+        let mut tree = Self {
+            root,
+            arena,
+            uuids,
+            names: HashMap::new(),
+        };
+        tree.rebuild_name_index();
+        tree
+    }
+
+    /// Indexes the nodes by name again, e.g. after renaming nodes through `get_node_mut`.
+    /// `rename_node` keeps the index up to date on its own.
+    pub fn rebuild_name_index(&mut self) {
+        self.names.clear();
+        for id in self.root.descendants(&self.arena) {
+            let node = self.arena[id].get();
+            self.names
+                .entry(node.name.clone())
+                .or_default()
+                .push(node.uuid);
+        }
+    }
+
+    /// Gets the node named `name`. If several nodes have that name, the first one
+    /// in depth-first order is returned, see `find_all_by_name` and `find_by_name_under`.
+    pub fn find_by_name(&self, name: &str) -> Option<InoxNodeUuid> {
+        self.names.get(name)?.first().copied()
+    }
+
+    /// Gets all the nodes named `name`, in depth-first order, as names aren't unique.
+    pub fn find_all_by_name(&self, name: &str) -> Vec<InoxNodeUuid> {
+        self.names.get(name).cloned().unwrap_or_default()
+    }
+
+    /// Gets the first node named `name` among the descendants of `ancestor`,
+    /// to tell apart nodes with the same name, like the "Eye" of the "Left" and "Right" groups.
+    pub fn find_by_name_under(&self, ancestor: InoxNodeUuid, name: &str) -> Option<InoxNodeUuid> {
+        let ancestor_id = *self.uuids.get(&ancestor)?;
+        self.names.get(name)?.iter().copied().find(|uuid| {
+            uuid != &ancestor
+                && self.uuids[uuid]
+                    .ancestors(&self.arena)
+                    .any(|id| id == ancestor_id)
+        })
+    }
+
+    /// Renames a node, keeping the name index up to date.
+    /// Returns false if there is no node `uuid`.
+    pub fn rename_node(&mut self, uuid: InoxNodeUuid, name: &str) -> bool {
+        let Some(node) = self.get_node_mut(uuid) else {
+            return false;
+        };
+        let old_name = std::mem::replace(&mut node.name, name.to_owned());
+
+        if let Some(uuids) = self.names.get_mut(&old_name) {
+            uuids.retain(|&other| other != uuid);
+            if uuids.is_empty() {
+                self.names.remove(&old_name);
+            }
+        }
+
+        // Keep the depth-first order, which other nodes with the same name may come before
+        let order = |uuid: &InoxNodeUuid| {
+            let id = self.uuids[uuid];
+            self.root
+                .descendants(&self.arena)
+                .position(|other| other == id)
+        };
+        let mut uuids = self.names.remove(name).unwrap_or_default();
+        uuids.push(uuid);
+        uuids.sort_by_cached_key(order);
+        self.names.insert(name.to_owned(), uuids);
+        true
+    }
+
+    /// Adds a node as the last child of `parent`, and returns the uuid it was given.
+    ///
+    /// The node's own uuid is replaced by one that no other node of the tree has.
+    /// Returns None if there is no node `parent`.
+    pub fn add_node(
+        &mut self,
+        parent: InoxNodeUuid,
+        mut node: InoxNode<T>,
+    ) -> Option<InoxNodeUuid> {
+        let parent_id = *self.uuids.get(&parent)?;
+        let uuid = self.unused_uuid();
+        node.uuid = uuid;
+
+        let id = self.arena.new_node(node);
+        parent_id.append(id, &mut self.arena);
+        self.uuids.insert(uuid, id);
+        self.rebuild_name_index();
+        Some(uuid)
+    }
+
+    /// Removes a node along with its descendants.
+    /// Returns false if there is no node `uuid`, or if it is the root.
+    pub fn remove_node(&mut self, uuid: InoxNodeUuid) -> bool {
+        let Some(&id) = self.uuids.get(&uuid) else {
+            return false;
+        };
+        if id == self.root {
+            return false;
+        }
+
+        for descendant in id.descendants(&self.arena) {
+            self.uuids.remove(&self.arena[descendant].get().uuid);
+        }
+        id.remove_subtree(&mut self.arena);
+
+        let uuids = &self.uuids;
+        self.names.retain(|_, named| {
+            named.retain(|uuid| uuids.contains_key(uuid));
+            !named.is_empty()
+        });
+        true
+    }
+
+    /// Moves a node along with its descendants to be the last child of `new_parent`.
+    ///
+    /// Returns false if either node doesn't exist, if the node is the root,
+    /// or if `new_parent` is the node itself or one of its descendants.
+    pub fn reparent(&mut self, uuid: InoxNodeUuid, new_parent: InoxNodeUuid) -> bool {
+        let (Some(&id), Some(&parent_id)) = (self.uuids.get(&uuid), self.uuids.get(&new_parent))
+        else {
+            return false;
+        };
+        if id == self.root || parent_id.ancestors(&self.arena).any(|other| other == id) {
+            return false;
+        }
+
+        id.detach(&mut self.arena);
+        parent_id.append(id, &mut self.arena);
+        // Nodes with the same name may now come in a different depth-first order
+        self.rebuild_name_index();
+        true
+    }
+
+    /// Finds a uuid that no node has, preferably after all the existing ones
+    /// so that the uuids of removed nodes aren't given out again.
+    fn unused_uuid(&self) -> InoxNodeUuid {
+        let last = self.uuids.keys().next_back().map_or(0, |uuid| uuid.0);
+        match last.checked_add(1) {
+            Some(next) => InoxNodeUuid(next),
+            None => (0..)
+                .map(InoxNodeUuid)
+                .find(|uuid| !self.uuids.contains_key(uuid))
+                .expect("a tree can't have 2^32 nodes"),
+        }
+    }
+
     fn get_internal_node(&self, uuid: InoxNodeUuid) -> Option<&indextree::Node<InoxNode<T>>> {
         self.arena.get(*self.uuids.get(&uuid)?)
     }
@@ -50,49 +212,251 @@ impl<T> InoxNodeTree<T> {
         )
     }
 
-    fn rec_all_childen_from_node(
+    /// Pushes a node and its descendants along with their absolute zsort,
+    /// which adds up the zsort of their ancestors and the offsets given by `zsort_offset`.
+    fn push_zsorts(
         &self,
-        node: &InoxNode<T>,
+        id: NodeId,
         zsort: f32,
         skip_composites: bool,
-    ) -> Vec<(InoxNodeUuid, f32)> {
-        let node_state = node;
-        let zsort = zsort + node_state.zsort;
-        let mut vec = vec![(node_state.uuid, zsort)];
+        zsort_offset: &dyn Fn(InoxNodeUuid) -> f32,
+        zsorts: &mut Vec<(InoxNodeUuid, f32)>,
+    ) {
+        let node = self.arena[id].get();
+        let zsort = zsort + node.zsort + zsort_offset(node.uuid);
+        zsorts.push((node.uuid, zsort));
 
         // Skip composite children because they're a special case
         if !skip_composites || !node.data.is_composite() {
-            for child_uuid in self.children_uuids(node.uuid).unwrap_or_default() {
-                if let Some(child) = self.get_node(child_uuid) {
-                    vec.extend(self.rec_all_childen_from_node(child, zsort, skip_composites));
-                }
+            for child in id.children(&self.arena) {
+                self.push_zsorts(child, zsort, skip_composites, zsort_offset, zsorts);
             }
         }
+    }
+
+    /// Gets the uuids of a node's ancestors, from its parent up to the root.
+    /// Empty for the root and for unknown nodes.
+    pub fn ancestors(&self, uuid: InoxNodeUuid) -> impl Iterator<Item = InoxNodeUuid> + '_ {
+        self.traverse(uuid, |id, arena| id.ancestors(arena).skip(1))
+    }
+
+    /// Gets the uuids of a node's children, in order. Empty for unknown nodes.
+    pub fn children(&self, uuid: InoxNodeUuid) -> impl Iterator<Item = InoxNodeUuid> + '_ {
+        self.traverse(uuid, |id, arena| id.children(arena))
+    }
 
-        vec
+    /// Gets the uuids of a node's descendants, depth-first, without the node itself.
+    /// Empty for unknown nodes.
+    pub fn descendants(&self, uuid: InoxNodeUuid) -> impl Iterator<Item = InoxNodeUuid> + '_ {
+        self.traverse(uuid, |id, arena| id.descendants(arena).skip(1))
     }
 
-    pub fn ancestors(&self, uuid: InoxNodeUuid) -> indextree::Ancestors<InoxNode<T>> {
-        self.uuids[&uuid].ancestors(&self.arena)
+    /// Gets the uuids of the other children of a node's parent, in order.
+    /// Empty for the root and for unknown nodes.
+    pub fn siblings(&self, uuid: InoxNodeUuid) -> impl Iterator<Item = InoxNodeUuid> + '_ {
+        let parent = self
+            .uuids
+            .get(&uuid)
+            .and_then(|id| self.arena[*id].parent());
+        (parent.into_iter())
+            .flat_map(|parent| parent.children(&self.arena))
+            .map(|id| self.arena[id].get().uuid)
+            .filter(move |&sibling| sibling != uuid)
     }
 
-    fn sort_by_zsort(&self, node: &InoxNode<T>, skip_composites: bool) -> Vec<InoxNodeUuid> {
-        let uuid_zsorts = self.rec_all_childen_from_node(node, 0.0, skip_composites);
+    /// Maps the node ids that `ids` walks from the node `uuid` to uuids, lazily.
+    fn traverse<'a, I: Iterator<Item = NodeId> + 'a>(
+        &'a self,
+        uuid: InoxNodeUuid,
+        ids: impl FnOnce(NodeId, &'a Arena<InoxNode<T>>) -> I,
+    ) -> impl Iterator<Item = InoxNodeUuid> + 'a {
+        (self
+            .uuids
+            .get(&uuid)
+            .map(|&id| ids(id, &self.arena))
+            .into_iter())
+        .flatten()
+        .map(|id| self.arena[id].get().uuid)
+    }
+
+    fn sort_by_zsort(&self, id: NodeId, skip_composites: bool) -> Vec<InoxNodeUuid> {
+        let mut uuid_zsorts = Vec::new();
+        self.push_zsorts(id, 0.0, skip_composites, &|_| 0.0, &mut uuid_zsorts);
         sort_uuids_by_zsort(uuid_zsorts)
     }
 
+    /// Gets the nodes in the order they are drawn, sorted by zsort.
+    ///
+    /// The descendants of composites are not included, as composites draw them themselves.
     pub fn zsorted_root(&self) -> Vec<InoxNodeUuid> {
-        let root = self.arena.get(self.root).unwrap().get();
-        self.sort_by_zsort(root, true)
+        self.zsorted_root_with_offsets(|_| 0.0)
+    }
+
+    /// Like `zsorted_root`, with the zsort of each node offset by `zsort_offset`,
+    /// e.g. by parameter bindings.
+    ///
+    /// This visits every node outside of composites and sorts them, without other allocations
+    /// than the returned list, so it is cheap enough to call whenever zsorts change.
+    pub fn zsorted_root_with_offsets(
+        &self,
+        zsort_offset: impl Fn(InoxNodeUuid) -> f32,
+    ) -> Vec<InoxNodeUuid> {
+        let mut uuid_zsorts = Vec::with_capacity(self.uuids.len());
+        self.push_zsorts(self.root, 0.0, true, &zsort_offset, &mut uuid_zsorts);
+        sort_uuids_by_zsort(uuid_zsorts)
+    }
+
+    /// Gets the absolute zsort of every node, which adds up the zsorts of its ancestors
+    /// and the offsets given by `zsort_offset`, in no particular order.
+    pub fn absolute_zsorts_with_offsets(
+        &self,
+        zsort_offset: impl Fn(InoxNodeUuid) -> f32,
+    ) -> Vec<(InoxNodeUuid, f32)> {
+        let mut uuid_zsorts = Vec::with_capacity(self.uuids.len());
+        self.push_zsorts(self.root, 0.0, false, &zsort_offset, &mut uuid_zsorts);
+        uuid_zsorts
+    }
+
+    /// Gets a node and its descendants in the order they are drawn, sorted by zsort.
+    ///
+    /// Like `zsorted_root`, the descendants of composites are not included,
+    /// as composites draw them themselves.
+    pub fn zsorted_subtree(&self, id: InoxNodeUuid) -> Vec<InoxNodeUuid> {
+        let Some(&node_id) = self.uuids.get(&id) else {
+            return Vec::new();
+        };
+        self.sort_by_zsort(node_id, true)
     }
 
     pub fn zsorted_children(&self, id: InoxNodeUuid) -> Vec<InoxNodeUuid> {
-        let node = self.arena.get(self.uuids[&id]).unwrap().get();
-        self.sort_by_zsort(node, false)
+        self.sort_by_zsort(self.uuids[&id], false)
+    }
+
+    /// Gets the nodes a composite draws, sorted by zsort.
+    ///
+    /// These are the composite's descendants, except for the descendants of nested composites,
+    /// which draw them themselves. The composite itself is not included.
+    pub fn zsorted_composite_children(&self, id: InoxNodeUuid) -> Vec<InoxNodeUuid> {
+        self.zsorted_composite_children_with_offsets(id, |_| 0.0)
+    }
+
+    /// Like `zsorted_composite_children`, with the zsort of each node offset by `zsort_offset`.
+    pub fn zsorted_composite_children_with_offsets(
+        &self,
+        id: InoxNodeUuid,
+        zsort_offset: impl Fn(InoxNodeUuid) -> f32,
+    ) -> Vec<InoxNodeUuid> {
+        let Some(&node_id) = self.uuids.get(&id) else {
+            return Vec::new();
+        };
+        let zsort = self.arena[node_id].get().zsort + zsort_offset(id);
+
+        let mut uuid_zsorts = Vec::new();
+        for child in node_id.children(&self.arena) {
+            self.push_zsorts(child, zsort, true, &zsort_offset, &mut uuid_zsorts);
+        }
+        sort_uuids_by_zsort(uuid_zsorts)
     }
 
     pub fn all_node_ids(&self) -> Vec<InoxNodeUuid> {
-        self.arena.iter().map(|n| n.get().uuid).collect()
+        // The arena keeps the slots of removed nodes
+        (self.arena.iter())
+            .filter(|n| !n.is_removed())
+            .map(|n| n.get().uuid)
+            .collect()
+    }
+
+    /// Whether a node is shown, which requires it and all of its ancestors to be enabled.
+    ///
+    /// Like in Inochi2D, disabling a node hides its whole subtree.
+    pub fn is_shown(&self, uuid: InoxNodeUuid) -> bool {
+        let Some(&node_id) = self.uuids.get(&uuid) else {
+            return false;
+        };
+        node_id
+            .ancestors(&self.arena)
+            .all(|id| self.arena[id].get().enabled)
+    }
+
+    /// Gets the absolute transform of a node in the model's rest pose,
+    /// combining the transform offsets of its ancestors like `Puppet::update_trans` does.
+    pub fn rest_trans(&self, uuid: InoxNodeUuid) -> Option<Mat4> {
+        let node_id = *self.uuids.get(&uuid)?;
+        let node = self.arena.get(node_id)?.get();
+        let trans = node.trans_offset.to_matrix();
+
+        if node_id == self.root {
+            return Some(trans);
+        }
+
+        let parent_id = if node.lock_to_root {
+            self.root
+        } else {
+            self.arena.get(node_id)?.parent()?
+        };
+        let parent_uuid = self.arena.get(parent_id)?.get().uuid;
+        Some(self.rest_trans(parent_uuid)? * trans)
+    }
+
+    /// Gets the absolute transforms of all nodes in the model's rest pose, in a single top-down pass.
+    ///
+    /// This gives the same transforms as calling `rest_trans` on every node,
+    /// without walking up the ancestors of each of them.
+    pub fn rest_transforms(&self) -> HashMap<InoxNodeUuid, Mat4> {
+        let mut transforms = HashMap::with_capacity(self.uuids.len());
+        let root_trans = self.arena[self.root].get().trans_offset.to_matrix();
+
+        // Pre-order traversal, so that parents are computed before their children
+        for id in self.root.descendants(&self.arena) {
+            let node = self.arena[id].get();
+            let parent_trans = match self.arena[id].parent() {
+                Some(_) if node.lock_to_root => root_trans,
+                Some(parent_id) => transforms[&self.arena[parent_id].get().uuid],
+                None => Mat4::IDENTITY,
+            };
+            transforms.insert(node.uuid, parent_trans * node.trans_offset.to_matrix());
+        }
+
+        transforms
+    }
+
+    /// Gets the bounding box of a node in model space, in the model's rest pose.
+    ///
+    /// Parts are bounded by their mesh, and composites by the union of the nodes they draw.
+    /// Other nodes, and composites without parts, have no bounds.
+    pub fn node_bounds(&self, uuid: InoxNodeUuid) -> Option<Aabb> {
+        self.node_bounds_with(uuid, &|uuid| self.rest_trans(uuid))
+    }
+
+    fn node_bounds_with(
+        &self,
+        uuid: InoxNodeUuid,
+        rest_trans: &impl Fn(InoxNodeUuid) -> Option<Mat4>,
+    ) -> Option<Aabb> {
+        match &self.get_node(uuid)?.data {
+            InoxData::Part(part) => Some(part.mesh.bounds()?.transformed(rest_trans(uuid)?)),
+            InoxData::Composite(_) => self
+                .zsorted_composite_children(uuid)
+                .into_iter()
+                .filter_map(|child| self.node_bounds_with(child, rest_trans))
+                .reduce(|a, b| a.union(&b)),
+            _ => None,
+        }
+    }
+
+    /// Gets the bounding box of all the model's parts in model space, in the model's rest pose.
+    ///
+    /// Useful to frame the whole model with the camera. Returns `None` if the model has no parts.
+    pub fn model_bounds(&self) -> Option<Aabb> {
+        let transforms = self.rest_transforms();
+        self.arena
+            .iter()
+            .map(|n| n.get())
+            .filter(|node| node.is_part())
+            .filter_map(|node| {
+                self.node_bounds_with(node.uuid, &|uuid| transforms.get(&uuid).copied())
+            })
+            .reduce(|a, b| a.union(&b))
     }
 }
 
@@ -151,3 +515,213 @@ fn sort_uuids_by_zsort(mut uuid_zsorts: Vec<(InoxNodeUuid, f32)>) -> Vec<InoxNod
     uuid_zsorts.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap().reverse());
     uuid_zsorts.into_iter().map(|(uuid, _zsort)| uuid).collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use glam::{vec2, vec3, Vec3};
+
+    use crate::math::transform::TransformOffset;
+    use crate::mesh::Mesh;
+    use crate::nodes::node_data::{BlendMode, Drawable, Part};
+
+    use super::*;
+
+    fn node(uuid: u32, data: InoxData<()>) -> InoxNode {
+        InoxNode {
+            uuid: InoxNodeUuid(uuid),
+            name: String::new(),
+            enabled: true,
+            zsort: 0.0,
+            trans_offset: TransformOffset::default(),
+            lock_to_root: false,
+            data,
+            extra: json::object::Object::new(),
+        }
+    }
+
+    #[test]
+    fn test_zsort_offsets_reorder_nodes() {
+        let mut arena = Arena::new();
+        let root = arena.new_node(node(0, InoxData::Node));
+        let front = arena.new_node(node(1, InoxData::Node));
+        let back = arena.new_node(node(2, InoxData::Node));
+        root.append(front, &mut arena);
+        root.append(back, &mut arena);
+        arena[back].get_mut().zsort = 1.0;
+
+        let tree = InoxNodeTree::new(root, arena);
+
+        // Higher zsorts are further back, so they are drawn first
+        let order = |uuids: &[u32]| {
+            uuids
+                .iter()
+                .map(|&uuid| InoxNodeUuid(uuid))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(tree.zsorted_root(), order(&[2, 0, 1]));
+
+        let offsets = |uuid| if uuid == InoxNodeUuid(1) { 2.0 } else { 0.0 };
+        assert_eq!(tree.zsorted_root_with_offsets(offsets), order(&[1, 2, 0]));
+    }
+
+    #[test]
+    fn test_find_nodes_by_name() {
+        let named = |uuid, name: &str| InoxNode {
+            name: name.to_owned(),
+            ..node(uuid, InoxData::Node)
+        };
+
+        let mut arena = Arena::new();
+        let root = arena.new_node(named(0, "Root"));
+        for (group_uuid, group_name) in [(1, "Left"), (3, "Right")] {
+            let group = arena.new_node(named(group_uuid, group_name));
+            let eye = arena.new_node(named(group_uuid + 1, "Eye"));
+            root.append(group, &mut arena);
+            group.append(eye, &mut arena);
+        }
+        let mut tree = InoxNodeTree::new(root, arena);
+
+        let (left_eye, right_eye) = (InoxNodeUuid(2), InoxNodeUuid(4));
+        assert_eq!(tree.find_by_name("Eye"), Some(left_eye));
+        assert_eq!(tree.find_all_by_name("Eye"), vec![left_eye, right_eye]);
+        assert_eq!(
+            tree.find_by_name_under(InoxNodeUuid(3), "Eye"),
+            Some(right_eye)
+        );
+        assert_eq!(tree.find_by_name("Nose"), None);
+
+        assert!(tree.rename_node(left_eye, "Eye_L"));
+        assert_eq!(tree.find_by_name("Eye"), Some(right_eye));
+        assert_eq!(tree.find_by_name("Eye_L"), Some(left_eye));
+
+        assert!(tree.rename_node(left_eye, "Eye"));
+        assert_eq!(tree.find_all_by_name("Eye"), vec![left_eye, right_eye]);
+    }
+
+    #[test]
+    fn test_tree_iterators() {
+        // 0 -> (1 -> (2, 3), 4)
+        let mut arena = Arena::new();
+        let ids: Vec<_> = (0..5)
+            .map(|uuid| arena.new_node(node(uuid, InoxData::Node)))
+            .collect();
+        for (parent, child) in [(0, 1), (1, 2), (1, 3), (0, 4)] {
+            ids[parent].append(ids[child], &mut arena);
+        }
+        let tree = InoxNodeTree::new(ids[0], arena);
+
+        let uuids = |iter: &mut dyn Iterator<Item = InoxNodeUuid>| {
+            iter.map(|uuid| uuid.0).collect::<Vec<_>>()
+        };
+        assert_eq!(uuids(&mut tree.children(InoxNodeUuid(1))), [2, 3]);
+        assert_eq!(uuids(&mut tree.descendants(InoxNodeUuid(0))), [1, 2, 3, 4]);
+        assert_eq!(uuids(&mut tree.ancestors(InoxNodeUuid(3))), [1, 0]);
+        assert_eq!(uuids(&mut tree.siblings(InoxNodeUuid(3))), [2]);
+        assert_eq!(uuids(&mut tree.siblings(InoxNodeUuid(0))), [0; 0]);
+        assert_eq!(uuids(&mut tree.children(InoxNodeUuid(42))), [0; 0]);
+    }
+
+    #[test]
+    fn test_edit_tree_structure() {
+        let named = |uuid, name: &str| InoxNode {
+            name: name.to_owned(),
+            ..node(uuid, InoxData::Node)
+        };
+
+        let mut arena = Arena::new();
+        let root = arena.new_node(named(0, "Root"));
+        let head = arena.new_node(named(7, "Head"));
+        root.append(head, &mut arena);
+        let mut tree = InoxNodeTree::new(root, arena);
+        let (root, head) = (InoxNodeUuid(0), InoxNodeUuid(7));
+
+        // New nodes get a uuid no other node has, whatever they had
+        let hat = tree.add_node(head, named(0, "Hat")).unwrap();
+        assert_eq!(hat, InoxNodeUuid(8));
+        assert_eq!(tree.find_by_name("Hat"), Some(hat));
+        assert_eq!(tree.add_node(InoxNodeUuid(42), named(0, "Hat")), None);
+
+        // A node can't be moved under itself
+        assert!(!tree.reparent(head, hat));
+        assert!(tree.reparent(hat, root));
+        assert_eq!(tree.children(root).collect::<Vec<_>>(), [head, hat]);
+
+        assert!(tree.reparent(hat, head));
+        assert!(!tree.remove_node(root));
+        assert!(tree.remove_node(head));
+        assert_eq!(tree.all_node_ids(), [root]);
+        assert_eq!(tree.find_by_name("Hat"), None);
+        assert!(tree.get_node(hat).is_none());
+    }
+
+    #[test]
+    fn test_disabled_nodes_hide_their_subtree() {
+        let part = Part {
+            draw_state: Drawable {
+                blend_mode: BlendMode::Normal,
+                tint: Vec3::ONE,
+                screen_tint: Vec3::ZERO,
+                mask_threshold: 0.5,
+                masks: Vec::new(),
+                opacity: 1.0,
+            },
+            mesh: Mesh::default(),
+            tex_albedo: 0,
+            tex_emissive: None,
+            tex_bumpmap: None,
+        };
+
+        let mut arena = Arena::new();
+        let root = arena.new_node(node(0, InoxData::Node));
+        let group = arena.new_node(node(1, InoxData::Node));
+        let part = arena.new_node(node(2, InoxData::Part(part)));
+        root.append(group, &mut arena);
+        group.append(part, &mut arena);
+
+        let mut tree = InoxNodeTree::new(root, arena);
+
+        let drawn = |tree: &InoxNodeTree| {
+            (tree.zsorted_root().into_iter())
+                .filter(|&uuid| tree.is_shown(uuid))
+                .collect::<Vec<_>>()
+        };
+        assert!(drawn(&tree).contains(&InoxNodeUuid(2)));
+
+        tree.get_node_mut(InoxNodeUuid(2)).unwrap().enabled = false;
+        assert!(!drawn(&tree).contains(&InoxNodeUuid(2)));
+
+        tree.get_node_mut(InoxNodeUuid(2)).unwrap().enabled = true;
+        tree.get_node_mut(InoxNodeUuid(1)).unwrap().enabled = false;
+        assert_eq!(drawn(&tree), vec![InoxNodeUuid(0)]);
+    }
+
+    #[test]
+    fn test_rest_transforms_include_ancestor_rotation_and_scale() {
+        let mut arena = Arena::new();
+        let mut parent = node(1, InoxData::Node);
+        parent.trans_offset = TransformOffset::default()
+            .with_rotation(vec3(0.0, 0.0, FRAC_PI_2))
+            .with_scale(vec2(2.0, 2.0));
+        let mut child = node(2, InoxData::Node);
+        child.trans_offset = TransformOffset::default().with_translation(vec3(1.0, 0.0, 0.0));
+
+        let root = arena.new_node(node(0, InoxData::Node));
+        let parent = arena.new_node(parent);
+        let child = arena.new_node(child);
+        root.append(parent, &mut arena);
+        parent.append(child, &mut arena);
+
+        let tree = InoxNodeTree::new(root, arena);
+
+        let transforms = tree.rest_transforms();
+        for uuid in tree.all_node_ids() {
+            assert_eq!(Some(transforms[&uuid]), tree.rest_trans(uuid));
+        }
+
+        // The child is moved along the parent's rotated and scaled X axis
+        let position = transforms[&InoxNodeUuid(2)].transform_point3(Vec3::ZERO);
+        assert!(position.distance(vec3(0.0, 2.0, 0.0)) < 1e-5);
+    }
+}
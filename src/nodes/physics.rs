@@ -1,10 +1,66 @@
 use glam::Vec2;
 
+/// Physics model of a SimplePhysics node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PhysicsModel {
+    /// Rigid pendulum.
+    Pendulum,
+    /// Pendulum whose length can stretch like a spring.
+    SpringPendulum,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Unknown physics model {0:?}")]
+pub struct UnknownPhysicsModelError(String);
+
+impl TryFrom<&str> for PhysicsModel {
+    type Error = UnknownPhysicsModelError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "Pendulum" => Ok(PhysicsModel::Pendulum),
+            "SpringPendulum" => Ok(PhysicsModel::SpringPendulum),
+            unknown => Err(UnknownPhysicsModelError(unknown.to_owned())),
+        }
+    }
+}
+
+/// How the output of a SimplePhysics node is mapped onto its parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamMapMode {
+    /// X is the angle of the pendulum, Y is its relative length.
+    AngleLength,
+    /// X and Y are the relative position of the pendulum's bob.
+    XY,
+    /// X is the relative length of the pendulum, Y is its angle.
+    LengthAngle,
+    /// X and Y are the relative position of the pendulum's bob, swapped.
+    YX,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Unknown param map mode {0:?}")]
+pub struct UnknownParamMapModeError(String);
+
+impl TryFrom<&str> for ParamMapMode {
+    type Error = UnknownParamMapModeError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "AngleLength" => Ok(ParamMapMode::AngleLength),
+            "XY" => Ok(ParamMapMode::XY),
+            "LengthAngle" => Ok(ParamMapMode::LengthAngle),
+            "YX" => Ok(ParamMapMode::YX),
+            unknown => Err(UnknownParamMapModeError(unknown.to_owned())),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SimplePhysics {
     pub param: u32,
-    pub model_type: String,
-    pub map_mode: String,
+    pub model_type: PhysicsModel,
+    pub map_mode: ParamMapMode,
     pub gravity: f32,
     pub length: f32,
     pub frequency: f32,
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use glam::{vec2, Vec2};
 
 use crate::math::interp::{
@@ -6,7 +8,7 @@ use crate::math::interp::{
 use crate::math::matrix::Matrix2d;
 use crate::nodes::node::InoxNodeUuid;
 use crate::puppet::Puppet;
-use crate::render::{NodeRenderCtxs, PartRenderCtx, RenderCtxKind};
+use crate::render::NodeRenderCtxs;
 
 /// Parameter binding to a node. This allows to animate a node based on the value of the parameter that owns it.
 #[derive(Debug, Clone)]
@@ -27,6 +29,7 @@ pub enum BindingValues {
     TransformRX(Matrix2d<f32>),
     TransformRY(Matrix2d<f32>),
     TransformRZ(Matrix2d<f32>),
+    Opacity(Matrix2d<f32>),
     Deform(Matrix2d<Vec<Vec2>>),
 }
 
@@ -103,9 +106,17 @@ impl Param {
             );
 
             match binding.values {
-                BindingValues::ZSort(_) => {
-                    // Seems complicated to do currently...
-                    // Do nothing for now
+                BindingValues::ZSort(ref matrix) => {
+                    let (out_top, out_bottom) =
+                        ranges_out(matrix, x_mindex, x_maxdex, y_mindex, y_maxdex);
+
+                    node_offsets.zsort_offset += bi_interpolate_f32(
+                        val_normed,
+                        range_in,
+                        out_top,
+                        out_bottom,
+                        binding.interpolate_mode,
+                    );
                 }
                 BindingValues::TransformTX(ref matrix) => {
                     let (out_top, out_bottom) =
@@ -191,6 +202,18 @@ impl Param {
                         binding.interpolate_mode,
                     );
                 }
+                BindingValues::Opacity(ref matrix) => {
+                    let (out_top, out_bottom) =
+                        ranges_out(matrix, x_mindex, x_maxdex, y_mindex, y_maxdex);
+
+                    node_offsets.opacity *= bi_interpolate_f32(
+                        val_normed,
+                        range_in,
+                        out_top,
+                        out_bottom,
+                        binding.interpolate_mode,
+                    );
+                }
                 BindingValues::Deform(ref matrix) => {
                     let out_top = InterpRange::new(
                         matrix[(x_mindex, y_mindex)].as_slice(),
@@ -201,22 +224,15 @@ impl Param {
                         matrix[(x_maxdex, y_maxdex)].as_slice(),
                     );
 
-                    if let RenderCtxKind::Part(PartRenderCtx {
-                        vert_offset,
-                        vert_len,
-                        ..
-                    }) = node_offsets.kind
-                    {
-                        let def_beg = vert_offset as usize;
-                        let def_end = def_beg + vert_len;
-
+                    // Parts and mesh groups have vertices to deform
+                    if let Some(range) = node_offsets.kind.vertex_range() {
                         bi_interpolate_vec2s_additive(
                             val_normed,
                             range_in,
                             out_top,
                             out_bottom,
                             binding.interpolate_mode,
-                            &mut deform_buf[def_beg..def_end],
+                            &mut deform_buf[range],
                         );
                     }
                 }
@@ -225,19 +241,138 @@ impl Param {
     }
 }
 
+/// Set of parameters of which only one is on at a time, like radio buttons.
+///
+/// This is useful for expression selectors, to prevent conflicting expressions from stacking.
+/// The active member is set to its maximum value and the others to their default value.
+#[derive(Debug, Clone, Default)]
+pub struct ParamGroup {
+    members: Vec<String>,
+    active: Option<usize>,
+}
+
+impl ParamGroup {
+    /// Creates a group of the parameters named `members`, with none of them active.
+    pub fn new(members: Vec<String>) -> Self {
+        Self {
+            members,
+            active: None,
+        }
+    }
+
+    pub fn members(&self) -> &[String] {
+        &self.members
+    }
+
+    /// Gets the name of the active member, if any.
+    pub fn active(&self) -> Option<&str> {
+        self.active.map(|i| self.members[i].as_str())
+    }
+
+    /// Makes `member` the active member, or makes none active if `None`.
+    /// Returns false if `member` is not in the group, leaving it unchanged.
+    pub fn set_active(&mut self, member: Option<&str>) -> bool {
+        match member {
+            Some(member) => match self.members.iter().position(|m| m == member) {
+                Some(i) => {
+                    self.active = Some(i);
+                    true
+                }
+                None => false,
+            },
+            None => {
+                self.active = None;
+                true
+            }
+        }
+    }
+
+    /// Gets the value each member should be set to, skipping unknown parameters.
+    pub fn values<'a>(
+        &'a self,
+        parameters: &'a HashMap<String, Param>,
+    ) -> impl Iterator<Item = (&'a str, Vec2)> + 'a {
+        self.members
+            .iter()
+            .enumerate()
+            .filter_map(move |(i, name)| {
+                let param = parameters.get(name)?;
+                let value = if self.active == Some(i) {
+                    param.max
+                } else {
+                    param.defaults
+                };
+                Some((name.as_str(), value))
+            })
+    }
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum SetExclusiveError {
+    #[error("No parameter group named {0:?}")]
+    UnknownGroup(String),
+    #[error("Parameter {member:?} is not in group {group:?}")]
+    NotAMember { group: String, member: String },
+}
+
 impl Puppet {
+    /// Adds an exclusive group of parameters, or replaces the one with the same name.
+    pub fn add_param_group(&mut self, name: &str, members: Vec<String>) {
+        self.param_groups
+            .insert(name.to_owned(), ParamGroup::new(members));
+    }
+
+    /// Makes `member` the only parameter of `group` that is on.
+    ///
+    /// This only records the choice: the values are applied by `apply_param_groups`.
+    pub fn set_exclusive(&mut self, group: &str, member: &str) -> Result<(), SetExclusiveError> {
+        let param_group = self
+            .param_groups
+            .get_mut(group)
+            .ok_or_else(|| SetExclusiveError::UnknownGroup(group.to_owned()))?;
+
+        if !param_group.set_active(Some(member)) {
+            return Err(SetExclusiveError::NotAMember {
+                group: group.to_owned(),
+                member: member.to_owned(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Sets the parameters of every exclusive group: the active member to its maximum
+    /// and the others to their default.
+    ///
+    /// Call this between `begin_set_params` and `end_set_params`, instead of setting
+    /// the groups' parameters individually.
+    pub fn apply_param_groups(&mut self) {
+        let render_ctx = &mut self.render_ctx;
+        for group in self.param_groups.values() {
+            for (name, value) in group.values(&self.parameters) {
+                self.parameters[name].apply(
+                    value,
+                    &mut render_ctx.node_render_ctxs,
+                    render_ctx.vertex_buffers.deforms.as_mut_slice(),
+                );
+            }
+        }
+    }
+
     pub fn get_param(&self, name: &str) -> Option<&Param> {
         self.parameters.get(name)
     }
 
     pub fn begin_set_params(&mut self) {
-        // Reset all transform and deform offsets before applying bindings
+        // Reset all transform, opacity, zsort and deform offsets before applying bindings
         for (key, value) in self.render_ctx.node_render_ctxs.iter_mut() {
             value.trans_offset = self
                 .nodes
                 .get_node(*key)
                 .expect("node to be in tree")
                 .trans_offset;
+            value.opacity = 1.0;
+            value.zsort_offset = 0.0;
         }
 
         for v in self.render_ctx.vertex_buffers.deforms.iter_mut() {
@@ -258,7 +393,58 @@ impl Puppet {
         );
     }
 
+    /// Applies the parameters set since `begin_set_params`, updating the transforms and deforms,
+    /// and the draw order if a zsort binding changed it.
     pub fn end_set_params(&mut self) {
         self.update_trans();
+        self.render_ctx.apply_mesh_group_deforms();
+        if self.render_ctx.is_zsort_dirty() {
+            self.resort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn param(name: &str) -> Param {
+        Param {
+            uuid: 0,
+            name: name.to_owned(),
+            is_vec2: false,
+            min: Vec2::ZERO,
+            max: Vec2::ONE,
+            defaults: Vec2::ZERO,
+            axis_points: AxisPoints {
+                x: vec![0.0, 1.0],
+                y: vec![0.0, 1.0],
+            },
+            bindings: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_exclusive_member_zeroes_others() {
+        let parameters = ["smile", "angry", "sad"]
+            .into_iter()
+            .map(|name| (name.to_owned(), param(name)))
+            .collect::<HashMap<_, _>>();
+        let mut group = ParamGroup::new(vec!["smile".into(), "angry".into(), "sad".into()]);
+
+        assert!(group.set_active(Some("smile")));
+        assert!(group.set_active(Some("angry")));
+        assert!(!group.set_active(Some("unknown")));
+        assert_eq!(group.active(), Some("angry"));
+
+        let values = group.values(&parameters).collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec![
+                ("smile", Vec2::ZERO),
+                ("angry", Vec2::ONE),
+                ("sad", Vec2::ZERO)
+            ]
+        );
     }
 }
@@ -0,0 +1,277 @@
+pub mod pendulum;
+
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use glam::{vec2, Mat4, Vec2, Vec3};
+
+use crate::nodes::node::InoxNodeUuid;
+use crate::nodes::node_data::InoxData;
+use crate::nodes::physics::{ParamMapMode, PhysicsModel, SimplePhysics};
+use crate::puppet::{Puppet, PuppetPhysics};
+
+use self::pendulum::{Pendulum, SpringPendulum};
+
+/// Physical properties of a SimplePhysics node, scaled by the puppet's global physics parameters.
+#[derive(Debug, Clone, Copy)]
+pub struct PhysicsProps {
+    pub gravity: f32,
+    pub length: f32,
+    pub frequency: f32,
+    pub angle_damping: f32,
+    pub length_damping: f32,
+}
+
+impl PhysicsProps {
+    pub fn new(simple_physics: &SimplePhysics, puppet_physics: &PuppetPhysics) -> Self {
+        Self {
+            gravity: simple_physics.gravity
+                * puppet_physics.gravity
+                * puppet_physics.pixels_per_meter,
+            length: simple_physics.length,
+            frequency: simple_physics.frequency,
+            angle_damping: simple_physics.angle_damping,
+            length_damping: simple_physics.length_damping,
+        }
+    }
+}
+
+/// Longest step the pendulums are advanced by at once, longer updates are split in substeps.
+const MAX_STEP: f32 = 1.0 / 60.0;
+/// Maximum number of substeps in an update, beyond which the rest of the time is dropped.
+const MAX_SUBSTEPS: u32 = 64;
+
+/// Simulation state of a SimplePhysics node, depending on its physics model.
+#[derive(Debug, Clone)]
+pub enum PhysicsState {
+    Pendulum(Pendulum),
+    SpringPendulum(SpringPendulum),
+}
+
+impl PhysicsState {
+    /// Creates a pendulum of the given model at rest, hanging straight down from the anchor.
+    pub fn new(model: PhysicsModel, anchor: Vec2, length: f32) -> Self {
+        match model {
+            PhysicsModel::Pendulum => Self::Pendulum(Pendulum::new(anchor, length)),
+            PhysicsModel::SpringPendulum => {
+                Self::SpringPendulum(SpringPendulum::new(anchor, length))
+            }
+        }
+    }
+
+    pub fn model(&self) -> PhysicsModel {
+        match self {
+            Self::Pendulum(_) => PhysicsModel::Pendulum,
+            Self::SpringPendulum(_) => PhysicsModel::SpringPendulum,
+        }
+    }
+
+    /// World-space position of the bob.
+    pub fn bob(&self) -> Vec2 {
+        match self {
+            Self::Pendulum(pendulum) => pendulum.bob,
+            Self::SpringPendulum(pendulum) => pendulum.bob,
+        }
+    }
+
+    /// Advances the pendulum by `h` seconds, the anchor being at its current world-space position.
+    pub fn tick(&mut self, anchor: Vec2, props: &PhysicsProps, h: f32) {
+        match self {
+            Self::Pendulum(pendulum) => pendulum.tick(anchor, props, h),
+            Self::SpringPendulum(pendulum) => pendulum.tick(anchor, props, h),
+        }
+    }
+}
+
+/// Simulation state of the puppet's SimplePhysics nodes.
+#[derive(Debug)]
+pub struct PhysicsCtx {
+    pub pendulums: HashMap<InoxNodeUuid, PhysicsState>,
+    /// Factor applied to the time given to `update_physics`. See `Puppet::set_time_scale`.
+    pub time_scale: f32,
+}
+
+impl Default for PhysicsCtx {
+    fn default() -> Self {
+        Self {
+            pendulums: HashMap::new(),
+            time_scale: 1.0,
+        }
+    }
+}
+
+impl PhysicsCtx {
+    /// Resets every pendulum, which will start again at rest on the next update.
+    pub fn reset(&mut self) {
+        self.pendulums.clear();
+    }
+}
+
+/// Maps the bob of a pendulum onto the value of the parameter driven by the physics node.
+fn param_value(
+    simple_physics: &SimplePhysics,
+    props: &PhysicsProps,
+    trans: Mat4,
+    anchor: Vec2,
+    bob: Vec2,
+) -> Vec2 {
+    // Translate the angle back to local space, but not the length
+    let local_angle = (trans.inverse())
+        .transform_point3(bob.extend(0.0))
+        .truncate()
+        .normalize_or_zero();
+    let rel_length = bob.distance(anchor) / props.length;
+
+    let position = || {
+        let val = local_angle * rel_length - vec2(0.0, 1.0);
+        // Y goes up for params
+        vec2(val.x, -val.y)
+    };
+    let angle = || (-local_angle.x).atan2(local_angle.y) / PI;
+    let val = match simple_physics.map_mode {
+        ParamMapMode::XY => position(),
+        ParamMapMode::YX => {
+            let val = position();
+            vec2(val.y, val.x)
+        }
+        ParamMapMode::AngleLength => vec2(angle(), rel_length),
+        ParamMapMode::LengthAngle => vec2(rel_length, angle()),
+    };
+
+    val * simple_physics.output_scale
+}
+
+impl Puppet {
+    /// Sets the speed of the simulation clock, multiplying the `dt` given to `update_physics`.
+    ///
+    /// 1 is real time, 0.5 is half-speed slow motion, and 0 or less freezes the physics in place.
+    /// Large scales stay stable, as the scaled time is simulated in steps of at most 1/60 s.
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.physics_ctx.time_scale = time_scale;
+    }
+
+    /// Gets the speed of the simulation clock. See `set_time_scale`.
+    pub fn time_scale(&self) -> f32 {
+        self.physics_ctx.time_scale
+    }
+
+    /// Advances the physics simulation by `dt` seconds and applies its output to the driven parameters.
+    ///
+    /// This must be called between `begin_set_params` and `end_set_params`, after the other parameters are set,
+    /// so that the physics react to the motion they cause (e.g. hair swinging when the head turns).
+    ///
+    /// `dt` is scaled by the time scale, see `set_time_scale`.
+    pub fn update_physics(&mut self, dt: f32) {
+        // Frozen physics still drive their parameters with the last pendulum positions
+        let dt = (dt * self.physics_ctx.time_scale).max(0.0);
+        let substeps = ((dt / MAX_STEP).ceil() as u32).clamp(1, MAX_SUBSTEPS);
+        let h = dt.min(MAX_STEP * MAX_SUBSTEPS as f32) / substeps as f32;
+
+        // Anchors follow the pose given by the parameters set so far
+        self.update_trans();
+
+        for node in (self.nodes.arena.iter())
+            .filter(|n| !n.is_removed())
+            .map(|n| n.get())
+        {
+            let InoxData::SimplePhysics(ref simple_physics) = node.data else {
+                continue;
+            };
+            let Some(node_render_ctx) = self.render_ctx.node_render_ctxs.get(&node.uuid) else {
+                continue;
+            };
+
+            let trans = node_render_ctx.trans;
+            let anchor = trans.transform_point3(Vec3::ZERO).truncate();
+            let props = PhysicsProps::new(simple_physics, &self.physics);
+
+            let model = simple_physics.model_type;
+            let pendulum = (self.physics_ctx.pendulums)
+                .entry(node.uuid)
+                .or_insert_with(|| PhysicsState::new(model, anchor, props.length));
+            if pendulum.model() != model {
+                // The model was changed, e.g. in an editor
+                *pendulum = PhysicsState::new(model, anchor, props.length);
+            }
+            for _ in 0..substeps {
+                pendulum.tick(anchor, &props, h);
+            }
+
+            if props.length <= 0.0 {
+                continue;
+            }
+            let val = param_value(simple_physics, &props, trans, anchor, pendulum.bob());
+
+            let Some(param) = (self.parameters.values()).find(|p| p.uuid == simple_physics.param)
+            else {
+                continue;
+            };
+            param.apply(
+                val,
+                &mut self.render_ctx.node_render_ctxs,
+                self.render_ctx.vertex_buffers.deforms.as_mut_slice(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec3;
+
+    use crate::math::transform::TransformOffset;
+    use crate::model::Model;
+    use crate::testing::puppet_json;
+
+    use super::*;
+
+    /// Positions of the bob of a pendulum after each of `ticks` updates of 1/60 s at `time_scale`,
+    /// once its anchor is moved sideways from the rest position.
+    fn swing(time_scale: f32, ticks: usize) -> Vec<Vec2> {
+        let children = r#"{
+            "uuid": 2, "name": "Physics", "type": "SimplePhysics", "enabled": true, "zsort": 0,
+            "lockToRoot": false,
+            "transform": { "trans": [0, 0, 0], "rot": [0, 0, 0], "scale": [1, 1] },
+            "param": 3, "model_type": "Pendulum", "map_mode": "AngleLength",
+            "gravity": 1, "length": 100, "frequency": 1, "angle_damping": 0.5,
+            "length_damping": 0.5, "output_scale": [1, 1]
+        }"#;
+        let mut puppet = Model::from_bytes(puppet_json(children).as_bytes())
+            .unwrap()
+            .puppet;
+        puppet.set_time_scale(time_scale);
+        let update = |puppet: &mut Puppet| {
+            puppet.begin_set_params();
+            puppet.update_physics(1.0 / 60.0);
+            puppet.end_set_params();
+            puppet.physics_ctx.pendulums[&InoxNodeUuid(2)].bob()
+        };
+
+        update(&mut puppet);
+        let moved = TransformOffset::default().with_translation(vec3(50.0, 0.0, 0.0));
+        puppet.set_node_transform(InoxNodeUuid(2), moved);
+        (0..ticks).map(|_| update(&mut puppet)).collect()
+    }
+
+    #[test]
+    fn test_time_scale_slows_down_motion() {
+        let full_speed = swing(1.0, 10);
+        let half_speed = swing(0.5, 20);
+
+        // At half speed, the bob goes through the same positions in twice the ticks,
+        // up to the error of simulating in smaller steps...
+        for (i, bob) in full_speed.iter().enumerate() {
+            assert!(bob.distance(half_speed[2 * i + 1]) < 2.5);
+        }
+        // ...so it moves half as far in each tick
+        for i in 1..full_speed.len() {
+            let full_motion = full_speed[i].distance(full_speed[i - 1]);
+            let half_motion = half_speed[2 * i + 1].distance(half_speed[2 * i]);
+            assert!((half_motion / full_motion - 0.5).abs() < 0.05);
+        }
+
+        // A stopped clock freezes the pendulum where it is
+        let frozen = swing(0.0, 5);
+        assert!(frozen.iter().all(|bob| *bob == frozen[0]));
+    }
+}
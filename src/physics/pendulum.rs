@@ -0,0 +1,168 @@
+use std::f32::consts::TAU;
+
+use glam::{vec2, Vec2};
+
+use super::PhysicsProps;
+
+/// Rigid pendulum hanging from an anchor.
+///
+/// The bob keeps its world-space position from the previous frame, and the angle is recomputed
+/// against the current anchor position on every tick. This way, when the anchor moves
+/// (e.g. a parameter snapping the head sideways), the bob lags behind and swings naturally.
+#[derive(Debug, Clone)]
+pub struct Pendulum {
+    /// World-space position of the bob.
+    pub bob: Vec2,
+    /// Angle of the pendulum, 0 when hanging straight down.
+    pub angle: f32,
+    /// Angular velocity of the pendulum.
+    pub d_angle: f32,
+}
+
+impl Pendulum {
+    /// Creates a pendulum at rest, hanging straight down from the anchor.
+    pub fn new(anchor: Vec2, length: f32) -> Self {
+        Self {
+            bob: anchor + vec2(0.0, length),
+            angle: 0.0,
+            d_angle: 0.0,
+        }
+    }
+
+    /// Advances the pendulum by `h` seconds, the anchor being at its current world-space position.
+    pub fn tick(&mut self, anchor: Vec2, props: &PhysicsProps, h: f32) {
+        if props.length <= 0.0 {
+            self.bob = anchor;
+            return;
+        }
+
+        // Compute the angle against the updated anchor position
+        let d_bob = self.bob - anchor;
+        self.angle = (-d_bob.x).atan2(d_bob.y);
+
+        // Semi-implicit Euler step
+        let length_ratio = props.gravity / props.length;
+        let crit_damp = 2.0 * length_ratio.abs().sqrt();
+        let dd_angle = -length_ratio * self.angle.sin()
+            - self.d_angle * props.angle_damping * crit_damp;
+        self.d_angle += h * dd_angle;
+        self.angle += h * self.d_angle;
+
+        // Update the bob position at the new angle
+        self.bob = anchor + vec2(-self.angle.sin(), self.angle.cos()) * props.length;
+    }
+}
+
+/// Pendulum whose rod is a spring, so that the bob can also bounce towards and away from the anchor.
+///
+/// `frequency` is the natural frequency of the spring in Hz, and the bob settles slightly further
+/// than `length` as gravity stretches the spring.
+#[derive(Debug, Clone)]
+pub struct SpringPendulum {
+    /// World-space position of the bob.
+    pub bob: Vec2,
+    /// World-space velocity of the bob.
+    pub velocity: Vec2,
+}
+
+impl SpringPendulum {
+    /// Creates a pendulum at rest, hanging straight down from the anchor.
+    pub fn new(anchor: Vec2, length: f32) -> Self {
+        Self {
+            bob: anchor + vec2(0.0, length),
+            velocity: Vec2::ZERO,
+        }
+    }
+
+    /// Advances the pendulum by `h` seconds, the anchor being at its current world-space position.
+    pub fn tick(&mut self, anchor: Vec2, props: &PhysicsProps, h: f32) {
+        if props.length <= 0.0 {
+            self.bob = anchor;
+            self.velocity = Vec2::ZERO;
+            return;
+        }
+
+        let d_bob = self.bob - anchor;
+        let dist = d_bob.length();
+        // Straight down if the bob is on the anchor, where the direction is undefined
+        let dir = if dist > 0.0 { d_bob / dist } else { Vec2::Y };
+
+        // Split the velocity along the rod, damped like a spring, and across it, damped like a pendulum
+        let omega = TAU * props.frequency;
+        let radial_speed = self.velocity.dot(dir);
+        let tangent_velocity = self.velocity - dir * radial_speed;
+        let crit_damp_angle = 2.0 * (props.gravity / props.length).abs().sqrt();
+
+        let accel = vec2(0.0, props.gravity)
+            - dir * (omega * omega * (dist - props.length))
+            - dir * (radial_speed * props.length_damping * 2.0 * omega)
+            - tangent_velocity * (props.angle_damping * crit_damp_angle);
+
+        // Semi-implicit Euler step
+        self.velocity += h * accel;
+        self.bob += h * self.velocity;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROPS: PhysicsProps = PhysicsProps {
+        gravity: 9.8,
+        length: 1.0,
+        frequency: 1.0,
+        angle_damping: 0.1,
+        length_damping: 0.5,
+    };
+
+    #[test]
+    fn test_anchor_motion_induces_swing() {
+        let mut pendulum = Pendulum::new(Vec2::ZERO, PROPS.length);
+
+        // at rest, a still anchor doesn't move the pendulum
+        for _ in 0..10 {
+            pendulum.tick(Vec2::ZERO, &PROPS, 1.0 / 60.0);
+        }
+        assert_eq!(pendulum.angle, 0.0);
+        assert_eq!(pendulum.d_angle, 0.0);
+
+        // snapping the anchor sideways makes the bob lag behind...
+        let anchor = vec2(0.5, 0.0);
+        pendulum.tick(anchor, &PROPS, 1.0 / 60.0);
+        assert!(pendulum.angle > 0.0);
+        assert!(pendulum.bob.x < anchor.x);
+
+        // ...then swing past the rest position
+        let mut min_x = f32::MAX;
+        let mut max_x = f32::MIN;
+        for _ in 0..120 {
+            pendulum.tick(anchor, &PROPS, 1.0 / 60.0);
+            min_x = min_x.min(pendulum.bob.x);
+            max_x = max_x.max(pendulum.bob.x);
+        }
+        assert!(min_x < anchor.x && anchor.x < max_x);
+    }
+
+    #[test]
+    fn test_spring_pendulum_settles_under_gravity() {
+        let mut pendulum = SpringPendulum::new(Vec2::ZERO, PROPS.length);
+
+        // gravity stretches the spring beyond its length, then the bob settles
+        let stretch = PROPS.gravity / (TAU * PROPS.frequency).powi(2);
+        for _ in 0..60 * 20 {
+            pendulum.tick(Vec2::ZERO, &PROPS, 1.0 / 60.0);
+        }
+        assert!(pendulum.bob.x.abs() < 1e-3);
+        assert!((pendulum.bob.y - (PROPS.length + stretch)).abs() < 1e-2);
+
+        // snapping the anchor sideways makes the bob lag behind, then follow
+        let anchor = vec2(0.5, 0.0);
+        pendulum.tick(anchor, &PROPS, 1.0 / 60.0);
+        assert!(pendulum.bob.x < anchor.x);
+        for _ in 0..60 * 20 {
+            pendulum.tick(anchor, &PROPS, 1.0 / 60.0);
+        }
+        assert!((pendulum.bob.x - anchor.x).abs() < 1e-2);
+    }
+}
@@ -3,8 +3,12 @@
 use std::collections::HashMap;
 use std::fmt;
 
+use crate::math::transform::TransformOffset;
+use crate::nodes::node::{InoxNode, InoxNodeUuid};
+use crate::nodes::node_data::InoxData;
 use crate::nodes::node_tree::InoxNodeTree;
-use crate::params::Param;
+use crate::params::{Param, ParamGroup};
+use crate::physics::PhysicsCtx;
 use crate::render::RenderCtx;
 
 /// Who is allowed to use the puppet?
@@ -192,6 +196,64 @@ impl fmt::Display for PuppetUsageRights {
     }
 }
 
+/// Version of the Inochi2D spec, as `major.minor.patch`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl Version {
+    /// Latest spec version that is supported.
+    pub const LATEST: Version = Version::new(1, 0, 0);
+
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Invalid version {0:?}")]
+pub struct InvalidVersionError(String);
+
+impl TryFrom<&str> for Version {
+    type Error = InvalidVersionError;
+
+    /// Parses versions like `1.0`, `v0.8.2` or `1.0-alpha`.
+    /// A pre-release suffix is ignored and missing numbers are 0.
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let invalid = || InvalidVersionError(value.to_owned());
+
+        let numbers = value.trim().trim_start_matches('v');
+        let numbers = numbers.split(['-', '+']).next().unwrap_or_default();
+
+        let mut parts = numbers.split('.').map(|n| n.parse::<u32>());
+        let major = parts.next().ok_or_else(invalid)?.map_err(|_| invalid())?;
+        let minor = parts.next().transpose().map_err(|_| invalid())?;
+        let patch = parts.next().transpose().map_err(|_| invalid())?;
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+
+        Ok(Version::new(
+            major,
+            minor.unwrap_or_default(),
+            patch.unwrap_or_default(),
+        ))
+    }
+}
+
 /// Puppet meta information.
 #[derive(Clone, Debug)]
 pub struct PuppetMeta {
@@ -218,6 +280,16 @@ pub struct PuppetMeta {
     /// Whether the puppet should preserve pixel borders.
     /// This feature is mainly useful for puppets that use pixel art.
     pub preserve_pixels: bool,
+    /// JSON fields of the meta information that aren't understood.
+    /// They are written back when saving the model.
+    pub extra: json::object::Object,
+}
+
+impl PuppetMeta {
+    /// Parses the spec version the puppet declares in `version`.
+    pub fn spec_version(&self) -> Result<Version, InvalidVersionError> {
+        Version::try_from(self.version.as_str())
+    }
 }
 
 fn writeln_opt<T: fmt::Display>(
@@ -294,6 +366,7 @@ impl Default for PuppetMeta {
             reference: Default::default(),
             thumbnail_id: Default::default(),
             preserve_pixels: Default::default(),
+            extra: json::object::Object::new(),
         }
     }
 }
@@ -312,5 +385,379 @@ pub struct Puppet<T = ()> {
     pub physics: PuppetPhysics,
     pub nodes: InoxNodeTree<T>,
     pub parameters: HashMap<String, Param>,
+    /// Exclusive parameter groups, by name. See `Puppet::set_exclusive`.
+    pub param_groups: HashMap<String, ParamGroup>,
     pub render_ctx: RenderCtx,
+    pub physics_ctx: PhysicsCtx,
+}
+
+impl Puppet {
+    /// Sets the transform of a node relative to its parent, and updates the world transforms.
+    ///
+    /// Changing `trans_offset` through `nodes.get_node_mut` instead only takes effect
+    /// on the next `begin_set_params`. Offsets of parameters bound to the node's transform
+    /// are applied on top again on the next `begin_set_params` and `end_set_params`.
+    /// Returns false if there is no node `uuid`.
+    pub fn set_node_transform(
+        &mut self,
+        uuid: InoxNodeUuid,
+        trans_offset: TransformOffset,
+    ) -> bool {
+        let Some(node) = self.nodes.get_node_mut(uuid) else {
+            return false;
+        };
+        node.trans_offset = trans_offset;
+
+        if let Some(node_render_ctx) = self.render_ctx.node_render_ctxs.get_mut(&uuid) {
+            node_render_ctx.trans_offset = trans_offset;
+        }
+        self.update_trans();
+        true
+    }
+
+    /// Sets the zsort of a node, and updates the draw order with `resort`.
+    /// Returns false if there is no node `uuid`.
+    pub fn set_node_zsort(&mut self, uuid: InoxNodeUuid, zsort: f32) -> bool {
+        let Some(node) = self.nodes.get_node_mut(uuid) else {
+            return false;
+        };
+        node.zsort = zsort;
+
+        self.resort();
+        true
+    }
+
+    /// Shows or hides a node along with its descendants.
+    /// Returns false if there is no node `uuid`.
+    pub fn set_node_enabled(&mut self, uuid: InoxNodeUuid, enabled: bool) -> bool {
+        let Some(node) = self.nodes.get_node_mut(uuid) else {
+            return false;
+        };
+        // Renderers check whether nodes are shown as they draw them, so nothing else is cached
+        node.enabled = enabled;
+        true
+    }
+
+    /// Adds a node as the last child of `parent`, see `InoxNodeTree::add_node`,
+    /// and rebuilds the render context so that it gets drawn.
+    /// Returns the uuid the node was given, or None if there is no node `parent`.
+    pub fn add_node(&mut self, parent: InoxNodeUuid, node: InoxNode) -> Option<InoxNodeUuid> {
+        let uuid = self.nodes.add_node(parent, node)?;
+        self.rebuild_render_ctx();
+        Some(uuid)
+    }
+
+    /// Removes a node along with its descendants, and the parameter bindings, physics state
+    /// and masks that refer to them, then rebuilds the render context.
+    /// Returns false if there is no node `uuid`, or if it is the root.
+    ///
+    /// Masks drawn from a removed node are removed from the parts and composites that use them,
+    /// and the masks turned off with `set_mask_enabled` after them keep their state.
+    pub fn remove_node(&mut self, uuid: InoxNodeUuid) -> bool {
+        if !self.nodes.remove_node(uuid) {
+            return false;
+        }
+
+        let uuids = &self.nodes.uuids;
+        for param in self.parameters.values_mut() {
+            param
+                .bindings
+                .retain(|binding| uuids.contains_key(&binding.node));
+        }
+        (self.physics_ctx.pendulums).retain(|uuid, _| uuids.contains_key(uuid));
+        self.remove_dangling_masks();
+
+        self.rebuild_render_ctx();
+        true
+    }
+
+    /// Removes the masks whose source isn't a node anymore, shifting the indices
+    /// of the disabled masks that follow them.
+    fn remove_dangling_masks(&mut self) {
+        let nodes = &mut self.nodes;
+        let disabled_masks = &mut self.render_ctx.disabled_masks;
+        for (&uuid, &id) in &nodes.uuids {
+            let masks = match nodes.arena[id].get_mut().data {
+                InoxData::Part(ref mut part) => &mut part.draw_state.masks,
+                InoxData::Composite(ref mut composite) => &mut composite.draw_state.masks,
+                _ => continue,
+            };
+
+            let (mut index, mut new_index) = (0, 0);
+            masks.retain(|mask| {
+                let kept = nodes.uuids.contains_key(&mask.source);
+                if disabled_masks.remove(&(uuid, index)) && kept {
+                    disabled_masks.insert((uuid, new_index));
+                }
+                index += 1;
+                new_index += kept as usize;
+                kept
+            });
+        }
+    }
+
+    /// Moves a node along with its descendants under `new_parent`, see `InoxNodeTree::reparent`,
+    /// and rebuilds the render context. Returns false if the node couldn't be moved.
+    pub fn reparent_node(&mut self, uuid: InoxNodeUuid, new_parent: InoxNodeUuid) -> bool {
+        if !self.nodes.reparent(uuid, new_parent) {
+            return false;
+        }
+        self.rebuild_render_ctx();
+        true
+    }
+
+    /// Creates the render context again from the nodes, after changing the structure
+    /// of the tree or the meshes of nodes through `nodes`.
+    ///
+    /// The offsets and deforms set by parameters are lost until the next `begin_set_params`
+    /// and `end_set_params`. Renderers have to pick up the new buffers, e.g. with
+    /// `OpenglRenderer::rebuild`.
+    pub fn rebuild_render_ctx(&mut self) {
+        let mut render_ctx = RenderCtx::new(&self.nodes);
+        render_ctx.disabled_masks = std::mem::take(&mut self.render_ctx.disabled_masks);
+        render_ctx
+            .disabled_masks
+            .retain(|(uuid, _)| self.nodes.uuids.contains_key(uuid));
+        self.render_ctx = render_ctx;
+        self.update_trans();
+    }
+
+    /// Makes a copy of the puppet with its current pose frozen in.
+    ///
+    /// The transform offsets, deforms, opacities and zsort offsets set by parameters are applied
+    /// permanently to each node's transform, mesh, opacity and zsort, and the copy has
+    /// no parameters.
+    /// The node structure is kept as is, so the copy renders with the model's textures
+    /// exactly like this puppet does after `end_set_params`.
+    pub fn bake_pose(&self) -> Puppet {
+        let mut nodes = self.nodes.clone();
+        let deforms = &self.render_ctx.vertex_buffers.deforms;
+
+        for (&uuid, node_render_ctx) in &self.render_ctx.node_render_ctxs {
+            let Some(node) = nodes.get_node_mut(uuid) else {
+                continue;
+            };
+            node.trans_offset = node_render_ctx.trans_offset;
+            node.zsort += node_render_ctx.zsort_offset;
+            match node.data {
+                InoxData::Part(ref mut part) => part.draw_state.opacity *= node_render_ctx.opacity,
+                InoxData::Composite(ref mut composite) => {
+                    composite.draw_state.opacity *= node_render_ctx.opacity
+                }
+                _ => (),
+            }
+
+            let mesh = match node.data {
+                InoxData::Part(ref mut part) => &mut part.mesh,
+                InoxData::MeshGroup(ref mut mesh_group) => &mut mesh_group.mesh,
+                _ => continue,
+            };
+            if let Some(range) = node_render_ctx.kind.vertex_range() {
+                for (vertex, deform) in mesh.vertices.iter_mut().zip(&deforms[range]) {
+                    *vertex += *deform;
+                }
+            }
+        }
+
+        let render_ctx = RenderCtx::new(&nodes);
+        let mut baked = Puppet {
+            meta: self.meta.clone(),
+            physics: self.physics.clone(),
+            nodes,
+            parameters: HashMap::new(),
+            param_groups: HashMap::new(),
+            render_ctx,
+            physics_ctx: PhysicsCtx::default(),
+        };
+        baked.update_trans();
+        baked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use glam::{vec3, Vec2, Vec3};
+
+    use crate::model::Model;
+    use crate::nodes::node::InoxNodeUuid;
+    use crate::nodes::node_data::{Mask, MaskMode};
+    use crate::render::RenderCtxKind;
+    use crate::testing::{part_json, puppet_json};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(
+            Version::try_from("1.0-alpha").unwrap(),
+            Version::new(1, 0, 0)
+        );
+        assert_eq!(Version::try_from("v0.8.2").unwrap(), Version::new(0, 8, 2));
+        assert!(Version::try_from("").is_err());
+        assert!(Version::try_from("1.x").is_err());
+    }
+
+    /// Puppet with a part under a rotated and scaled parent.
+    fn parent_and_child_puppet() -> Puppet {
+        let mut child = json::parse(&part_json(3, "0, 0, 1, 0, 0, 1", 1.0, "")).unwrap();
+        child["transform"]["trans"] = json::array![1, 0, 0];
+        let children = format!(
+            r#"{{
+                "uuid": 2, "name": "Parent", "type": "Node", "enabled": true, "zsort": 0,
+                "lockToRoot": false,
+                "transform": {{ "trans": [0, 0, 0], "rot": [0, 0, {FRAC_PI_2}], "scale": [2, 2] }},
+                "children": [{}]
+            }}"#,
+            child.dump()
+        );
+        Model::from_bytes(puppet_json(&children).as_bytes())
+            .unwrap()
+            .puppet
+    }
+
+    #[test]
+    fn test_parent_rotation_and_scale_move_children() {
+        let mut puppet = parent_and_child_puppet();
+        puppet.begin_set_params();
+        puppet.end_set_params();
+
+        // The child's offset is rotated and scaled by its parent, and so are its vertices
+        let trans = puppet.render_ctx.node_render_ctxs[&InoxNodeUuid(3)].trans;
+        let origin = trans.transform_point3(Vec3::ZERO);
+        assert!(origin.distance(vec3(0.0, 2.0, 0.0)) < 1e-5);
+        let vertex = trans.transform_point3(vec3(1.0, 0.0, 0.0));
+        assert!(vertex.distance(vec3(0.0, 4.0, 0.0)) < 1e-5);
+    }
+
+    #[test]
+    fn test_set_node_transform_moves_descendants() {
+        let mut puppet = parent_and_child_puppet();
+        puppet.begin_set_params();
+        puppet.end_set_params();
+
+        // Moving the parent moves where the child is drawn, without setting parameters again
+        let moved = TransformOffset::default().with_translation(vec3(5.0, 0.0, 0.0));
+        assert!(puppet.set_node_transform(InoxNodeUuid(2), moved));
+        let trans = puppet.render_ctx.node_render_ctxs[&InoxNodeUuid(3)].trans;
+        let origin = trans.transform_point3(Vec3::ZERO);
+        assert!(origin.distance(vec3(6.0, 0.0, 0.0)) < 1e-5);
+
+        // The new transform is the base that parameters apply to
+        puppet.begin_set_params();
+        puppet.end_set_params();
+        let trans = puppet.render_ctx.node_render_ctxs[&InoxNodeUuid(3)].trans;
+        assert!(trans.transform_point3(Vec3::ZERO).distance(origin) < 1e-5);
+
+        assert!(!puppet.set_node_transform(InoxNodeUuid(42), moved));
+    }
+
+    /// Puppet with a part and a composite nested in a composite, and a part in the inner one.
+    fn nested_composites_puppet() -> Puppet {
+        let part = |uuid: u32| part_json(uuid, "0, 0, 1, 0, 0, 1", 1.0, "");
+        let composite = |uuid: u32, children: &[String]| {
+            format!(
+                r#"{{
+                    "uuid": {uuid}, "name": "Composite", "type": "Composite", "enabled": true,
+                    "zsort": 0, "lockToRoot": false,
+                    "transform": {{ "trans": [0, 0, 0], "rot": [0, 0, 0], "scale": [1, 1] }},
+                    "blend_mode": "Normal", "tint": [1, 1, 1], "screenTint": [0, 0, 0],
+                    "mask_threshold": 0.5, "opacity": 0.5,
+                    "children": [{}]
+                }}"#,
+                children.join(",")
+            )
+        };
+        let inner = composite(4, &[part(5)]);
+        let outer = composite(2, &[part(3), inner]);
+        Model::from_bytes(puppet_json(&outer).as_bytes())
+            .unwrap()
+            .puppet
+    }
+
+    #[test]
+    fn test_nested_composites_draw_their_own_children() {
+        let mut puppet = nested_composites_puppet();
+        puppet.begin_set_params();
+        puppet.end_set_params();
+
+        let children = |uuid| match puppet.render_ctx.node_render_ctxs[&InoxNodeUuid(uuid)].kind {
+            RenderCtxKind::Composite(ref children) => children.clone(),
+            _ => panic!("Node {uuid} is not a composite"),
+        };
+        // Each composite only lists its direct children, which it draws into its own framebuffer
+        let mut outer = children(2);
+        outer.sort_by_key(|uuid| uuid.0);
+        assert_eq!(outer, [InoxNodeUuid(3), InoxNodeUuid(4)]);
+        assert_eq!(children(4), [InoxNodeUuid(5)]);
+        // Parts in composites are not drawn along with the root's nodes
+        let zsorted = &puppet.render_ctx.nodes_zsorted;
+        assert!(zsorted.contains(&InoxNodeUuid(2)));
+        assert!(!zsorted.contains(&InoxNodeUuid(3)));
+        assert!(!zsorted.contains(&InoxNodeUuid(5)));
+    }
+
+    #[test]
+    fn test_remove_node_removes_its_masks() {
+        let mut puppet = nested_composites_puppet();
+        let InoxData::Part(ref mut part) = puppet.nodes.get_node_mut(InoxNodeUuid(3)).unwrap().data
+        else {
+            panic!("Node 3 is not a part");
+        };
+        part.draw_state.masks = [4, 3, 5]
+            .map(|source| Mask {
+                source: InoxNodeUuid(source),
+                mode: MaskMode::Mask,
+            })
+            .to_vec();
+        puppet.set_mask_enabled(InoxNodeUuid(3), 1, false).unwrap();
+        puppet.set_mask_enabled(InoxNodeUuid(3), 2, false).unwrap();
+
+        // Removing the inner composite removes the masks drawn from it and from its part
+        assert!(puppet.remove_node(InoxNodeUuid(4)));
+        let InoxData::Part(ref part) = puppet.nodes.get_node(InoxNodeUuid(3)).unwrap().data else {
+            panic!("Node 3 is not a part");
+        };
+        let sources = part.draw_state.masks.iter().map(|mask| mask.source);
+        assert_eq!(sources.collect::<Vec<_>>(), [InoxNodeUuid(3)]);
+        // The remaining mask, which was second, stays turned off
+        assert!(!puppet.is_mask_enabled(InoxNodeUuid(3), 0));
+        assert!(puppet.is_mask_enabled(InoxNodeUuid(3), 1));
+    }
+
+    #[test]
+    fn test_bake_pose_keeps_opacity_and_zsort() {
+        let children = part_json(2, "0, 0, 1, 0, 0, 1", 0.8, "");
+        let param = r#"[{
+            "uuid": 3, "name": "Fade", "is_vec2": true,
+            "min": [0, 0], "max": [1, 1], "defaults": [0, 0],
+            "axis_points": [[0, 1], [0, 1]],
+            "bindings": [{
+                "node": 2, "param_name": "opacity", "interpolate_mode": "Linear",
+                "values": [[1, 1], [0.5, 0.5]], "isSet": [[true, true], [true, true]]
+            }, {
+                "node": 2, "param_name": "zSort", "interpolate_mode": "Linear",
+                "values": [[0, 0], [2, 2]], "isSet": [[true, true], [true, true]]
+            }]
+        }]"#;
+        let mut payload = json::parse(&puppet_json(&children)).unwrap();
+        payload["param"] = json::parse(param).unwrap();
+        let mut puppet = Model::from_bytes(payload.dump().as_bytes()).unwrap().puppet;
+        puppet.begin_set_params();
+        puppet.set_param("Fade", Vec2::new(1.0, 0.0));
+        puppet.end_set_params();
+
+        // The opacity and zsort set by the parameter are part of the node in the baked copy
+        let baked = puppet.bake_pose();
+        let node = baked.nodes.get_node(InoxNodeUuid(2)).unwrap();
+        let InoxData::Part(ref part) = node.data else {
+            panic!("The baked node is not a part");
+        };
+        assert!((part.draw_state.opacity - 0.4).abs() < 1e-5);
+        assert!((node.zsort - 2.0).abs() < 1e-5);
+        let node_render_ctx = &baked.render_ctx.node_render_ctxs[&InoxNodeUuid(2)];
+        assert_eq!(node_render_ctx.opacity, 1.0);
+        assert_eq!(node_render_ctx.zsort_offset, 0.0);
+    }
 }
@@ -0,0 +1,142 @@
+use std::ops::Range;
+
+use glam::Vec2;
+
+/// Part to be drawn, as far as reordering draws is concerned.
+#[derive(Debug, Clone, Copy)]
+pub struct DrawItem {
+    /// Albedo texture of the part.
+    pub texture: usize,
+    /// Corner of the part's screen-space bounding box with the smallest coordinates.
+    pub min: Vec2,
+    /// Corner of the part's screen-space bounding box with the largest coordinates.
+    pub max: Vec2,
+}
+
+impl DrawItem {
+    fn overlaps(&self, other: &DrawItem) -> bool {
+        self.min.x < other.max.x
+            && other.min.x < self.max.x
+            && self.min.y < other.max.y
+            && other.min.y < self.max.y
+    }
+}
+
+/// Reorders consecutive draws so that parts sharing a texture are drawn one after the other,
+/// and returns the new order as indices into `items`.
+///
+/// Two parts whose bounding boxes overlap are never swapped, so blending produces the same image
+/// whatever the blend modes are: only parts that don't share a single pixel move past each other.
+pub fn batch_draw_order(items: &[DrawItem]) -> Vec<usize> {
+    let n = items.len();
+
+    // Parts that must be drawn after each part, and how many parts each part waits for
+    let mut successors = vec![Vec::new(); n];
+    let mut pending = vec![0_usize; n];
+    for i in 0..n {
+        for j in i + 1..n {
+            if items[i].overlaps(&items[j]) {
+                successors[i].push(j);
+                pending[j] += 1;
+            }
+        }
+    }
+
+    let mut ready = (0..n).filter(|&i| pending[i] == 0).collect::<Vec<_>>();
+    let mut order = Vec::with_capacity(n);
+    let mut texture = None;
+
+    while !ready.is_empty() {
+        // Keep the same texture if possible, otherwise go in the original order
+        let pos = ready
+            .iter()
+            .position(|&i| Some(items[i].texture) == texture)
+            .unwrap_or_else(|| {
+                let first = ready.iter().min().unwrap();
+                ready.iter().position(|i| i == first).unwrap()
+            });
+
+        let i = ready.swap_remove(pos);
+        texture = Some(items[i].texture);
+        order.push(i);
+
+        for &j in &successors[i] {
+            pending[j] -= 1;
+            if pending[j] == 0 {
+                ready.push(j);
+            }
+        }
+    }
+
+    order
+}
+
+/// Groups consecutive draws that can be merged into a single draw call, and returns
+/// the ranges of `keys` that each draw call covers, in order.
+///
+/// Draws with the same key share all their state. Draws without a key, such as masked parts
+/// and composites, are never merged and get a range of their own.
+pub fn merge_draws<K: PartialEq>(keys: &[Option<K>]) -> Vec<Range<usize>> {
+    let mut draws: Vec<Range<usize>> = Vec::new();
+    for (i, key) in keys.iter().enumerate() {
+        match draws.last_mut() {
+            Some(draw) if key.is_some() && keys[draw.start] == *key => draw.end = i + 1,
+            _ => draws.push(i..i + 1),
+        }
+    }
+    draws
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec2;
+
+    use super::*;
+
+    fn item(texture: usize, x: f32) -> DrawItem {
+        DrawItem {
+            texture,
+            min: vec2(x, 0.0),
+            max: vec2(x + 1.0, 1.0),
+        }
+    }
+
+    fn texture_switches(items: &[DrawItem], order: &[usize]) -> usize {
+        order
+            .windows(2)
+            .filter(|w| items[w[0]].texture != items[w[1]].texture)
+            .count()
+    }
+
+    #[test]
+    fn test_batching_groups_textures_without_reordering_overlaps() {
+        let items = [
+            item(0, 0.0),
+            item(1, 2.0),
+            item(0, 4.0),
+            item(1, 6.0),
+            // overlaps the first part, so must stay after it
+            item(1, 0.5),
+        ];
+
+        let order = batch_draw_order(&items);
+        assert_eq!(texture_switches(&items, &[0, 1, 2, 3, 4]), 3);
+        assert_eq!(texture_switches(&items, &order), 1);
+
+        let pos = |i| order.iter().position(|&o| o == i).unwrap();
+        assert!(pos(0) < pos(4));
+
+        let mut sorted = order.clone();
+        sorted.sort();
+        assert_eq!(sorted, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_merge_draws() {
+        // 100 parts sharing their state are a single draw call
+        assert_eq!(merge_draws(&[Some(0); 100]), vec![0..100]);
+
+        let keys = [Some(0), Some(0), None, None, Some(0), Some(1), Some(1)];
+        assert_eq!(merge_draws(&keys), [0..2, 2..3, 3..4, 4..5, 5..7]);
+    }
+}
@@ -0,0 +1,80 @@
+use glam::Vec4;
+
+/// Color space that 8-bit color values are encoded in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    /// Values are proportional to light intensity.
+    Linear,
+    /// Values are gamma-encoded with the sRGB transfer function, as in most image files.
+    Srgb,
+}
+
+impl ColorSpace {
+    /// Converts RGBA8 pixels from this color space to `target` in place.
+    ///
+    /// Only the color channels are converted, alpha is always linear.
+    pub fn convert_rgba8(self, target: ColorSpace, pixels: &mut [u8]) {
+        let convert: fn(f32) -> f32 = match (self, target) {
+            (ColorSpace::Linear, ColorSpace::Srgb) => linear_to_srgb,
+            (ColorSpace::Srgb, ColorSpace::Linear) => srgb_to_linear,
+            _ => return,
+        };
+
+        let lut: [u8; 256] =
+            core::array::from_fn(|i| (convert(i as f32 / 255.0) * 255.0).round() as u8);
+
+        for pixel in pixels.chunks_exact_mut(4) {
+            for channel in &mut pixel[..3] {
+                *channel = lut[*channel as usize];
+            }
+        }
+    }
+}
+
+/// How the alpha channel of a color relates to its color channels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum AlphaMode {
+    /// Color channels are independent from alpha, as in most image files.
+    #[default]
+    Straight,
+    /// Color channels are already multiplied by alpha.
+    Premultiplied,
+}
+
+/// Multiplies the color channels of a straight alpha color by its alpha.
+pub fn premultiply(color: Vec4) -> Vec4 {
+    (color.truncate() * color.w).extend(color.w)
+}
+
+/// Decodes an sRGB-encoded value in `[0, 1]` to linear.
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encodes a linear value in `[0, 1]` with the sRGB transfer function.
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_rgba8_keeps_alpha_and_extremes() {
+        let mut pixels = vec![0, 128, 255, 128];
+        ColorSpace::Srgb.convert_rgba8(ColorSpace::Linear, &mut pixels);
+        assert_eq!(pixels, vec![0, 55, 255, 128]);
+
+        ColorSpace::Linear.convert_rgba8(ColorSpace::Srgb, &mut pixels);
+        assert_eq!(pixels, vec![0, 128, 255, 128]);
+    }
+}
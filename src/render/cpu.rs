@@ -0,0 +1,728 @@
+//! Software renderer drawing puppets into images on the CPU.
+//!
+//! It needs no GPU, driver or display, and its output only depends on the puppet, its textures
+//! and the camera, so it suits golden-image tests of the model, parameter and deform logic.
+//! Nodes are drawn in the same order and with the same transforms as the OpenGL renderer,
+//! with masks, composites and all blend modes, following the OpenGL renderer's formulas
+//! and its default settings, or its linear space blending. Images are stored in 8 bits per
+//! channel after every draw like the OpenGL renderer's framebuffers, or as floats in linear space.
+//!
+//! It is not meant to be fast, and leaves out what only changes how smooth the result looks:
+//! there is no antialiasing, textures are sampled bilinearly without mipmaps and perspective
+//! correction, and only albedo is drawn, without emission and lighting.
+
+use glam::{vec2, UVec2, Vec2, Vec3, Vec4, Vec4Swizzles};
+use image::RgbaImage;
+
+use crate::math::camera::Camera;
+use crate::model::Model;
+use crate::nodes::node::InoxNodeUuid;
+use crate::nodes::node_data::{BlendMode, Composite, InoxData, Part};
+use crate::puppet::{Puppet, Version};
+use crate::render::color::{linear_to_srgb, premultiply, srgb_to_linear};
+use crate::render::draw_rules::{
+    clamp_opacity_tint, compat_blend_mode, compat_screen_tint, mask_stencil_clear_value,
+    mask_stencil_value, MASK_SHOWN,
+};
+use crate::render::{NodeRenderCtx, PartRenderCtx, RenderCtxKind, Renderer};
+use crate::texture::{decode_model_textures, ShallowTexture};
+
+/// Renderer drawing puppets into images on the CPU, see the module documentation.
+pub struct CpuRenderer {
+    pub camera: Camera,
+    /// Samples the model's textures upside down, see `RendererConfig::flip_textures_v`
+    /// of the OpenGL renderer.
+    pub flip_textures_v: bool,
+    /// Blends colors in linear space, see `RendererConfig::linear_space` of the OpenGL renderer.
+    pub linear_space: bool,
+    textures: Vec<ShallowTexture>,
+    /// Stands in for textures that parts use but the model doesn't have.
+    placeholder: ShallowTexture,
+    size: UVec2,
+    compat_version: Version,
+}
+
+impl CpuRenderer {
+    /// Creates a renderer for a model's puppet, drawing images of `size` pixels,
+    /// and decodes the model's textures.
+    ///
+    /// Textures that can't be decoded are replaced by a placeholder, like in the OpenGL renderer.
+    /// Like it, the puppet is drawn as Inochi2D draws models of the spec version the model declares.
+    pub fn from_model(model: &Model, size: UVec2) -> Self {
+        let textures = decode_model_textures(&model.textures)
+            .into_iter()
+            .map(|texture| texture.unwrap_or_else(|_| ShallowTexture::placeholder()))
+            .collect();
+
+        Self {
+            camera: Camera::default(),
+            flip_textures_v: false,
+            linear_space: false,
+            textures,
+            placeholder: ShallowTexture::placeholder(),
+            size: size.max(UVec2::ONE),
+            compat_version: model.puppet.meta.spec_version().unwrap_or(Version::LATEST),
+        }
+    }
+
+    /// Size of the rendered images, in pixels.
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// Changes the size of the rendered images.
+    pub fn resize(&mut self, size: UVec2) {
+        self.size = size.max(UVec2::ONE);
+    }
+
+    /// Renders the puppet into an image with a transparent background, with the origin
+    /// at the top-left.
+    ///
+    /// Its colors are premultiplied by alpha, like the pixels read back from the OpenGL renderer.
+    /// In linear space, they are encoded back to sRGB.
+    pub fn render(&self, puppet: &Puppet) -> RgbaImage {
+        let mut target = self.target();
+        for &uuid in &puppet.render_ctx.nodes_zsorted {
+            self.draw_node(puppet, uuid, &mut target);
+        }
+
+        let pixels = (target.pixels.iter())
+            .map(|&color| {
+                if !self.linear_space {
+                    color
+                } else if color.w <= 0.0 {
+                    Vec4::ZERO
+                } else {
+                    // Encoding applies to the color itself, so it is premultiplied again afterward
+                    let straight = (color.xyz() / color.w).clamp(Vec3::ZERO, Vec3::ONE);
+                    (Vec3::from_array(straight.to_array().map(linear_to_srgb)) * color.w)
+                        .extend(color.w)
+                }
+            })
+            .flat_map(|color| color.to_array().map(|c| (c * 255.0).round() as u8))
+            .collect();
+        RgbaImage::from_raw(self.size.x, self.size.y, pixels).unwrap()
+    }
+
+    fn draw_node(&self, puppet: &Puppet, uuid: InoxNodeUuid, target: &mut Target) {
+        // Disabled nodes hide their subtree
+        if !puppet.nodes.is_shown(uuid) {
+            return;
+        }
+
+        let node = puppet.nodes.get_node(uuid).unwrap();
+        let node_render_ctx = &puppet.render_ctx.node_render_ctxs[&uuid];
+
+        match (&node.data, &node_render_ctx.kind) {
+            (InoxData::Part(ref part), RenderCtxKind::Part(ref part_render_ctx)) => {
+                self.draw_part(puppet, uuid, part, node_render_ctx, part_render_ctx, target);
+            }
+            (InoxData::Composite(ref composite), RenderCtxKind::Composite(ref children)) => {
+                self.draw_composite(puppet, composite, node_render_ctx, children, target);
+            }
+            _ => (),
+        }
+    }
+
+    fn draw_part(
+        &self,
+        puppet: &Puppet,
+        uuid: InoxNodeUuid,
+        part: &Part,
+        node_render_ctx: &NodeRenderCtx,
+        part_render_ctx: &PartRenderCtx,
+        target: &mut Target,
+    ) {
+        let masks = (part.draw_state.masks.iter().enumerate())
+            .filter(|&(i, _)| puppet.is_mask_enabled(uuid, i))
+            .map(|(_, mask)| mask)
+            .collect::<Vec<_>>();
+
+        // Same stencil values as the OpenGL renderer, one per pixel
+        let stencil = (!masks.is_empty()).then(|| {
+            let clear_value = mask_stencil_clear_value(masks.iter().map(|mask| mask.mode));
+            let mut stencil = vec![clear_value; target.pixels.len()];
+            for mask in &masks {
+                self.draw_mask(
+                    puppet,
+                    mask.source,
+                    mask_stencil_value(mask.mode),
+                    &mut stencil,
+                );
+            }
+            stencil
+        });
+
+        let draw_state = &part.draw_state;
+        let (opacity, tint) = clamp_opacity_tint(
+            draw_state.opacity * node_render_ctx.opacity,
+            draw_state.tint,
+        );
+        let screen_tint = draw_state.screen_tint.clamp(Vec3::ZERO, Vec3::ONE);
+        let screen_tint = compat_screen_tint(self.compat_version, screen_tint);
+        let (tint, screen_tint) = (self.shader_color(tint), self.shader_color(screen_tint));
+        let blend_mode = compat_blend_mode(self.compat_version, draw_state.blend_mode);
+
+        let texture = self.texture(part.tex_albedo);
+        let (positions, uvs) = self.part_vertices(puppet, node_render_ctx, part_render_ctx);
+        rasterize(self.size, &positions, uvs, &part.mesh.indices, |i, uv| {
+            if stencil
+                .as_ref()
+                .is_some_and(|stencil| stencil[i] != MASK_SHOWN)
+            {
+                return;
+            }
+            let color = premultiply(self.sample(texture, uv));
+            target.blend(i, shade(color, opacity, tint, screen_tint), blend_mode);
+        });
+    }
+
+    /// Samples a model texture, flipped if `flip_textures_v` is set,
+    /// and decoded to linear space if `linear_space` is set.
+    fn sample(&self, texture: &ShallowTexture, uv: Vec2) -> Vec4 {
+        let uv = if self.flip_textures_v {
+            vec2(uv.x, 1.0 - uv.y)
+        } else {
+            uv
+        };
+        sample(texture, uv, self.linear_space)
+    }
+
+    /// Converts a color of the model, which is in sRGB like the textures, to the space colors
+    /// are blended in.
+    fn shader_color(&self, color: Vec3) -> Vec3 {
+        if self.linear_space {
+            Vec3::from_array(color.to_array().map(srgb_to_linear))
+        } else {
+            color
+        }
+    }
+
+    /// Creates an image to draw into, in the space colors are blended in.
+    fn target(&self) -> Target {
+        Target {
+            pixels: vec![Vec4::ZERO; (self.size.x * self.size.y) as usize],
+            float: self.linear_space,
+        }
+    }
+
+    /// Writes `value` to the stencil where a mask source covers it.
+    fn draw_mask(&self, puppet: &Puppet, uuid: InoxNodeUuid, value: u32, stencil: &mut [u32]) {
+        let Some(node) = puppet.nodes.get_node(uuid) else {
+            return;
+        };
+        let node_render_ctx = &puppet.render_ctx.node_render_ctxs[&uuid];
+
+        match (&node.data, &node_render_ctx.kind) {
+            (InoxData::Part(ref part), RenderCtxKind::Part(ref part_render_ctx)) => {
+                let threshold = part.draw_state.mask_threshold.clamp(0.0, 1.0);
+                let texture = self.texture(part.tex_albedo);
+                let (positions, uvs) = self.part_vertices(puppet, node_render_ctx, part_render_ctx);
+                rasterize(self.size, &positions, uvs, &part.mesh.indices, |i, uv| {
+                    if self.sample(texture, uv).w > threshold {
+                        stencil[i] = value;
+                    }
+                });
+            }
+            (InoxData::Composite(_), RenderCtxKind::Composite(ref children)) => {
+                for &child in children {
+                    self.draw_mask(puppet, child, value, stencil);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn draw_composite(
+        &self,
+        puppet: &Puppet,
+        composite: &Composite,
+        node_render_ctx: &NodeRenderCtx,
+        children: &[InoxNodeUuid],
+        target: &mut Target,
+    ) {
+        if children.is_empty() {
+            return;
+        }
+
+        let mut offscreen = self.target();
+        for &child in children {
+            self.draw_node(puppet, child, &mut offscreen);
+        }
+
+        let draw_state = &composite.draw_state;
+        let (opacity, tint) = clamp_opacity_tint(
+            draw_state.opacity * node_render_ctx.opacity,
+            draw_state.tint,
+        );
+        let screen_tint = draw_state.screen_tint.clamp(Vec3::ZERO, Vec3::ONE);
+        let screen_tint = compat_screen_tint(self.compat_version, screen_tint);
+        let (tint, screen_tint) = (self.shader_color(tint), self.shader_color(screen_tint));
+        let blend_mode = compat_blend_mode(self.compat_version, draw_state.blend_mode);
+
+        // Like the quad of the OpenGL renderer, every pixel is blended, even transparent ones
+        for (i, &color) in offscreen.pixels.iter().enumerate() {
+            target.blend(i, shade(color, opacity, tint, screen_tint), blend_mode);
+        }
+    }
+
+    /// Gets a texture of the model, or the placeholder if there is none at `index`.
+    fn texture(&self, index: usize) -> &ShallowTexture {
+        self.textures.get(index).unwrap_or(&self.placeholder)
+    }
+
+    /// Gets the deformed vertices of a part in pixels, with their UVs.
+    fn part_vertices<'a>(
+        &self,
+        puppet: &'a Puppet,
+        node_render_ctx: &NodeRenderCtx,
+        part_render_ctx: &PartRenderCtx,
+    ) -> (Vec<Vec2>, &'a [Vec2]) {
+        let size = self.size.as_vec2();
+        let mvp = self.camera.matrix(size)
+            * self.camera.zsort_translation(node_render_ctx.zsort)
+            * node_render_ctx.trans;
+
+        let vertex_buffers = &puppet.render_ctx.vertex_buffers;
+        let beg = part_render_ctx.vert_offset as usize;
+        let end = beg + part_render_ctx.vert_len;
+        let positions = (vertex_buffers.verts[beg..end].iter())
+            .zip(&vertex_buffers.deforms[beg..end])
+            .map(|(&vert, &deform)| {
+                let ndc = mvp.project_point3((vert + deform).extend(0.0));
+                vec2(ndc.x + 1.0, 1.0 - ndc.y) * 0.5 * size
+            })
+            .collect();
+        (positions, &vertex_buffers.uvs[beg..end])
+    }
+}
+
+impl Renderer for CpuRenderer {
+    type Target<'a> = ();
+    type Output = RgbaImage;
+
+    fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    fn viewport(&self) -> UVec2 {
+        self.size
+    }
+
+    fn resize(&mut self, viewport: UVec2) {
+        CpuRenderer::resize(self, viewport);
+    }
+
+    fn draw(&mut self, puppet: &Puppet, _target: ()) -> RgbaImage {
+        self.render(puppet)
+    }
+}
+
+/// Image being drawn into, with premultiplied colors.
+struct Target {
+    pixels: Vec<Vec4>,
+    /// Whether colors are stored as floats, like in the float framebuffers that the OpenGL
+    /// renderer draws into in linear space, rather than in 8 bits per channel.
+    float: bool,
+}
+
+impl Target {
+    /// Blends a premultiplied color over a pixel, and stores the result.
+    fn blend(&mut self, index: usize, src: Vec4, blend_mode: BlendMode) {
+        let color = blend(src, self.pixels[index], blend_mode);
+        self.pixels[index] = if self.float {
+            color
+        } else {
+            (color.clamp(Vec4::ZERO, Vec4::ONE) * 255.0).round() / 255.0
+        };
+    }
+}
+
+/// Applies the opacity and tints of a part or composite to a premultiplied color,
+/// like the fragment shaders of the OpenGL renderer.
+fn shade(color: Vec4, opacity: f32, tint: Vec3, screen_tint: Vec3) -> Vec4 {
+    let screen = Vec3::ONE - (Vec3::ONE - color.xyz()) * (Vec3::ONE - screen_tint * color.w);
+    (screen * tint).extend(color.w) * opacity
+}
+
+/// Blends a premultiplied color over another with a blend mode, like the OpenGL renderer.
+fn blend(src: Vec4, dst: Vec4, blend_mode: BlendMode) -> Vec4 {
+    let normal = |src: Vec4| src + dst * (1.0 - src.w);
+    // Colors blended differently from alpha, which is composited normally
+    let separate = |rgb: Vec3| rgb.extend(src.w + dst.w * (1.0 - src.w));
+
+    match blend_mode {
+        BlendMode::Normal => normal(src),
+        BlendMode::Multiply => src * dst + dst * (1.0 - src.w),
+        BlendMode::ColorDodge => src * dst + dst,
+        BlendMode::LinearDodge => src + dst,
+        BlendMode::Screen => src + dst * (Vec4::ONE - src),
+        BlendMode::ClipToLower => src * dst.w + dst * (1.0 - src.w),
+        BlendMode::SliceFromLower => src * (1.0 - dst.w) - dst * (1.0 - src.w),
+        BlendMode::Lighten => separate(src.xyz().max(dst.xyz())),
+        BlendMode::Subtract => separate(dst.xyz() - src.xyz()),
+        BlendMode::Exclusion => {
+            separate(src.xyz() * (Vec3::ONE - dst.xyz()) + dst.xyz() * (Vec3::ONE - src.xyz()))
+        }
+        BlendMode::Overlay
+        | BlendMode::SoftLight
+        | BlendMode::HardLight
+        | BlendMode::Darken
+        | BlendMode::ColorBurn
+        | BlendMode::LinearBurn
+        | BlendMode::Difference => normal(blend_with_backdrop(src, dst, blend_mode)),
+    }
+}
+
+/// Blends the color of a premultiplied color with the backdrop, keeping its alpha,
+/// for the blend modes that the OpenGL renderer blends in shaders.
+fn blend_with_backdrop(src: Vec4, dst: Vec4, blend_mode: BlendMode) -> Vec4 {
+    let unpremultiply = |color: Vec4| {
+        if color.w > 0.0 {
+            color.xyz() / color.w
+        } else {
+            Vec3::ZERO
+        }
+    };
+    let (cb, cs) = (unpremultiply(dst), unpremultiply(src));
+
+    let hard_light = |cb: Vec3, cs: Vec3| {
+        let multiply = cb * 2.0 * cs;
+        let screen = Vec3::ONE - (Vec3::ONE - cb) * (2.0 - 2.0 * cs);
+        Vec3::select(cs.cmpge(Vec3::splat(0.5)), screen, multiply)
+    };
+    let soft_light = |cb: Vec3, cs: Vec3| {
+        let d = Vec3::select(
+            cb.cmpge(Vec3::splat(0.25)),
+            cb.powf(0.5),
+            ((16.0 * cb - 12.0) * cb + 4.0) * cb,
+        );
+        let darker = cb - (1.0 - 2.0 * cs) * cb * (1.0 - cb);
+        let lighter = cb + (2.0 * cs - 1.0) * (d - cb);
+        Vec3::select(cs.cmpge(Vec3::splat(0.5)), lighter, darker)
+    };
+    let blended = match blend_mode {
+        BlendMode::Overlay => hard_light(cs, cb),
+        BlendMode::SoftLight => soft_light(cb, cs),
+        BlendMode::HardLight => hard_light(cb, cs),
+        BlendMode::Darken => cb.min(cs),
+        BlendMode::ColorBurn => Vec3::ONE - (Vec3::ONE - cb) / cs.max(Vec3::splat(1e-5)),
+        BlendMode::LinearBurn => (cb + cs - 1.0).max(Vec3::ZERO),
+        BlendMode::Difference => (cb - cs).abs(),
+        _ => cs,
+    };
+
+    // Where the backdrop is transparent, the color is kept
+    let blended = cs.lerp(blended.clamp(Vec3::ZERO, Vec3::ONE), dst.w);
+    (blended * src.w).extend(src.w)
+}
+
+/// Samples a texture bilinearly, clamping to its edges, into a straight alpha color.
+///
+/// If `decode_srgb` is set, the colors of texels are decoded to linear space before
+/// being filtered, like the GL does with sRGB textures.
+fn sample(texture: &ShallowTexture, uv: Vec2, decode_srgb: bool) -> Vec4 {
+    let (width, height) = (texture.width() as i32, texture.height() as i32);
+    let pixels = texture.pixels();
+    let texel = |x: i32, y: i32| {
+        let i = ((y.clamp(0, height - 1) * width + x.clamp(0, width - 1)) * 4) as usize;
+        let texel = Vec4::from_array([0, 1, 2, 3].map(|c| pixels[i + c] as f32 / 255.0));
+        if decode_srgb {
+            Vec3::from_array(texel.xyz().to_array().map(srgb_to_linear)).extend(texel.w)
+        } else {
+            texel
+        }
+    };
+
+    // Texel centers are at half coordinates
+    let position = uv * vec2(width as f32, height as f32) - 0.5;
+    let (x, y) = (position.x.floor() as i32, position.y.floor() as i32);
+    let fraction = position - position.floor();
+
+    let top = texel(x, y).lerp(texel(x + 1, y), fraction.x);
+    let bottom = texel(x, y + 1).lerp(texel(x + 1, y + 1), fraction.x);
+    top.lerp(bottom, fraction.y)
+}
+
+/// Subdivisions of a pixel that vertices are snapped to, like GPUs do.
+const SUBPIXELS: f32 = 256.0;
+
+/// Calls `fragment` with the index and the interpolated UVs of the pixels of an image of `size`
+/// whose center is covered by triangles, with positions in pixels from the top-left.
+///
+/// Positions are snapped to subpixels, so that coverage is computed exactly. Pixels whose
+/// center is on an edge between two triangles are only covered by one of them, so that
+/// the inner edges of a mesh aren't blended twice.
+fn rasterize(
+    size: UVec2,
+    positions: &[Vec2],
+    uvs: &[Vec2],
+    indices: &[u16],
+    mut fragment: impl FnMut(usize, Vec2),
+) {
+    // Far outside of the image, but small enough for edge functions not to overflow
+    let limit = (size.max_element() as f32 + 1.0) * 64.0 * SUBPIXELS;
+    let snapped = (positions.iter())
+        .map(|p| {
+            let p = (*p * SUBPIXELS)
+                .round()
+                .clamp(Vec2::splat(-limit), Vec2::splat(limit));
+            // NaN becomes 0 when cast
+            [p.x as i64, p.y as i64]
+        })
+        .collect::<Vec<_>>();
+
+    for triangle in indices.chunks_exact(3) {
+        let corner = |i: u16| Some((*snapped.get(i as usize)?, *uvs.get(i as usize)?));
+        let (Some(a), Some(b), Some(c)) = (
+            corner(triangle[0]),
+            corner(triangle[1]),
+            corner(triangle[2]),
+        ) else {
+            continue;
+        };
+
+        // Wound so that the edge functions are positive inside
+        let area = edge(a.0, b.0, c.0);
+        if area == 0 {
+            continue;
+        }
+        let [(p0, uv0), (p1, uv1), (p2, uv2)] = if area > 0 { [a, b, c] } else { [a, c, b] };
+
+        let pixel = |v: i64| v.div_euclid(SUBPIXELS as i64);
+        let min_x = pixel(p0[0].min(p1[0]).min(p2[0])).max(0);
+        let min_y = pixel(p0[1].min(p1[1]).min(p2[1])).max(0);
+        let max_x = (pixel(p0[0].max(p1[0]).max(p2[0])) + 1).min(size.x as i64);
+        let max_y = (pixel(p0[1].max(p1[1]).max(p2[1])) + 1).min(size.y as i64);
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let half = SUBPIXELS as i64 / 2;
+                let p = [x * SUBPIXELS as i64 + half, y * SUBPIXELS as i64 + half];
+                let (w0, w1, w2) = (edge(p1, p2, p), edge(p2, p0, p), edge(p0, p1, p));
+                if covers(w0, p1, p2) && covers(w1, p2, p0) && covers(w2, p0, p1) {
+                    let (w0, w1, w2) = (w0 as f32, w1 as f32, w2 as f32);
+                    let uv = (uv0 * w0 + uv1 * w1 + uv2 * w2) / (w0 + w1 + w2);
+                    fragment((y as u32 * size.x + x as u32) as usize, uv);
+                }
+            }
+        }
+    }
+}
+
+/// Gets the cross product of `a` to `b` and `a` to `p`, positive when `p` is on one side of
+/// the edge and negative on the other.
+fn edge(a: [i64; 2], b: [i64; 2], p: [i64; 2]) -> i64 {
+    (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])
+}
+
+/// Whether a pixel center with the edge function `w` is inside the edge from `a` to `b`.
+///
+/// Centers on the edge belong to only one of two triangles sharing it: those on top and
+/// left edges are covered, like GPUs do.
+fn covers(w: i64, a: [i64; 2], b: [i64; 2]) -> bool {
+    w > 0 || (w == 0 && (b[1] < a[1] || (b[1] == a[1] && b[0] < a[0])))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use glam::uvec2;
+    use image::{ImageOutputFormat, Rgba};
+
+    use super::*;
+    use crate::model::ModelTexture;
+    use crate::testing::{compare_images, part_json, puppet_json};
+
+    /// Model with a 4x4 quad around the origin for each of `opacities`,
+    /// wrapped in a composite at half opacity if `composite` is set.
+    fn quads_model(opacities: &[f32], composite: bool) -> Model {
+        let parts = (opacities.iter().enumerate())
+            .map(|(i, &opacity)| part_json(i as u32 + 3, "-2, -2, 2, -2, 2, 2, -2, 2", opacity, ""))
+            .collect::<Vec<_>>()
+            .join(",");
+        let children = if composite {
+            format!(
+                r#"{{
+                    "uuid": 2, "name": "Composite", "type": "Composite", "enabled": true,
+                    "zsort": 0, "lockToRoot": false,
+                    "transform": {{ "trans": [0, 0, 0], "rot": [0, 0, 0], "scale": [1, 1] }},
+                    "blend_mode": "Normal", "tint": [1, 1, 1], "screenTint": [0, 0, 0],
+                    "mask_threshold": 0.5, "opacity": 0.5, "children": [{parts}]
+                }}"#
+            )
+        } else {
+            parts
+        };
+        Model::from_bytes(puppet_json(&children).as_bytes()).unwrap()
+    }
+
+    /// Renders a model into an 8x8 image, with a pixel per unit.
+    fn render(mut model: Model) -> RgbaImage {
+        model.puppet.begin_set_params();
+        model.puppet.end_set_params();
+        CpuRenderer::from_model(&model, uvec2(8, 8)).render(&model.puppet)
+    }
+
+    #[test]
+    fn test_parts_cover_their_pixels_once() {
+        // The model has no textures, so the part is drawn with the magenta placeholder.
+        // Pixels along the diagonal between its triangles are only blended once.
+        let image = render(quads_model(&[0.5], false));
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let inside = (2..6).contains(&x) && (2..6).contains(&y);
+            let expected = if inside { [128, 0, 128, 128] } else { [0; 4] };
+            assert_eq!(pixel.0, expected, "at {x}, {y}");
+        }
+    }
+
+    #[test]
+    fn test_composites_blend_their_children_together() {
+        // Opaque parts cover each other in the composite, which is then drawn at half opacity
+        let image = render(quads_model(&[1.0, 1.0], true));
+        assert_eq!(image.get_pixel(4, 4).0, [128, 0, 128, 128]);
+        assert_eq!(image.get_pixel(0, 0).0, [0; 4]);
+
+        // Without the composite, the second part is blended over the first one, stored in 8 bits
+        let image = render(quads_model(&[0.5, 0.5], false));
+        assert_eq!(image.get_pixel(4, 4).0, [192, 0, 192, 192]);
+    }
+
+    #[test]
+    fn test_textures_are_right_side_up() {
+        // 1x2 TGA stored bottom to top, red above blue
+        let tga = [
+            &[0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 2, 0, 32, 0x08][..],
+            &[255, 0, 0, 255, 0, 0, 255, 255],
+        ]
+        .concat();
+        let mut model = quads_model(&[1.0], false);
+        model.textures.push(ModelTexture {
+            format: image::ImageFormat::Tga,
+            data: tga,
+        });
+        model.puppet.begin_set_params();
+        model.puppet.end_set_params();
+
+        // V = 0 is the top of the texture, at the top of the quad
+        let mut renderer = CpuRenderer::from_model(&model, uvec2(8, 8));
+        let image = renderer.render(&model.puppet);
+        assert_eq!(image.get_pixel(3, 2).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(3, 5).0, [0, 0, 255, 255]);
+
+        renderer.flip_textures_v = true;
+        let image = renderer.render(&model.puppet);
+        assert_eq!(image.get_pixel(3, 2).0, [0, 0, 255, 255]);
+        assert_eq!(image.get_pixel(3, 5).0, [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_mixed_mask_sources() {
+        // Transparent regions over x in [-3, 1] and [-1, 3], and a part over the whole image
+        let shown_columns = |masks: &str| {
+            let children = [
+                part_json(2, "-3, -4, 1, -4, 1, 4, -3, 4", 0.0, ""),
+                part_json(3, "-1, -4, 3, -4, 3, 4, -1, 4", 0.0, ""),
+                part_json(4, "-4, -4, 4, -4, 4, 4, -4, 4", 1.0, masks),
+            ]
+            .join(",");
+            let image = render(Model::from_bytes(puppet_json(&children).as_bytes()).unwrap());
+            (0..8)
+                .filter(|&x| image.get_pixel(x, 4).0[3] > 0)
+                .collect::<Vec<_>>()
+        };
+        let mask = r#"{ "source": 2, "mode": "Mask" }"#;
+        let dodge = r#"{ "source": 3, "mode": "DodgeMask" }"#;
+
+        // The part is only shown inside its mask and outside its dodge mask
+        assert_eq!(shown_columns(&format!("{mask}, {dodge}")), [1, 2]);
+        assert_eq!(shown_columns(mask), [1, 2, 3, 4]);
+        assert_eq!(shown_columns(dodge), [0, 1, 2, 7]);
+
+        // Like in Inochi2D, sources are drawn in the order they are listed in,
+        // so a mask listed after a dodge mask shows the part inside both
+        assert_eq!(shown_columns(&format!("{dodge}, {mask}")), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_cross_fade_in_linear_space() {
+        // An opaque red part under a green one fading in from left to right, over 8x8 pixels
+        let part = |uuid: u32, texture: usize| {
+            let verts = "-4, -4, 4, -4, 4, 4, -4, 4";
+            let mut part = json::parse(&part_json(uuid, verts, 1.0, "")).unwrap();
+            part["textures"] = json::array![texture];
+            part.dump()
+        };
+        let children = [part(2, 0), part(3, 1)].join(",");
+        let mut model = Model::from_bytes(puppet_json(&children).as_bytes()).unwrap();
+        let fade = |x: u32| (x * 255 / 7) as u8;
+        let textures = [
+            RgbaImage::from_pixel(1, 1, Rgba([255, 0, 0, 255])),
+            RgbaImage::from_fn(8, 1, |x, _| Rgba([0, 255, 0, fade(x)])),
+        ];
+        for texture in textures {
+            let mut data = Cursor::new(Vec::new());
+            texture.write_to(&mut data, ImageOutputFormat::Png).unwrap();
+            model.textures.push(ModelTexture {
+                format: image::ImageFormat::Png,
+                data: data.into_inner(),
+            });
+        }
+        model.puppet.begin_set_params();
+        model.puppet.end_set_params();
+
+        let mut renderer = CpuRenderer::from_model(&model, uvec2(8, 8));
+        let srgb = renderer.render(&model.puppet);
+        renderer.linear_space = true;
+        let linear = renderer.render(&model.puppet);
+
+        // Red and green are mixed in proportion to the fade, in sRGB values or in light intensity
+        let cross_fade = |encode: fn(f32) -> f32| {
+            RgbaImage::from_fn(8, 8, |x, _| {
+                let t = fade(x) as f32 / 255.0;
+                let [r, g] = [1.0 - t, t].map(|c| (encode(c) * 255.0).round() as u8);
+                Rgba([r, g, 0, 255])
+            })
+        };
+        assert!(compare_images(&srgb, &cross_fade(|c| c), 1).is_match());
+        assert!(compare_images(&linear, &cross_fade(linear_to_srgb), 1).is_match());
+
+        // Both modes agree at the ends of the fade, but blending sRGB values darkens the middle
+        let diff = compare_images(&srgb, &linear, 1);
+        assert_eq!(diff.differing_pixels, 6 * 8);
+        for y in 0..8 {
+            assert_eq!(srgb.get_pixel(0, y), linear.get_pixel(0, y));
+            assert_eq!(srgb.get_pixel(7, y), linear.get_pixel(7, y));
+        }
+        let brightness = |image: &RgbaImage| {
+            image.get_pixel(4, 4).0[..3]
+                .iter()
+                .map(|&c| c as u32)
+                .sum::<u32>()
+        };
+        assert!(brightness(&linear) > brightness(&srgb));
+    }
+
+    #[test]
+    fn test_blend_modes() {
+        let dst = Vec4::new(0.5, 0.5, 0.5, 1.0);
+        let src = Vec4::new(0.5, 0.25, 0.0, 0.5);
+
+        assert_eq!(
+            blend(src, dst, BlendMode::Normal),
+            Vec4::new(0.75, 0.5, 0.25, 1.0)
+        );
+        // Multiplies the colors where the source is opaque
+        assert_eq!(
+            blend(src, dst, BlendMode::Multiply),
+            Vec4::new(0.5, 0.375, 0.25, 1.0)
+        );
+        // Drawn over nothing, the source keeps its colors
+        assert_eq!(blend(src, Vec4::ZERO, BlendMode::Multiply), Vec4::ZERO);
+        assert_eq!(blend(src, Vec4::ZERO, BlendMode::Darken), src);
+    }
+}
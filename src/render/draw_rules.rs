@@ -0,0 +1,110 @@
+//! How parts and composites are drawn, shared by the OpenGL and CPU renderers.
+
+use glam::Vec3;
+
+use crate::nodes::node_data::{BlendMode, MaskMode};
+use crate::puppet::Version;
+
+/// Stencil value of the pixels where a masked part is shown, the others holding 0.
+///
+/// Mask sources are drawn in order, each replacing the value of the pixels it covers with
+/// the one of its mode, and the part is then drawn where the stencil equals this value.
+/// A single bit is enough, so that the OpenGL renderer can compare it along with the clip
+/// polygon's bit.
+pub(crate) const MASK_SHOWN: u32 = 1;
+
+/// Stencil value that a mask source writes over the pixels it covers.
+pub(crate) fn mask_stencil_value(mode: MaskMode) -> u32 {
+    match mode {
+        // Shows the part inside the source
+        MaskMode::Mask => MASK_SHOWN,
+        // Hides the part inside the source
+        MaskMode::Dodge => 0,
+    }
+}
+
+/// Clamps the opacity and tint that a part or composite is drawn with to the range the renderers
+/// expect, as parameters, animations and overrides can drive them out of it.
+pub(crate) fn clamp_opacity_tint(opacity: f32, tint: Vec3) -> (f32, Vec3) {
+    (opacity.clamp(0.0, 1.0), tint.clamp(Vec3::ZERO, Vec3::ONE))
+}
+
+/// Gets the blend mode that parts and composites are drawn with for models of the Inochi2D spec
+/// version `version`, as versions before 0.8 didn't have the clipping blend modes.
+pub(crate) fn compat_blend_mode(version: Version, blend_mode: BlendMode) -> BlendMode {
+    match blend_mode {
+        BlendMode::ClipToLower | BlendMode::SliceFromLower if version < Version::new(0, 8, 0) => {
+            BlendMode::Normal
+        }
+        blend_mode => blend_mode,
+    }
+}
+
+/// Gets the screen tint that parts and composites are drawn with for models of the Inochi2D spec
+/// version `version`, as versions before 0.8 didn't have screen tints.
+pub(crate) fn compat_screen_tint(version: Version, screen_tint: Vec3) -> Vec3 {
+    if version < Version::new(0, 8, 0) {
+        Vec3::ZERO
+    } else {
+        screen_tint
+    }
+}
+
+/// Stencil value that the pixels start with before mask sources are drawn.
+///
+/// Parts with a `Mask` source are only shown inside their `Mask` sources,
+/// and parts with only `Dodge` sources are shown everywhere else than inside them.
+pub(crate) fn mask_stencil_clear_value(modes: impl IntoIterator<Item = MaskMode>) -> u32 {
+    if modes.into_iter().any(|mode| mode == MaskMode::Mask) {
+        0
+    } else {
+        MASK_SHOWN
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether a masked part is shown at a pixel, given its mask sources in the order they are
+    /// listed and drawn in, and whether each covers the pixel, as the stencil test decides it.
+    fn shown(masks: &[(MaskMode, bool)]) -> bool {
+        let mut stencil = mask_stencil_clear_value(masks.iter().map(|&(mode, _)| mode));
+        for &(mode, covers) in masks {
+            if covers {
+                stencil = mask_stencil_value(mode);
+            }
+        }
+        stencil == MASK_SHOWN
+    }
+
+    #[test]
+    fn test_mask_modes() {
+        // A mask shows the part inside its source only
+        assert!(shown(&[(MaskMode::Mask, true)]));
+        assert!(!shown(&[(MaskMode::Mask, false)]));
+
+        // A dodge mask does the opposite, hiding the part inside its source only
+        assert!(!shown(&[(MaskMode::Dodge, true)]));
+        assert!(shown(&[(MaskMode::Dodge, false)]));
+
+        // With both, the part is shown inside masks, except where a later dodge mask covers them
+        assert!(shown(&[(MaskMode::Mask, true), (MaskMode::Dodge, false)]));
+        assert!(!shown(&[(MaskMode::Mask, true), (MaskMode::Dodge, true)]));
+        assert!(!shown(&[(MaskMode::Mask, false), (MaskMode::Dodge, false)]));
+
+        // Like in Inochi2D, a later mask shows the part again inside an earlier dodge mask
+        assert!(shown(&[(MaskMode::Dodge, true), (MaskMode::Mask, true)]));
+    }
+
+    #[test]
+    fn test_opacity_and_tint_are_clamped() {
+        // An opacity above 1 is drawn like an opacity of 1, for parts and composites alike
+        let tint = Vec3::new(0.2, 0.4, 0.6);
+        assert_eq!(clamp_opacity_tint(1.5, tint), clamp_opacity_tint(1.0, tint));
+        assert_eq!(clamp_opacity_tint(-0.5, tint), (0.0, tint));
+
+        let (_, tint) = clamp_opacity_tint(1.0, Vec3::new(-1.0, 0.5, 2.0));
+        assert_eq!(tint, Vec3::new(0.0, 0.5, 1.0));
+    }
+}
@@ -4,17 +4,62 @@ pub mod opengl;
 #[cfg(feature = "wgpu")]
 pub mod wgpu;
 
-use std::collections::HashMap;
+#[cfg(feature = "cpu")]
+pub mod cpu;
 
-use glam::{vec2, Mat4, Vec2};
+pub mod batching;
+pub mod color;
+#[cfg(any(feature = "opengl", feature = "cpu"))]
+mod draw_rules;
 
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use glam::{vec2, Mat4, UVec2, Vec2};
+
+use crate::math::camera::Camera;
 use crate::math::transform::TransformOffset;
-use crate::mesh::Mesh;
-use crate::nodes::node::InoxNodeUuid;
+use crate::mesh::{triangle_weights, Mesh, TriangleWeights};
+use crate::nodes::node::{InoxNode, InoxNodeUuid};
 use crate::nodes::node_data::InoxData;
 use crate::nodes::node_tree::InoxNodeTree;
 use crate::puppet::Puppet;
 
+/// What the OpenGL, wgpu and CPU renderers have in common, to draw puppets with any of them.
+///
+/// Everything else, like settings and texture uploads, is specific to each renderer.
+pub trait Renderer {
+    /// What frames are drawn into, besides what the renderer holds itself,
+    /// like the device, queue and view of the wgpu renderer.
+    type Target<'a>;
+    /// What drawing a frame gives back, like the image drawn by the CPU renderer.
+    type Output;
+
+    /// Camera that puppets are seen through.
+    fn camera(&self) -> &Camera;
+
+    fn camera_mut(&mut self) -> &mut Camera;
+
+    /// Size of the frames drawn, in pixels.
+    fn viewport(&self) -> UVec2;
+
+    /// Changes the size of the frames drawn, as each renderer's own `resize` does.
+    fn resize(&mut self, viewport: UVec2);
+
+    /// Draws a frame of the puppet, in the pose of its last `end_set_params`.
+    fn draw(&mut self, puppet: &Puppet, target: Self::Target<'_>) -> Self::Output;
+
+    /// Moves and zooms the camera so that the whole puppet in its rest pose is centered and
+    /// fills the viewport, with `padding` as the fraction of the viewport left empty around it.
+    /// See `Camera::fit_bounds`.
+    fn frame_puppet(&mut self, puppet: &Puppet, padding: f32) {
+        if let Some(bounds) = puppet.nodes.model_bounds() {
+            let viewport = self.viewport().as_vec2();
+            self.camera_mut().fit_bounds(bounds, viewport, padding);
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct VertexBuffers {
     pub verts: Vec<Vec2>,
@@ -64,16 +109,25 @@ impl VertexBuffers {
     /// adds the mesh's vertices and UVs to the buffers and returns its index offset.
     pub fn push(&mut self, mesh: &Mesh) -> (u16, u16) {
         let index_offset = self.indices.len() as u16;
+        let vert_offset = self.push_vertices(mesh);
+
+        self.indices
+            .extend(mesh.indices.iter().map(|index| index + vert_offset));
+
+        (index_offset, vert_offset)
+    }
+
+    /// adds the mesh's vertices and UVs to the buffers, but not its indices, as for meshes that aren't drawn.
+    /// Returns the vertex offset.
+    pub fn push_vertices(&mut self, mesh: &Mesh) -> u16 {
         let vert_offset = self.verts.len() as u16;
 
         self.verts.extend_from_slice(&mesh.vertices);
         self.uvs.extend_from_slice(&mesh.uvs);
-        self.indices
-            .extend(mesh.indices.iter().map(|index| index + vert_offset));
         self.deforms
             .resize(self.deforms.len() + mesh.vertices.len(), Vec2::ZERO);
 
-        (index_offset, vert_offset)
+        vert_offset
     }
 }
 
@@ -85,17 +139,54 @@ pub struct PartRenderCtx {
     pub vert_len: usize,
 }
 
+/// Part deformed by a mesh group, with the weights of its vertices against the group's mesh.
+#[derive(Debug, Clone)]
+pub struct MeshGroupChild {
+    pub uuid: InoxNodeUuid,
+    pub weights: Vec<TriangleWeights>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MeshGroupRenderCtx {
+    pub vert_offset: u16,
+    pub vert_len: usize,
+    /// Parts whose closest mesh group ancestor is this one.
+    pub children: Vec<MeshGroupChild>,
+}
+
 #[derive(Debug, Clone)]
 pub enum RenderCtxKind {
     Node,
     Part(PartRenderCtx),
     Composite(Vec<InoxNodeUuid>),
+    MeshGroup(MeshGroupRenderCtx),
+}
+
+impl RenderCtxKind {
+    /// Range of the node's vertices in the vertex buffers, for parts and mesh groups.
+    pub fn vertex_range(&self) -> Option<Range<usize>> {
+        let (vert_offset, vert_len) = match self {
+            RenderCtxKind::Part(prc) => (prc.vert_offset, prc.vert_len),
+            RenderCtxKind::MeshGroup(mgrc) => (mgrc.vert_offset, mgrc.vert_len),
+            _ => return None,
+        };
+        Some(vert_offset as usize..vert_offset as usize + vert_len)
+    }
 }
 
 #[derive(Debug)]
 pub struct NodeRenderCtx {
     pub trans: Mat4,
     pub trans_offset: TransformOffset,
+    /// Opacity multiplier set by parameter bindings, on top of the node's own opacity.
+    pub opacity: f32,
+    /// Offset set by parameter bindings, added to the node's own zsort.
+    pub zsort_offset: f32,
+    /// `zsort_offset` when the draw order was last computed, see `RenderCtx::resort`.
+    sorted_zsort_offset: f32,
+    /// Zsort of the node added to the ones of its ancestors, with offsets,
+    /// as of when the draw order was last computed.
+    pub zsort: f32,
     pub kind: RenderCtxKind,
 }
 
@@ -106,80 +197,101 @@ pub struct RenderCtx {
     pub vertex_buffers: VertexBuffers,
     pub nodes_zsorted: Vec<InoxNodeUuid>,
     pub node_render_ctxs: NodeRenderCtxs,
+    /// Masks turned off at runtime, as `(part uuid, mask index)`.
+    pub disabled_masks: HashSet<(InoxNodeUuid, usize)>,
 }
 
 impl RenderCtx {
-    fn add_part<T>(
-        nodes: &InoxNodeTree<T>,
-        uuid: InoxNodeUuid,
-        vertex_buffers: &mut VertexBuffers,
-        node_render_ctxs: &mut NodeRenderCtxs,
-    ) {
-        let node = nodes.get_node(uuid).unwrap();
-
-        if let InoxData::Part(ref part) = node.data {
-            let (index_offset, vert_offset) = vertex_buffers.push(&part.mesh);
-            node_render_ctxs.insert(
-                uuid,
-                NodeRenderCtx {
-                    trans: Mat4::default(),
-                    trans_offset: node.trans_offset,
-                    kind: RenderCtxKind::Part(PartRenderCtx {
-                        index_offset,
-                        vert_offset,
-                        index_len: part.mesh.indices.len(),
-                        vert_len: part.mesh.vertices.len(),
-                    }),
-                },
-            );
-        }
-    }
-
     pub fn new<T>(nodes: &InoxNodeTree<T>) -> Self {
         let mut vertex_buffers = VertexBuffers::default();
         let nodes_zsorted = nodes.zsorted_root();
         let mut node_render_ctxs = HashMap::new();
 
-        for &uuid in &nodes_zsorted {
+        // Every node gets a context, including the ones inside composites,
+        // as transforms are computed for the whole tree.
+        for uuid in nodes.all_node_ids() {
             let node = nodes.get_node(uuid).unwrap();
 
-            match node.data {
-                InoxData::Part(_) => {
-                    Self::add_part(nodes, uuid, &mut vertex_buffers, &mut node_render_ctxs);
+            let kind = match node.data {
+                InoxData::Part(ref part) => {
+                    let (index_offset, vert_offset) = vertex_buffers.push(&part.mesh);
+                    RenderCtxKind::Part(PartRenderCtx {
+                        index_offset,
+                        vert_offset,
+                        index_len: part.mesh.indices.len(),
+                        vert_len: part.mesh.vertices.len(),
+                    })
                 }
+                // Nested composites are children too, and draw their own children
                 InoxData::Composite(_) => {
-                    // Children include the parent composite, so we have to filter it out.
-                    // TODO: wait... does it make sense for it to do that?
-                    let children = nodes
-                        .zsorted_children(node.uuid)
-                        .into_iter()
-                        .filter(|uuid| *uuid != node.uuid)
-                        .collect::<Vec<_>>();
-
-                    // put composite children's meshes into composite bufs
-                    for &uuid in &children {
-                        Self::add_part(nodes, uuid, &mut vertex_buffers, &mut node_render_ctxs);
-                    }
-
-                    node_render_ctxs.insert(
-                        uuid,
-                        NodeRenderCtx {
-                            trans: Mat4::default(),
-                            trans_offset: node.trans_offset,
-                            kind: RenderCtxKind::Composite(children),
-                        },
-                    );
+                    RenderCtxKind::Composite(nodes.zsorted_composite_children(uuid))
                 }
-                _ => {
-                    node_render_ctxs.insert(
-                        uuid,
-                        NodeRenderCtx {
-                            trans: Mat4::default(),
-                            trans_offset: node.trans_offset,
-                            kind: RenderCtxKind::Node,
-                        },
-                    );
+                InoxData::MeshGroup(ref mesh_group) => {
+                    RenderCtxKind::MeshGroup(MeshGroupRenderCtx {
+                        vert_offset: vertex_buffers.push_vertices(&mesh_group.mesh),
+                        vert_len: mesh_group.mesh.vertices.len(),
+                        children: Vec::new(),
+                    })
                 }
+                _ => RenderCtxKind::Node,
+            };
+
+            node_render_ctxs.insert(
+                uuid,
+                NodeRenderCtx {
+                    trans: Mat4::default(),
+                    trans_offset: node.trans_offset,
+                    opacity: 1.0,
+                    zsort_offset: 0.0,
+                    sorted_zsort_offset: 0.0,
+                    zsort: 0.0,
+                    kind,
+                },
+            );
+        }
+
+        // Parts are deformed by their closest mesh group ancestor, weighted in the rest pose
+        let rest_transforms = nodes.rest_transforms();
+        for uuid in nodes.all_node_ids() {
+            let Some(InoxData::Part(ref part)) = nodes.get_node(uuid).map(|node| &node.data) else {
+                continue;
+            };
+            let Some((group_uuid, mesh_group)) =
+                nodes
+                    .ancestors(uuid)
+                    .find_map(|ancestor| match nodes.get_node(ancestor)? {
+                        InoxNode {
+                            uuid,
+                            data: InoxData::MeshGroup(mesh_group),
+                            ..
+                        } => Some((*uuid, mesh_group)),
+                        _ => None,
+                    })
+            else {
+                continue;
+            };
+            let to_group = rest_transforms[&group_uuid].inverse() * rest_transforms[&uuid];
+            let weights = (part.mesh.vertices.iter())
+                .map(|vertex| {
+                    let vertex = to_group.transform_point3(vertex.extend(0.0)).truncate();
+                    triangle_weights(&mesh_group.mesh.vertices, &mesh_group.mesh.indices, vertex)
+                })
+                .collect::<Option<Vec<_>>>();
+
+            // A group mesh without triangles deforms nothing
+            if let (Some(weights), Some(RenderCtxKind::MeshGroup(mgrc))) = (
+                weights,
+                node_render_ctxs
+                    .get_mut(&group_uuid)
+                    .map(|nrc| &mut nrc.kind),
+            ) {
+                mgrc.children.push(MeshGroupChild { uuid, weights });
+            }
+        }
+
+        for (uuid, zsort) in nodes.absolute_zsorts_with_offsets(|_| 0.0) {
+            if let Some(nrc) = node_render_ctxs.get_mut(&uuid) {
+                nrc.zsort = zsort;
             }
         }
 
@@ -187,11 +299,198 @@ impl RenderCtx {
             vertex_buffers,
             nodes_zsorted,
             node_render_ctxs,
+            disabled_masks: HashSet::new(),
+        }
+    }
+
+    /// Whether a zsort offset changed since the draw order was last computed.
+    pub fn is_zsort_dirty(&self) -> bool {
+        (self.node_render_ctxs.values()).any(|nrc| nrc.zsort_offset != nrc.sorted_zsort_offset)
+    }
+
+    /// Recomputes the draw order of the nodes and of the children of composites,
+    /// from the zsort of the nodes in `nodes` and the zsort offsets.
+    ///
+    /// This sorts every node of the puppet, which is cheap compared to drawing it,
+    /// but still allocates, so it is best only done when a zsort changed.
+    pub fn resort<T>(&mut self, nodes: &InoxNodeTree<T>) {
+        let node_render_ctxs = &mut self.node_render_ctxs;
+        let zsort_offset = |uuid| {
+            node_render_ctxs
+                .get(&uuid)
+                .map_or(0.0, |nrc| nrc.zsort_offset)
+        };
+
+        self.nodes_zsorted = nodes.zsorted_root_with_offsets(zsort_offset);
+
+        let composites = (node_render_ctxs.iter())
+            .filter(|(_, nrc)| matches!(nrc.kind, RenderCtxKind::Composite(_)))
+            .map(|(&uuid, _)| {
+                let children = nodes.zsorted_composite_children_with_offsets(uuid, zsort_offset);
+                (uuid, children)
+            })
+            .collect::<Vec<_>>();
+        let zsorts = nodes.absolute_zsorts_with_offsets(zsort_offset);
+        for (uuid, children) in composites {
+            if let Some(nrc) = node_render_ctxs.get_mut(&uuid) {
+                nrc.kind = RenderCtxKind::Composite(children);
+            }
+        }
+
+        for (uuid, zsort) in zsorts {
+            if let Some(nrc) = node_render_ctxs.get_mut(&uuid) {
+                nrc.zsort = zsort;
+            }
+        }
+        for nrc in node_render_ctxs.values_mut() {
+            nrc.sorted_zsort_offset = nrc.zsort_offset;
+        }
+    }
+
+    /// Adds the deforms of mesh groups to the parts they deform.
+    ///
+    /// The deforms are moved from the space of the group to the space of each part,
+    /// so this must be called once the transforms are up to date.
+    pub fn apply_mesh_group_deforms(&mut self) {
+        let deforms = &mut self.vertex_buffers.deforms;
+
+        for group_render_ctx in self.node_render_ctxs.values() {
+            let RenderCtxKind::MeshGroup(ref mgrc) = group_render_ctx.kind else {
+                continue;
+            };
+            let start = mgrc.vert_offset as usize;
+            let group_deforms = deforms[start..start + mgrc.vert_len].to_vec();
+            if group_deforms.iter().all(|deform| *deform == Vec2::ZERO) {
+                continue;
+            }
+
+            for child in &mgrc.children {
+                let Some(child_render_ctx) = self.node_render_ctxs.get(&child.uuid) else {
+                    continue;
+                };
+                let Some(range) = child_render_ctx.kind.vertex_range() else {
+                    continue;
+                };
+
+                let to_child = child_render_ctx.trans.inverse() * group_render_ctx.trans;
+                for (deform, weights) in deforms[range].iter_mut().zip(&child.weights) {
+                    let group_deform = weights.interpolate(&group_deforms);
+                    *deform += to_child
+                        .transform_vector3(group_deform.extend(0.0))
+                        .truncate();
+                }
+            }
         }
     }
 }
 
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum SetMaskEnabledError {
+    #[error("Node {0:?} is not a part")]
+    NotAPart(InoxNodeUuid),
+    #[error("Part {uuid:?} has {len} masks, mask index {index} is out of bounds")]
+    OutOfBounds {
+        uuid: InoxNodeUuid,
+        index: usize,
+        len: usize,
+    },
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum SetPartVerticesError {
+    #[error("Node {0:?} is not a part")]
+    NotAPart(InoxNodeUuid),
+    #[error("Part {uuid:?} has {expected} vertices, got {actual}")]
+    LengthMismatch {
+        uuid: InoxNodeUuid,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl RenderCtx {
+    /// Replaces the vertices of a part in the vertex buffer, and returns the offset of
+    /// the part's first vertex in it.
+    pub fn set_part_vertices(
+        &mut self,
+        uuid: InoxNodeUuid,
+        verts: &[Vec2],
+    ) -> Result<usize, SetPartVerticesError> {
+        let Some(RenderCtxKind::Part(prc)) = self.node_render_ctxs.get(&uuid).map(|nrc| &nrc.kind)
+        else {
+            return Err(SetPartVerticesError::NotAPart(uuid));
+        };
+
+        if verts.len() != prc.vert_len {
+            return Err(SetPartVerticesError::LengthMismatch {
+                uuid,
+                expected: prc.vert_len,
+                actual: verts.len(),
+            });
+        }
+
+        let start = prc.vert_offset as usize;
+        self.vertex_buffers.verts[start..start + verts.len()].copy_from_slice(verts);
+        Ok(start)
+    }
+}
+
 impl Puppet {
+    /// Turns a part's mask on or off, without editing the model.
+    ///
+    /// `mask_index` is the index of the mask in the part's `draw_state.masks`.
+    /// Useful to find which mask clips a part, or to temporarily reveal a masked region.
+    pub fn set_mask_enabled(
+        &mut self,
+        part_uuid: InoxNodeUuid,
+        mask_index: usize,
+        enabled: bool,
+    ) -> Result<(), SetMaskEnabledError> {
+        let Some(InoxData::Part(part)) = self.nodes.get_node(part_uuid).map(|node| &node.data)
+        else {
+            return Err(SetMaskEnabledError::NotAPart(part_uuid));
+        };
+
+        let len = part.draw_state.masks.len();
+        if mask_index >= len {
+            return Err(SetMaskEnabledError::OutOfBounds {
+                uuid: part_uuid,
+                index: mask_index,
+                len,
+            });
+        }
+
+        let disabled_masks = &mut self.render_ctx.disabled_masks;
+        if enabled {
+            disabled_masks.remove(&(part_uuid, mask_index));
+        } else {
+            disabled_masks.insert((part_uuid, mask_index));
+        }
+
+        Ok(())
+    }
+
+    /// Whether a part's mask is on. Masks are on unless turned off with `set_mask_enabled`.
+    pub fn is_mask_enabled(&self, part_uuid: InoxNodeUuid, mask_index: usize) -> bool {
+        !self
+            .render_ctx
+            .disabled_masks
+            .contains(&(part_uuid, mask_index))
+    }
+
+    /// Turns every mask turned off with `set_mask_enabled` back on.
+    pub fn clear_disabled_masks(&mut self) {
+        self.render_ctx.disabled_masks.clear();
+    }
+
+    /// Recomputes the draw order, e.g. after changing the `zsort` of nodes at runtime.
+    ///
+    /// Parameters binding zsorts already trigger this from `end_set_params`, only when an offset
+    /// changed. This sorts every node, so there is no need to call it every frame.
+    pub fn resort(&mut self) {
+        self.render_ctx.resort(&self.nodes);
+    }
+
     /// Update the puppet's nodes' absolute transforms, by combining transforms
     /// from each node's ancestors in a pre-order traversal manner.
     pub fn update_trans(&mut self) {
@@ -0,0 +1,168 @@
+use glam::{UVec2, Vec4};
+
+use crate::puppet::Puppet;
+use crate::render::color::AlphaMode;
+
+use super::texture::TextureFilter;
+use super::{
+    BloomParams, CompositeResolution, OpenglRenderer, OpenglRendererError, RendererConfig,
+};
+
+/// Builds an `OpenglRenderer` with options set before its first frame.
+///
+/// Options that can be changed later are applied through the renderer's setters,
+/// so the builder is only a shortcut for them. Get one with `OpenglRenderer::builder`.
+pub struct OpenglRendererBuilder<'a> {
+    puppet: &'a Puppet,
+    viewport: UVec2,
+    config: RendererConfig,
+    clear_color: Option<Vec4>,
+    culling: bool,
+    min_part_pixels: f32,
+    flip_y: bool,
+    max_texture_size: Option<u32>,
+    texture_alpha: AlphaMode,
+    output_alpha: AlphaMode,
+    composite_caching: bool,
+    bloom: Option<BloomParams>,
+}
+
+impl<'a> OpenglRendererBuilder<'a> {
+    pub(super) fn new(puppet: &'a Puppet, viewport: UVec2) -> Self {
+        Self {
+            puppet,
+            viewport,
+            config: RendererConfig::default(),
+            clear_color: None,
+            culling: true,
+            min_part_pixels: 0.0,
+            flip_y: false,
+            max_texture_size: None,
+            texture_alpha: AlphaMode::Straight,
+            output_alpha: AlphaMode::Premultiplied,
+            composite_caching: false,
+            bloom: None,
+        }
+    }
+
+    /// Replaces all the options of `RendererConfig` at once.
+    pub fn config(mut self, config: RendererConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// See `RendererConfig::samples`.
+    pub fn samples(mut self, samples: u32) -> Self {
+        self.config.samples = samples;
+        self
+    }
+
+    /// See `RendererConfig::texture_filter`.
+    pub fn texture_filter(mut self, texture_filter: TextureFilter) -> Self {
+        self.config.texture_filter = texture_filter;
+        self
+    }
+
+    /// See `RendererConfig::anisotropy`.
+    pub fn anisotropy(mut self, anisotropy: f32) -> Self {
+        self.config.anisotropy = anisotropy;
+        self
+    }
+
+    /// See `RendererConfig::debug_messages`.
+    pub fn debug_messages(mut self, debug_messages: bool) -> Self {
+        self.config.debug_messages = debug_messages;
+        self
+    }
+
+    /// See `RendererConfig::linear_space`.
+    pub fn linear_space(mut self, linear_space: bool) -> Self {
+        self.config.linear_space = linear_space;
+        self
+    }
+
+    /// See `RendererConfig::texture_array`.
+    pub fn texture_array(mut self, texture_array: bool) -> Self {
+        self.config.texture_array = texture_array;
+        self
+    }
+
+    /// See `RendererConfig::composite_resolution`.
+    pub fn composite_resolution(mut self, composite_resolution: CompositeResolution) -> Self {
+        self.config.composite_resolution = composite_resolution;
+        self
+    }
+
+    /// See `OpenglRenderer::set_clear_color`.
+    pub fn clear_color(mut self, rgba: Vec4) -> Self {
+        self.clear_color = Some(rgba);
+        self
+    }
+
+    /// See `OpenglRenderer::set_culling`.
+    pub fn culling(mut self, culling: bool) -> Self {
+        self.culling = culling;
+        self
+    }
+
+    /// See `OpenglRenderer::set_min_part_pixels`.
+    pub fn min_part_pixels(mut self, min_part_pixels: f32) -> Self {
+        self.min_part_pixels = min_part_pixels;
+        self
+    }
+
+    /// See `OpenglRenderer::set_flip_y`.
+    pub fn flip_y(mut self, flip_y: bool) -> Self {
+        self.flip_y = flip_y;
+        self
+    }
+
+    /// See `OpenglRenderer::set_max_texture_size`.
+    pub fn max_texture_size(mut self, max_texture_size: Option<u32>) -> Self {
+        self.max_texture_size = max_texture_size;
+        self
+    }
+
+    /// See `OpenglRenderer::set_texture_alpha`.
+    pub fn texture_alpha(mut self, texture_alpha: AlphaMode) -> Self {
+        self.texture_alpha = texture_alpha;
+        self
+    }
+
+    /// See `OpenglRenderer::set_output_alpha`.
+    pub fn output_alpha(mut self, output_alpha: AlphaMode) -> Self {
+        self.output_alpha = output_alpha;
+        self
+    }
+
+    /// See `OpenglRenderer::set_composite_caching`.
+    pub fn composite_caching(mut self, composite_caching: bool) -> Self {
+        self.composite_caching = composite_caching;
+        self
+    }
+
+    /// See `OpenglRenderer::set_bloom`.
+    pub fn bloom(mut self, bloom: BloomParams) -> Self {
+        self.bloom = Some(bloom);
+        self
+    }
+
+    /// Creates the renderer on `gl`. Textures still have to be uploaded.
+    pub fn build(self, gl: glow::Context) -> Result<OpenglRenderer, OpenglRendererError> {
+        let mut renderer = OpenglRenderer::new(gl, self.viewport, self.puppet, self.config)?;
+
+        if let Some(clear_color) = self.clear_color {
+            renderer.set_clear_color(clear_color);
+        }
+        renderer.set_culling(self.culling);
+        renderer.set_min_part_pixels(self.min_part_pixels);
+        renderer.set_flip_y(self.flip_y);
+        renderer.set_max_texture_size(self.max_texture_size);
+        renderer.set_texture_alpha(self.texture_alpha);
+        renderer.set_output_alpha(self.output_alpha);
+        renderer.set_composite_caching(self.composite_caching);
+        renderer.set_bloom(self.bloom)?;
+
+        Ok(renderer)
+    }
+}
@@ -0,0 +1,134 @@
+//! Display of a puppet in an [egui](https://docs.rs/egui) UI painted with `egui_glow`.
+
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use ::egui::{PaintCallback, PaintCallbackInfo, Rect};
+use egui_glow::CallbackFn;
+use glam::{uvec2, Vec4};
+use glow::HasContext;
+use tracing::error;
+
+use crate::puppet::Puppet;
+
+use super::framebuffer::ViewFramebuffer;
+use super::state::SavedGlState;
+use super::OpenglRenderer;
+
+/// Renderer and puppet of a `PuppetView`.
+pub struct PuppetViewState {
+    pub renderer: OpenglRenderer,
+    pub puppet: Puppet,
+    /// Framebuffer the puppet is rendered into before being copied into egui's, created on first paint.
+    framebuffer: Option<ViewFramebuffer>,
+}
+
+impl PuppetViewState {
+    /// Renders the puppet in the viewport that egui set for the callback, in `target`.
+    fn paint(&mut self, info: &PaintCallbackInfo, target: Option<glow::Framebuffer>) {
+        let gl = self.renderer.gl();
+
+        // egui sets the viewport to the callback's rectangle, rounded to pixels
+        let mut rect = [0; 4];
+        unsafe { gl.get_parameter_i32_slice(glow::VIEWPORT, &mut rect) };
+        let [x, y, width, height] = rect;
+        if width <= 0 || height <= 0 {
+            return;
+        }
+        let size = uvec2(width as u32, height as u32);
+
+        let state = unsafe { SavedGlState::save(gl) };
+        // egui clips with the scissor test, which would also clip the offscreen frame
+        let scissor = unsafe { gl.is_enabled(glow::SCISSOR_TEST) };
+
+        let framebuffer = match self.framebuffer.take() {
+            Some(framebuffer) => unsafe {
+                framebuffer.resize(gl, size.x, size.y).map(|()| framebuffer)
+            },
+            None => unsafe { ViewFramebuffer::new(gl, size.x, size.y) },
+        };
+        let framebuffer = match framebuffer {
+            Ok(framebuffer) => framebuffer,
+            Err(e) => {
+                error!("Could not create a framebuffer to paint the puppet in egui: {e}");
+                return;
+            }
+        };
+
+        unsafe {
+            gl.disable(glow::SCISSOR_TEST);
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer.framebuffer));
+            gl.viewport(0, 0, width, height);
+        }
+        match self.renderer.clear_color() {
+            Some(_) => self.renderer.clear(),
+            None => self.renderer.clear_with(Vec4::ZERO),
+        }
+
+        // The camera's scale is in points, so the puppet keeps its size in the UI on any display
+        let scale = self.renderer.camera.scale;
+        self.renderer.camera.scale = scale * info.pixels_per_point;
+        self.renderer
+            .draw_model_to(&self.puppet, framebuffer.framebuffer, size);
+        self.renderer.camera.scale = scale;
+
+        let gl = self.renderer.gl();
+        unsafe {
+            state.restore(gl);
+            if scissor {
+                gl.enable(glow::SCISSOR_TEST);
+            }
+            framebuffer.blit_to(gl, target, x, y, size.x, size.y);
+        }
+        self.renderer.invalidate_gl_cache();
+
+        self.framebuffer = Some(framebuffer);
+    }
+}
+
+/// Puppet displayed in egui widgets, which is rendered while egui paints its UI with `egui_glow`.
+///
+/// The renderer must use the GL context that egui paints with, e.g. through a second
+/// `glow::Context` loaded from the same context. The rectangle of the puppet is filled with the
+/// renderer's clear color, transparent if it has none, as the frame is copied over what egui
+/// drew rather than blended with it. Masks need the target of the renderer to have a stencil
+/// buffer, so the puppet is rendered offscreen first.
+///
+/// The camera's scale is in points rather than pixels: it is multiplied by egui's pixels per point
+/// while drawing, so the puppet keeps the same size relative to the UI on high-DPI displays.
+#[derive(Clone)]
+pub struct PuppetView {
+    state: Arc<Mutex<PuppetViewState>>,
+}
+
+impl PuppetView {
+    pub fn new(renderer: OpenglRenderer, puppet: Puppet) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(PuppetViewState {
+                renderer,
+                puppet,
+                framebuffer: None,
+            })),
+        }
+    }
+
+    /// Locks the renderer and the puppet, e.g. to set parameters or move the camera.
+    ///
+    /// Don't hold the lock while egui paints, which would deadlock.
+    pub fn lock(&self) -> MutexGuard<'_, PuppetViewState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Gets a callback drawing the puppet in `rect`, in egui's points, to add to an `egui::Painter`.
+    pub fn paint_callback(&self, rect: Rect) -> PaintCallback {
+        let state = self.state.clone();
+        let callback = CallbackFn::new(move |info, painter| {
+            let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+            state.paint(&info, painter.intermediate_fbo());
+        });
+
+        PaintCallback {
+            rect,
+            callback: Arc::new(callback),
+        }
+    }
+}
@@ -0,0 +1,744 @@
+use glow::HasContext;
+
+use super::shaders::{BUMPMAP_TEXTURE_UNIT, EMISSIVE_TEXTURE_UNIT};
+use super::texture;
+
+/// Internal format and pixel type of a color attachment, storing floats or 8-bit values.
+///
+/// Emissive light can exceed 1, so it is stored as floats where they are color-renderable.
+/// Elsewhere, such as WebGL without `EXT_color_buffer_float`, it is clamped to 8-bit.
+/// Albedo is stored as floats when blending in linear space, which would band in 8-bit.
+fn color_format(float: bool) -> (u32, u32) {
+    if float {
+        (glow::RGBA16F, glow::FLOAT)
+    } else {
+        (glow::RGBA8, glow::UNSIGNED_BYTE)
+    }
+}
+
+/// Checks that the framebuffer bound to `FRAMEBUFFER` can be drawn to.
+unsafe fn check_framebuffer_status(gl: &glow::Context) -> Result<(), String> {
+    let reason = match gl.check_framebuffer_status(glow::FRAMEBUFFER) {
+        glow::FRAMEBUFFER_COMPLETE => return Ok(()),
+        glow::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => "an attachment can't be rendered to",
+        glow::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => "it has no attachments",
+        glow::FRAMEBUFFER_INCOMPLETE_DIMENSIONS => "its attachments have different sizes",
+        glow::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE => "its attachments have different sample counts",
+        glow::FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER => "a draw buffer has no attachment",
+        glow::FRAMEBUFFER_INCOMPLETE_READ_BUFFER => "the read buffer has no attachment",
+        glow::FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS => "its attachments have different layer counts",
+        glow::FRAMEBUFFER_UNSUPPORTED => "the combination of attachment formats is unsupported",
+        glow::FRAMEBUFFER_UNDEFINED => "the default framebuffer doesn't exist",
+        status => return Err(format!("Framebuffer is incomplete (status 0x{status:x})")),
+    };
+    Err(format!("Framebuffer is incomplete: {reason}"))
+}
+
+/// Offscreen framebuffer that composites are drawn into, with albedo, emissive,
+/// bumpmap and depth-stencil attachments.
+pub struct CompositeFramebuffer {
+    pub framebuffer: glow::Framebuffer,
+    pub albedo: glow::Texture,
+    pub emissive: glow::Texture,
+    pub bump: glow::Texture,
+    pub stencil: glow::Texture,
+    /// Multisampled targets drawn into instead, and resolved to the textures afterwards.
+    pub multisample: Option<MultisampleTargets>,
+    /// Whether the emissive attachment stores floats, see `color_format`.
+    pub float_emissive: bool,
+    /// Whether the albedo attachment stores floats, see `color_format`.
+    pub float_albedo: bool,
+}
+
+/// Multisampled renderbuffers mirroring the attachments of a `CompositeFramebuffer`.
+pub struct MultisampleTargets {
+    pub framebuffer: glow::Framebuffer,
+    /// Albedo, emissive and bumpmap renderbuffers.
+    pub colors: [glow::Renderbuffer; 3],
+    pub depth_stencil: glow::Renderbuffer,
+    pub samples: u32,
+}
+
+impl CompositeFramebuffer {
+    /// Creates the framebuffer and its attachments, allocated to `width` x `height`.
+    ///
+    /// With more than 1 sample, draws go to multisampled renderbuffers that `resolve` blits
+    /// to the attachments' textures.
+    /// `float_emissive` and `float_albedo` should only be set if float textures can be rendered to.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the GL objects can't be created, or if the context can't draw to them.
+    ///
+    /// # Safety
+    ///
+    /// The framebuffer must only be used with the GL context it was created with.
+    pub unsafe fn new(
+        gl: &glow::Context,
+        width: u32,
+        height: u32,
+        samples: u32,
+        float_emissive: bool,
+        float_albedo: bool,
+    ) -> Result<Self, String> {
+        let multisample = if samples > 1 {
+            Some(MultisampleTargets {
+                framebuffer: gl.create_framebuffer()?,
+                colors: [
+                    gl.create_renderbuffer()?,
+                    gl.create_renderbuffer()?,
+                    gl.create_renderbuffer()?,
+                ],
+                depth_stencil: gl.create_renderbuffer()?,
+                samples,
+            })
+        } else {
+            None
+        };
+
+        let cf = Self {
+            framebuffer: gl.create_framebuffer()?,
+            albedo: gl.create_texture()?,
+            emissive: gl.create_texture()?,
+            bump: gl.create_texture()?,
+            stencil: gl.create_texture()?,
+            multisample,
+            float_emissive,
+            float_albedo,
+        };
+        cf.resize(gl, width, height)?;
+        Ok(cf)
+    }
+
+    /// Gets the framebuffer that draws should go to, which is multisampled if there are several samples.
+    pub fn draw_framebuffer(&self) -> glow::Framebuffer {
+        match &self.multisample {
+            Some(multisample) => multisample.framebuffer,
+            None => self.framebuffer,
+        }
+    }
+
+    /// Resolves the multisampled renderbuffers into the attachments' textures.
+    /// Does nothing without multisampling.
+    ///
+    /// # Safety
+    ///
+    /// The framebuffer must only be used with the GL context it was created with.
+    pub unsafe fn resolve(&self, gl: &glow::Context, width: u32, height: u32) {
+        let Some(multisample) = &self.multisample else {
+            return;
+        };
+
+        gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(multisample.framebuffer));
+        gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, Some(self.framebuffer));
+
+        // Attachments are blitted one at a time, as a blit reads a single color buffer
+        for i in 0..3 {
+            let attachment = glow::COLOR_ATTACHMENT0 + i;
+            gl.read_buffer(attachment);
+            let mut draw_buffers = [glow::NONE; 3];
+            draw_buffers[i as usize] = attachment;
+            gl.draw_buffers(&draw_buffers);
+
+            let (w, h) = (width as i32, height as i32);
+            gl.blit_framebuffer(
+                0,
+                0,
+                w,
+                h,
+                0,
+                0,
+                w,
+                h,
+                glow::COLOR_BUFFER_BIT,
+                glow::NEAREST,
+            );
+        }
+
+        gl.draw_buffers(&[
+            glow::COLOR_ATTACHMENT0,
+            glow::COLOR_ATTACHMENT1,
+            glow::COLOR_ATTACHMENT2,
+        ]);
+        gl.read_buffer(glow::COLOR_ATTACHMENT0);
+        gl.bind_framebuffer(glow::READ_FRAMEBUFFER, None);
+    }
+
+    /// Reallocates the attachments to `width` x `height`, discarding their contents.
+    ///
+    /// # Errors
+    ///
+    /// Fails with the framebuffer status if the context can't draw to the attachments.
+    ///
+    /// # Safety
+    ///
+    /// The framebuffer must only be used with the GL context it was created with.
+    pub unsafe fn resize(&self, gl: &glow::Context, width: u32, height: u32) -> Result<(), String> {
+        let (albedo_internal, albedo_ty) = color_format(self.float_albedo);
+        let (emissive_internal, emissive_ty) = color_format(self.float_emissive);
+
+        if let Some(multisample) = &self.multisample {
+            let formats = [albedo_internal, emissive_internal, glow::RGBA8];
+            multisample.resize(gl, width, height, formats)?;
+        }
+
+        texture::upload_empty(gl, self.albedo, width, height, albedo_internal, albedo_ty);
+        texture::upload_empty(
+            gl,
+            self.emissive,
+            width,
+            height,
+            emissive_internal,
+            emissive_ty,
+        );
+        texture::upload_empty(
+            gl,
+            self.bump,
+            width,
+            height,
+            glow::RGBA8,
+            glow::UNSIGNED_BYTE,
+        );
+
+        gl.bind_texture(glow::TEXTURE_2D, Some(self.stencil));
+        gl.tex_image_2d(
+            glow::TEXTURE_2D,
+            0,
+            glow::DEPTH24_STENCIL8 as i32,
+            width as i32,
+            height as i32,
+            0,
+            glow::DEPTH_STENCIL,
+            glow::UNSIGNED_INT_24_8,
+            None,
+        );
+
+        self.attach_textures(gl)
+    }
+
+    unsafe fn attach_textures(&self, gl: &glow::Context) -> Result<(), String> {
+        gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.framebuffer));
+
+        gl.framebuffer_texture_2d(
+            glow::FRAMEBUFFER,
+            glow::COLOR_ATTACHMENT0,
+            glow::TEXTURE_2D,
+            Some(self.albedo),
+            0,
+        );
+        gl.framebuffer_texture_2d(
+            glow::FRAMEBUFFER,
+            glow::COLOR_ATTACHMENT1,
+            glow::TEXTURE_2D,
+            Some(self.emissive),
+            0,
+        );
+        gl.framebuffer_texture_2d(
+            glow::FRAMEBUFFER,
+            glow::COLOR_ATTACHMENT2,
+            glow::TEXTURE_2D,
+            Some(self.bump),
+            0,
+        );
+        gl.framebuffer_texture_2d(
+            glow::FRAMEBUFFER,
+            glow::DEPTH_STENCIL_ATTACHMENT,
+            glow::TEXTURE_2D,
+            Some(self.stencil),
+            0,
+        );
+
+        let status = check_framebuffer_status(gl);
+        gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+        status
+    }
+
+    /// Binds the attachments' textures on texture unit 0 for the albedo,
+    /// `EMISSIVE_TEXTURE_UNIT` and `BUMPMAP_TEXTURE_UNIT`.
+    ///
+    /// # Safety
+    ///
+    /// The framebuffer must only be used with the GL context it was created with.
+    pub unsafe fn bind_textures(&self, gl: &glow::Context) {
+        gl.active_texture(glow::TEXTURE0);
+        gl.bind_texture(glow::TEXTURE_2D, Some(self.albedo));
+        gl.active_texture(glow::TEXTURE0 + EMISSIVE_TEXTURE_UNIT);
+        gl.bind_texture(glow::TEXTURE_2D, Some(self.emissive));
+        gl.active_texture(glow::TEXTURE0 + BUMPMAP_TEXTURE_UNIT);
+        gl.bind_texture(glow::TEXTURE_2D, Some(self.bump));
+    }
+
+    /// Deletes the framebuffer and its attachments.
+    ///
+    /// # Safety
+    ///
+    /// The framebuffer must only be used with the GL context it was created with.
+    pub unsafe fn delete(self, gl: &glow::Context) {
+        gl.delete_framebuffer(self.framebuffer);
+        gl.delete_texture(self.albedo);
+        gl.delete_texture(self.emissive);
+        gl.delete_texture(self.bump);
+        gl.delete_texture(self.stencil);
+
+        if let Some(multisample) = self.multisample {
+            gl.delete_framebuffer(multisample.framebuffer);
+            for color in multisample.colors {
+                gl.delete_renderbuffer(color);
+            }
+            gl.delete_renderbuffer(multisample.depth_stencil);
+        }
+    }
+}
+
+impl MultisampleTargets {
+    unsafe fn resize(
+        &self,
+        gl: &glow::Context,
+        width: u32,
+        height: u32,
+        formats: [u32; 3],
+    ) -> Result<(), String> {
+        let (w, h) = (width as i32, height as i32);
+        let samples = self.samples as i32;
+
+        gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.framebuffer));
+        for (i, (&color, format)) in self.colors.iter().zip(formats).enumerate() {
+            gl.bind_renderbuffer(glow::RENDERBUFFER, Some(color));
+            gl.renderbuffer_storage_multisample(glow::RENDERBUFFER, samples, format, w, h);
+            gl.framebuffer_renderbuffer(
+                glow::FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0 + i as u32,
+                glow::RENDERBUFFER,
+                Some(color),
+            );
+        }
+
+        // Masks are written per sample, so the stencil buffer is multisampled too
+        gl.bind_renderbuffer(glow::RENDERBUFFER, Some(self.depth_stencil));
+        gl.renderbuffer_storage_multisample(
+            glow::RENDERBUFFER,
+            samples,
+            glow::DEPTH24_STENCIL8,
+            w,
+            h,
+        );
+        gl.framebuffer_renderbuffer(
+            glow::FRAMEBUFFER,
+            glow::DEPTH_STENCIL_ATTACHMENT,
+            glow::RENDERBUFFER,
+            Some(self.depth_stencil),
+        );
+
+        let status = check_framebuffer_status(gl);
+        gl.bind_renderbuffer(glow::RENDERBUFFER, None);
+        gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+        status
+    }
+}
+
+/// Composite framebuffers that composite passes check out while they draw and give back once
+/// their composite is drawn, so that a composite nested in another gets one of its own.
+///
+/// The pool grows when more passes are in progress at once than it has free framebuffers.
+pub struct CompositeFramebufferPool {
+    free: Vec<CompositeFramebuffer>,
+    width: u32,
+    height: u32,
+    samples: u32,
+    float_emissive: bool,
+    float_albedo: bool,
+}
+
+impl CompositeFramebufferPool {
+    /// Creates a pool of framebuffers of `width` x `height`, with one allocated up front.
+    /// See `CompositeFramebuffer::new` for the other arguments.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the first framebuffer can't be created.
+    ///
+    /// # Safety
+    ///
+    /// The pool must only be used with the GL context it was created with.
+    pub unsafe fn new(
+        gl: &glow::Context,
+        width: u32,
+        height: u32,
+        samples: u32,
+        float_emissive: bool,
+        float_albedo: bool,
+    ) -> Result<Self, String> {
+        let mut pool = Self {
+            free: Vec::new(),
+            width,
+            height,
+            samples,
+            float_emissive,
+            float_albedo,
+        };
+        let framebuffer = pool.check_out(gl)?;
+        pool.give_back(framebuffer);
+        Ok(pool)
+    }
+
+    /// Takes a free framebuffer out of the pool, creating one if there are none.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a new framebuffer can't be created.
+    ///
+    /// # Safety
+    ///
+    /// The pool must only be used with the GL context it was created with.
+    pub unsafe fn check_out(&mut self, gl: &glow::Context) -> Result<CompositeFramebuffer, String> {
+        match self.free.pop() {
+            Some(framebuffer) => Ok(framebuffer),
+            None => CompositeFramebuffer::new(
+                gl,
+                self.width,
+                self.height,
+                self.samples,
+                self.float_emissive,
+                self.float_albedo,
+            ),
+        }
+    }
+
+    /// Puts a framebuffer taken with `check_out` back into the pool.
+    pub fn give_back(&mut self, framebuffer: CompositeFramebuffer) {
+        self.free.push(framebuffer);
+    }
+
+    /// Reallocates the pool's framebuffers to `width` x `height`.
+    ///
+    /// Only one framebuffer is kept and resized, the others are freed,
+    /// and created again if passes are nested deeply enough to need them.
+    /// Framebuffers that are checked out must not be given back afterwards.
+    ///
+    /// # Safety
+    ///
+    /// The pool must only be used with the GL context it was created with.
+    pub unsafe fn resize(
+        &mut self,
+        gl: &glow::Context,
+        width: u32,
+        height: u32,
+    ) -> Result<(), String> {
+        self.width = width;
+        self.height = height;
+        while self.free.len() > 1 {
+            if let Some(framebuffer) = self.free.pop() {
+                framebuffer.delete(gl);
+            }
+        }
+        match self.free.first() {
+            Some(framebuffer) => framebuffer.resize(gl, width, height),
+            None => Ok(()),
+        }
+    }
+
+    /// # Safety
+    ///
+    /// The pool must only be used with the GL context it was created with.
+    pub unsafe fn delete(self, gl: &glow::Context) {
+        for framebuffer in self.free {
+            framebuffer.delete(gl);
+        }
+    }
+}
+
+/// Offscreen framebuffer that a puppet is rendered into, then copied to a rectangle of another
+/// framebuffer whose origin the renderer can't draw at, such as a UI widget within a window.
+pub struct ViewFramebuffer {
+    pub framebuffer: glow::Framebuffer,
+    pub color: glow::Renderbuffer,
+    pub depth_stencil: glow::Renderbuffer,
+}
+
+impl ViewFramebuffer {
+    /// Creates the framebuffer and its renderbuffers, allocated to `width` x `height`.
+    ///
+    /// # Safety
+    ///
+    /// The framebuffer must only be used with the GL context it was created with.
+    pub unsafe fn new(gl: &glow::Context, width: u32, height: u32) -> Result<Self, String> {
+        let view = Self {
+            framebuffer: gl.create_framebuffer()?,
+            color: gl.create_renderbuffer()?,
+            depth_stencil: gl.create_renderbuffer()?,
+        };
+        view.resize(gl, width, height)?;
+        Ok(view)
+    }
+
+    /// Reallocates the renderbuffers to `width` x `height`.
+    ///
+    /// # Safety
+    ///
+    /// The framebuffer must only be used with the GL context it was created with.
+    pub unsafe fn resize(&self, gl: &glow::Context, width: u32, height: u32) -> Result<(), String> {
+        let (w, h) = (width as i32, height as i32);
+
+        gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.framebuffer));
+        for (renderbuffer, format, attachment) in [
+            (self.color, glow::RGBA8, glow::COLOR_ATTACHMENT0),
+            (
+                self.depth_stencil,
+                glow::DEPTH24_STENCIL8,
+                glow::DEPTH_STENCIL_ATTACHMENT,
+            ),
+        ] {
+            gl.bind_renderbuffer(glow::RENDERBUFFER, Some(renderbuffer));
+            gl.renderbuffer_storage(glow::RENDERBUFFER, format, w, h);
+            gl.framebuffer_renderbuffer(
+                glow::FRAMEBUFFER,
+                attachment,
+                glow::RENDERBUFFER,
+                Some(renderbuffer),
+            );
+        }
+
+        let status = check_framebuffer_status(gl);
+        gl.bind_renderbuffer(glow::RENDERBUFFER, None);
+        gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+        status
+    }
+
+    /// Copies the `width` x `height` color of the framebuffer to `target`, with its bottom-left
+    /// corner at `x`, `y`, leaving `target` bound. The copy is clipped by the scissor test, if enabled.
+    ///
+    /// # Safety
+    ///
+    /// The framebuffer must only be used with the GL context it was created with,
+    /// and be at least `width` x `height`.
+    pub unsafe fn blit_to(
+        &self,
+        gl: &glow::Context,
+        target: Option<glow::Framebuffer>,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    ) {
+        gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(self.framebuffer));
+        gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, target);
+
+        let (w, h) = (width as i32, height as i32);
+        gl.blit_framebuffer(
+            0,
+            0,
+            w,
+            h,
+            x,
+            y,
+            x + w,
+            y + h,
+            glow::COLOR_BUFFER_BIT,
+            glow::NEAREST,
+        );
+
+        gl.bind_framebuffer(glow::FRAMEBUFFER, target);
+    }
+
+    /// Deletes the framebuffer and its renderbuffers.
+    ///
+    /// # Safety
+    ///
+    /// The framebuffer must only be used with the GL context it was created with.
+    pub unsafe fn delete(self, gl: &glow::Context) {
+        gl.delete_framebuffer(self.framebuffer);
+        gl.delete_renderbuffer(self.color);
+        gl.delete_renderbuffer(self.depth_stencil);
+    }
+}
+
+/// Copy of the framebuffer being drawn to, read by the shaders of blend modes that
+/// can't be done with fixed-function blending.
+pub struct BackdropFramebuffer {
+    pub framebuffer: glow::Framebuffer,
+    pub texture: glow::Texture,
+    /// Whether the texture stores floats, to copy float framebuffers, see `color_format`.
+    pub float: bool,
+}
+
+impl BackdropFramebuffer {
+    /// Creates the framebuffer and its texture, allocated to `width` x `height`.
+    ///
+    /// Multisampled framebuffers can only be resolved into the same format, so `float` should match
+    /// the framebuffers that are copied.
+    ///
+    /// # Safety
+    ///
+    /// The framebuffer must only be used with the GL context it was created with.
+    pub unsafe fn new(
+        gl: &glow::Context,
+        width: u32,
+        height: u32,
+        float: bool,
+    ) -> Result<Self, String> {
+        let backdrop = Self {
+            framebuffer: gl.create_framebuffer()?,
+            texture: gl.create_texture()?,
+            float,
+        };
+        backdrop.resize(gl, width, height)?;
+        Ok(backdrop)
+    }
+
+    /// Reallocates the texture to `width` x `height`.
+    ///
+    /// # Safety
+    ///
+    /// The framebuffer must only be used with the GL context it was created with.
+    pub unsafe fn resize(&self, gl: &glow::Context, width: u32, height: u32) -> Result<(), String> {
+        let (internal_format, ty) = color_format(self.float);
+        texture::upload_empty(gl, self.texture, width, height, internal_format, ty);
+
+        gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.framebuffer));
+        gl.framebuffer_texture_2d(
+            glow::FRAMEBUFFER,
+            glow::COLOR_ATTACHMENT0,
+            glow::TEXTURE_2D,
+            Some(self.texture),
+            0,
+        );
+        let status = check_framebuffer_status(gl);
+        gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+        status
+    }
+
+    /// Copies the color of `source` into the texture and binds it on `unit`,
+    /// leaving `source` bound as the framebuffer.
+    ///
+    /// # Safety
+    ///
+    /// The framebuffer must only be used with the GL context it was created with,
+    /// and `source` must be `width` x `height` like the texture.
+    pub unsafe fn copy_from(
+        &self,
+        gl: &glow::Context,
+        source: Option<glow::Framebuffer>,
+        width: u32,
+        height: u32,
+        unit: u32,
+    ) {
+        gl.bind_framebuffer(glow::READ_FRAMEBUFFER, source);
+        gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, Some(self.framebuffer));
+
+        let (w, h) = (width as i32, height as i32);
+        gl.blit_framebuffer(
+            0,
+            0,
+            w,
+            h,
+            0,
+            0,
+            w,
+            h,
+            glow::COLOR_BUFFER_BIT,
+            glow::NEAREST,
+        );
+
+        gl.bind_framebuffer(glow::FRAMEBUFFER, source);
+        gl.active_texture(glow::TEXTURE0 + unit);
+        gl.bind_texture(glow::TEXTURE_2D, Some(self.texture));
+        gl.active_texture(glow::TEXTURE0);
+    }
+
+    /// Deletes the framebuffer and its texture.
+    ///
+    /// # Safety
+    ///
+    /// The framebuffer must only be used with the GL context it was created with.
+    pub unsafe fn delete(self, gl: &glow::Context) {
+        gl.delete_framebuffer(self.framebuffer);
+        gl.delete_texture(self.texture);
+    }
+}
+
+/// Pair of half-resolution textures that the bloom pass blurs the emissive light back and forth
+/// between, see `OpenglRenderer::set_bloom`.
+pub struct BloomFramebuffers {
+    pub framebuffers: [glow::Framebuffer; 2],
+    pub textures: [glow::Texture; 2],
+    /// Whether the textures store floats, see `color_format`.
+    pub float: bool,
+}
+
+impl BloomFramebuffers {
+    /// Creates the framebuffers and their textures, allocated to half of `width` x `height`.
+    ///
+    /// # Safety
+    ///
+    /// The framebuffers must only be used with the GL context they were created with.
+    pub unsafe fn new(
+        gl: &glow::Context,
+        width: u32,
+        height: u32,
+        float: bool,
+    ) -> Result<Self, String> {
+        let bloom = Self {
+            framebuffers: [gl.create_framebuffer()?, gl.create_framebuffer()?],
+            textures: [gl.create_texture()?, gl.create_texture()?],
+            float,
+        };
+
+        // The blur reads past the edges, which must not wrap around to the other side
+        for texture in bloom.textures {
+            gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+            for wrap in [glow::TEXTURE_WRAP_S, glow::TEXTURE_WRAP_T] {
+                gl.tex_parameter_i32(glow::TEXTURE_2D, wrap, glow::CLAMP_TO_EDGE as i32);
+            }
+        }
+        gl.bind_texture(glow::TEXTURE_2D, None);
+
+        bloom.resize(gl, width, height)?;
+        Ok(bloom)
+    }
+
+    /// Gets the size of the textures for a `width` x `height` frame.
+    pub fn size(width: u32, height: u32) -> (u32, u32) {
+        ((width / 2).max(1), (height / 2).max(1))
+    }
+
+    /// Reallocates the textures to half of `width` x `height`.
+    ///
+    /// # Safety
+    ///
+    /// The framebuffers must only be used with the GL context they were created with.
+    pub unsafe fn resize(&self, gl: &glow::Context, width: u32, height: u32) -> Result<(), String> {
+        let (width, height) = Self::size(width, height);
+        let (internal_format, ty) = color_format(self.float);
+
+        for (&framebuffer, &texture) in self.framebuffers.iter().zip(&self.textures) {
+            texture::upload_empty(gl, texture, width, height, internal_format, ty);
+
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer));
+            gl.framebuffer_texture_2d(
+                glow::FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::TEXTURE_2D,
+                Some(texture),
+                0,
+            );
+            let status = check_framebuffer_status(gl);
+            gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+            status?;
+        }
+        Ok(())
+    }
+
+    /// Deletes the framebuffers and their textures.
+    ///
+    /// # Safety
+    ///
+    /// The framebuffers must only be used with the GL context they were created with.
+    pub unsafe fn delete(self, gl: &glow::Context) {
+        for framebuffer in self.framebuffers {
+            gl.delete_framebuffer(framebuffer);
+        }
+        for texture in self.textures {
+            gl.delete_texture(texture);
+        }
+    }
+}
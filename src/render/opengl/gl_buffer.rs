@@ -1,35 +1,146 @@
+use glam::Vec2;
 use glow::HasContext;
 
 use crate::render::RenderCtx;
 
 use super::OpenglRendererError;
 
-impl RenderCtx {
-    unsafe fn upload_array_to_gl<T>(gl: &glow::Context, array: &Vec<T>, target: u32, usage: u32) {
-        let bytes: &[u8] = core::slice::from_raw_parts(
-            array.as_ptr() as *const u8,
-            array.len() * core::mem::size_of::<T>(),
+/// OpenGL buffers holding the meshes of a puppet's parts.
+pub struct InoxGlBuffers {
+    /// Vertex array of the vertices (location 0), UVs (location 1) and deforms (location 2),
+    /// along with the indices.
+    pub vao: glow::VertexArray,
+    pub verts: glow::Buffer,
+    pub uvs: glow::Buffer,
+    pub deforms: glow::Buffer,
+    pub indices: glow::Buffer,
+    /// Vertex array of the same vertices, with the indices of a batch of parts
+    /// drawn together, see `upload_batch_indices`.
+    pub batch_vao: glow::VertexArray,
+    pub batch_indices: glow::Buffer,
+}
+
+impl InoxGlBuffers {
+    /// Overwrites the vertices starting at `vert_offset` with `verts`, in place.
+    ///
+    /// # Safety
+    ///
+    /// The buffers must only be used with the GL context they were created with,
+    /// and the vertices must fit in the vertex buffer.
+    pub unsafe fn update_vertices(&self, gl: &glow::Context, vert_offset: usize, verts: &[Vec2]) {
+        gl.bind_buffer(glow::ARRAY_BUFFER, Some(self.verts));
+        RenderCtx::reupload_array_to_gl(gl, verts, glow::ARRAY_BUFFER, 0, verts.len(), vert_offset);
+
+        // Leave the deform buffer bound, as deform uploads expect
+        gl.bind_buffer(glow::ARRAY_BUFFER, Some(self.deforms));
+    }
+
+    /// Replaces the contents of all the buffers with the meshes of another render context,
+    /// e.g. after reloading a model, reusing the existing GL objects.
+    ///
+    /// Buffers keeping the same size are overwritten in place, the others are reallocated.
+    /// This assumes the vertex array layout is unchanged, which is the case for any puppet.
+    ///
+    /// # Safety
+    ///
+    /// The buffers must only be used with the GL context they were created with.
+    pub unsafe fn reupload(&self, gl: &glow::Context, render_ctx: &RenderCtx) {
+        let vertex_buffers = &render_ctx.vertex_buffers;
+
+        // The index buffer binding is part of the vertex array's state
+        gl.bind_vertex_array(Some(self.vao));
+
+        let uploads = [
+            (
+                self.verts,
+                glow::ARRAY_BUFFER,
+                glow::DYNAMIC_DRAW,
+                as_bytes(&vertex_buffers.verts),
+            ),
+            (
+                self.uvs,
+                glow::ARRAY_BUFFER,
+                glow::STATIC_DRAW,
+                as_bytes(&vertex_buffers.uvs),
+            ),
+            (
+                self.indices,
+                glow::ELEMENT_ARRAY_BUFFER,
+                glow::STATIC_DRAW,
+                as_bytes(&vertex_buffers.indices),
+            ),
+            (
+                self.deforms,
+                glow::ARRAY_BUFFER,
+                glow::DYNAMIC_DRAW,
+                as_bytes(&vertex_buffers.deforms),
+            ),
+        ];
+        for (buffer, target, usage, bytes) in uploads {
+            gl.bind_buffer(target, Some(buffer));
+            if gl.get_buffer_parameter_i32(target, glow::BUFFER_SIZE) as usize == bytes.len() {
+                gl.buffer_sub_data_u8_slice(target, 0, bytes);
+            } else {
+                gl.buffer_data_u8_slice(target, bytes, usage);
+            }
+        }
+
+        // The deform buffer is left bound last, as deform uploads expect
+    }
+
+    /// Replaces the indices of the batch vertex array, and leaves it bound.
+    ///
+    /// # Safety
+    ///
+    /// The buffers must only be used with the GL context they were created with.
+    pub unsafe fn upload_batch_indices(&self, gl: &glow::Context, indices: &[u16]) {
+        // The index buffer binding is part of the vertex array's state
+        gl.bind_vertex_array(Some(self.batch_vao));
+        gl.buffer_data_u8_slice(
+            glow::ELEMENT_ARRAY_BUFFER,
+            as_bytes(indices),
+            glow::STREAM_DRAW,
         );
+    }
+}
+
+unsafe fn as_bytes<T>(array: &[T]) -> &[u8] {
+    core::slice::from_raw_parts(array.as_ptr() as *const u8, core::mem::size_of_val(array))
+}
+
+impl RenderCtx {
+    unsafe fn upload_array_to_gl<T>(
+        gl: &glow::Context,
+        array: &[T],
+        target: u32,
+        usage: u32,
+    ) -> glow::Buffer {
+        let bytes = as_bytes(array);
         let buffer = gl.create_buffer().unwrap();
         gl.bind_buffer(target, Some(buffer));
         gl.buffer_data_u8_slice(target, bytes, usage);
+        buffer
     }
 
+    /// Uploads `array[start_idx..end_idx]` to the buffer bound to `target`,
+    /// starting at element `dst_idx` of the buffer.
     unsafe fn reupload_array_to_gl<T>(
         gl: &glow::Context,
         array: &[T],
         target: u32,
         start_idx: usize,
         end_idx: usize,
+        dst_idx: usize,
     ) {
-        let slice = &array[start_idx..end_idx];
-        let bytes: &[u8] =
-            core::slice::from_raw_parts(slice.as_ptr() as *const u8, core::mem::size_of_val(slice));
-        gl.buffer_sub_data_u8_slice(target, start_idx as i32, bytes);
+        let bytes = as_bytes(&array[start_idx..end_idx]);
+        let offset = dst_idx * core::mem::size_of::<T>();
+        gl.buffer_sub_data_u8_slice(target, offset as i32, bytes);
     }
 
     /// Uploads the vertex and index buffers to OpenGL.
     ///
+    /// Returns the vertex array and the buffers, which are needed to update them.
+    ///
     /// # Errors
     ///
     /// This function will return an error if it couldn't create a vertex array.
@@ -40,22 +151,22 @@ impl RenderCtx {
     pub unsafe fn setup_gl_buffers(
         &self,
         gl: &glow::Context,
-    ) -> Result<glow::VertexArray, OpenglRendererError> {
+    ) -> Result<InoxGlBuffers, OpenglRendererError> {
         let vao = gl
             .create_vertex_array()
             .map_err(OpenglRendererError::Opengl)?;
         gl.bind_vertex_array(Some(vao));
 
-        Self::upload_array_to_gl(
+        let verts = Self::upload_array_to_gl(
             gl,
             &self.vertex_buffers.verts,
             glow::ARRAY_BUFFER,
-            glow::STATIC_DRAW,
+            glow::DYNAMIC_DRAW,
         );
         gl.vertex_attrib_pointer_f32(0, 2, glow::FLOAT, false, 0, 0);
         gl.enable_vertex_attrib_array(0);
 
-        Self::upload_array_to_gl(
+        let uvs = Self::upload_array_to_gl(
             gl,
             &self.vertex_buffers.uvs,
             glow::ARRAY_BUFFER,
@@ -64,7 +175,7 @@ impl RenderCtx {
         gl.vertex_attrib_pointer_f32(1, 2, glow::FLOAT, false, 0, 0);
         gl.enable_vertex_attrib_array(1);
 
-        Self::upload_array_to_gl(
+        let deforms = Self::upload_array_to_gl(
             gl,
             &self.vertex_buffers.deforms,
             glow::ARRAY_BUFFER,
@@ -73,27 +184,53 @@ impl RenderCtx {
         gl.vertex_attrib_pointer_f32(2, 2, glow::FLOAT, false, 0, 0);
         gl.enable_vertex_attrib_array(2);
 
-        Self::upload_array_to_gl(
+        let indices = Self::upload_array_to_gl(
             gl,
             &self.vertex_buffers.indices,
             glow::ELEMENT_ARRAY_BUFFER,
             glow::STATIC_DRAW,
         );
 
-        Ok(vao)
+        let batch_vao = gl
+            .create_vertex_array()
+            .map_err(OpenglRendererError::Opengl)?;
+        gl.bind_vertex_array(Some(batch_vao));
+        for (location, buffer) in [(0, verts), (1, uvs), (2, deforms)] {
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(buffer));
+            gl.vertex_attrib_pointer_f32(location, 2, glow::FLOAT, false, 0, 0);
+            gl.enable_vertex_attrib_array(location);
+        }
+        let batch_indices = gl.create_buffer().map_err(OpenglRendererError::Opengl)?;
+        gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(batch_indices));
+
+        // Leave the vertex array of the parts and the deform buffer bound, as before
+        gl.bind_vertex_array(Some(vao));
+        gl.bind_buffer(glow::ARRAY_BUFFER, Some(deforms));
+
+        Ok(InoxGlBuffers {
+            vao,
+            verts,
+            uvs,
+            deforms,
+            indices,
+            batch_vao,
+            batch_indices,
+        })
     }
 
     /// # Safety
     ///
     /// unsafe as initiating GL calls. can be safely called for multiple times,
     /// but only needed once after deform update and before rendering.
-    pub unsafe fn upload_deforms_to_gl(&self, gl: &glow::Context) {
+    pub unsafe fn upload_deforms_to_gl(&self, gl: &glow::Context, deform_buffer: glow::Buffer) {
+        gl.bind_buffer(glow::ARRAY_BUFFER, Some(deform_buffer));
         Self::reupload_array_to_gl(
             gl,
             &self.vertex_buffers.deforms,
             glow::ARRAY_BUFFER,
             0,
             self.vertex_buffers.deforms.len(),
+            0,
         );
     }
 }
@@ -0,0 +1,185 @@
+//! Rendering of puppets without a window, e.g. to make thumbnails on a server.
+//!
+//! The GL context is created with EGL directly on a device, without a display server, and has
+//! no default framebuffer: the puppet is rendered offscreen and read back into an image.
+//! This requires an EGL implementation with `EGL_EXT_device_enumeration`, `EGL_EXT_platform_device`
+//! and `EGL_KHR_surfaceless_context`, and OpenGL 3.3. Mesa provides them on Linux, including with
+//! its software renderer on machines without a GPU, and so do the proprietary NVIDIA drivers.
+
+use std::ffi::CString;
+
+use glam::UVec2;
+use glow::HasContext;
+use glutin::api::egl::context::PossiblyCurrentContext;
+use glutin::api::egl::device::Device;
+use glutin::api::egl::display::Display;
+use glutin::config::{ConfigSurfaceTypes, ConfigTemplateBuilder};
+use glutin::context::{ContextApi, ContextAttributesBuilder, GlProfile, Version};
+use glutin::prelude::GlDisplay;
+use tracing::{debug, info};
+
+use crate::formats::inp::parse_inp;
+use crate::model::Model;
+use crate::puppet::Puppet;
+
+use super::framebuffer::ViewFramebuffer;
+use super::{OpenglRenderer, OpenglRendererFromInpError, RendererConfig};
+
+#[derive(Debug, thiserror::Error)]
+pub enum HeadlessError {
+    #[error("Could not create a headless GL context: {0}")]
+    Context(#[from] glutin::error::Error),
+    #[error("There is no EGL device to render with")]
+    NoDevice,
+    #[error("Could not create a framebuffer to render into: {0}")]
+    Framebuffer(String),
+    #[error(transparent)]
+    Load(#[from] OpenglRendererFromInpError),
+}
+
+/// Renderer drawing puppets into images, with its own headless GL context.
+///
+/// The context is made current on the thread creating the renderer, which must then be
+/// the only one to use it. See the module documentation for the platform requirements.
+pub struct HeadlessRenderer {
+    pub renderer: OpenglRenderer,
+    framebuffer: ViewFramebuffer,
+    size: UVec2,
+    // Dropped after the renderer, which uses them
+    _context: PossiblyCurrentContext,
+    _display: Display,
+}
+
+impl HeadlessRenderer {
+    /// Creates a headless renderer for a model's puppet, rendering images of `size` pixels,
+    /// and uploads the model's textures. See `OpenglRenderer::from_model`.
+    pub fn from_model(
+        model: &Model,
+        size: UVec2,
+        config: RendererConfig,
+    ) -> Result<Self, HeadlessError> {
+        let size = size.max(UVec2::ONE);
+        let (display, context) = create_context()?;
+        let gl = unsafe {
+            glow::Context::from_loader_function(|symbol| {
+                display.get_proc_address(&CString::new(symbol).unwrap()) as *const _
+            })
+        };
+
+        let framebuffer = unsafe { ViewFramebuffer::new(&gl, size.x, size.y) }
+            .map_err(HeadlessError::Framebuffer)?;
+        let renderer = OpenglRenderer::from_model(gl, size, model, config)?;
+        info!(
+            "Rendering headless with {} ({})",
+            renderer.capabilities().renderer,
+            renderer.capabilities().version
+        );
+
+        Ok(Self {
+            renderer,
+            framebuffer,
+            size,
+            _context: context,
+            _display: display,
+        })
+    }
+
+    /// Parses an INP model and creates a headless renderer for its puppet,
+    /// which is returned alongside it. See `OpenglRenderer::from_inp`.
+    pub fn from_inp(data: &[u8], size: UVec2) -> Result<(Self, Puppet), HeadlessError> {
+        let model = parse_inp(data).map_err(OpenglRendererFromInpError::from)?;
+        let renderer = Self::from_model(&model, size, RendererConfig::default())?;
+        Ok((renderer, model.puppet))
+    }
+
+    /// Size of the rendered images, in pixels.
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// Changes the size of the rendered images.
+    pub fn resize(&mut self, size: UVec2) -> Result<(), HeadlessError> {
+        let size = size.max(UVec2::ONE);
+        unsafe { (self.framebuffer).resize(self.renderer.gl(), size.x, size.y) }
+            .map_err(HeadlessError::Framebuffer)?;
+        self.size = size;
+        Ok(())
+    }
+
+    /// Renders the puppet and reads it back, with the origin at the top-left.
+    ///
+    /// The image is cleared to the renderer's clear color first, transparent if it has none.
+    /// Its pixels are as stored, see `OpenglRenderer::read_framebuffer`.
+    pub fn render(&mut self, puppet: &Puppet) -> image::RgbaImage {
+        let target = self.framebuffer.framebuffer;
+        unsafe {
+            let gl = self.renderer.gl();
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(target));
+            gl.viewport(0, 0, self.size.x as i32, self.size.y as i32);
+        }
+        match self.renderer.clear_color() {
+            Some(_) => self.renderer.clear(),
+            None => self.renderer.clear_with(glam::Vec4::ZERO),
+        }
+        self.renderer.draw_model_to(puppet, target, self.size);
+
+        let pixels = self.renderer.read_pixels(Some(target));
+        image::RgbaImage::from_raw(self.size.x, self.size.y, pixels).unwrap()
+    }
+}
+
+/// Renders the puppet of an INP model in its rest pose, framed by the camera, into an image
+/// of `size` pixels with a transparent background, e.g. to make a thumbnail.
+pub fn render_inp(data: &[u8], size: UVec2) -> Result<image::RgbaImage, HeadlessError> {
+    let (mut headless, mut puppet) = HeadlessRenderer::from_inp(data, size)?;
+    if let Some(bounds) = puppet.nodes.model_bounds() {
+        let size = headless.size().as_vec2();
+        headless.renderer.camera.fit_bounds(bounds, size, 0.1);
+    }
+
+    puppet.begin_set_params();
+    puppet.end_set_params();
+    Ok(headless.render(&puppet))
+}
+
+/// Creates an OpenGL 3.3 core context on the first EGL device that supports one,
+/// and makes it current without a surface.
+fn create_context() -> Result<(Display, PossiblyCurrentContext), HeadlessError> {
+    let mut last_error = None;
+    for device in Device::query_devices()? {
+        debug!(
+            "Trying EGL device {}",
+            device.name().or(device.vendor()).unwrap_or("<unnamed>")
+        );
+        match unsafe { create_device_context(&device) } {
+            Ok(context) => return Ok(context),
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    Err(last_error.map_or(HeadlessError::NoDevice, HeadlessError::from))
+}
+
+unsafe fn create_device_context(
+    device: &Device,
+) -> Result<(Display, PossiblyCurrentContext), glutin::error::Error> {
+    let display = Display::with_device(device, None)?;
+
+    let template = ConfigTemplateBuilder::new()
+        .with_surface_type(ConfigSurfaceTypes::empty())
+        .build();
+    let config = display
+        .find_configs(template)?
+        .next()
+        .ok_or(glutin::error::ErrorKind::BadConfig)?;
+
+    let attributes = ContextAttributesBuilder::new()
+        .with_context_api(ContextApi::OpenGl(Some(Version::new(3, 3))))
+        .with_profile(GlProfile::Core)
+        .build(None);
+    let context = display
+        .create_context(&config, &attributes)?
+        .make_current_surfaceless()?;
+
+    Ok((display, context))
+}
@@ -1,27 +1,58 @@
+pub mod builder;
+#[cfg(feature = "egui")]
+pub mod egui;
+pub mod framebuffer;
 pub mod gl_buffer;
+#[cfg(all(feature = "headless", target_os = "linux"))]
+pub mod headless;
+pub mod scene;
 pub mod shader;
 pub mod shaders;
+pub mod state;
 pub mod texture;
 
 use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::mem;
 use std::ops::Deref;
 
-use glam::{uvec2, UVec2, Vec3};
+use glam::{uvec2, vec3, Mat4, UVec2, Vec2, Vec3, Vec4};
 use glow::HasContext;
-use tracing::error;
+use image::ImageFormat;
+use tracing::{debug, error, info, warn};
 
+use crate::formats::inp::{parse_inp, ParseInpError};
+use crate::math::aabb::Aabb;
 use crate::math::camera::Camera;
-use crate::model::ModelTexture;
+use crate::mesh::triangles_contain;
+use crate::model::{Model, ModelTexture};
 use crate::nodes::node::InoxNodeUuid;
-use crate::nodes::node_data::{BlendMode, Composite, InoxData, Mask, MaskMode, Part};
-use crate::puppet::Puppet;
-use crate::render::{NodeRenderCtx, PartRenderCtx, RenderCtxKind};
-use crate::texture::decode_model_textures;
-
+use crate::nodes::node_data::{BlendMode, Composite, InoxData, Mask, Part};
+use crate::puppet::{Puppet, Version};
+use crate::render::batching::{batch_draw_order, merge_draws, DrawItem};
+use crate::render::color::{srgb_to_linear, AlphaMode, ColorSpace};
+use crate::render::draw_rules::{
+    clamp_opacity_tint, compat_blend_mode, compat_screen_tint, mask_stencil_clear_value,
+    mask_stencil_value, MASK_SHOWN,
+};
+use crate::render::{NodeRenderCtx, PartRenderCtx, RenderCtxKind, Renderer, SetPartVerticesError};
+use crate::texture::{
+    decode_model_textures_in_order, decode_texture, ShallowTexture, TextureDecodeError,
+};
+
+use self::builder::OpenglRendererBuilder;
+use self::framebuffer::{
+    BackdropFramebuffer, BloomFramebuffers, CompositeFramebuffer, CompositeFramebufferPool,
+};
+use self::gl_buffer::InoxGlBuffers;
 use self::shader::ShaderCompileError;
-use self::shaders::{CompositeMaskShader, CompositeShader, PartMaskShader, PartShader};
-use self::texture::{Texture, TextureError};
+use self::shaders::{
+    BloomAddShader, BloomBlurShader, BloomPrefilterShader, CompositeMaskShader, CompositeShader,
+    PartMaskShader, PartShader, PointsShader, SceneShader, UnpremultiplyShader, WireframeShader,
+    BACKDROP_TEXTURE_UNIT, BUMPMAP_TEXTURE_UNIT, EMISSIVE_TEXTURE_UNIT,
+};
+use self::state::SavedGlState;
+use self::texture::{Texture, TextureError, TextureFilter, TextureUploader};
 
 #[derive(Debug, thiserror::Error)]
 #[error("Could not initialize OpenGL renderer: {0}")]
@@ -30,382 +61,2631 @@ pub enum OpenglRendererError {
     Opengl(String),
 }
 
-#[derive(Default, Clone)]
-pub struct GlCache {
-    pub camera: Option<Camera>,
-    pub viewport: Option<UVec2>,
-    pub blend_mode: Option<BlendMode>,
-    pub program: Option<glow::Program>,
-    pub vao: Option<glow::VertexArray>,
-    pub albedo: Option<usize>,
+#[derive(Debug, thiserror::Error)]
+#[error("Could not load INP model into the OpenGL renderer: {0}")]
+pub enum OpenglRendererFromInpError {
+    ParseInp(#[from] ParseInpError),
+    Renderer(#[from] OpenglRendererError),
+    Texture(#[from] TextureError),
 }
 
-impl GlCache {
-    pub fn update_camera(&mut self, camera: &Camera) -> bool {
-        if let Some(prev_camera) = &mut self.camera {
-            let mut changed = false;
-
-            if prev_camera.position != camera.position {
-                prev_camera.position = camera.position;
-                changed = true;
-            }
-            if prev_camera.rotation != camera.rotation {
-                prev_camera.rotation = camera.rotation;
-                changed = true;
-            }
-            if prev_camera.scale != camera.scale {
-                prev_camera.scale = camera.scale;
-                changed = true;
-            }
+/// Settings fixed for the lifetime of an `OpenglRenderer`.
+#[derive(Debug, Clone, Copy)]
+pub struct RendererConfig {
+    /// Number of samples per pixel of the composite framebuffers, for antialiasing.
+    ///
+    /// 1 disables multisampling. Counts above `GL_MAX_SAMPLES` are lowered to it.
+    /// The default framebuffer's multisampling is chosen when creating the GL context instead.
+    pub samples: u32,
+    /// How the model's textures are sampled. Can be changed per texture with `set_texture_filter`.
+    pub texture_filter: TextureFilter,
+    /// Anisotropic filtering of trilinear-filtered textures, sharpening them when seen at an angle.
+    ///
+    /// 1 disables it. Values above the hardware maximum are lowered to it.
+    /// Without `GL_EXT_texture_filter_anisotropic`, textures are only filtered trilinearly.
+    pub anisotropy: f32,
+    /// Forwards the driver's debug messages to `tracing`, high severity ones as errors
+    /// and medium severity ones as warnings.
+    ///
+    /// Needs `GL_KHR_debug`, and most drivers only report messages to debug contexts.
+    /// Off by default, as it slows rendering down.
+    pub debug_messages: bool,
+    /// Blends colors in linear space rather than in the sRGB space of the textures.
+    ///
+    /// Blending sRGB-encoded values darkens semi-transparent edges and cross-fades.
+    /// In linear space, textures are decoded when sampled, the frame is drawn into a float
+    /// framebuffer, and then encoded back to sRGB onto the target. The target's existing contents,
+    /// like the clear color, are blended over in sRGB space as before.
+    /// This needs float render targets, see `GlCapabilities::float_render_targets`.
+    /// Off by default, to match how Inochi2D blends.
+    pub linear_space: bool,
+    /// Uploads the model's textures as the layers of a single texture array, so that parts
+    /// using different textures are drawn without binding textures in between.
+    ///
+    /// The layers of an array all have the same size: textures of another size than the first
+    /// one are resized to it, so this is meant for models whose textures share a size.
+    /// Textures then share their filter, see `set_texture_filter`. Off by default.
+    pub texture_array: bool,
+    /// Resolution of the framebuffers that composites are drawn into before being drawn
+    /// to the target. The viewport's by default.
+    pub composite_resolution: CompositeResolution,
+    /// Flips the model's textures upside down when uploading them, for puppets exported by
+    /// tools whose V coordinates go up from the bottom of the textures.
+    ///
+    /// Textures are otherwise uploaded with V = 0 at their top row, as Inochi2D expects,
+    /// whatever the origin stored in their file. This also applies to `reload_texture`,
+    /// but textures given to `upload_decoded_textures` are uploaded as they are. Off by default.
+    pub flip_textures_v: bool,
+}
 
-            changed
-        } else {
-            self.camera = Some(camera.clone());
-            true
+impl Default for RendererConfig {
+    fn default() -> Self {
+        Self {
+            samples: 1,
+            texture_filter: TextureFilter::default(),
+            anisotropy: 1.0,
+            debug_messages: false,
+            linear_space: false,
+            texture_array: false,
+            composite_resolution: CompositeResolution::default(),
+            flip_textures_v: false,
         }
     }
+}
 
-    pub fn update_viewport(&mut self, viewport: UVec2) -> bool {
-        if let Some(prev_viewport) = self.viewport.replace(viewport) {
-            prev_viewport != viewport
-        } else {
-            true
-        }
+/// Resolution of the composite framebuffers, see `RendererConfig::composite_resolution`.
+///
+/// Composites are drawn offscreen, then stretched over the whole viewport with bilinear filtering.
+/// Below the viewport's resolution, they take less memory and fill rate, e.g. a quarter at half
+/// resolution, which matters for puppets with many composites rendered at 4K. In exchange, the parts
+/// inside composites are blurrier and their edges less precise than the parts drawn directly.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum CompositeResolution {
+    /// The viewport's resolution.
+    #[default]
+    Viewport,
+    /// The viewport's resolution times a factor, e.g. 0.5 for half resolution.
+    Scaled(f32),
+    /// A fixed resolution, stretched to the viewport's aspect ratio.
+    Fixed(UVec2),
+}
+
+impl CompositeResolution {
+    /// Gets the size of the composite framebuffers for a viewport, at least 1x1.
+    pub fn size(self, viewport: UVec2) -> UVec2 {
+        let size = match self {
+            CompositeResolution::Viewport => viewport,
+            CompositeResolution::Scaled(scale) => (viewport.as_vec2() * scale).round().as_uvec2(),
+            CompositeResolution::Fixed(size) => size,
+        };
+        size.max(UVec2::ONE)
     }
+}
 
-    pub fn update_blend_mode(&mut self, blend_mode: BlendMode) -> bool {
-        if let Some(prev_mode) = self.blend_mode.replace(blend_mode) {
-            prev_mode != blend_mode
-        } else {
-            true
+/// Settings of the bloom pass, which makes emissive parts glow. See `OpenglRenderer::set_bloom`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BloomParams {
+    /// Emissive light below this value is left out of the glow.
+    pub threshold: f32,
+    /// Factor of the glow added to the frame.
+    pub intensity: f32,
+    /// Spread of the blur, 1 being a 9-pixel wide Gaussian at half resolution.
+    pub radius: f32,
+}
+
+impl Default for BloomParams {
+    fn default() -> Self {
+        Self {
+            threshold: 0.0,
+            intensity: 1.0,
+            radius: 1.0,
         }
     }
+}
+
+/// Features and limits of the GL context, detected when creating the renderer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlCapabilities {
+    /// `GL_VERSION`, as reported by the driver.
+    pub version: String,
+    /// `GL_RENDERER`, usually the name of the GPU.
+    pub renderer: String,
+    /// `GL_VENDOR`.
+    pub vendor: String,
+    /// Major and minor GL version, e.g. `(3, 3)`.
+    pub version_number: (u32, u32),
+    /// Whether the context is OpenGL ES or WebGL rather than desktop OpenGL.
+    pub is_embedded: bool,
+    /// Whether the context is WebGL.
+    pub is_webgl: bool,
+    /// Names of the supported extensions.
+    pub extensions: HashSet<String>,
+    /// `GL_MAX_TEXTURE_SIZE`.
+    pub max_texture_size: u32,
+    /// `GL_MAX_SAMPLES`.
+    pub max_samples: u32,
+    /// Maximum anisotropy, if anisotropic filtering is supported.
+    pub max_anisotropy: Option<f32>,
+    /// Whether `GL_KHR_debug` is supported, to label draws in graphics debuggers.
+    pub debug_groups: bool,
+    /// Whether float textures can be rendered to, as the emissive buffer of composites is,
+    /// and as blending in linear space requires.
+    pub float_render_targets: bool,
+    /// Whether `glTexStorage2D` is supported, to allocate the mipmaps of textures up front.
+    pub texture_storage: bool,
+}
+
+impl GlCapabilities {
+    fn detect(gl: &glow::Context) -> Self {
+        let extensions = gl.supported_extensions().clone();
+        let has_any = |names: &[&str]| names.iter().any(|name| extensions.contains(*name));
+
+        let gl_version = gl.version();
+        let version_number = (gl_version.major, gl_version.minor);
+        let is_embedded = gl_version.is_embedded;
+
+        let (version, renderer, vendor) = unsafe {
+            (
+                gl.get_parameter_string(glow::VERSION),
+                gl.get_parameter_string(glow::RENDERER),
+                gl.get_parameter_string(glow::VENDOR),
+            )
+        };
+        let is_webgl = version.contains("WebGL");
 
-    pub fn update_program(&mut self, program: glow::Program) -> bool {
-        if let Some(prev_program) = self.program.replace(program) {
-            prev_program != program
+        // Float color attachments are core from OpenGL 3.0 and OpenGL ES 3.2
+        let float_render_targets = if is_embedded {
+            version_number >= (3, 2)
+                || has_any(&["GL_EXT_color_buffer_float", "EXT_color_buffer_float"])
         } else {
-            true
-        }
-    }
+            version_number >= (3, 0)
+        };
+
+        let anisotropic = has_any(&[
+            "GL_EXT_texture_filter_anisotropic",
+            "GL_ARB_texture_filter_anisotropic",
+            "EXT_texture_filter_anisotropic",
+        ]);
+        let debug_groups = has_any(&["GL_KHR_debug"]);
 
-    pub fn update_vao(&mut self, vao: glow::VertexArray) -> bool {
-        if let Some(prev_vao) = self.vao.replace(vao) {
-            prev_vao != vao
+        // Core from OpenGL 4.2 and OpenGL ES 3.0, which includes WebGL 2
+        let texture_storage = if is_embedded {
+            version_number >= (3, 0)
         } else {
-            true
+            version_number >= (4, 2) || has_any(&["GL_ARB_texture_storage"])
+        };
+
+        unsafe {
+            Self {
+                version,
+                renderer,
+                vendor,
+                version_number,
+                is_embedded,
+                is_webgl,
+                max_texture_size: gl.get_parameter_i32(glow::MAX_TEXTURE_SIZE) as u32,
+                max_samples: gl.get_parameter_i32(glow::MAX_SAMPLES).max(1) as u32,
+                max_anisotropy: anisotropic
+                    .then(|| gl.get_parameter_f32(glow::MAX_TEXTURE_MAX_ANISOTROPY_EXT)),
+                debug_groups,
+                float_render_targets,
+                texture_storage,
+                extensions,
+            }
         }
     }
 
-    pub fn update_albedo(&mut self, albedo: usize) -> bool {
-        if let Some(prev_texture) = self.albedo.replace(albedo) {
-            prev_texture != albedo
-        } else {
-            true
+    /// Whether anisotropic filtering is supported, see `RendererConfig::anisotropy`.
+    pub fn anisotropic_filtering(&self) -> bool {
+        self.max_anisotropy.is_some()
+    }
+}
+
+/// Forwards a GL debug message to `tracing`.
+///
+/// This must stay a plain function: glow doesn't keep the callback alive past registration,
+/// which is only sound for callbacks without captured state.
+fn log_gl_debug_message(_source: u32, _ty: u32, id: u32, severity: u32, message: &str) {
+    match severity {
+        glow::DEBUG_SEVERITY_HIGH => error!("GL error {id}: {message}"),
+        glow::DEBUG_SEVERITY_MEDIUM => warn!("GL warning {id}: {message}"),
+        glow::DEBUG_SEVERITY_LOW => info!("GL {id}: {message}"),
+        _ => debug!("GL {id}: {message}"),
+    }
+}
+
+/// Stencil bit that holds the clip polygon. Part masks use the other bits.
+const CLIP_STENCIL_BIT: u32 = 0x80;
+
+/// Textures a part is drawn with: its albedo, emissive map and bump map.
+pub type PartTextures = (usize, Option<usize>, Option<usize>);
+
+fn part_textures(part: &Part) -> PartTextures {
+    (part.tex_albedo, part.tex_emissive, part.tex_bumpmap)
+}
+
+/// GL state set by the last draws, used to skip redundant GL calls.
+///
+/// Fields are cells so that draws, which only borrow the renderer, can update them
+/// without any runtime borrow tracking.
+#[derive(Default, Clone)]
+pub struct GlCache {
+    pub camera: Cell<Option<Camera>>,
+    pub viewport: Cell<Option<UVec2>>,
+    pub blend_mode: Cell<Option<BlendMode>>,
+    pub program: Cell<Option<glow::Program>>,
+    pub vao: Cell<Option<glow::VertexArray>>,
+    pub textures: Cell<Option<PartTextures>>,
+    /// Clear color currently set in the GL state.
+    pub clear_color: Cell<Option<Vec4>>,
+    /// Number of updates that found the state already set, since the cache was created.
+    pub hits: Cell<u64>,
+    /// Number of updates that changed the state, since the cache was created.
+    pub misses: Cell<u64>,
+}
+
+impl GlCache {
+    /// Stores `value` in `cell` and returns whether it differs from what was there.
+    fn update_cell<T: Copy + PartialEq>(&self, cell: &Cell<Option<T>>, value: T) -> bool {
+        let changed = cell.replace(Some(value)) != Some(value);
+        let counter = if changed { &self.misses } else { &self.hits };
+        counter.set(counter.get().wrapping_add(1));
+        changed
+    }
+
+    pub fn update_camera(&self, camera: &Camera) -> bool {
+        self.update_cell(&self.camera, *camera)
+    }
+
+    pub fn update_viewport(&self, viewport: UVec2) -> bool {
+        self.update_cell(&self.viewport, viewport)
+    }
+
+    pub fn update_blend_mode(&self, blend_mode: BlendMode) -> bool {
+        self.update_cell(&self.blend_mode, blend_mode)
+    }
+
+    pub fn update_program(&self, program: glow::Program) -> bool {
+        self.update_cell(&self.program, program)
+    }
+
+    pub fn update_vao(&self, vao: glow::VertexArray) -> bool {
+        self.update_cell(&self.vao, vao)
+    }
+
+    pub fn update_textures(&self, textures: PartTextures) -> bool {
+        self.update_cell(&self.textures, textures)
+    }
+
+    pub fn update_clear_color(&self, clear_color: Vec4) -> bool {
+        self.update_cell(&self.clear_color, clear_color)
+    }
+}
+
+/// What a frame drew, to understand why a puppet is slow to render.
+/// See `OpenglRenderer::render_with_stats`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RenderStats {
+    /// Parts drawn, including the parts used as masks. Culled parts aren't counted.
+    pub parts: u32,
+    /// Composites drawn, including nested ones and the ones drawn from their cache.
+    pub composites: u32,
+    /// Parts whose masks were drawn into the stencil buffer before drawing them.
+    pub mask_passes: u32,
+    /// GL draw calls, including the ones of the clip polygon and of post-processing.
+    pub draw_calls: u32,
+    /// Changes of the blend mode, shader, vertex array, texture, clear color, camera
+    /// or viewport that were skipped as the state was already set, see `GlCache`.
+    pub state_changes_skipped: u32,
+    /// Changes of the same state that were made.
+    pub state_changes: u32,
+}
+
+/// Runtime changes to how a part is drawn, on top of its draw state in the model.
+#[derive(Debug, Clone, Copy)]
+struct PartOverride {
+    opacity: f32,
+    tint: Vec3,
+}
+
+impl Default for PartOverride {
+    fn default() -> Self {
+        Self {
+            opacity: 1.0,
+            tint: Vec3::ONE,
         }
     }
 }
 
+/// Everything a part's draw call depends on besides its triangles.
+/// Consecutive parts with equal keys are drawn in a single call, see `set_draw_batching`.
+#[derive(PartialEq)]
+struct PartBatchKey {
+    textures: PartTextures,
+    blend_mode: BlendMode,
+    trans: Mat4,
+    opacity: f32,
+    tint: Vec3,
+    screen_tint: Vec3,
+}
+
+/// Composite kept rendered in its own framebuffer, along with what it was drawn from.
+struct CachedComposite {
+    framebuffer: CompositeFramebuffer,
+    size: UVec2,
+    signature: Option<Vec<f32>>,
+}
+
 pub struct OpenglRenderer {
     gl: glow::Context,
-    support_debug_extension: bool,
+    capabilities: GlCapabilities,
     pub camera: Camera,
     pub viewport: UVec2,
-    cache: RefCell<GlCache>,
-    is_compositing: Cell<bool>,
-
-    vao: glow::VertexArray,
-
-    composite_framebuffer: glow::Framebuffer,
-    cf_albedo: glow::Texture,
-    cf_emissive: glow::Texture,
-    cf_bump: glow::Texture,
-    cf_stencil: glow::Texture,
+    flip_y: bool,
+    min_part_pixels: f32,
+    cull_offscreen: bool,
+    samples: u32,
+    composite_resolution: CompositeResolution,
+    /// GL viewport of the target, saved while drawing into a composite framebuffer of another size.
+    target_gl_viewport: Cell<[i32; 4]>,
+    compat_version: Version,
+    sort_draws: bool,
+    batch_draws: bool,
+    puppet_transform: Mat4,
+    texture_alpha: AlphaMode,
+    output_alpha: AlphaMode,
+    clip_polygon: Option<Vec<Vec2>>,
+    part_overrides: HashMap<InoxNodeUuid, PartOverride>,
+    cache: GlCache,
+    /// Counts of the frame being rendered by `render_with_stats`, `None` otherwise.
+    stats: Cell<Option<RenderStats>>,
+    /// Number of composite passes in progress, more than 1 for composites nested in others.
+    composite_depth: Cell<u32>,
+    /// Framebuffer that the puppet is rendered to, `None` being the default framebuffer.
+    target_framebuffer: Cell<Option<glow::Framebuffer>>,
+    /// Whether colors are blended in linear space, see `RendererConfig::linear_space`.
+    linear_space: bool,
+    /// Framebuffer that frames are drawn into in linear space or with bloom, before being drawn
+    /// to the target that was set at the start of the frame, see `begin_scene`.
+    scene_framebuffer: Option<CompositeFramebuffer>,
+    output_framebuffer: Cell<Option<glow::Framebuffer>>,
+    bloom: Option<BloomParams>,
+    bloom_framebuffers: Option<BloomFramebuffers>,
+
+    buffers: InoxGlBuffers,
+    wireframe_vao: glow::VertexArray,
+    wireframe_buffer: glow::Buffer,
+
+    /// Framebuffers of uncached composites, checked out while a composite is drawn.
+    composite_framebuffers: RefCell<CompositeFramebufferPool>,
+    /// Color that `clear` uses, see `set_clear_color`.
+    clear_color: Cell<Option<Vec4>>,
+    /// Framebuffer that the current composite pass draws to, see `begin_composite`.
+    compositing_framebuffer: Cell<Option<glow::Framebuffer>>,
+    /// Copy of the target read by shader blend modes, `None` if it couldn't be created.
+    backdrop: Option<BackdropFramebuffer>,
+    cache_composites: bool,
+    cached_composites: RefCell<HashMap<InoxNodeUuid, CachedComposite>>,
 
     part_shader: PartShader,
     part_mask_shader: PartMaskShader,
     composite_shader: CompositeShader,
     composite_mask_shader: CompositeMaskShader,
+    wireframe_shader: WireframeShader,
+    points_shader: PointsShader,
+    unpremultiply_shader: UnpremultiplyShader,
+    scene_shader: SceneShader,
+    bloom_prefilter_shader: BloomPrefilterShader,
+    bloom_blur_shader: BloomBlurShader,
+    bloom_add_shader: BloomAddShader,
 
     textures: Vec<Texture>,
+    /// `ShallowTexture::placeholder`, drawn in place of the textures that parts use but weren't uploaded.
+    fallback_texture: Texture,
+    /// Opaque black texture, bound for the emissive and bump maps that parts don't have,
+    /// which is what Inochi2D's shaders read from the texture units it leaves empty.
+    black_texture: Texture,
+    /// Indices of the missing textures that were already warned about, see `part_texture`.
+    missing_textures: RefCell<HashSet<usize>>,
+    /// Whether textures are uploaded to `texture_array` rather than `textures`.
+    use_texture_array: bool,
+    /// Model textures as the layers of a single array, see `RendererConfig::texture_array`.
+    texture_array: Option<Texture>,
+    texture_filter: TextureFilter,
+    anisotropy: f32,
+    max_texture_size: Option<u32>,
+    /// See `RendererConfig::flip_textures_v`.
+    flip_textures_v: bool,
 }
 
 impl OpenglRenderer {
+    /// Starts building a renderer for `puppet`, to set options before the first frame.
+    /// See `OpenglRendererBuilder`.
+    pub fn builder(puppet: &Puppet, viewport: UVec2) -> OpenglRendererBuilder<'_> {
+        OpenglRendererBuilder::new(puppet, viewport)
+    }
+
+    /// Creates a renderer for `puppet` with `config` and the default settings.
     pub fn new(
         gl: glow::Context,
         viewport: UVec2,
         puppet: &Puppet,
+        config: RendererConfig,
     ) -> Result<Self, OpenglRendererError> {
-        let vao = unsafe { puppet.render_ctx.setup_gl_buffers(&gl)? };
+        // Neither the viewport nor the framebuffers can be zero-sized
+        let viewport = viewport.max(UVec2::ONE);
+
+        let capabilities = GlCapabilities::detect(&gl);
+        let samples = config.samples.clamp(1, capabilities.max_samples);
+
+        if config.debug_messages {
+            if capabilities.debug_groups {
+                unsafe {
+                    gl.enable(glow::DEBUG_OUTPUT);
+                    // Report messages from the call that caused them, for useful backtraces
+                    gl.enable(glow::DEBUG_OUTPUT_SYNCHRONOUS);
+                    gl.debug_message_callback(log_gl_debug_message);
+                }
+            } else {
+                warn!("GL_KHR_debug is not supported, GL debug messages won't be reported");
+            }
+        }
 
-        // Initialize framebuffers
-        let composite_framebuffer;
-        let cf_albedo;
-        let cf_emissive;
-        let cf_bump;
-        let cf_stencil;
-        unsafe {
-            cf_albedo = gl.create_texture().map_err(OpenglRendererError::Opengl)?;
-            cf_emissive = gl.create_texture().map_err(OpenglRendererError::Opengl)?;
-            cf_bump = gl.create_texture().map_err(OpenglRendererError::Opengl)?;
-            cf_stencil = gl.create_texture().map_err(OpenglRendererError::Opengl)?;
+        let linear_space = config.linear_space && capabilities.float_render_targets;
+        if config.linear_space && !linear_space {
+            warn!("Float render targets are not supported, colors will be blended in sRGB space");
+        }
+
+        let buffers = unsafe { puppet.render_ctx.setup_gl_buffers(&gl)? };
 
-            composite_framebuffer = gl
-                .create_framebuffer()
+        // Wireframes are drawn from their own buffer of line vertices
+        let wireframe_vao;
+        let wireframe_buffer;
+        unsafe {
+            wireframe_vao = gl
+                .create_vertex_array()
                 .map_err(OpenglRendererError::Opengl)?;
+            wireframe_buffer = gl.create_buffer().map_err(OpenglRendererError::Opengl)?;
+
+            gl.bind_vertex_array(Some(wireframe_vao));
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(wireframe_buffer));
+            gl.vertex_attrib_pointer_f32(0, 2, glow::FLOAT, false, 0, 0);
+            gl.enable_vertex_attrib_array(0);
+            gl.bind_vertex_array(None);
         }
 
+        // Initialize framebuffers
+        let composite_size = config.composite_resolution.size(viewport);
+        let composite_framebuffers = unsafe {
+            CompositeFramebufferPool::new(
+                &gl,
+                composite_size.x,
+                composite_size.y,
+                samples,
+                capabilities.float_render_targets,
+                linear_space,
+            )
+            .map_err(OpenglRendererError::Opengl)?
+        };
+        let placeholder = ShallowTexture::placeholder();
+        let fallback_texture = Texture::from_raw_pixels_filtered(
+            &gl,
+            placeholder.pixels(),
+            placeholder.width(),
+            placeholder.height(),
+            TextureFilter::Nearest,
+        )
+        .map_err(|e| OpenglRendererError::Opengl(e.to_string()))?;
+        let black_texture =
+            Texture::from_raw_pixels_filtered(&gl, &[0, 0, 0, 255], 1, 1, TextureFilter::Nearest)
+                .map_err(|e| OpenglRendererError::Opengl(e.to_string()))?;
+
+        let backdrop = match unsafe {
+            BackdropFramebuffer::new(&gl, viewport.x, viewport.y, linear_space)
+        } {
+            Ok(backdrop) => Some(backdrop),
+            Err(e) => {
+                warn!("Could not create the backdrop framebuffer, advanced blend modes will blend normally: {e}");
+                None
+            }
+        };
+
         // Shaders
-        let part_shader = PartShader::new(&gl)?;
-        let part_mask_shader = PartMaskShader::new(&gl)?;
+        let part_shader = PartShader::new(&gl, config.texture_array)?;
+        let part_mask_shader = PartMaskShader::new(&gl, config.texture_array)?;
         let composite_shader = CompositeShader::new(&gl)?;
         let composite_mask_shader = CompositeMaskShader::new(&gl)?;
-
-        let support_debug_extension = gl.supported_extensions().contains("GL_KHR_debug");
+        let wireframe_shader = WireframeShader::new(&gl)?;
+        let points_shader = PointsShader::new(&gl)?;
+        let unpremultiply_shader = UnpremultiplyShader::new(&gl)?;
+        let scene_shader = SceneShader::new(&gl)?;
+        let bloom_prefilter_shader = BloomPrefilterShader::new(&gl)?;
+        let bloom_blur_shader = BloomBlurShader::new(&gl)?;
+        let bloom_add_shader = BloomAddShader::new(&gl)?;
 
         let mut renderer = Self {
             gl,
-            support_debug_extension,
+            capabilities,
             camera: Camera::default(),
             viewport,
-            cache: RefCell::new(GlCache::default()),
-            is_compositing: Cell::new(false),
-
-            vao,
-
-            composite_framebuffer,
-            cf_albedo,
-            cf_emissive,
-            cf_bump,
-            cf_stencil,
+            flip_y: false,
+            min_part_pixels: 0.0,
+            cull_offscreen: true,
+            samples,
+            composite_resolution: config.composite_resolution,
+            target_gl_viewport: Cell::new([0; 4]),
+            compat_version: Version::LATEST,
+            sort_draws: false,
+            batch_draws: false,
+            puppet_transform: Mat4::IDENTITY,
+            texture_alpha: AlphaMode::Straight,
+            output_alpha: AlphaMode::Premultiplied,
+            clip_polygon: None,
+            part_overrides: HashMap::new(),
+            cache: GlCache::default(),
+            stats: Cell::new(None),
+            composite_depth: Cell::new(0),
+            target_framebuffer: Cell::new(None),
+            linear_space,
+            scene_framebuffer: None,
+            output_framebuffer: Cell::new(None),
+            bloom: None,
+            bloom_framebuffers: None,
+
+            buffers,
+            wireframe_vao,
+            wireframe_buffer,
+
+            composite_framebuffers: RefCell::new(composite_framebuffers),
+            clear_color: Cell::new(None),
+            compositing_framebuffer: Cell::new(None),
+            backdrop,
+            cache_composites: false,
+            cached_composites: RefCell::new(HashMap::new()),
 
             part_shader,
             part_mask_shader,
             composite_shader,
             composite_mask_shader,
+            wireframe_shader,
+            points_shader,
+            unpremultiply_shader,
+            scene_shader,
+            bloom_prefilter_shader,
+            bloom_blur_shader,
+            bloom_add_shader,
 
             textures: Vec::new(),
+            fallback_texture,
+            black_texture,
+            missing_textures: RefCell::new(HashSet::new()),
+            use_texture_array: config.texture_array,
+            texture_array: None,
+            texture_filter: config.texture_filter,
+            anisotropy: config.anisotropy,
+            max_texture_size: None,
+            flip_textures_v: config.flip_textures_v,
         };
 
+        if linear_space {
+            let scene = renderer
+                .create_scene_framebuffer()
+                .map_err(OpenglRendererError::Opengl)?;
+            renderer.scene_framebuffer = Some(scene);
+        }
+
         renderer.resize(viewport.x, viewport.y);
-        unsafe { renderer.attach_framebuffer_textures() };
 
         Ok(renderer)
     }
 
+    /// Sets a cap on the size of uploaded textures, below the hardware maximum.
+    ///
+    /// Textures bigger than the cap are downscaled in `upload_model_textures`,
+    /// which is useful to save memory on low-end devices. `None` only uses the hardware maximum.
+    pub fn set_max_texture_size(&mut self, max_texture_size: Option<u32>) {
+        self.max_texture_size = max_texture_size;
+    }
+
+    /// Features and limits of the GL context.
+    pub fn capabilities(&self) -> &GlCapabilities {
+        &self.capabilities
+    }
+
+    /// Gets the maximum size a texture can have when uploaded, accounting for both
+    /// the hardware maximum (`GL_MAX_TEXTURE_SIZE`) and the user-set cap.
+    pub fn max_texture_size(&self) -> u32 {
+        let hw_max = self.capabilities.max_texture_size;
+        match self.max_texture_size {
+            Some(max) => max.min(hw_max),
+            None => hw_max,
+        }
+    }
+
+    /// Parses an INP model, creates a renderer for its puppet and uploads its textures.
+    ///
+    /// This is a shortcut for `parse_inp`, `OpenglRenderer::new` with the default `RendererConfig`
+    /// and `upload_model_textures`, which remain available for finer control. The puppet is returned alongside the renderer
+    /// since it is needed to render it. The compatibility version is set to the spec version
+    /// declared by the model, see `set_compat_version`.
+    pub fn from_inp(
+        gl: glow::Context,
+        viewport: UVec2,
+        data: &[u8],
+    ) -> Result<(Self, Puppet), OpenglRendererFromInpError> {
+        let model = parse_inp(data)?;
+        let renderer = Self::from_model(gl, viewport, &model, RendererConfig::default())?;
+        Ok((renderer, model.puppet))
+    }
+
+    /// Creates a renderer for a model's puppet and uploads the model's textures.
+    ///
+    /// The model is only borrowed: keep it, or move its puppet out, to render the puppet.
+    /// The compatibility version is set to the spec version declared by the model,
+    /// see `set_compat_version`.
+    pub fn from_model(
+        gl: glow::Context,
+        viewport: UVec2,
+        model: &Model,
+        config: RendererConfig,
+    ) -> Result<Self, OpenglRendererFromInpError> {
+        let mut renderer = Self::new(gl, viewport, &model.puppet, config)?;
+        renderer.upload_model_textures(&model.textures)?;
+        if let Ok(version) = model.puppet.meta.spec_version() {
+            renderer.set_compat_version(version);
+        }
+        Ok(renderer)
+    }
+
+    /// Switches to another model, e.g. in a gallery of puppets, without recreating
+    /// the shaders and framebuffers that `new` sets up.
+    ///
+    /// The meshes of the model's puppet replace the previous ones in the existing buffers,
+    /// and its textures replace the uploaded ones. Part overrides and cached composites
+    /// are dropped, and the compatibility version is set like `from_model` does.
+    /// Settings such as the camera and the clear color are kept.
+    pub fn set_model(&mut self, model: &Model) -> Result<(), TextureError> {
+        self.unload_textures();
+        self.part_overrides.clear();
+        let gl = &self.gl;
+        for (_, cached) in self.cached_composites.get_mut().drain() {
+            unsafe { cached.framebuffer.delete(gl) };
+        }
+
+        unsafe { self.buffers.reupload(&self.gl, &model.puppet.render_ctx) };
+        // The cached program, vertex array and texture may not be bound anymore
+        self.invalidate_gl_cache();
+
+        self.upload_model_textures(&model.textures)?;
+        self.set_compat_version(model.puppet.meta.spec_version().unwrap_or(Version::LATEST));
+        Ok(())
+    }
+
+    /// Decodes and uploads the model's textures.
+    ///
+    /// Textures that fail to decode are replaced by `ShallowTexture::placeholder`,
+    /// see `upload_model_textures_with` to substitute them with other images.
     pub fn upload_model_textures(
         &mut self,
         model_textures: &[ModelTexture],
     ) -> Result<(), TextureError> {
-        // decode textures in parallel
-        let shalltexs = decode_model_textures(model_textures);
+        self.upload_model_textures_with(model_textures, |_, _| None)
+    }
+
+    /// Decodes and uploads the model's textures, calling `on_texture_error` with the index
+    /// of each texture that fails to decode and the reason why.
+    ///
+    /// The callback can return a replacement image, e.g. a cached or downloaded version
+    /// of the texture. If it returns `None`, `ShallowTexture::placeholder` is used instead,
+    /// so that the indices of the following textures stay aligned with the model's.
+    pub fn upload_model_textures_with(
+        &mut self,
+        model_textures: &[ModelTexture],
+        on_texture_error: impl FnMut(usize, &TextureDecodeError) -> Option<ShallowTexture>,
+    ) -> Result<(), TextureError> {
+        self.upload_model_textures_streaming(model_textures, on_texture_error, |_, _| {})
+    }
+
+    /// Decodes and uploads the model's textures like `upload_model_textures`,
+    /// calling `on_progress` with the number of uploaded textures and the total after each one.
+    ///
+    /// Textures are decoded in parallel in the background, and each is uploaded as soon as
+    /// it and the ones before it are decoded. `on_progress` is called on this thread,
+    /// between uploads, so a loading screen can be drawn with the context from there.
+    pub fn upload_model_textures_with_progress(
+        &mut self,
+        model_textures: &[ModelTexture],
+        on_progress: impl FnMut(usize, usize),
+    ) -> Result<(), TextureError> {
+        self.upload_model_textures_streaming(model_textures, |_, _| None, on_progress)
+    }
+
+    fn upload_model_textures_streaming(
+        &mut self,
+        model_textures: &[ModelTexture],
+        mut on_texture_error: impl FnMut(usize, &TextureDecodeError) -> Option<ShallowTexture>,
+        mut on_progress: impl FnMut(usize, usize),
+    ) -> Result<(), TextureError> {
+        self.check_texture_array_empty()?;
+        let max_texture_size = self.max_texture_size();
+        let total = model_textures.len();
+        let uploader = TextureUploader {
+            filter: self.texture_filter,
+            srgb: self.linear_space,
+            anisotropy: (self.capabilities.max_anisotropy)
+                .map(|max_anisotropy| self.anisotropy.clamp(1.0, max_anisotropy)),
+            texture_storage: self.capabilities.texture_storage,
+        };
 
-        // upload textures
-        for shalltex in shalltexs {
-            let tex = texture::Texture::from_shallow_texture(&self.gl, &shalltex)?;
+        decode_model_textures_in_order::<TextureError>(model_textures, |i, shalltex| {
+            let mut shalltex = shalltex
+                .unwrap_or_else(|e| {
+                    on_texture_error(i, &e).unwrap_or_else(ShallowTexture::placeholder)
+                })
+                .downscaled(max_texture_size);
+            if self.flip_textures_v {
+                shalltex = shalltex.flipped_vertically();
+            }
+            if self.use_texture_array {
+                self.upload_layer(i, total, shalltex)?;
+                on_progress(i + 1, total);
+                return Ok(());
+            }
+
+            let tex = uploader.upload(
+                &self.gl,
+                shalltex.pixels(),
+                shalltex.width(),
+                shalltex.height(),
+            )?;
             self.textures.push(tex);
-        }
 
+            on_progress(i + 1, total);
+            Ok(())
+        })?;
+
+        // Uploaded textures are left bound to save a bind per texture
+        unsafe { self.gl.bind_texture(glow::TEXTURE_2D, None) };
+        self.cache.textures.set(None);
+
+        self.finish_texture_array();
+        self.invalidate_composite_cache();
         Ok(())
     }
 
-    pub fn resize(&mut self, w: u32, h: u32) {
-        self.viewport = uvec2(w, h);
-
-        let gl = &self.gl;
-        unsafe {
-            gl.viewport(0, 0, w as i32, h as i32);
+    /// Fails if textures were already uploaded to a texture array, which can't grow.
+    fn check_texture_array_empty(&self) -> Result<(), TextureError> {
+        match self.texture_array {
+            Some(_) => Err(TextureError::TextureArrayFull),
+            None => Ok(()),
+        }
+    }
 
-            // Reupload composite framebuffer textures
-            texture::upload_empty(gl, self.cf_albedo, w, h, glow::UNSIGNED_BYTE);
-            texture::upload_empty(gl, self.cf_emissive, w, h, glow::FLOAT);
-            texture::upload_empty(gl, self.cf_bump, w, h, glow::UNSIGNED_BYTE);
+    /// Uploads a texture as layer `index` of the texture array, creating the array
+    /// of `count` layers with the texture's size on the first one.
+    fn upload_layer(
+        &mut self,
+        index: usize,
+        count: usize,
+        shalltex: ShallowTexture,
+    ) -> Result<(), TextureError> {
+        let array = match self.texture_array {
+            Some(ref array) => array,
+            None => {
+                let array = Texture::new_array(
+                    &self.gl,
+                    shalltex.width(),
+                    shalltex.height(),
+                    count as u32,
+                    self.texture_filter,
+                    self.linear_space,
+                )?;
+                self.apply_anisotropy(&array);
+                self.texture_array.insert(array)
+            }
+        };
 
-            gl.bind_texture(glow::TEXTURE_2D, Some(self.cf_stencil));
-            gl.tex_image_2d(
-                glow::TEXTURE_2D,
-                0,
-                glow::DEPTH24_STENCIL8 as i32,
-                w as i32,
-                h as i32,
-                0,
-                glow::DEPTH_STENCIL,
-                glow::UNSIGNED_INT_24_8,
-                None,
+        let (width, height) = (array.width(), array.height());
+        if (shalltex.width(), shalltex.height()) != (width, height) {
+            warn!(
+                "Resizing {}x{} texture {index} to {width}x{height} to fit in the texture array",
+                shalltex.width(),
+                shalltex.height()
             );
+        }
+        let shalltex = shalltex.resized(width, height);
+        array.replace_layer(&self.gl, index as u32, shalltex.pixels());
+        Ok(())
+    }
 
-            self.attach_framebuffer_textures();
+    /// Generates the mipmaps of the texture array once its layers are uploaded.
+    fn finish_texture_array(&self) {
+        if let Some(ref array) = self.texture_array {
+            array.update_mipmaps(&self.gl);
         }
+    }
 
-        self.update_camera();
+    /// Uploads textures that were already decoded by the application, bypassing the built-in decoders.
+    ///
+    /// Each image is given as `(pixels, width, height)` with RGBA8 pixels, and they must be
+    /// in the same order as the model's textures so that the parts' texture indices match.
+    /// If the pixels of any image don't match its size, none of them are uploaded.
+    pub fn upload_decoded_textures(
+        &mut self,
+        images: &[(Vec<u8>, u32, u32)],
+    ) -> Result<(), TextureError> {
+        self.check_texture_array_empty()?;
+        let max_texture_size = self.max_texture_size();
+
+        // Checked up front, so that the textures of a failed call aren't partially uploaded
+        for &(ref pixels, width, height) in images {
+            let expected = width as usize * height as usize * 4;
+            if pixels.len() != expected {
+                return Err(TextureError::PixelDataSize {
+                    width,
+                    height,
+                    expected,
+                    actual: pixels.len(),
+                });
+            }
+        }
+
+        for (i, &(ref pixels, width, height)) in images.iter().enumerate() {
+            if self.use_texture_array {
+                let shalltex =
+                    ShallowTexture::new(pixels.clone(), width, height).downscaled(max_texture_size);
+                self.upload_layer(i, images.len(), shalltex)?;
+                continue;
+            }
+
+            let tex = if width > max_texture_size || height > max_texture_size {
+                let shalltex =
+                    ShallowTexture::new(pixels.clone(), width, height).downscaled(max_texture_size);
+                texture::Texture::from_raw_pixels_with(
+                    &self.gl,
+                    shalltex.pixels(),
+                    shalltex.width(),
+                    shalltex.height(),
+                    self.texture_filter,
+                    self.linear_space,
+                )?
+            } else {
+                texture::Texture::from_raw_pixels_with(
+                    &self.gl,
+                    pixels,
+                    width,
+                    height,
+                    self.texture_filter,
+                    self.linear_space,
+                )?
+            };
+            self.apply_anisotropy(&tex);
+            self.textures.push(tex);
+        }
+
+        self.finish_texture_array();
+        self.invalidate_composite_cache();
+        Ok(())
     }
 
-    pub fn clear(&self) {
-        unsafe { self.gl.clear(glow::COLOR_BUFFER_BIT) };
+    /// Number of uploaded textures, which are layers of the texture array if there is one.
+    fn texture_count(&self) -> usize {
+        match self.texture_array {
+            Some(ref array) => array.layers() as usize,
+            None => self.textures.len(),
+        }
     }
 
-    /// Pushes an OpenGL debug group.
-    /// This is very useful to debug OpenGL calls per node with `apitrace`, as it will nest calls inside of labels,
-    /// making it trivial to know which calls correspond to which nodes.
+    /// Deletes all the uploaded textures, e.g. before switching to another puppet.
+    pub fn unload_textures(&mut self) {
+        for tex in self.textures.drain(..) {
+            tex.delete(&self.gl);
+        }
+        if let Some(array) = self.texture_array.take() {
+            array.delete(&self.gl);
+        }
+        self.missing_textures.get_mut().clear();
+        self.cache.textures.set(None);
+        self.invalidate_composite_cache();
+    }
+
+    /// Decodes an image and swaps it in place of the texture at `index`.
     ///
-    /// It is a no-op on platforms that don't support it (like Apple *OS).
-    #[inline]
-    fn push_debug_group(&self, name: &str) {
-        if self.support_debug_extension {
-            unsafe {
-                self.gl
-                    .push_debug_group(glow::DEBUG_SOURCE_APPLICATION, 0, name);
-            }
+    /// The image can have a different size than the previous one, as UVs are normalized.
+    /// The layers of a texture array keep the size of the array, see `RendererConfig::texture_array`.
+    pub fn reload_texture(
+        &mut self,
+        index: usize,
+        data: &[u8],
+        format: ImageFormat,
+    ) -> Result<(), TextureError> {
+        let len = self.texture_count();
+        if index >= len {
+            return Err(TextureError::NoSuchTexture { index, len });
+        }
+
+        let mut shalltex = decode_texture(data, format)?.downscaled(self.max_texture_size());
+        if self.flip_textures_v {
+            shalltex = shalltex.flipped_vertically();
+        }
+        if self.texture_array.is_some() {
+            self.upload_layer(index, len, shalltex)?;
+            self.finish_texture_array();
+        } else {
+            self.textures[index].replace_pixels(
+                &self.gl,
+                shalltex.pixels(),
+                shalltex.width(),
+                shalltex.height(),
+            )?;
         }
+
+        // The texture unit no longer holds what the cache thinks it does
+        self.cache.textures.set(None);
+        self.invalidate_composite_cache();
+        Ok(())
     }
 
-    /// Pops the last OpenGL debug group.
+    /// Changes how the texture at `index` is sampled, e.g. to keep a pixel-art texture crisp
+    /// in an otherwise smooth puppet.
     ///
-    /// It is a no-op on platforms that don't support it (like Apple *OS).
-    #[inline]
-    fn pop_debug_group(&self) {
-        if self.support_debug_extension {
-            unsafe {
-                self.gl.pop_debug_group();
-            }
+    /// The layers of a texture array are all sampled the same way, so with
+    /// `RendererConfig::texture_array` this changes the filter of every texture.
+    pub fn set_texture_filter(
+        &mut self,
+        index: usize,
+        filter: TextureFilter,
+    ) -> Result<(), TextureError> {
+        let len = self.texture_count();
+        if index >= len {
+            return Err(TextureError::NoSuchTexture { index, len });
         }
+        let tex = match self.texture_array {
+            Some(ref mut array) => array,
+            None => &mut self.textures[index],
+        };
+        tex.set_filter(&self.gl, filter);
+        let tex = self.texture_array.as_ref().unwrap_or(&self.textures[index]);
+        self.apply_anisotropy(tex);
+
+        self.cache.textures.set(None);
+        self.invalidate_composite_cache();
+        Ok(())
     }
 
-    /// Updates the camera in the GL cache and returns whether it changed.
-    fn update_camera(&self) -> bool {
-        {
-            let mut cache = self.cache.borrow_mut();
-            if !cache.update_camera(&self.camera) && !cache.update_viewport(self.viewport) {
-                return false;
-            }
+    /// Sets the anisotropy of a texture according to its filter, if supported.
+    fn apply_anisotropy(&self, tex: &Texture) {
+        if let Some(max_anisotropy) = self.capabilities.max_anisotropy {
+            let anisotropy = if tex.filter() == TextureFilter::Trilinear {
+                self.anisotropy.clamp(1.0, max_anisotropy)
+            } else {
+                1.0
+            };
+            tex.set_anisotropy(&self.gl, anisotropy);
         }
+    }
 
-        let matrix = self.camera.matrix(self.viewport.as_vec2());
+    /// Resizes the viewport and the framebuffers.
+    ///
+    /// A zero width or height, as reported for minimized windows, is ignored:
+    /// the previous size is kept until the window is restored to a non-zero size.
+    pub fn resize(&mut self, w: u32, h: u32) {
+        if w == 0 || h == 0 {
+            return;
+        }
 
-        self.bind_shader(&self.composite_shader);
-        self.composite_shader.set_mvp(&self.gl, matrix);
+        self.viewport = uvec2(w, h);
 
-        self.bind_shader(&self.composite_mask_shader);
-        self.composite_mask_shader.set_mvp(&self.gl, matrix);
+        let gl = &self.gl;
+        unsafe {
+            gl.viewport(0, 0, w as i32, h as i32);
 
-        true
+            // Reupload composite framebuffer textures
+            let composite_size = self.composite_size();
+            let resized = (self.composite_framebuffers.get_mut()).resize(
+                gl,
+                composite_size.x,
+                composite_size.y,
+            );
+            if let Err(e) = resized {
+                error!("Could not resize the composite framebuffers: {e}");
+            }
+            if let Some(Err(e)) = self.scene_framebuffer.as_ref().map(|s| s.resize(gl, w, h)) {
+                error!("Could not resize the scene framebuffer: {e}");
+            }
+            if let Some(Err(e)) = self.bloom_framebuffers.as_ref().map(|b| b.resize(gl, w, h)) {
+                error!("Could not resize the bloom framebuffers: {e}");
+            }
+            if let Some(Err(e)) = self.backdrop.as_ref().map(|b| b.resize(gl, w, h)) {
+                error!("Could not resize the backdrop framebuffer: {e}");
+            }
+        }
+
+        // Cached composites are resized lazily, the next time they are drawn
+        self.invalidate_composite_cache();
+
+        self.update_camera();
     }
 
-    /// Set blending mode. See `BlendMode` for supported blend modes.
-    pub fn set_blend_mode(&self, blend_mode: BlendMode) {
-        if !self.cache.borrow_mut().update_blend_mode(blend_mode) {
-            return;
+    /// Size of the composite framebuffers, see `RendererConfig::composite_resolution`.
+    pub fn composite_size(&self) -> UVec2 {
+        self.composite_resolution.size(self.viewport)
+    }
+
+    /// Clears the target to the color set with `set_clear_color`, or to the GL clear color
+    /// set by the application if none was.
+    pub fn clear(&self) {
+        if let Some(color) = self.clear_color.get() {
+            self.set_gl_clear_color(color);
+        }
+        unsafe { self.gl.clear(glow::COLOR_BUFFER_BIT) };
+    }
+
+    /// Clears the target to `color`, in straight RGBA, without changing what `clear` uses.
+    pub fn clear_with(&self, color: Vec4) {
+        self.set_gl_clear_color(color);
+        unsafe { self.gl.clear(glow::COLOR_BUFFER_BIT) };
+    }
+
+    /// Sets the color that `clear` fills the target with, in straight RGBA.
+    ///
+    /// A transparent color keeps the background transparent, e.g. to export a frame with
+    /// `read_framebuffer` as a transparent PNG. This needs a target with an alpha channel,
+    /// which the default framebuffer only has if the GL context was created with one.
+    /// Composites are always cleared to transparent black.
+    pub fn set_clear_color(&self, rgba: Vec4) {
+        self.clear_color.set(Some(rgba));
+    }
+
+    /// Gets the color set with `set_clear_color`, if any.
+    pub fn clear_color(&self) -> Option<Vec4> {
+        self.clear_color.get()
+    }
+
+    fn set_gl_clear_color(&self, color: Vec4) {
+        if self.cache.update_clear_color(color) {
+            unsafe { self.gl.clear_color(color.x, color.y, color.z, color.w) };
+        }
+    }
+
+    /// Reads the rendered frame back from the default framebuffer, for thumbnails or screenshot tests.
+    ///
+    /// The image has the size of the viewport, its origin at the top-left, and sRGB-encoded
+    /// RGBA8 values. See `read_framebuffer_as` to convert them.
+    ///
+    /// Model textures are sRGB images, and they are uploaded and blended without being decoded,
+    /// so the renderer writes sRGB-encoded values. In linear space, see
+    /// `RendererConfig::linear_space`, the frame is encoded back to sRGB when drawn to the target.
+    /// The renderer leaves `GL_FRAMEBUFFER_SRGB` as it is: if the application enables it with
+    /// an sRGB-capable framebuffer, the GL encodes these values a second time when writing them.
+    pub fn read_framebuffer(&self) -> image::RgbaImage {
+        let pixels = self.read_pixels(None);
+        image::RgbaImage::from_raw(self.viewport.x, self.viewport.y, pixels).unwrap()
+    }
+
+    /// Reads the rendered frame back from the default framebuffer, converted to `color_space`.
+    ///
+    /// The image has the size of the viewport and its origin at the top-left. Its values are
+    /// converted from the sRGB-encoded ones of `read_framebuffer`.
+    /// Converting to `ColorSpace::Linear` applies the sRGB decoding function to the color channels,
+    /// which loses precision in the dark tones with 8 bits per channel. Alpha is left as is.
+    pub fn read_framebuffer_as(&self, color_space: ColorSpace) -> image::RgbaImage {
+        let mut pixels = self.read_pixels(None);
+        ColorSpace::Srgb.convert_rgba8(color_space, &mut pixels);
+
+        image::RgbaImage::from_raw(self.viewport.x, self.viewport.y, pixels).unwrap()
+    }
+
+    /// Reads the viewport's RGBA8 pixels from the first color attachment of `framebuffer`,
+    /// or from the default framebuffer if `None`, with the origin at the top-left.
+    fn read_pixels(&self, framebuffer: Option<glow::Framebuffer>) -> Vec<u8> {
+        let UVec2 {
+            x: width,
+            y: height,
+        } = self.viewport;
+        let mut pixels = vec![0_u8; width as usize * height as usize * 4];
+
+        let gl = &self.gl;
+        unsafe {
+            gl.bind_framebuffer(glow::READ_FRAMEBUFFER, framebuffer);
+            if framebuffer.is_some() {
+                gl.read_buffer(glow::COLOR_ATTACHMENT0);
+            }
+            // rows of non-multiple-of-4 widths are tightly packed
+            gl.pixel_store_i32(glow::PACK_ALIGNMENT, 1);
+            gl.read_pixels(
+                0,
+                0,
+                width as i32,
+                height as i32,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                glow::PixelPackData::Slice(&mut pixels),
+            );
+        }
+
+        // GL rows go bottom to top
+        let row_len = width as usize * 4;
+        if row_len > 0 {
+            let rows = pixels.chunks_exact(row_len).rev();
+            pixels = rows.flatten().copied().collect();
+        }
+
+        unsafe { gl.bind_framebuffer(glow::READ_FRAMEBUFFER, None) };
+
+        pixels
+    }
+
+    /// Renders the puppet's coverage as a grayscale matte, for external compositors.
+    ///
+    /// The puppet is rendered offscreen exactly like `render` does, masks included,
+    /// and each pixel of the matte is the alpha of the render, repeated in the color channels.
+    /// The matte itself is opaque. What was rendered to the default framebuffer is left untouched.
+    pub fn render_matte(&self, puppet: &Puppet) -> Result<image::RgbaImage, OpenglRendererError> {
+        let mut pixels = self.with_offscreen_target("Matte", |framebuffer| {
+            self.clear_offscreen_target(framebuffer);
+            self.render(puppet);
+            self.read_pixels(Some(framebuffer.framebuffer))
+        })?;
+
+        for pixel in pixels.chunks_exact_mut(4) {
+            let alpha = pixel[3];
+            pixel.copy_from_slice(&[alpha, alpha, alpha, 255]);
+        }
+
+        Ok(image::RgbaImage::from_raw(self.viewport.x, self.viewport.y, pixels).unwrap())
+    }
+
+    /// Renders groups of nodes each into their own transparent frame, for layer-based compositing.
+    ///
+    /// Groups would typically be top-level composites, e.g. one for the body, the face and the hair.
+    /// Nodes of a group are drawn in the puppet's draw order, and masks are resolved as usual,
+    /// whether their source is in the same group or not. The frames have the size of the viewport,
+    /// their origin at the top-left, and premultiplied alpha like what `render` draws.
+    pub fn render_layers(
+        &self,
+        puppet: &Puppet,
+        groups: &[&[InoxNodeUuid]],
+    ) -> Result<Vec<image::RgbaImage>, OpenglRendererError> {
+        self.with_offscreen_target("Layers", |framebuffer| {
+            self.prepare_render(puppet);
+
+            groups
+                .iter()
+                .map(|group| {
+                    self.clear_offscreen_target(framebuffer);
+                    self.begin_scene();
+
+                    // Nodes that aren't drawn on their own, like composite children, come last
+                    let zsorted = &puppet.render_ctx.nodes_zsorted;
+                    let mut group = group.to_vec();
+                    group.sort_by_key(|uuid| {
+                        zsorted
+                            .iter()
+                            .position(|zsorted_uuid| zsorted_uuid == uuid)
+                            .unwrap_or(zsorted.len())
+                    });
+
+                    for uuid in group {
+                        self.draw_node(puppet, uuid, false, false);
+                    }
+                    self.end_scene();
+
+                    let pixels = self.read_pixels(Some(framebuffer.framebuffer));
+                    image::RgbaImage::from_raw(self.viewport.x, self.viewport.y, pixels).unwrap()
+                })
+                .collect()
+        })
+    }
+
+    /// Runs `f` with a temporary viewport-sized framebuffer as the render target.
+    fn with_offscreen_target<R>(
+        &self,
+        debug_label: &str,
+        f: impl FnOnce(&CompositeFramebuffer) -> R,
+    ) -> Result<R, OpenglRendererError> {
+        let gl = &self.gl;
+        let framebuffer = unsafe {
+            CompositeFramebuffer::new(
+                gl,
+                self.viewport.x,
+                self.viewport.y,
+                1,
+                self.capabilities.float_render_targets,
+                false,
+            )
+            .map_err(OpenglRendererError::Opengl)?
+        };
+
+        self.push_debug_group(debug_label);
+        self.target_framebuffer.set(Some(framebuffer.framebuffer));
+
+        let result = f(&framebuffer);
+
+        self.target_framebuffer.set(None);
+        unsafe {
+            gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+            framebuffer.delete(gl);
+        }
+        self.pop_debug_group();
+
+        Ok(result)
+    }
+
+    /// Binds an offscreen framebuffer and clears it to transparent.
+    fn clear_offscreen_target(&self, framebuffer: &CompositeFramebuffer) {
+        let gl = &self.gl;
+        unsafe {
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer.framebuffer));
+            gl.draw_buffers(&[
+                glow::COLOR_ATTACHMENT0,
+                glow::COLOR_ATTACHMENT1,
+                glow::COLOR_ATTACHMENT2,
+            ]);
+        }
+        self.set_gl_clear_color(Vec4::ZERO);
+        unsafe { gl.clear(glow::COLOR_BUFFER_BIT) };
+    }
+
+    /// Pushes an OpenGL debug group.
+    /// This is very useful to debug OpenGL calls per node with `apitrace`, as it will nest calls inside of labels,
+    /// making it trivial to know which calls correspond to which nodes.
+    ///
+    /// It is a no-op on platforms that don't support it (like Apple *OS).
+    #[inline]
+    fn push_debug_group(&self, name: &str) {
+        if self.capabilities.debug_groups {
+            unsafe {
+                self.gl
+                    .push_debug_group(glow::DEBUG_SOURCE_APPLICATION, 0, name);
+            }
+        }
+    }
+
+    /// Pops the last OpenGL debug group.
+    ///
+    /// It is a no-op on platforms that don't support it (like Apple *OS).
+    #[inline]
+    fn pop_debug_group(&self) {
+        if self.capabilities.debug_groups {
+            unsafe {
+                self.gl.pop_debug_group();
+            }
+        }
+    }
+
+    /// Sets whether the puppet should be rendered flipped vertically.
+    ///
+    /// This is meant for targets expecting a bottom-left origin (some video encoders and FBOs).
+    /// The projection itself is flipped, so masks and composites stay consistent with the parts.
+    ///
+    /// Note that when reading pixels back with a row flip to get a top-left origin image,
+    /// enabling this results in an upside-down image: only use one of the two flips.
+    pub fn set_flip_y(&mut self, flip_y: bool) {
+        if self.flip_y != flip_y {
+            self.flip_y = flip_y;
+            // force the camera matrix to be recomputed
+            self.cache.camera.set(None);
+        }
+    }
+
+    /// Whether the puppet is rendered flipped vertically. See `set_flip_y`.
+    pub fn flip_y(&self) -> bool {
+        self.flip_y
+    }
+
+    /// Sets the minimum size in pixels a part must have on screen to be drawn,
+    /// which is compared against the larger side of its screen-space bounding box.
+    ///
+    /// This is a level-of-detail setting for zoomed-out puppets: tiny parts are skipped,
+    /// and so are composites whose parts are all too small. Defaults to 0, which draws everything.
+    pub fn set_min_part_pixels(&mut self, min_part_pixels: f32) {
+        self.min_part_pixels = min_part_pixels.max(0.0);
+    }
+
+    /// Sets whether parts entirely outside the viewport are skipped, which is enabled by default.
+    ///
+    /// Parts are tested with their deformed screen-space bounding box, so culling never changes
+    /// the rendered image, but disabling it can help when debugging draw calls.
+    /// Composite children are culled the same way, as composites are drawn offscreen with the
+    /// same projection and viewport, and composites whose children are all culled are skipped.
+    pub fn set_culling(&mut self, cull_offscreen: bool) {
+        self.cull_offscreen = cull_offscreen;
+    }
+
+    /// Whether parts outside the viewport are skipped. See `set_culling`.
+    pub fn culling(&self) -> bool {
+        self.cull_offscreen
+    }
+
+    /// Multiplies the opacity of a part by `opacity` when drawing it, without changing the model.
+    ///
+    /// This is meant for tools to dim or highlight parts. Overrides only apply to parts drawn
+    /// as themselves, not as mask sources, and they stay until `clear_overrides` is called.
+    pub fn set_part_opacity(&mut self, uuid: InoxNodeUuid, opacity: f32) {
+        self.part_overrides.entry(uuid).or_default().opacity = opacity;
+    }
+
+    /// Multiplies the tint of a part by `tint` when drawing it, without changing the model.
+    /// See `set_part_opacity`.
+    pub fn set_part_tint(&mut self, uuid: InoxNodeUuid, tint: Vec3) {
+        self.part_overrides.entry(uuid).or_default().tint = tint;
+    }
+
+    /// Removes all the opacity and tint overrides set on parts.
+    pub fn clear_overrides(&mut self) {
+        self.part_overrides.clear();
+    }
+
+    /// Gets the opacity and tint a part is drawn with, parameters and overrides included,
+    /// clamped like those of composites.
+    fn part_opacity_tint(
+        &self,
+        uuid: InoxNodeUuid,
+        part: &Part,
+        node_render_ctx: &NodeRenderCtx,
+    ) -> (f32, Vec3) {
+        let part_override = self.part_overrides.get(&uuid).copied().unwrap_or_default();
+        clamp_opacity_tint(
+            part.draw_state.opacity * node_render_ctx.opacity * part_override.opacity,
+            part.draw_state.tint * part_override.tint,
+        )
+    }
+
+    /// Moves and zooms the camera so that the whole puppet is centered and fills the viewport,
+    /// which makes a good default view for a freshly loaded model. See `Camera::fit_bounds`.
+    ///
+    /// The puppet is framed in its rest pose, with `padding` as the fraction of the viewport
+    /// left empty around it. Nothing changes if the puppet has no parts.
+    pub fn frame_model(&mut self, puppet: &Puppet, padding: f32) {
+        if let Some(bounds) = puppet.nodes.model_bounds() {
+            let bounds = bounds.transformed(self.puppet_transform);
+            self.camera
+                .fit_bounds(bounds, self.viewport.as_vec2(), padding);
+        }
+    }
+
+    /// Gets the topmost part under a point in world space, as given by `Camera::screen_to_world`.
+    ///
+    /// Parts are tested in reverse draw order, including the children of composites, against
+    /// their deformed mesh triangles, so this works on the CPU without reading the framebuffer back.
+    /// Transparent texels still count as hits, but parts with a zero opacity are skipped.
+    pub fn pick_node(&self, puppet: &Puppet, world: Vec2) -> Option<InoxNodeUuid> {
+        let mut draw_order = Vec::new();
+        Self::push_draw_order(puppet, &puppet.render_ctx.nodes_zsorted, &mut draw_order);
+
+        let vertex_buffers = &puppet.render_ctx.vertex_buffers;
+        draw_order.into_iter().rev().find(|&uuid| {
+            let Some(InoxData::Part(ref part)) = puppet.nodes.get_node(uuid).map(|n| &n.data)
+            else {
+                return false;
+            };
+            let node_render_ctx = &puppet.render_ctx.node_render_ctxs[&uuid];
+            let RenderCtxKind::Part(ref part_render_ctx) = node_render_ctx.kind else {
+                return false;
+            };
+            if self.part_opacity_tint(uuid, part, node_render_ctx).0 <= 0.0
+                || !puppet.nodes.is_shown(uuid)
+            {
+                return false;
+            }
+
+            // Bring the point into the part's space rather than transforming every vertex
+            let local = (self.puppet_transform * node_render_ctx.trans)
+                .inverse()
+                .transform_point3(world.extend(0.0))
+                .truncate();
+
+            let beg = part_render_ctx.vert_offset as usize;
+            let end = beg + part_render_ctx.vert_len;
+            let vertices = (vertex_buffers.verts[beg..end].iter())
+                .zip(&vertex_buffers.deforms[beg..end])
+                .map(|(&vert, &deform)| vert + deform)
+                .collect::<Vec<_>>();
+
+            triangles_contain(&vertices, &part.mesh.indices, local)
+        })
+    }
+
+    /// Appends `uuids` to `draw_order`, followed by the children of the composites among them.
+    fn push_draw_order(
+        puppet: &Puppet,
+        uuids: &[InoxNodeUuid],
+        draw_order: &mut Vec<InoxNodeUuid>,
+    ) {
+        for &uuid in uuids {
+            draw_order.push(uuid);
+            if let RenderCtxKind::Composite(ref children) =
+                puppet.render_ctx.node_render_ctxs[&uuid].kind
+            {
+                Self::push_draw_order(puppet, children, draw_order);
+            }
+        }
+    }
+
+    /// Whether a part should be skipped, because it is outside the viewport or smaller
+    /// than `min_part_pixels` on screen.
+    fn is_part_culled(
+        &self,
+        puppet: &Puppet,
+        node_render_ctx: &NodeRenderCtx,
+        part_render_ctx: &PartRenderCtx,
+    ) -> bool {
+        if !self.cull_offscreen && self.min_part_pixels <= 0.0 {
+            return false;
+        }
+
+        let (min, max) = self.part_ndc_bounds(puppet, node_render_ctx, part_render_ctx);
+
+        // The viewport spans -1 to 1 in NDC
+        let ndc_viewport = Aabb::new(Vec2::NEG_ONE, Vec2::ONE);
+        if self.cull_offscreen && !Aabb::new(min, max).intersects(&ndc_viewport) {
+            return true;
+        }
+
+        // NDC spans 2 units across the viewport
+        let pixel_size = ((max - min) * self.viewport.as_vec2() / 2.0).max_element();
+        pixel_size < self.min_part_pixels
+    }
+
+    /// Gets the corners of a part's bounding box in normalized device coordinates.
+    fn part_ndc_bounds(
+        &self,
+        puppet: &Puppet,
+        node_render_ctx: &NodeRenderCtx,
+        part_render_ctx: &PartRenderCtx,
+    ) -> (Vec2, Vec2) {
+        let mvp = self.node_mvp(node_render_ctx);
+        let vertex_buffers = &puppet.render_ctx.vertex_buffers;
+
+        let beg = part_render_ctx.vert_offset as usize;
+        let end = beg + part_render_ctx.vert_len;
+        let (min, max) = (vertex_buffers.verts[beg..end].iter())
+            .zip(&vertex_buffers.deforms[beg..end])
+            .map(|(&vert, &deform)| mvp.project_point3((vert + deform).extend(0.0)).truncate())
+            .fold((Vec2::MAX, Vec2::MIN), |(min, max), point| {
+                (min.min(point), max.max(point))
+            });
+
+        (min, max)
+    }
+
+    /// Whether a node would draw nothing because it is a culled part, or a composite of culled parts.
+    fn is_culled(&self, puppet: &Puppet, uuid: InoxNodeUuid) -> bool {
+        let node_render_ctx = &puppet.render_ctx.node_render_ctxs[&uuid];
+        match node_render_ctx.kind {
+            RenderCtxKind::Part(ref part_render_ctx) => {
+                self.is_part_culled(puppet, node_render_ctx, part_render_ctx)
+            }
+            RenderCtxKind::Composite(ref children) => {
+                children.iter().all(|&uuid| self.is_culled(puppet, uuid))
+            }
+            // Mesh groups aren't drawn themselves
+            RenderCtxKind::Node | RenderCtxKind::MeshGroup(_) => true,
+        }
+    }
+
+    /// Sets the Inochi2D spec version whose rendering behavior should be reproduced.
+    ///
+    /// Defaults to `Version::LATEST`. A model's version can be read with `PuppetMeta::spec_version`.
+    /// The behaviors gated by version are:
+    /// - before 0.8, screen tint doesn't exist and is ignored;
+    /// - before 0.8, the `ClipToLower` and `SliceFromLower` blend modes don't exist
+    ///   and are drawn as `Normal`.
+    pub fn set_compat_version(&mut self, version: Version) {
+        self.compat_version = version;
+        self.invalidate_composite_cache();
+    }
+
+    /// Sets whether the albedo textures have straight or premultiplied alpha.
+    ///
+    /// The renderer works with premultiplied alpha throughout, like the Inochi2D editor:
+    /// parts and composites are blended with premultiplied blend functions, composites are
+    /// drawn into a buffer cleared to transparent black and then drawn as premultiplied colors.
+    /// Straight alpha textures, which is what models contain, are premultiplied when sampled.
+    /// Defaults to `AlphaMode::Straight`, use `AlphaMode::Premultiplied` for textures uploaded
+    /// with `upload_decoded_textures` that are already premultiplied.
+    pub fn set_texture_alpha(&mut self, texture_alpha: AlphaMode) {
+        self.texture_alpha = texture_alpha;
+        self.invalidate_composite_cache();
+    }
+
+    /// Gets the Inochi2D spec version whose rendering behavior is reproduced.
+    pub fn compat_version(&self) -> Version {
+        self.compat_version
+    }
+
+    /// Gets the screen tint to draw with, according to the compatibility version.
+    fn compat_screen_tint(&self, screen_tint: Vec3) -> Vec3 {
+        compat_screen_tint(self.compat_version, screen_tint)
+    }
+
+    /// Converts a color of the model, which is in sRGB like the textures, to the space colors are blended in.
+    fn shader_color(&self, color: Vec3) -> Vec3 {
+        if self.linear_space {
+            Vec3::from_array(color.to_array().map(srgb_to_linear))
+        } else {
+            color
+        }
+    }
+
+    /// Gets the blend mode to draw with, according to the compatibility version.
+    fn compat_blend_mode(&self, blend_mode: BlendMode) -> BlendMode {
+        compat_blend_mode(self.compat_version, blend_mode)
+    }
+
+    /// Sets a transform applied to the whole puppet, to place it in a scene.
+    ///
+    /// It is applied on top of the puppet's own transforms, before the camera.
+    pub fn set_puppet_transform(&mut self, transform: Mat4) {
+        self.puppet_transform = transform;
+    }
+
+    /// Gets the transform applied to the whole puppet. See `set_puppet_transform`.
+    pub fn puppet_transform(&self) -> Mat4 {
+        self.puppet_transform
+    }
+
+    /// Gets the matrix transforming the puppet's world space to clip space.
+    fn view_projection(&self) -> Mat4 {
+        self.projection() * self.puppet_transform
+    }
+
+    /// Gets the matrix transforming a node's local space to clip space.
+    fn node_mvp(&self, node_render_ctx: &NodeRenderCtx) -> Mat4 {
+        self.view_projection()
+            * self.camera.zsort_translation(node_render_ctx.zsort)
+            * node_render_ctx.trans
+    }
+
+    /// Gets the projection matrix from the camera and viewport, flipped if requested.
+    fn projection(&self) -> Mat4 {
+        let matrix = self.camera.matrix(self.viewport.as_vec2());
+        if self.flip_y {
+            Mat4::from_scale(vec3(1.0, -1.0, 1.0)) * matrix
+        } else {
+            matrix
+        }
+    }
+
+    /// Updates the camera in the GL cache and returns whether it changed.
+    fn update_camera(&self) -> bool {
+        if !self.cache.update_camera(&self.camera) && !self.cache.update_viewport(self.viewport) {
+            return false;
+        }
+
+        let matrix = self.projection();
+
+        self.bind_shader(&self.composite_shader);
+        self.composite_shader.set_mvp(&self.gl, matrix);
+
+        self.bind_shader(&self.composite_mask_shader);
+        self.composite_mask_shader.set_mvp(&self.gl, matrix);
+
+        true
+    }
+
+    /// Set blending mode. See `BlendMode` for supported blend modes.
+    ///
+    /// Modes that need a backdrop (see `BlendMode::needs_backdrop`) are blended in the shaders
+    /// while drawing, so the pipeline is set to normal blending for them.
+    pub fn set_blend_mode(&self, blend_mode: BlendMode) {
+        if !self.cache.update_blend_mode(blend_mode) {
+            return;
+        }
+
+        let gl = &self.gl;
+        unsafe {
+            match blend_mode {
+                BlendMode::Normal
+                | BlendMode::Overlay
+                | BlendMode::SoftLight
+                | BlendMode::HardLight
+                | BlendMode::Darken
+                | BlendMode::ColorBurn
+                | BlendMode::LinearBurn
+                | BlendMode::Difference => {
+                    gl.blend_equation(glow::FUNC_ADD);
+                    gl.blend_func(glow::ONE, glow::ONE_MINUS_SRC_ALPHA);
+                }
+                BlendMode::Multiply => {
+                    gl.blend_equation(glow::FUNC_ADD);
+                    gl.blend_func(glow::DST_COLOR, glow::ONE_MINUS_SRC_ALPHA);
+                }
+                BlendMode::ColorDodge => {
+                    gl.blend_equation(glow::FUNC_ADD);
+                    gl.blend_func(glow::DST_COLOR, glow::ONE);
+                }
+                BlendMode::LinearDodge => {
+                    gl.blend_equation(glow::FUNC_ADD);
+                    gl.blend_func(glow::ONE, glow::ONE);
+                }
+                BlendMode::Screen => {
+                    gl.blend_equation(glow::FUNC_ADD);
+                    gl.blend_func(glow::ONE, glow::ONE_MINUS_SRC_COLOR);
+                }
+                BlendMode::ClipToLower => {
+                    gl.blend_equation(glow::FUNC_ADD);
+                    gl.blend_func(glow::DST_ALPHA, glow::ONE_MINUS_SRC_ALPHA);
+                }
+                BlendMode::SliceFromLower => {
+                    gl.blend_equation(glow::FUNC_SUBTRACT);
+                    gl.blend_func(glow::ONE_MINUS_DST_ALPHA, glow::ONE_MINUS_SRC_ALPHA);
+                }
+                // Colors are blended differently from alpha, which is composited normally
+                BlendMode::Lighten => {
+                    gl.blend_equation_separate(glow::MAX, glow::FUNC_ADD);
+                    gl.blend_func(glow::ONE, glow::ONE_MINUS_SRC_ALPHA);
+                }
+                BlendMode::Subtract => {
+                    gl.blend_equation_separate(glow::FUNC_REVERSE_SUBTRACT, glow::FUNC_ADD);
+                    gl.blend_func_separate(
+                        glow::ONE,
+                        glow::ONE,
+                        glow::ONE,
+                        glow::ONE_MINUS_SRC_ALPHA,
+                    );
+                }
+                BlendMode::Exclusion => {
+                    gl.blend_equation(glow::FUNC_ADD);
+                    gl.blend_func_separate(
+                        glow::ONE_MINUS_DST_COLOR,
+                        glow::ONE_MINUS_SRC_COLOR,
+                        glow::ONE,
+                        glow::ONE_MINUS_SRC_ALPHA,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Copies what was drawn to the current target into the backdrop texture, if `blend_mode`
+    /// needs it, and returns the blend mode that shaders should apply.
+    ///
+    /// Without a backdrop framebuffer, shaders don't blend and the mode falls back to normal blending.
+    fn prepare_backdrop(&self, blend_mode: BlendMode) -> BlendMode {
+        if !blend_mode.needs_backdrop() {
+            return blend_mode;
+        }
+        let Some(backdrop) = &self.backdrop else {
+            return BlendMode::Normal;
+        };
+
+        let (target, size) = if self.is_compositing() {
+            (self.compositing_framebuffer.get(), self.composite_size())
+        } else {
+            (self.target_framebuffer.get(), self.viewport)
+        };
+        unsafe {
+            backdrop.copy_from(&self.gl, target, size.x, size.y, BACKDROP_TEXTURE_UNIT);
+        }
+        blend_mode
+    }
+
+    fn bind_shader<S: Deref<Target = glow::Program>>(&self, shader: &S) {
+        let program = **shader;
+        if !self.cache.update_program(program) {
+            return;
+        }
+
+        unsafe { self.gl.use_program(Some(program)) };
+    }
+
+    fn bind_part_textures(&self, part: &Part) {
+        // With a texture array, each texture is a layer of the array, which stays bound
+        // from one part to the next. Shaders get the layers with `set_layer` and `set_map_layers`.
+        if let Some(ref array) = self.texture_array {
+            let was_bound = self.cache.textures.replace(Some(part_textures(part)));
+            if was_bound.is_none() {
+                array.bind_on(&self.gl, 0);
+            }
+            return;
+        }
+
+        if !self.cache.update_textures(part_textures(part)) {
+            return;
+        }
+
+        let gl = &self.gl;
+        self.part_texture(part.tex_albedo).bind_on(gl, 0);
+        self.map_texture(part.tex_emissive)
+            .bind_on(gl, EMISSIVE_TEXTURE_UNIT);
+        self.map_texture(part.tex_bumpmap)
+            .bind_on(gl, BUMPMAP_TEXTURE_UNIT);
+    }
+
+    /// Gets the texture of a part's emissive or bump map, black if it has none.
+    fn map_texture(&self, index: Option<usize>) -> &Texture {
+        index.map_or(&self.black_texture, |index| self.part_texture(index))
+    }
+
+    /// Gets the uploaded texture `index`, or the fallback texture if there is none,
+    /// e.g. for a model shipping fewer textures than its parts use. See `Model::validate`.
+    fn part_texture(&self, index: usize) -> &Texture {
+        self.textures.get(index).unwrap_or_else(|| {
+            // Parts are drawn every frame, so each missing texture is only warned about once
+            if self.missing_textures.borrow_mut().insert(index) {
+                warn!(
+                    "Texture {index} is used by a part but only {} were uploaded, drawing a placeholder",
+                    self.textures.len()
+                );
+            }
+            &self.fallback_texture
+        })
+    }
+
+    /// Gets the GL context the renderer draws with, to issue custom GL calls between its draws,
+    /// e.g. drawing a background before `render`.
+    ///
+    /// The renderer skips GL calls for state it believes is already set, so callers are
+    /// responsible for the state they change: either restore it, or call `invalidate_gl_cache`
+    /// before the renderer draws again.
+    pub fn gl(&self) -> &glow::Context {
+        &self.gl
+    }
+
+    /// Forgets the GL state that was cached to skip redundant calls.
+    ///
+    /// Call this when something else changed the GL state between two renders,
+    /// such as another renderer or an UI library sharing the same context.
+    pub fn invalidate_gl_cache(&self) {
+        let cache = &self.cache;
+        cache.blend_mode.set(None);
+        cache.program.set(None);
+        cache.vao.set(None);
+        cache.textures.set(None);
+        cache.clear_color.set(None);
+    }
+
+    /// Clear the texture cache
+    /// This one method missing made me pull my hair out for an entire month.
+    pub fn clear_texture_cache(&self) {
+        self.cache.textures.set(None);
+    }
+
+    /// Renders the puppet to the target framebuffer.
+    ///
+    /// Rendering changes the following GL state, and leaves it changed:
+    /// - `GL_BLEND`, `GL_STENCIL_TEST` and `GL_DEPTH_TEST`, and the blend equations and functions,
+    /// - the stencil function, operations, write mask and clear value, the color write mask
+    ///   and the clear color,
+    /// - the bound program, vertex array and array buffer,
+    /// - the textures bound on units 0 to 3, and the active texture unit,
+    /// - the framebuffers bound for drawing and reading.
+    ///
+    /// Applications sharing the context, e.g. with an UI library, can wrap rendering with
+    /// `with_saved_state` to get their state back.
+    pub fn render(&self, puppet: &Puppet) {
+        self.begin_render(puppet);
+
+        if self.sort_draws {
+            self.draw_nodes(puppet, &self.batched_draw_order(puppet));
+        } else {
+            self.draw_nodes(puppet, &puppet.render_ctx.nodes_zsorted);
+        }
+
+        self.end_render();
+    }
+
+    /// Renders the puppet like `render`, counting what the frame drew.
+    pub fn render_with_stats(&self, puppet: &Puppet) -> RenderStats {
+        let (hits, misses) = (self.cache.hits.get(), self.cache.misses.get());
+        self.stats.set(Some(RenderStats::default()));
+
+        self.render(puppet);
+
+        let mut stats = self.stats.take().unwrap_or_default();
+        stats.state_changes_skipped = self.cache.hits.get().wrapping_sub(hits) as u32;
+        stats.state_changes = self.cache.misses.get().wrapping_sub(misses) as u32;
+        stats
+    }
+
+    /// Updates the counts of the frame, if it is rendered by `render_with_stats`.
+    fn count(&self, update: impl FnOnce(&mut RenderStats)) {
+        if let Some(mut stats) = self.stats.get() {
+            update(&mut stats);
+            self.stats.set(Some(stats));
+        }
+    }
+
+    /// Draws nodes in order, merging the draw calls of parts if enabled.
+    fn draw_nodes(&self, puppet: &Puppet, order: &[InoxNodeUuid]) {
+        if !self.batch_draws {
+            for &uuid in order {
+                self.draw_node(puppet, uuid, false, false);
+            }
+            return;
+        }
+
+        // Parts that aren't drawn would needlessly split batches
+        let order = (order.iter().copied())
+            .filter(|&uuid| {
+                puppet.nodes.is_shown(uuid)
+                    && match puppet.render_ctx.node_render_ctxs[&uuid].kind {
+                        RenderCtxKind::Part(ref part_render_ctx) => !self.is_part_culled(
+                            puppet,
+                            &puppet.render_ctx.node_render_ctxs[&uuid],
+                            part_render_ctx,
+                        ),
+                        _ => true,
+                    }
+            })
+            .collect::<Vec<_>>();
+        let keys = (order.iter())
+            .map(|&uuid| self.part_batch_key(puppet, uuid))
+            .collect::<Vec<_>>();
+
+        for draw in merge_draws(&keys) {
+            if draw.len() == 1 {
+                self.draw_node(puppet, order[draw.start], false, false);
+            } else {
+                self.draw_part_batch(puppet, &order[draw]);
+            }
+        }
+    }
+
+    /// Gets the state of a part's draw call, if it can be merged with other parts'.
+    ///
+    /// Masked parts and parts blended with the backdrop, which changes with every draw,
+    /// are drawn on their own, like nodes that aren't parts.
+    fn part_batch_key(&self, puppet: &Puppet, uuid: InoxNodeUuid) -> Option<PartBatchKey> {
+        let node = puppet.nodes.get_node(uuid)?;
+        let node_render_ctx = &puppet.render_ctx.node_render_ctxs[&uuid];
+        let InoxData::Part(ref part) = node.data else {
+            return None;
+        };
+
+        let blend_mode = self.compat_blend_mode(part.draw_state.blend_mode);
+        let masked = (0..part.draw_state.masks.len()).any(|i| puppet.is_mask_enabled(uuid, i));
+        if masked || blend_mode.needs_backdrop() {
+            return None;
+        }
+
+        let (opacity, tint) = self.part_opacity_tint(uuid, part, node_render_ctx);
+        Some(PartBatchKey {
+            textures: part_textures(part),
+            blend_mode,
+            trans: self.camera.zsort_translation(node_render_ctx.zsort) * node_render_ctx.trans,
+            opacity,
+            tint,
+            screen_tint: part.draw_state.screen_tint,
+        })
+    }
+
+    /// Draws parts with the same `PartBatchKey` in a single call.
+    fn draw_part_batch(&self, puppet: &Puppet, uuids: &[InoxNodeUuid]) {
+        let render_ctx = &puppet.render_ctx;
+        let first = uuids[0];
+        let Some(InoxData::Part(ref part)) = puppet.nodes.get_node(first).map(|node| &node.data)
+        else {
+            return;
+        };
+
+        self.push_debug_group(&format!("Batch of {} parts", uuids.len()));
+        self.set_part_state(first, part, &render_ctx.node_render_ctxs[&first], false);
+
+        let all_indices = &render_ctx.vertex_buffers.indices;
+        let indices = (uuids.iter())
+            .filter_map(|uuid| match render_ctx.node_render_ctxs[uuid].kind {
+                RenderCtxKind::Part(ref part_render_ctx) => Some(part_render_ctx),
+                _ => None,
+            })
+            .flat_map(|prc| {
+                let start = prc.index_offset as usize;
+                &all_indices[start..start + prc.index_len]
+            })
+            .copied()
+            .collect::<Vec<_>>();
+
+        let gl = &self.gl;
+        unsafe {
+            self.buffers.upload_batch_indices(gl, &indices);
+            self.count(|stats| {
+                stats.parts += uuids.len() as u32;
+                stats.draw_calls += 1;
+            });
+            gl.draw_elements(
+                glow::TRIANGLES,
+                indices.len() as i32,
+                glow::UNSIGNED_SHORT,
+                0,
+            );
+            gl.bind_vertex_array(Some(self.buffers.vao));
+        }
+
+        self.pop_debug_group();
+    }
+
+    /// Renders the puppet into a framebuffer of the application, e.g. the texture of a UI widget
+    /// or of a game engine's scene, instead of the default framebuffer.
+    ///
+    /// The renderer is resized to `viewport` if needed, so rendering to a target of a constant size
+    /// only reallocates the composite framebuffers once. The target must have a stencil buffer for masks.
+    /// The framebuffer and GL viewport that were bound before are restored afterward.
+    pub fn draw_model_to(&mut self, puppet: &Puppet, target: glow::Framebuffer, viewport: UVec2) {
+        if viewport != self.viewport {
+            self.resize(viewport.x, viewport.y);
+        }
+
+        let gl = &self.gl;
+        let previous_framebuffer = self.bound_draw_framebuffer();
+        let mut previous_viewport = [0; 4];
+        unsafe {
+            gl.get_parameter_i32_slice(glow::VIEWPORT, &mut previous_viewport);
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(target));
+            gl.viewport(0, 0, self.viewport.x as i32, self.viewport.y as i32);
+        }
+
+        let previous_target = self.target_framebuffer.replace(Some(target));
+        self.render(puppet);
+        self.target_framebuffer.set(previous_target);
+
+        let [x, y, width, height] = previous_viewport;
+        unsafe {
+            gl.bind_framebuffer(glow::FRAMEBUFFER, previous_framebuffer);
+            gl.viewport(x, y, width, height);
+        }
+    }
+
+    /// Gets the framebuffer currently bound for drawing.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn bound_draw_framebuffer(&self) -> Option<glow::Framebuffer> {
+        let id = unsafe { self.gl.get_parameter_i32(glow::DRAW_FRAMEBUFFER_BINDING) };
+        std::num::NonZeroU32::new(id as u32).map(glow::NativeFramebuffer)
+    }
+
+    /// Gets the framebuffer currently bound for drawing.
+    ///
+    /// WebGL framebuffers can't be looked up from the GL state, so this is the renderer's own target.
+    #[cfg(target_arch = "wasm32")]
+    fn bound_draw_framebuffer(&self) -> Option<glow::Framebuffer> {
+        self.target_framebuffer.get()
+    }
+
+    /// Runs `f`, typically rendering, then restores the GL state that rendering changes.
+    ///
+    /// See `render` for the saved state. Querying it takes a few round trips to the driver.
+    /// On WebGL, bound objects can't be queried, so they are unbound instead of restored.
+    pub fn with_saved_state<R>(&self, f: impl FnOnce(&Self) -> R) -> R {
+        let state = unsafe { SavedGlState::save(&self.gl) };
+        let result = f(self);
+        unsafe { state.restore(&self.gl) };
+
+        // The GL state no longer matches what the renderer last set
+        self.invalidate_gl_cache();
+        result
+    }
+
+    /// Sets whether the rendered frame has straight or premultiplied alpha.
+    ///
+    /// Blending assumes premultiplied colors: `Normal` blends with `ONE, ONE_MINUS_SRC_ALPHA`,
+    /// and the other fixed-function blend modes also treat the source as premultiplied.
+    /// The frame is thus premultiplied by default, which is what compositors displaying the window
+    /// usually expect. With `AlphaMode::Straight`, `render` ends with a full-screen pass dividing
+    /// the colors by alpha, for consumers of straight alpha like PNG files or capture software
+    /// overlaying the puppet on a webcam feed. Semi-transparent edges then don't get dark fringes.
+    ///
+    /// The conversion needs the backdrop framebuffer, without which the frame stays premultiplied.
+    pub fn set_output_alpha(&mut self, output_alpha: AlphaMode) {
+        self.output_alpha = output_alpha;
+    }
+
+    /// Enables a bloom pass making emissive parts glow, or disables it with `None`.
+    ///
+    /// The emissive light of the frame is downsampled to half resolution, thresholded,
+    /// blurred with a separable Gaussian, and added over the frame once it is drawn.
+    /// This needs the frame to be drawn into an offscreen framebuffer first, which is allocated
+    /// here and freed when bloom is disabled, so a disabled bloom costs nothing.
+    /// Without float render targets, emissive light is clamped to 1 before being blurred.
+    /// Disabled by default.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the framebuffers can't be created, in which case bloom stays disabled.
+    pub fn set_bloom(&mut self, bloom: Option<BloomParams>) -> Result<(), OpenglRendererError> {
+        let gl = &self.gl;
+
+        if bloom.is_none() {
+            self.bloom = None;
+            if let Some(bloom_framebuffers) = self.bloom_framebuffers.take() {
+                unsafe { bloom_framebuffers.delete(gl) };
+            }
+            if !self.linear_space {
+                if let Some(scene) = self.scene_framebuffer.take() {
+                    unsafe { scene.delete(gl) };
+                }
+            }
+            return Ok(());
+        }
+
+        if self.scene_framebuffer.is_none() {
+            let scene = self
+                .create_scene_framebuffer()
+                .map_err(OpenglRendererError::Opengl)?;
+            self.scene_framebuffer = Some(scene);
+        }
+        if self.bloom_framebuffers.is_none() {
+            let float = self.capabilities.float_render_targets;
+            let bloom_framebuffers = unsafe {
+                BloomFramebuffers::new(&self.gl, self.viewport.x, self.viewport.y, float)
+                    .map_err(OpenglRendererError::Opengl)?
+            };
+            self.bloom_framebuffers = Some(bloom_framebuffers);
+        }
+
+        self.bloom = bloom;
+        Ok(())
+    }
+
+    /// Gets the settings of the bloom pass, if it is enabled. See `set_bloom`.
+    pub fn bloom(&self) -> Option<BloomParams> {
+        self.bloom
+    }
+
+    /// Creates the framebuffer that frames are drawn into before being drawn to the target,
+    /// see `begin_scene`. Its albedo is stored as floats in linear space.
+    fn create_scene_framebuffer(&self) -> Result<CompositeFramebuffer, String> {
+        unsafe {
+            CompositeFramebuffer::new(
+                &self.gl,
+                self.viewport.x,
+                self.viewport.y,
+                self.samples,
+                self.capabilities.float_render_targets,
+                self.linear_space,
+            )
+        }
+    }
+
+    /// Whether colors are blended in linear space, see `RendererConfig::linear_space`.
+    ///
+    /// This is false if it was requested but float render targets aren't supported.
+    pub fn linear_space(&self) -> bool {
+        self.linear_space
+    }
+
+    /// Gets whether the rendered frame has straight or premultiplied alpha. See `set_output_alpha`.
+    pub fn output_alpha(&self) -> AlphaMode {
+        self.output_alpha
+    }
+
+    /// Renders only the branch of the puppet rooted at `root`, the root included.
+    ///
+    /// Nodes are drawn in the same order and with the same state setup as in `render`,
+    /// and composites within the branch are drawn as usual. Masks of parts in the branch are
+    /// always honored, even when their source lives outside of it: mask sources only write
+    /// to the stencil buffer, so the branch looks exactly as it does in the full render.
+    pub fn draw_subtree(&self, puppet: &Puppet, root: InoxNodeUuid) {
+        self.begin_render(puppet);
+
+        for uuid in puppet.nodes.zsorted_subtree(root) {
+            self.draw_node(puppet, uuid, false, false);
+        }
+
+        self.end_render();
+    }
+
+    /// Enables reordering draws to group parts that share a texture, reducing texture switches.
+    ///
+    /// Only consecutive parts are reordered, and never two parts whose bounding boxes overlap
+    /// on screen, so the rendered image stays the same. Composites are never moved.
+    /// Disabled by default, as computing the order has a cost of its own.
+    pub fn set_draw_sorting(&mut self, sort_draws: bool) {
+        self.sort_draws = sort_draws;
+    }
+
+    /// Enables merging the draw calls of consecutive parts that are drawn the same way:
+    /// same texture, blend mode, transform, opacity and tints, without masks.
+    ///
+    /// Parts still come out in the same order, so the rendered image stays the same.
+    /// Combined with `set_draw_sorting`, which brings parts sharing a texture together,
+    /// puppets made of many static parts on the same texture take few draw calls.
+    /// The indices of merged parts are uploaded every frame. Disabled by default.
+    pub fn set_draw_batching(&mut self, batch_draws: bool) {
+        self.batch_draws = batch_draws;
+    }
+
+    /// Whether draw calls of parts are merged. See `set_draw_batching`.
+    pub fn draw_batching(&self) -> bool {
+        self.batch_draws
+    }
+
+    /// Gets the puppet's draw order, with consecutive parts batched by texture.
+    fn batched_draw_order(&self, puppet: &Puppet) -> Vec<InoxNodeUuid> {
+        let mut order = Vec::with_capacity(puppet.render_ctx.nodes_zsorted.len());
+        let mut run = Vec::new();
+        let mut run_items = Vec::new();
+
+        let flush = |order: &mut Vec<InoxNodeUuid>,
+                     run: &mut Vec<InoxNodeUuid>,
+                     run_items: &mut Vec<DrawItem>| {
+            order.extend(batch_draw_order(run_items).into_iter().map(|i| run[i]));
+            run.clear();
+            run_items.clear();
+        };
+
+        for &uuid in &puppet.render_ctx.nodes_zsorted {
+            let node = puppet.nodes.get_node(uuid).unwrap();
+            let node_render_ctx = &puppet.render_ctx.node_render_ctxs[&uuid];
+
+            match (&node.data, &node_render_ctx.kind) {
+                (InoxData::Part(ref part), RenderCtxKind::Part(ref part_render_ctx)) => {
+                    let (min, max) = self.part_ndc_bounds(puppet, node_render_ctx, part_render_ctx);
+                    run.push(uuid);
+                    run_items.push(DrawItem {
+                        texture: part.tex_albedo,
+                        min,
+                        max,
+                    });
+                }
+                _ => {
+                    flush(&mut order, &mut run, &mut run_items);
+                    order.push(uuid);
+                }
+            }
+        }
+        flush(&mut order, &mut run, &mut run_items);
+
+        order
+    }
+
+    /// Replaces the vertices of a part's mesh, uploading only that part's region of the vertex buffer.
+    ///
+    /// This is meant to animate meshes every frame, e.g. from an external deformer, without
+    /// reallocating any buffer. Deforms set by parameters still apply on top of the new vertices.
+    /// There must be as many vertices as in the part's mesh, as the triangles are kept.
+    pub fn update_part_vertices(
+        &self,
+        puppet: &mut Puppet,
+        uuid: InoxNodeUuid,
+        verts: &[Vec2],
+    ) -> Result<(), SetPartVerticesError> {
+        let vert_offset = puppet.render_ctx.set_part_vertices(uuid, verts)?;
+        unsafe { self.buffers.update_vertices(&self.gl, vert_offset, verts) };
+        Ok(())
+    }
+
+    /// Sets up the camera, the deforms and the GL state for drawing the puppet's nodes,
+    /// then starts drawing to the target, see `begin_scene`.
+    fn begin_render(&self, puppet: &Puppet) {
+        self.prepare_render(puppet);
+        self.begin_scene();
+    }
+
+    /// Sets up the camera, the deforms and the GL state for drawing the puppet's nodes.
+    fn prepare_render(&self, puppet: &Puppet) {
+        self.update_camera();
+
+        let gl = &self.gl;
+        unsafe {
+            puppet
+                .render_ctx
+                .upload_deforms_to_gl(gl, self.buffers.deforms);
+            gl.enable(glow::BLEND);
+            gl.disable(glow::DEPTH_TEST);
+        }
+    }
+
+    /// Starts drawing a frame to the target, writing the clip polygon to its stencil buffer.
+    ///
+    /// In linear space or with bloom, the target is swapped for the cleared scene framebuffer
+    /// until `end_scene`.
+    fn begin_scene(&self) {
+        if let Some(scene) = &self.scene_framebuffer {
+            self.output_framebuffer.set(self.target_framebuffer.get());
+            self.target_framebuffer.set(Some(scene.draw_framebuffer()));
+
+            let gl = &self.gl;
+            unsafe {
+                gl.bind_framebuffer(glow::FRAMEBUFFER, Some(scene.draw_framebuffer()));
+                gl.draw_buffers(&[
+                    glow::COLOR_ATTACHMENT0,
+                    glow::COLOR_ATTACHMENT1,
+                    glow::COLOR_ATTACHMENT2,
+                ]);
+            }
+            self.set_gl_clear_color(Vec4::ZERO);
+            unsafe { gl.clear(glow::COLOR_BUFFER_BIT) };
+        }
+
+        if let Some(polygon) = &self.clip_polygon {
+            self.draw_clip_polygon(polygon);
+        }
+        self.restore_clip_test();
+    }
+
+    /// Draws the scene framebuffer over the target set before `begin_scene`, encoding it to sRGB
+    /// in linear space, then adds the bloom to it.
+    fn end_scene(&self) {
+        let Some(scene) = &self.scene_framebuffer else {
+            return;
+        };
+
+        self.push_debug_group("Scene");
+
+        let output = self.output_framebuffer.take();
+        self.target_framebuffer.set(output);
+
+        let gl = &self.gl;
+        unsafe { scene.resolve(gl, self.viewport.x, self.viewport.y) };
+
+        let bloom = self.bloom.zip(self.bloom_framebuffers.as_ref());
+        if let Some((params, bloom_framebuffers)) = bloom {
+            self.draw_bloom(scene, params, bloom_framebuffers);
+        }
+
+        unsafe {
+            gl.bind_framebuffer(glow::FRAMEBUFFER, output);
+            gl.active_texture(glow::TEXTURE0 + BACKDROP_TEXTURE_UNIT);
+            gl.bind_texture(glow::TEXTURE_2D, Some(scene.albedo));
+            gl.active_texture(glow::TEXTURE0);
+
+            // The scene is already clipped, and the target's stencil buffer doesn't hold the clip polygon
+            gl.disable(glow::STENCIL_TEST);
+        }
+
+        self.set_blend_mode(BlendMode::Normal);
+        self.bind_shader(&self.scene_shader);
+        self.scene_shader.set_scene(gl);
+        self.scene_shader.set_encode_srgb(gl, self.linear_space);
+        unsafe {
+            gl.bind_vertex_array(Some(self.buffers.vao));
+            gl.draw_elements(glow::TRIANGLES, 6, glow::UNSIGNED_SHORT, 0);
+        }
+        self.count(|stats| stats.draw_calls += 1);
+
+        if let Some((params, bloom_framebuffers)) = bloom {
+            self.bind_shader(&self.bloom_add_shader);
+            self.bloom_add_shader.set_intensity(gl, params.intensity);
+            self.bloom_add_shader.set_encode_srgb(gl, self.linear_space);
+            unsafe {
+                // Light adds up, while the glow's coverage is blended like any other draw
+                gl.blend_func_separate(glow::ONE, glow::ONE, glow::ONE, glow::ONE_MINUS_SRC_ALPHA);
+                gl.active_texture(glow::TEXTURE0);
+                gl.bind_texture(glow::TEXTURE_2D, Some(bloom_framebuffers.textures[0]));
+                gl.draw_elements(glow::TRIANGLES, 6, glow::UNSIGNED_SHORT, 0);
+            }
+            self.count(|stats| stats.draw_calls += 1);
+            self.cache.blend_mode.set(None);
+        }
+
+        self.pop_debug_group();
+    }
+
+    /// Extracts the emissive light of the resolved scene framebuffer above the threshold,
+    /// and blurs it into the first of the bloom framebuffers.
+    fn draw_bloom(
+        &self,
+        scene: &CompositeFramebuffer,
+        params: BloomParams,
+        bloom_framebuffers: &BloomFramebuffers,
+    ) {
+        self.push_debug_group("Bloom");
+
+        let (width, height) = BloomFramebuffers::size(self.viewport.x, self.viewport.y);
+        let [first, second] = bloom_framebuffers.framebuffers;
+        let [first_texture, second_texture] = bloom_framebuffers.textures;
+
+        let gl = &self.gl;
+        let mut previous_viewport = [0; 4];
+        unsafe {
+            gl.get_parameter_i32_slice(glow::VIEWPORT, &mut previous_viewport);
+            gl.viewport(0, 0, width as i32, height as i32);
+            gl.disable(glow::BLEND);
+            gl.disable(glow::STENCIL_TEST);
+            gl.bind_vertex_array(Some(self.buffers.vao));
+
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(first));
+            gl.active_texture(glow::TEXTURE0);
+            gl.bind_texture(glow::TEXTURE_2D, Some(scene.emissive));
+        }
+        self.clear_texture_cache();
+
+        self.bind_shader(&self.bloom_prefilter_shader);
+        self.bloom_prefilter_shader
+            .set_threshold(gl, params.threshold);
+        unsafe { gl.draw_elements(glow::TRIANGLES, 6, glow::UNSIGNED_SHORT, 0) };
+        self.count(|stats| stats.draw_calls += 1);
+
+        // Separable blur, horizontally into the second framebuffer and vertically back into the first
+        let texel = params.radius / glam::vec2(width as f32, height as f32);
+        let passes = [
+            (second, first_texture, glam::vec2(texel.x, 0.0)),
+            (first, second_texture, glam::vec2(0.0, texel.y)),
+        ];
+        self.bind_shader(&self.bloom_blur_shader);
+        for (framebuffer, source, direction) in passes {
+            self.bloom_blur_shader.set_direction(gl, direction);
+            unsafe {
+                gl.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer));
+                gl.bind_texture(glow::TEXTURE_2D, Some(source));
+                gl.draw_elements(glow::TRIANGLES, 6, glow::UNSIGNED_SHORT, 0);
+            }
+            self.count(|stats| stats.draw_calls += 1);
+        }
+
+        let [x, y, w, h] = previous_viewport;
+        unsafe {
+            gl.viewport(x, y, w, h);
+            gl.enable(glow::BLEND);
+        }
+
+        self.pop_debug_group();
+    }
+
+    /// Finishes drawing to the target with `end_scene`, then converts the frame
+    /// to the output alpha mode, see `set_output_alpha`.
+    fn end_render(&self) {
+        self.end_scene();
+
+        if self.output_alpha == AlphaMode::Premultiplied {
+            return;
+        }
+        let Some(backdrop) = &self.backdrop else {
+            return;
+        };
+
+        self.push_debug_group("Unpremultiply");
+
+        let gl = &self.gl;
+        unsafe {
+            backdrop.copy_from(
+                gl,
+                self.target_framebuffer.get(),
+                self.viewport.x,
+                self.viewport.y,
+                BACKDROP_TEXTURE_UNIT,
+            );
+
+            // Every pixel is replaced, inside the clip polygon or not
+            gl.disable(glow::BLEND);
+            gl.disable(glow::STENCIL_TEST);
+        }
+
+        self.bind_shader(&self.unpremultiply_shader);
+        self.unpremultiply_shader.set_backdrop(gl);
+        unsafe {
+            gl.bind_vertex_array(Some(self.buffers.vao));
+            gl.draw_elements(glow::TRIANGLES, 6, glow::UNSIGNED_SHORT, 0);
+            gl.enable(glow::BLEND);
+        }
+        self.count(|stats| stats.draw_calls += 1);
+        // With a scene framebuffer, only it has the clip polygon
+        if self.scene_framebuffer.is_none() {
+            self.restore_clip_test();
+        }
+
+        self.pop_debug_group();
+    }
+
+    /// Restricts everything that `render` draws to the inside of a polygon, or removes the restriction.
+    ///
+    /// The polygon is in the puppet's world space, like the camera position, and can be concave.
+    /// Self-intersecting polygons are filled with the even-odd rule. It is written to the highest bit
+    /// of the target's stencil buffer at the start of each render, so part masks keep working inside it
+    /// with the remaining bits. Composites are drawn unclipped offscreen, then clipped when drawn
+    /// to the target. The target must have a stencil buffer, as it already needs for masks.
+    pub fn set_clip_polygon(&mut self, polygon: Option<&[Vec2]>) {
+        self.clip_polygon = polygon.map(<[Vec2]>::to_vec);
+        self.invalidate_composite_cache();
+    }
+
+    /// Gets the polygon that rendering is clipped to. See `set_clip_polygon`.
+    pub fn clip_polygon(&self) -> Option<&[Vec2]> {
+        self.clip_polygon.as_deref()
+    }
+
+    /// Gets the stencil bit of the clip polygon if it applies to the current draws.
+    ///
+    /// Composite passes have their own stencil buffer, which doesn't hold the clip polygon.
+    fn clip_stencil_bit(&self) -> u32 {
+        if self.clip_polygon.is_some() && !self.is_compositing() {
+            CLIP_STENCIL_BIT
+        } else {
+            0
+        }
+    }
+
+    /// Writes the clip polygon to the stencil buffer of the bound framebuffer.
+    fn draw_clip_polygon(&self, polygon: &[Vec2]) {
+        self.push_debug_group("Clip polygon");
+
+        self.bind_shader(&self.wireframe_shader);
+        self.wireframe_shader
+            .set_mvp(&self.gl, self.view_projection());
+
+        let gl = &self.gl;
+        unsafe {
+            gl.enable(glow::STENCIL_TEST);
+            gl.stencil_mask(0xff);
+            gl.clear_stencil(0);
+            gl.clear(glow::STENCIL_BUFFER_BIT);
+
+            // Every triangle of a fan from the first vertex flips the bit of the pixels it covers,
+            // so only pixels covered an odd number of times, inside the polygon, end up set
+            gl.color_mask(false, false, false, false);
+            gl.stencil_func(glow::ALWAYS, 0, 0xff);
+            gl.stencil_op(glow::KEEP, glow::KEEP, glow::INVERT);
+            gl.stencil_mask(CLIP_STENCIL_BIT);
+
+            let bytes: &[u8] = core::slice::from_raw_parts(
+                polygon.as_ptr() as *const u8,
+                core::mem::size_of_val(polygon),
+            );
+
+            gl.bind_vertex_array(Some(self.wireframe_vao));
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(self.wireframe_buffer));
+            gl.buffer_data_u8_slice(glow::ARRAY_BUFFER, bytes, glow::STREAM_DRAW);
+            gl.draw_arrays(glow::TRIANGLE_FAN, 0, polygon.len() as i32);
+            self.count(|stats| stats.draw_calls += 1);
+
+            gl.color_mask(true, true, true, true);
+
+            // Restore the puppet's vertex array, which the other draws expect to be bound
+            gl.bind_vertex_array(Some(self.buffers.vao));
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(self.buffers.deforms));
         }
 
+        self.pop_debug_group();
+    }
+
+    /// Sets the stencil test back to only testing the clip polygon, if there is one that applies.
+    fn restore_clip_test(&self) {
         let gl = &self.gl;
+        let clip_bit = self.clip_stencil_bit();
         unsafe {
-            match blend_mode {
-                BlendMode::Normal => {
-                    gl.blend_equation(glow::FUNC_ADD);
-                    gl.blend_func(glow::ONE, glow::ONE_MINUS_SRC_ALPHA);
-                }
-                BlendMode::Multiply => {
-                    gl.blend_equation(glow::FUNC_ADD);
-                    gl.blend_func(glow::DST_COLOR, glow::ONE_MINUS_SRC_ALPHA);
-                }
-                BlendMode::ColorDodge => {
-                    gl.blend_equation(glow::FUNC_ADD);
-                    gl.blend_func(glow::DST_COLOR, glow::ONE);
-                }
-                BlendMode::LinearDodge => {
-                    gl.blend_equation(glow::FUNC_ADD);
-                    gl.blend_func(glow::ONE, glow::ONE);
-                }
-                BlendMode::Screen => {
-                    gl.blend_equation(glow::FUNC_ADD);
-                    gl.blend_func(glow::ONE, glow::ONE_MINUS_SRC_COLOR);
-                }
-                BlendMode::ClipToLower => {
-                    gl.blend_equation(glow::FUNC_ADD);
-                    gl.blend_func(glow::DST_ALPHA, glow::ONE_MINUS_SRC_ALPHA);
-                }
-                BlendMode::SliceFromLower => {
-                    gl.blend_equation(glow::FUNC_SUBTRACT);
-                    gl.blend_func(glow::ONE_MINUS_DST_ALPHA, glow::ONE_MINUS_SRC_ALPHA);
-                }
+            if clip_bit != 0 {
+                gl.enable(glow::STENCIL_TEST);
+                gl.stencil_func(glow::EQUAL, clip_bit as i32, clip_bit);
+                gl.stencil_mask(0x00);
+            } else {
+                gl.stencil_mask(0xff);
+                gl.stencil_func(glow::ALWAYS, 1, 0xff);
+                gl.disable(glow::STENCIL_TEST);
             }
         }
     }
 
-    fn bind_shader<S: Deref<Target = glow::Program>>(&self, shader: &S) {
-        let program = **shader;
-        if !self.cache.borrow_mut().update_program(program) {
-            return;
+    /// Enables keeping each composite rendered in its own framebuffer across frames.
+    ///
+    /// A cached composite is only drawn offscreen again when something it was drawn from
+    /// changes: the camera, the viewport, the transforms, deforms, draw states, blend modes
+    /// and textures of its children and of their masks, or the renderer's settings and textures.
+    /// Static composites then only cost a single quad.
+    /// This uses a viewport-sized framebuffer per composite. Disabled by default.
+    pub fn set_composite_caching(&mut self, enabled: bool) {
+        self.cache_composites = enabled;
+        if !enabled {
+            let gl = &self.gl;
+            for (_, cached) in self.cached_composites.get_mut().drain() {
+                unsafe { cached.framebuffer.delete(gl) };
+            }
         }
+    }
 
-        unsafe { self.gl.use_program(Some(program)) };
+    /// Whether composites are cached across frames. See `set_composite_caching`.
+    pub fn composite_caching(&self) -> bool {
+        self.cache_composites
     }
 
-    fn bind_part_textures(&self, part: &Part) {
-        if !self.cache.borrow_mut().update_albedo(part.tex_albedo) {
-            return;
+    /// Replaces the meshes the renderer draws with those of another puppet, e.g. to hot-reload
+    /// a model being edited, without creating a new renderer.
+    ///
+    /// The GPU buffers are reused, and cached composites are drawn again.
+    /// Textures are kept: the puppet must use the same textures as the previous one.
+    pub fn reload_puppet(&mut self, puppet: &Puppet) {
+        unsafe { self.buffers.reupload(&self.gl, &puppet.render_ctx) };
+        self.invalidate_composite_cache();
+    }
+
+    /// Picks up a puppet whose nodes were added, removed or moved, e.g. with `Puppet::add_node`.
+    ///
+    /// Like `reload_puppet`, and the overrides and cached composites of removed nodes are dropped.
+    pub fn rebuild(&mut self, puppet: &Puppet) {
+        let nodes = &puppet.nodes;
+        (self.part_overrides).retain(|uuid, _| nodes.get_node(*uuid).is_some());
+        let cached_composites = self.cached_composites.get_mut();
+        let removed: Vec<_> = (cached_composites.keys().copied())
+            .filter(|uuid| nodes.get_node(*uuid).is_none())
+            .collect();
+        for uuid in removed {
+            if let Some(cached) = cached_composites.remove(&uuid) {
+                unsafe { cached.framebuffer.delete(&self.gl) };
+            }
         }
 
-        let gl = &self.gl;
-        self.textures[part.tex_albedo].bind_on(gl, 0);
-        self.textures[part.tex_bumpmap].bind_on(gl, 1);
-        self.textures[part.tex_emissive].bind_on(gl, 2);
+        self.reload_puppet(puppet);
     }
 
-    /// Clear the texture cache
-    /// This one method missing made me pull my hair out for an entire month.
-    pub fn clear_texture_cache(&self) {
-        self.cache.borrow_mut().albedo = None;
+    /// Forces every cached composite to be drawn offscreen again on the next render.
+    ///
+    /// Changes are detected automatically, this is only needed when something the renderer
+    /// can't see changed, such as the contents of a texture.
+    pub fn invalidate_composite_cache(&self) {
+        for cached in self.cached_composites.borrow_mut().values_mut() {
+            cached.signature = None;
+        }
     }
 
-    unsafe fn attach_framebuffer_textures(&self) {
-        let gl = &self.gl;
-        gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.composite_framebuffer));
-
-        gl.framebuffer_texture_2d(
-            glow::FRAMEBUFFER,
-            glow::COLOR_ATTACHMENT0,
-            glow::TEXTURE_2D,
-            Some(self.cf_albedo),
-            0,
-        );
-        gl.framebuffer_texture_2d(
-            glow::FRAMEBUFFER,
-            glow::COLOR_ATTACHMENT1,
-            glow::TEXTURE_2D,
-            Some(self.cf_emissive),
-            0,
-        );
-        gl.framebuffer_texture_2d(
-            glow::FRAMEBUFFER,
-            glow::COLOR_ATTACHMENT2,
-            glow::TEXTURE_2D,
-            Some(self.cf_bump),
-            0,
-        );
-        gl.framebuffer_texture_2d(
-            glow::FRAMEBUFFER,
-            glow::DEPTH_STENCIL_ATTACHMENT,
-            glow::TEXTURE_2D,
-            Some(self.cf_stencil),
-            0,
-        );
-
-        gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+    /// Collects everything the offscreen pass of a composite depends on.
+    ///
+    /// Two identical signatures mean the composite would be drawn exactly the same.
+    fn composite_signature(&self, puppet: &Puppet, children: &[InoxNodeUuid]) -> Vec<f32> {
+        let mut signature = Vec::new();
+        signature.extend_from_slice(&self.view_projection().to_cols_array());
+        signature.extend_from_slice(&self.viewport.as_vec2().to_array());
+        signature.push(self.min_part_pixels);
+        signature.push(self.cull_offscreen as u8 as f32);
+
+        self.push_nodes_signature(puppet, children, &mut signature);
+        signature
     }
 
-    pub fn render(&self, puppet: &Puppet) {
-        self.update_camera();
+    fn push_nodes_signature(
+        &self,
+        puppet: &Puppet,
+        uuids: &[InoxNodeUuid],
+        signature: &mut Vec<f32>,
+    ) {
+        let vertex_buffers = &puppet.render_ctx.vertex_buffers;
+
+        for &uuid in uuids {
+            let Some(node_render_ctx) = puppet.render_ctx.node_render_ctxs.get(&uuid) else {
+                continue;
+            };
+            signature.extend_from_slice(&node_render_ctx.trans.to_cols_array());
+            signature.push(puppet.nodes.is_shown(uuid) as u8 as f32);
+
+            match node_render_ctx.kind {
+                RenderCtxKind::Part(ref prc) => {
+                    // Vertices can be replaced at runtime, see `update_part_vertices`
+                    let range = prc.vert_offset as usize..prc.vert_offset as usize + prc.vert_len;
+                    let verts = &vertex_buffers.verts[range.clone()];
+                    for (vert, deform) in verts.iter().zip(&vertex_buffers.deforms[range]) {
+                        signature.extend_from_slice(&vert.to_array());
+                        signature.extend_from_slice(&deform.to_array());
+                    }
+                }
+                RenderCtxKind::Composite(ref children) => {
+                    // Nested composites are drawn with their own opacity and tints
+                    if let Some(InoxData::Composite(ref composite)) =
+                        puppet.nodes.get_node(uuid).map(|n| &n.data)
+                    {
+                        let draw_state = &composite.draw_state;
+                        signature.push(draw_state.opacity * node_render_ctx.opacity);
+                        signature.push(draw_state.blend_mode as u8 as f32);
+                        signature.extend_from_slice(&draw_state.tint.to_array());
+                        signature.extend_from_slice(&draw_state.screen_tint.to_array());
+                    }
+                    self.push_nodes_signature(puppet, children, signature);
+                }
+                RenderCtxKind::Node | RenderCtxKind::MeshGroup(_) => (),
+            }
 
-        let gl = &self.gl;
-        unsafe {
-            puppet.render_ctx.upload_deforms_to_gl(gl);
-            gl.enable(glow::BLEND);
-            gl.disable(glow::DEPTH_TEST);
-        }
+            let Some(InoxData::Part(ref part)) = puppet.nodes.get_node(uuid).map(|n| &n.data)
+            else {
+                continue;
+            };
+
+            let draw_state = &part.draw_state;
+            let (opacity, tint) = self.part_opacity_tint(uuid, part, node_render_ctx);
+            signature.push(opacity);
+            signature.push(draw_state.mask_threshold);
+            signature.extend_from_slice(&tint.to_array());
+            signature.extend_from_slice(&draw_state.screen_tint.to_array());
+            signature.push(self.compat_blend_mode(draw_state.blend_mode) as u8 as f32);
+            signature.push(part.tex_albedo as f32);
+            signature.extend(
+                [part.tex_emissive, part.tex_bumpmap].map(|index| index.map_or(-1.0, |i| i as f32)),
+            );
 
-        for &uuid in &puppet.render_ctx.nodes_zsorted {
-            self.draw_node(puppet, uuid, false, false);
+            let masks = (draw_state.masks.iter().enumerate())
+                .filter(|&(i, _)| puppet.is_mask_enabled(uuid, i))
+                .map(|(_, mask)| mask)
+                .collect::<Vec<_>>();
+            signature.extend(masks.iter().map(|mask| mask.mode as u8 as f32));
+            let mask_sources = masks.iter().map(|mask| mask.source).collect::<Vec<_>>();
+            self.push_nodes_signature(puppet, &mask_sources, signature);
         }
     }
 
@@ -416,6 +2696,11 @@ impl OpenglRenderer {
         is_composite_child: bool,
         is_mask: bool,
     ) {
+        // Disabled nodes hide their subtree, but still mask the parts using them as mask sources
+        if !is_mask && !puppet.nodes.is_shown(uuid) {
+            return;
+        }
+
         let node = puppet.nodes.get_node(uuid).unwrap();
         let node_render_ctx = &puppet.render_ctx.node_render_ctxs[&uuid];
 
@@ -423,6 +2708,7 @@ impl OpenglRenderer {
             (InoxData::Part(ref part), RenderCtxKind::Part(ref part_render_ctx)) => {
                 self.draw_part(
                     puppet,
+                    uuid,
                     part,
                     node_render_ctx,
                     part_render_ctx,
@@ -433,7 +2719,7 @@ impl OpenglRenderer {
             }
 
             (InoxData::Composite(ref composite), RenderCtxKind::Composite(ref children)) => {
-                self.draw_composite(puppet, composite, children, &node.name);
+                self.draw_composite(puppet, uuid, composite, children, &node.name);
             }
 
             _ => (),
@@ -444,6 +2730,52 @@ impl OpenglRenderer {
     //// Part rendering ////
     ////////////////////////
 
+    /// Binds the textures, blend mode, shader and uniforms to draw a part with.
+    fn set_part_state(
+        &self,
+        uuid: InoxNodeUuid,
+        part: &Part,
+        node_render_ctx: &NodeRenderCtx,
+        is_mask: bool,
+    ) {
+        let gl = &self.gl;
+        let mvp = self.node_mvp(node_render_ctx);
+
+        self.bind_part_textures(part);
+        let blend_mode = self.compat_blend_mode(part.draw_state.blend_mode);
+        self.set_blend_mode(blend_mode);
+
+        if is_mask {
+            let part_mask_shader = &self.part_mask_shader;
+            self.bind_shader(part_mask_shader);
+
+            // vert uniforms
+            part_mask_shader.set_mvp(gl, mvp);
+
+            // frag uniforms
+            part_mask_shader.set_threshold(gl, part.draw_state.mask_threshold.clamp(0.0, 1.0));
+            part_mask_shader.set_layer(gl, part.tex_albedo);
+        } else {
+            let part_shader = &self.part_shader;
+            self.bind_shader(part_shader);
+
+            // vert uniforms
+            part_shader.set_mvp(gl, mvp);
+
+            // frag uniforms
+            part_shader.set_premultiply(gl, self.texture_alpha == AlphaMode::Straight);
+            part_shader.set_layer(gl, part.tex_albedo);
+            part_shader.set_map_layers(gl, part.tex_emissive, part.tex_bumpmap);
+            let (opacity, tint) = self.part_opacity_tint(uuid, part, node_render_ctx);
+            part_shader.set_opacity(gl, opacity);
+            let screen_tint = part.draw_state.screen_tint.clamp(Vec3::ZERO, Vec3::ONE);
+            let screen_tint = self.compat_screen_tint(screen_tint);
+            part_shader.set_mult_color(gl, self.shader_color(tint));
+            part_shader.set_screen_color(gl, self.shader_color(screen_tint));
+            part_shader.set_blend_mode(gl, self.prepare_backdrop(blend_mode));
+        }
+    }
+
     fn draw_part_mask(&self, puppet: &Puppet, mask: &Mask, is_composite_child: bool) {
         let gl = &self.gl;
 
@@ -452,8 +2784,8 @@ impl OpenglRenderer {
             // Enable writing to stencil buffer and disable writing to color buffer
             gl.color_mask(false, false, false, false);
             gl.stencil_op(glow::KEEP, glow::KEEP, glow::REPLACE);
-            gl.stencil_func(glow::ALWAYS, (mask.mode == MaskMode::Mask) as i32, 0xff);
-            gl.stencil_mask(0xff);
+            gl.stencil_func(glow::ALWAYS, mask_stencil_value(mask.mode) as i32, 0xff);
+            gl.stencil_mask(0xff & !self.clip_stencil_bit());
         }
 
         // draw mask
@@ -469,6 +2801,7 @@ impl OpenglRenderer {
     fn draw_part(
         &self,
         puppet: &Puppet,
+        uuid: InoxNodeUuid,
         part: &Part,
         node_render_ctx: &NodeRenderCtx,
         part_render_ctx: &PartRenderCtx,
@@ -476,23 +2809,39 @@ impl OpenglRenderer {
         is_mask: bool,
         debug_label: &str,
     ) {
+        // Masks are always drawn, as skipping them would change what their parts show
+        if !is_mask && self.is_part_culled(puppet, node_render_ctx, part_render_ctx) {
+            return;
+        }
+
         self.push_debug_group(debug_label);
 
         let gl = &self.gl;
-        let masks = &part.draw_state.masks;
+        let masks = part
+            .draw_state
+            .masks
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| puppet.is_mask_enabled(uuid, i))
+            .map(|(_, mask)| mask)
+            .collect::<Vec<_>>();
 
         if !masks.is_empty() {
             self.push_debug_group("Masks");
+            self.count(|stats| stats.mask_passes += 1);
 
             // begin mask
             unsafe {
-                // Enable and clear the stencil buffer so we can write our mask to it
+                // Enable and clear the stencil buffer so we can write our mask to it,
+                // keeping the clip polygon's bit
                 gl.enable(glow::STENCIL_TEST);
-                gl.clear_stencil(!part.draw_state.has_masks() as i32);
+                let clear_value = mask_stencil_clear_value(masks.iter().map(|mask| mask.mode));
+                gl.stencil_mask(0xff & !self.clip_stencil_bit());
+                gl.clear_stencil(clear_value as i32);
                 gl.clear(glow::STENCIL_BUFFER_BIT);
             }
 
-            for mask in &part.draw_state.masks {
+            for mask in &masks {
                 self.draw_part_mask(puppet, mask, is_composite_child);
             }
 
@@ -500,40 +2849,16 @@ impl OpenglRenderer {
 
             // begin mask content
             unsafe {
-                gl.stencil_func(glow::EQUAL, 1, 0xff);
+                let shown = self.clip_stencil_bit() | MASK_SHOWN;
+                gl.stencil_func(glow::EQUAL, shown as i32, 0xff);
                 gl.stencil_mask(0x00);
             }
         }
 
-        let mvp = self.camera.matrix(self.viewport.as_vec2()) * node_render_ctx.trans;
-
-        self.bind_part_textures(part);
-        self.set_blend_mode(part.draw_state.blend_mode);
-
-        if is_mask {
-            let part_mask_shader = &self.part_mask_shader;
-            self.bind_shader(part_mask_shader);
-
-            // vert uniforms
-            part_mask_shader.set_mvp(gl, mvp);
-
-            // frag uniforms
-            part_mask_shader.set_threshold(gl, part.draw_state.mask_threshold.clamp(0.0, 1.0));
-        } else {
-            let part_shader = &self.part_shader;
-            self.bind_shader(part_shader);
-
-            // vert uniforms
-            part_shader.set_mvp(gl, mvp);
-
-            // frag uniforms
-            part_shader.set_opacity(gl, part.draw_state.opacity);
-            part_shader.set_mult_color(gl, part.draw_state.tint);
-            part_shader.set_screen_color(gl, part.draw_state.screen_tint);
-        }
+        self.set_part_state(uuid, part, node_render_ctx, is_mask);
 
         unsafe {
-            gl.bind_vertex_array(Some(self.vao));
+            gl.bind_vertex_array(Some(self.buffers.vao));
             gl.draw_elements(
                 glow::TRIANGLES,
                 part.mesh.indices.len() as i32,
@@ -541,14 +2866,145 @@ impl OpenglRenderer {
                 part_render_ctx.index_offset as i32 * mem::size_of::<u16>() as i32,
             );
         }
+        self.count(|stats| {
+            stats.parts += 1;
+            stats.draw_calls += 1;
+        });
 
         if !masks.is_empty() {
             // end mask
-            unsafe {
-                // We're done stencil testing, disable it again so that we don't accidentally mask more stuff out
-                gl.stencil_mask(0xff);
-                gl.stencil_func(glow::ALWAYS, 1, 0xff);
-                gl.disable(glow::STENCIL_TEST);
+            // We're done stencil testing, go back to the clip polygon so that we don't accidentally mask more stuff out
+            self.restore_clip_test();
+        }
+
+        self.pop_debug_group();
+    }
+
+    /// Draws the edges of the deformed meshes of all shown parts over what was rendered, in `color`.
+    ///
+    /// This is a debug view for deform and triangulation issues. Textures, blend modes and masks
+    /// are ignored, and the lines are generated from the triangle indices on the CPU,
+    /// so it also works on GLES and WebGL, which lack `glPolygonMode`.
+    pub fn draw_wireframe(&self, puppet: &Puppet, color: Vec4) {
+        let mut draw_order = Vec::new();
+        Self::push_draw_order(puppet, &puppet.render_ctx.nodes_zsorted, &mut draw_order);
+
+        for uuid in draw_order {
+            if puppet.nodes.is_shown(uuid) {
+                self.draw_part_wireframe(puppet, uuid, color);
+            }
+        }
+    }
+
+    /// Draws the edges of a single part's deformed mesh over what was rendered, in `color`.
+    ///
+    /// This is meant for mesh editing UIs, to show the mesh of the selected part.
+    /// Nothing is drawn if `uuid` is not a part.
+    pub fn draw_part_wireframe(&self, puppet: &Puppet, uuid: InoxNodeUuid, color: Vec4) {
+        let Some(InoxData::Part(ref part)) = puppet.nodes.get_node(uuid).map(|node| &node.data)
+        else {
+            return;
+        };
+        let Some(node_render_ctx) = puppet.render_ctx.node_render_ctxs.get(&uuid) else {
+            return;
+        };
+        let RenderCtxKind::Part(ref part_render_ctx) = node_render_ctx.kind else {
+            return;
+        };
+
+        let vertex_buffers = &puppet.render_ctx.vertex_buffers;
+        let start = part_render_ctx.vert_offset as usize;
+        let end = start + part_render_ctx.vert_len;
+        let verts = &vertex_buffers.verts[start..end];
+        let deforms = &vertex_buffers.deforms[start..end];
+
+        // Each triangle (a, b, c) becomes the segments (a, b), (b, c) and (c, a)
+        let mut lines = Vec::with_capacity(part.mesh.indices.len() * 2);
+        for triangle in part.mesh.indices.chunks_exact(3) {
+            for (i, j) in [(0, 1), (1, 2), (2, 0)] {
+                for index in [triangle[i], triangle[j]] {
+                    let index = index as usize;
+                    lines.push(verts[index] + deforms[index]);
+                }
+            }
+        }
+
+        self.push_debug_group("Wireframe");
+
+        self.bind_shader(&self.wireframe_shader);
+        self.wireframe_shader
+            .set_mvp(&self.gl, self.node_mvp(node_render_ctx));
+        self.wireframe_shader.set_color(&self.gl, color);
+        self.set_blend_mode(BlendMode::Normal);
+
+        let gl = &self.gl;
+        unsafe {
+            let bytes: &[u8] = core::slice::from_raw_parts(
+                lines.as_ptr() as *const u8,
+                core::mem::size_of_val(lines.as_slice()),
+            );
+
+            gl.bind_vertex_array(Some(self.wireframe_vao));
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(self.wireframe_buffer));
+            gl.buffer_data_u8_slice(glow::ARRAY_BUFFER, bytes, glow::STREAM_DRAW);
+            gl.draw_arrays(glow::LINES, 0, lines.len() as i32);
+
+            // Restore the puppet's vertex array, which the other draws expect to be bound
+            gl.bind_vertex_array(Some(self.buffers.vao));
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(self.buffers.deforms));
+        }
+
+        self.pop_debug_group();
+    }
+
+    /// Draws the vertices of a single part's deformed mesh over what was rendered,
+    /// as round points of `point_size` pixels in `color`.
+    ///
+    /// This is meant for mesh editing UIs. `selected` optionally gives the index of a vertex
+    /// in the part's mesh to draw in another color on top of the others.
+    /// Nothing is drawn if `uuid` is not a part.
+    pub fn draw_vertices(
+        &self,
+        puppet: &Puppet,
+        uuid: InoxNodeUuid,
+        point_size: f32,
+        color: Vec4,
+        selected: Option<(usize, Vec4)>,
+    ) {
+        let Some(node_render_ctx) = puppet.render_ctx.node_render_ctxs.get(&uuid) else {
+            return;
+        };
+        let RenderCtxKind::Part(ref part_render_ctx) = node_render_ctx.kind else {
+            return;
+        };
+
+        self.push_debug_group("Vertices");
+
+        let gl = &self.gl;
+        self.bind_shader(&self.points_shader);
+        self.points_shader
+            .set_mvp(gl, self.node_mvp(node_render_ctx));
+        self.points_shader.set_point_size(gl, point_size);
+        self.set_blend_mode(BlendMode::Normal);
+
+        let first = part_render_ctx.vert_offset as i32;
+        unsafe {
+            // GLES always takes the size from the shader, desktop GL needs to be told to
+            if !gl.version().is_embedded {
+                gl.enable(glow::PROGRAM_POINT_SIZE);
+            }
+
+            // The puppet's vertex array holds the vertices and their deforms
+            gl.bind_vertex_array(Some(self.buffers.vao));
+
+            self.points_shader.set_color(gl, color);
+            gl.draw_arrays(glow::POINTS, first, part_render_ctx.vert_len as i32);
+
+            if let Some((index, selected_color)) = selected {
+                if index < part_render_ctx.vert_len {
+                    self.points_shader.set_color(gl, selected_color);
+                    gl.draw_arrays(glow::POINTS, first + index as i32, 1);
+                }
             }
         }
 
@@ -559,53 +3015,94 @@ impl OpenglRenderer {
     //// Composite rendering ////
     /////////////////////////////
 
-    /// Begin a composition step
-    fn begin_composite(&self) {
-        if self.is_compositing.get() {
-            // We don't allow recursive compositing
-            return;
-        }
-        self.is_compositing.set(true);
+    /// Whether a composite pass is in progress.
+    fn is_compositing(&self) -> bool {
+        self.composite_depth.get() > 0
+    }
+
+    /// Begin a composition step, drawing into `framebuffer`.
+    ///
+    /// Returns the framebuffer of the composite pass in progress, if this one is nested in it,
+    /// to give back to `end_composite`.
+    fn begin_composite(&self, framebuffer: &CompositeFramebuffer) -> Option<glow::Framebuffer> {
+        let depth = self.composite_depth.get();
+        self.composite_depth.set(depth + 1);
+        let outer_framebuffer = self
+            .compositing_framebuffer
+            .replace(Some(framebuffer.draw_framebuffer()));
 
         self.clear_texture_cache();
+        self.restore_clip_test();
 
         let gl = &self.gl;
+        let composite_size = self.composite_size();
         unsafe {
-            gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, Some(self.composite_framebuffer));
+            // Nested passes are already drawn at the composite size
+            if depth == 0 && composite_size != self.viewport {
+                let mut target_gl_viewport = [0; 4];
+                gl.get_parameter_i32_slice(glow::VIEWPORT, &mut target_gl_viewport);
+                self.target_gl_viewport.set(target_gl_viewport);
+                gl.viewport(0, 0, composite_size.x as i32, composite_size.y as i32);
+            }
+            gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, Some(framebuffer.draw_framebuffer()));
             gl.disable(glow::DEPTH_TEST);
             gl.draw_buffers(&[
                 glow::COLOR_ATTACHMENT0,
                 glow::COLOR_ATTACHMENT1,
                 glow::COLOR_ATTACHMENT2,
             ]);
-            gl.clear_color(0.0, 0.0, 0.0, 0.0);
+        }
+        self.set_gl_clear_color(Vec4::ZERO);
+        unsafe {
             gl.clear(glow::COLOR_BUFFER_BIT);
 
             // Everything else is the actual texture used by the meshes at id 0
             gl.active_texture(glow::TEXTURE0);
             gl.blend_func(glow::ONE, glow::ONE_MINUS_SRC_ALPHA);
         }
+
+        outer_framebuffer
     }
 
-    /// End a composition step, re-binding the internal framebuffer
-    fn end_composite(&self) {
-        if !self.is_compositing.get() {
-            // We don't allow recursive compositing
-            return;
-        }
-        self.is_compositing.set(false);
+    /// End a composition step, resolving `framebuffer` if multisampled and re-binding
+    /// the framebuffer of the outer composite pass, or the target framebuffer.
+    fn end_composite(
+        &self,
+        framebuffer: &CompositeFramebuffer,
+        outer_framebuffer: Option<glow::Framebuffer>,
+    ) {
+        let depth = self.composite_depth.get() - 1;
+        self.composite_depth.set(depth);
+        self.compositing_framebuffer.set(outer_framebuffer);
 
         self.clear_texture_cache();
 
         let gl = &self.gl;
+        let composite_size = self.composite_size();
         unsafe {
-            gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+            framebuffer.resolve(gl, composite_size.x, composite_size.y);
+            if depth > 0 {
+                gl.bind_framebuffer(glow::FRAMEBUFFER, outer_framebuffer);
+                gl.draw_buffers(&[
+                    glow::COLOR_ATTACHMENT0,
+                    glow::COLOR_ATTACHMENT1,
+                    glow::COLOR_ATTACHMENT2,
+                ]);
+            } else {
+                gl.bind_framebuffer(glow::FRAMEBUFFER, self.target_framebuffer.get());
+                if composite_size != self.viewport {
+                    let [x, y, width, height] = self.target_gl_viewport.get();
+                    gl.viewport(x, y, width, height);
+                }
+            }
         }
+        self.restore_clip_test();
     }
 
     fn draw_composite(
         &self,
         puppet: &Puppet,
+        uuid: InoxNodeUuid,
         composite: &Composite,
         children: &[InoxNodeUuid],
         debug_label: &str,
@@ -615,43 +3112,165 @@ impl OpenglRenderer {
             return;
         }
 
+        if children.iter().all(|&uuid| self.is_culled(puppet, uuid)) {
+            // All parts are offscreen or too small to be seen, skip the offscreen pass
+            return;
+        }
+
         self.push_debug_group(debug_label);
+        self.count(|stats| stats.composites += 1);
 
-        self.begin_composite();
-        for uuid in children {
-            // debug_assert!(*uuid != node.uuid, "A composite lists itself as its child.");
+        let gl = &self.gl;
+        let checked_out = if self.cache_composites {
+            let composite_size = self.composite_size();
+            let signature = self.composite_signature(puppet, children);
+
+            // Taken out of the map while drawing, so that the map isn't borrowed during the pass
+            let cached = self.cached_composites.borrow_mut().remove(&uuid);
+            let mut cached = match cached {
+                Some(cached) => cached,
+                None => {
+                    let framebuffer = unsafe {
+                        CompositeFramebuffer::new(
+                            gl,
+                            composite_size.x,
+                            composite_size.y,
+                            self.samples,
+                            self.capabilities.float_render_targets,
+                            self.linear_space,
+                        )
+                    };
+                    match framebuffer {
+                        Ok(framebuffer) => CachedComposite {
+                            framebuffer,
+                            size: composite_size,
+                            signature: None,
+                        },
+                        Err(e) => {
+                            error!("Could not create a framebuffer to cache composite: {e}");
+                            self.pop_debug_group();
+                            return;
+                        }
+                    }
+                }
+            };
+
+            if cached.size != composite_size {
+                let resized = unsafe {
+                    cached
+                        .framebuffer
+                        .resize(gl, composite_size.x, composite_size.y)
+                };
+                if let Err(e) = resized {
+                    error!("Could not resize the framebuffer of a cached composite: {e}");
+                }
+                cached.size = composite_size;
+                cached.signature = None;
+            }
 
-            self.draw_node(puppet, *uuid, true, false);
-        }
-        self.end_composite();
+            if cached.signature.as_ref() != Some(&signature) {
+                self.draw_composite_children(puppet, &cached.framebuffer, children);
+                cached.signature = Some(signature);
+            }
 
-        let gl = &self.gl;
-        unsafe {
-            gl.bind_vertex_array(Some(self.vao));
+            unsafe { cached.framebuffer.bind_textures(gl) };
+            self.cached_composites.borrow_mut().insert(uuid, cached);
+            None
+        } else {
+            // Kept out of the pool until the composite is drawn, so that the composites
+            // nested in it are drawn into other framebuffers
+            let framebuffer = unsafe { self.composite_framebuffers.borrow_mut().check_out(gl) };
+            let framebuffer = match framebuffer {
+                Ok(framebuffer) => framebuffer,
+                Err(e) => {
+                    error!("Could not create a composite framebuffer: {e}");
+                    self.pop_debug_group();
+                    return;
+                }
+            };
 
-            gl.active_texture(glow::TEXTURE0);
-            gl.bind_texture(glow::TEXTURE_2D, Some(self.cf_albedo));
-            gl.active_texture(glow::TEXTURE1);
-            gl.bind_texture(glow::TEXTURE_2D, Some(self.cf_emissive));
-            gl.active_texture(glow::TEXTURE2);
-            gl.bind_texture(glow::TEXTURE_2D, Some(self.cf_bump));
+            self.draw_composite_children(puppet, &framebuffer, children);
+            unsafe { framebuffer.bind_textures(gl) };
+            Some(framebuffer)
+        };
+
+        unsafe {
+            gl.bind_vertex_array(Some(self.buffers.vao));
         }
 
         let comp = &composite.draw_state;
-        self.set_blend_mode(comp.blend_mode);
+        let blend_mode = self.compat_blend_mode(comp.blend_mode);
+        self.set_blend_mode(blend_mode);
 
-        let opacity = comp.opacity.clamp(0.0, 1.0);
-        let tint = comp.tint.clamp(Vec3::ZERO, Vec3::ONE);
-        let screen_tint = comp.screen_tint.clamp(Vec3::ZERO, Vec3::ONE);
+        let opacity = comp.opacity * puppet.render_ctx.node_render_ctxs[&uuid].opacity;
+        let (opacity, tint) = clamp_opacity_tint(opacity, comp.tint);
+        let screen_tint = self.compat_screen_tint(comp.screen_tint.clamp(Vec3::ZERO, Vec3::ONE));
 
         self.bind_shader(&self.composite_shader);
         self.composite_shader.set_opacity(gl, opacity);
-        self.composite_shader.set_mult_color(gl, tint);
-        self.composite_shader.set_screen_color(gl, screen_tint);
+        self.composite_shader
+            .set_mult_color(gl, self.shader_color(tint));
+        self.composite_shader
+            .set_screen_color(gl, self.shader_color(screen_tint));
+        self.composite_shader
+            .set_blend_mode(gl, self.prepare_backdrop(blend_mode));
         unsafe {
             gl.draw_elements(glow::TRIANGLES, 6, glow::UNSIGNED_SHORT, 0);
         }
+        self.count(|stats| stats.draw_calls += 1);
 
+        if let Some(framebuffer) = checked_out {
+            self.composite_framebuffers
+                .borrow_mut()
+                .give_back(framebuffer);
+        }
         self.pop_debug_group();
     }
+
+    /// Draws the children of a composite offscreen into `framebuffer`.
+    fn draw_composite_children(
+        &self,
+        puppet: &Puppet,
+        framebuffer: &CompositeFramebuffer,
+        children: &[InoxNodeUuid],
+    ) {
+        let outer_framebuffer = self.begin_composite(framebuffer);
+        for uuid in children {
+            // debug_assert!(*uuid != node.uuid, "A composite lists itself as its child.");
+
+            self.draw_node(puppet, *uuid, true, false);
+        }
+        self.end_composite(framebuffer, outer_framebuffer);
+    }
+}
+
+/// Draws into the framebuffer bound when drawing, like `render`.
+impl Renderer for OpenglRenderer {
+    type Target<'a> = ();
+    type Output = ();
+
+    fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    fn viewport(&self) -> UVec2 {
+        self.viewport
+    }
+
+    fn resize(&mut self, viewport: UVec2) {
+        OpenglRenderer::resize(self, viewport.x, viewport.y);
+    }
+
+    fn draw(&mut self, puppet: &Puppet, _target: ()) {
+        self.render(puppet);
+    }
+
+    /// Frames the puppet like `frame_model`, as moved by the puppet transform.
+    fn frame_puppet(&mut self, puppet: &Puppet, padding: f32) {
+        self.frame_model(puppet, padding);
+    }
 }
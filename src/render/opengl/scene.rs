@@ -0,0 +1,88 @@
+use crate::math::camera::Camera;
+use crate::math::transform::TransformOffset;
+use crate::puppet::Puppet;
+
+use super::OpenglRenderer;
+
+/// Puppet placed in a scene, with the renderer holding its buffers and textures.
+pub struct SceneItem {
+    pub renderer: OpenglRenderer,
+    pub puppet: Puppet,
+    /// Placement of the puppet in the scene.
+    pub transform: TransformOffset,
+    /// Depth of the puppet in the scene. Like nodes' zsort, higher values are further back.
+    pub z: f32,
+}
+
+/// Several puppets drawn together in a shared target, ordered by their scene depth.
+///
+/// Each puppet keeps its own renderer, so that its nodes and textures are independent
+/// from the others'. The renderers must all use the same GL context, which can be done
+/// by creating one `glow::Context` per renderer from the same loader.
+#[derive(Default)]
+pub struct Scene {
+    items: Vec<SceneItem>,
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a puppet to the scene and returns its index.
+    pub fn add(
+        &mut self,
+        renderer: OpenglRenderer,
+        puppet: Puppet,
+        transform: TransformOffset,
+        z: f32,
+    ) -> usize {
+        self.items.push(SceneItem {
+            renderer,
+            puppet,
+            transform,
+            z,
+        });
+        self.items.len() - 1
+    }
+
+    /// Removes the puppet at `index` from the scene, shifting the indices of the ones after it.
+    pub fn remove(&mut self, index: usize) -> SceneItem {
+        self.items.remove(index)
+    }
+
+    pub fn items(&self) -> &[SceneItem] {
+        &self.items
+    }
+
+    pub fn items_mut(&mut self) -> &mut [SceneItem] {
+        &mut self.items
+    }
+
+    /// Resizes the viewports of all the renderers.
+    pub fn resize(&mut self, w: u32, h: u32) {
+        for item in &mut self.items {
+            item.renderer.resize(w, h);
+        }
+    }
+
+    /// Draws every puppet through `camera`, from the furthest back to the closest,
+    /// so that puppets in front blend over the ones behind them.
+    ///
+    /// Puppets with the same depth are drawn in the order they were added.
+    pub fn draw(&mut self, camera: &Camera) {
+        let mut order = (0..self.items.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| self.items[b].z.total_cmp(&self.items[a].z));
+
+        for i in order {
+            let item = &mut self.items[i];
+            item.renderer.camera = *camera;
+            item.renderer
+                .set_puppet_transform(item.transform.to_matrix());
+
+            // The other renderers changed the GL state behind this one's back
+            item.renderer.invalidate_gl_cache();
+            item.renderer.render(&item.puppet);
+        }
+    }
+}
@@ -1,10 +1,80 @@
 use std::ops::Deref;
 
-use glam::{Mat4, Vec2, Vec3};
+use glam::{Mat4, Vec2, Vec3, Vec4};
 use glow::HasContext;
 
+use crate::nodes::node_data::BlendMode;
+
 use super::shader::{self, ShaderCompileError};
 
+const BLEND_GLSL: &str = include_str!("shaders/basic/blend.glsl");
+
+/// Texture unit that the backdrop of shader blend modes is bound to.
+pub const BACKDROP_TEXTURE_UNIT: u32 = 3;
+
+/// Texture unit that the emissive maps of parts and composites are bound to, like in Inochi2D.
+/// Albedos are bound to unit 0.
+pub const EMISSIVE_TEXTURE_UNIT: u32 = 1;
+
+/// Texture unit that the bump maps of parts and composites are bound to.
+pub const BUMPMAP_TEXTURE_UNIT: u32 = 2;
+
+/// Points the `emissive` and `bumpmap` samplers of a program to their texture units,
+/// which never change. Samplers default to unit 0, the albedo's.
+fn bind_map_samplers(gl: &glow::Context, program: glow::Program) {
+    unsafe {
+        gl.use_program(Some(program));
+        let emissive = gl.get_uniform_location(program, "emissive");
+        gl.uniform_1_i32(emissive.as_ref(), EMISSIVE_TEXTURE_UNIT as i32);
+        let bumpmap = gl.get_uniform_location(program, "bumpmap");
+        gl.uniform_1_i32(bumpmap.as_ref(), BUMPMAP_TEXTURE_UNIT as i32);
+        gl.use_program(None);
+    }
+}
+
+/// Inserts `code` into a shader, after its `#version`.
+fn insert_after_version(shader: &str, code: &str) -> String {
+    let version_end = shader
+        .find("#version")
+        .and_then(|start| shader[start..].find('\n').map(|end| start + end + 1))
+        .expect("shaders start with a #version directive");
+
+    let mut source = String::with_capacity(shader.len() + code.len());
+    source.push_str(&shader[..version_end]);
+    source.push_str(code);
+    source.push_str(&shader[version_end..]);
+    source
+}
+
+/// Inserts the functions of shader blend modes into a fragment shader, after its `#version`.
+fn with_blend_functions(frag: &str) -> String {
+    insert_after_version(frag, BLEND_GLSL)
+}
+
+/// Makes a part fragment shader sample a layer of a `sampler2DArray` rather than a `sampler2D`,
+/// when `texture_array` is true.
+fn with_texture_array(frag: String, texture_array: bool) -> String {
+    if texture_array {
+        insert_after_version(&frag, "#define TEXTURE_ARRAY\n")
+    } else {
+        frag
+    }
+}
+
+/// Gets the `blendMode` uniform value of a blend mode, 0 if it isn't blended in the shader.
+fn shader_blend_mode(blend_mode: BlendMode) -> i32 {
+    match blend_mode {
+        BlendMode::Overlay => 1,
+        BlendMode::SoftLight => 2,
+        BlendMode::HardLight => 3,
+        BlendMode::Darken => 4,
+        BlendMode::ColorBurn => 5,
+        BlendMode::LinearBurn => 6,
+        BlendMode::Difference => 7,
+        _ => 0,
+    }
+}
+
 const PART_VERT: &str = include_str!("shaders/basic/basic.vert");
 const PART_FRAG: &str = include_str!("shaders/basic/basic.frag");
 const PART_MASK_FRAG: &str = include_str!("shaders/basic/basic-mask.frag");
@@ -17,6 +87,12 @@ pub struct PartShader {
     u_opacity: Option<glow::UniformLocation>,
     u_mult_color: Option<glow::UniformLocation>,
     u_screen_color: Option<glow::UniformLocation>,
+    u_premultiply: Option<glow::UniformLocation>,
+    u_blend_mode: Option<glow::UniformLocation>,
+    u_backdrop: Option<glow::UniformLocation>,
+    u_layer: Option<glow::UniformLocation>,
+    u_emissive_layer: Option<glow::UniformLocation>,
+    u_bump_layer: Option<glow::UniformLocation>,
 }
 
 impl Deref for PartShader {
@@ -28,8 +104,12 @@ impl Deref for PartShader {
 }
 
 impl PartShader {
-    pub fn new(gl: &glow::Context) -> Result<Self, ShaderCompileError> {
-        let program = shader::compile(gl, PART_VERT, PART_FRAG)?;
+    /// With `texture_array`, textures are sampled from the layer set with `set_layer`
+    /// of a texture array, see `Texture::new_array`.
+    pub fn new(gl: &glow::Context, texture_array: bool) -> Result<Self, ShaderCompileError> {
+        let frag = with_texture_array(with_blend_functions(PART_FRAG), texture_array);
+        let program = shader::compile(gl, PART_VERT, &frag)?;
+        bind_map_samplers(gl, program);
 
         Ok(Self {
             program,
@@ -38,6 +118,12 @@ impl PartShader {
             u_opacity: unsafe { gl.get_uniform_location(program, "opacity") },
             u_mult_color: unsafe { gl.get_uniform_location(program, "multColor") },
             u_screen_color: unsafe { gl.get_uniform_location(program, "screenColor") },
+            u_premultiply: unsafe { gl.get_uniform_location(program, "premultiply") },
+            u_blend_mode: unsafe { gl.get_uniform_location(program, "blendMode") },
+            u_backdrop: unsafe { gl.get_uniform_location(program, "backdrop") },
+            u_layer: unsafe { gl.get_uniform_location(program, "layer") },
+            u_emissive_layer: unsafe { gl.get_uniform_location(program, "emissiveLayer") },
+            u_bump_layer: unsafe { gl.get_uniform_location(program, "bumpLayer") },
         })
     }
 
@@ -53,6 +139,28 @@ impl PartShader {
         unsafe { gl.uniform_2_f32_slice(self.u_offset.as_ref(), offset.as_ref()) };
     }
 
+    /// Sets the `layer` uniform of the shader, only used when sampling a texture array.
+    #[inline]
+    pub fn set_layer(&self, gl: &glow::Context, layer: usize) {
+        unsafe { gl.uniform_1_i32(self.u_layer.as_ref(), layer as i32) };
+    }
+
+    /// Sets the `emissiveLayer` and `bumpLayer` uniforms of the shader, like `set_layer`
+    /// for the part's emissive and bump maps. A missing map is sampled as black.
+    #[inline]
+    pub fn set_map_layers(
+        &self,
+        gl: &glow::Context,
+        emissive: Option<usize>,
+        bumpmap: Option<usize>,
+    ) {
+        let layer = |index: Option<usize>| index.map_or(-1, |index| index as i32);
+        unsafe {
+            gl.uniform_1_i32(self.u_emissive_layer.as_ref(), layer(emissive));
+            gl.uniform_1_i32(self.u_bump_layer.as_ref(), layer(bumpmap));
+        }
+    }
+
     /// Sets the `opacity` uniform of the shader.
     #[inline]
     pub fn set_opacity(&self, gl: &glow::Context, opacity: f32) {
@@ -70,6 +178,22 @@ impl PartShader {
     pub fn set_screen_color(&self, gl: &glow::Context, screen_color: Vec3) {
         unsafe { gl.uniform_3_f32_slice(self.u_screen_color.as_ref(), screen_color.as_ref()) };
     }
+
+    /// Sets the `premultiply` uniform of the shader.
+    #[inline]
+    pub fn set_premultiply(&self, gl: &glow::Context, premultiply: bool) {
+        unsafe { gl.uniform_1_i32(self.u_premultiply.as_ref(), premultiply as i32) };
+    }
+
+    /// Sets the `blendMode` uniform of the shader, and binds the `backdrop` sampler to
+    /// `BACKDROP_TEXTURE_UNIT`. Blend modes that don't need a backdrop disable shader blending.
+    #[inline]
+    pub fn set_blend_mode(&self, gl: &glow::Context, blend_mode: BlendMode) {
+        unsafe {
+            gl.uniform_1_i32(self.u_blend_mode.as_ref(), shader_blend_mode(blend_mode));
+            gl.uniform_1_i32(self.u_backdrop.as_ref(), BACKDROP_TEXTURE_UNIT as i32);
+        }
+    }
 }
 
 pub struct PartMaskShader {
@@ -77,6 +201,7 @@ pub struct PartMaskShader {
     u_mvp: Option<glow::UniformLocation>,
     u_offset: Option<glow::UniformLocation>,
     u_threshold: Option<glow::UniformLocation>,
+    u_layer: Option<glow::UniformLocation>,
 }
 
 impl Deref for PartMaskShader {
@@ -88,17 +213,26 @@ impl Deref for PartMaskShader {
 }
 
 impl PartMaskShader {
-    pub fn new(gl: &glow::Context) -> Result<Self, ShaderCompileError> {
-        let program = shader::compile(gl, PART_VERT, PART_MASK_FRAG)?;
+    /// See `PartShader::new` for `texture_array`.
+    pub fn new(gl: &glow::Context, texture_array: bool) -> Result<Self, ShaderCompileError> {
+        let frag = with_texture_array(PART_MASK_FRAG.to_owned(), texture_array);
+        let program = shader::compile(gl, PART_VERT, &frag)?;
 
         Ok(Self {
             program,
             u_mvp: unsafe { gl.get_uniform_location(program, "mvp") },
             u_offset: unsafe { gl.get_uniform_location(program, "offset") },
             u_threshold: unsafe { gl.get_uniform_location(program, "threshold") },
+            u_layer: unsafe { gl.get_uniform_location(program, "layer") },
         })
     }
 
+    /// Sets the `layer` uniform of the shader, only used when sampling a texture array.
+    #[inline]
+    pub fn set_layer(&self, gl: &glow::Context, layer: usize) {
+        unsafe { gl.uniform_1_i32(self.u_layer.as_ref(), layer as i32) };
+    }
+
     /// Sets the `mvp` uniform of the shader.
     #[inline]
     pub fn set_mvp(&self, gl: &glow::Context, mvp: Mat4) {
@@ -128,6 +262,8 @@ pub struct CompositeShader {
     u_opacity: Option<glow::UniformLocation>,
     u_mult_color: Option<glow::UniformLocation>,
     u_screen_color: Option<glow::UniformLocation>,
+    u_blend_mode: Option<glow::UniformLocation>,
+    u_backdrop: Option<glow::UniformLocation>,
 }
 
 impl Deref for CompositeShader {
@@ -140,7 +276,8 @@ impl Deref for CompositeShader {
 
 impl CompositeShader {
     pub fn new(gl: &glow::Context) -> Result<Self, ShaderCompileError> {
-        let program = shader::compile(gl, COMP_VERT, COMP_FRAG)?;
+        let program = shader::compile(gl, COMP_VERT, &with_blend_functions(COMP_FRAG))?;
+        bind_map_samplers(gl, program);
 
         Ok(Self {
             program,
@@ -148,6 +285,8 @@ impl CompositeShader {
             u_opacity: unsafe { gl.get_uniform_location(program, "opacity") },
             u_mult_color: unsafe { gl.get_uniform_location(program, "multColor") },
             u_screen_color: unsafe { gl.get_uniform_location(program, "screenColor") },
+            u_blend_mode: unsafe { gl.get_uniform_location(program, "blendMode") },
+            u_backdrop: unsafe { gl.get_uniform_location(program, "backdrop") },
         })
     }
 
@@ -174,6 +313,16 @@ impl CompositeShader {
     pub fn set_screen_color(&self, gl: &glow::Context, screen_color: Vec3) {
         unsafe { gl.uniform_3_f32_slice(self.u_screen_color.as_ref(), screen_color.as_ref()) };
     }
+
+    /// Sets the `blendMode` uniform of the shader, and binds the `backdrop` sampler to
+    /// `BACKDROP_TEXTURE_UNIT`. Blend modes that don't need a backdrop disable shader blending.
+    #[inline]
+    pub fn set_blend_mode(&self, gl: &glow::Context, blend_mode: BlendMode) {
+        unsafe {
+            gl.uniform_1_i32(self.u_blend_mode.as_ref(), shader_blend_mode(blend_mode));
+            gl.uniform_1_i32(self.u_backdrop.as_ref(), BACKDROP_TEXTURE_UNIT as i32);
+        }
+    }
 }
 
 pub struct CompositeMaskShader {
@@ -221,3 +370,274 @@ impl CompositeMaskShader {
         unsafe { gl.uniform_1_f32(self.u_opacity.as_ref(), opacity) };
     }
 }
+
+const WIREFRAME_VERT: &str = include_str!("shaders/basic/wireframe.vert");
+const WIREFRAME_FRAG: &str = include_str!("shaders/basic/wireframe.frag");
+
+pub struct WireframeShader {
+    program: glow::Program,
+    u_mvp: Option<glow::UniformLocation>,
+    u_color: Option<glow::UniformLocation>,
+}
+
+impl Deref for WireframeShader {
+    type Target = glow::Program;
+
+    fn deref(&self) -> &Self::Target {
+        &self.program
+    }
+}
+
+impl WireframeShader {
+    pub fn new(gl: &glow::Context) -> Result<Self, ShaderCompileError> {
+        let program = shader::compile(gl, WIREFRAME_VERT, WIREFRAME_FRAG)?;
+
+        Ok(Self {
+            program,
+            u_mvp: unsafe { gl.get_uniform_location(program, "mvp") },
+            u_color: unsafe { gl.get_uniform_location(program, "color") },
+        })
+    }
+
+    /// Sets the `mvp` uniform of the shader.
+    #[inline]
+    pub fn set_mvp(&self, gl: &glow::Context, mvp: Mat4) {
+        unsafe { gl.uniform_matrix_4_f32_slice(self.u_mvp.as_ref(), false, mvp.as_ref()) };
+    }
+
+    /// Sets the `color` uniform of the shader.
+    #[inline]
+    pub fn set_color(&self, gl: &glow::Context, color: Vec4) {
+        unsafe { gl.uniform_4_f32_slice(self.u_color.as_ref(), color.as_ref()) };
+    }
+}
+
+const UNPREMULTIPLY_FRAG: &str = include_str!("shaders/basic/unpremultiply.frag");
+
+/// Full-screen pass dividing the color of the backdrop by its alpha.
+pub struct UnpremultiplyShader {
+    program: glow::Program,
+    u_backdrop: Option<glow::UniformLocation>,
+}
+
+impl Deref for UnpremultiplyShader {
+    type Target = glow::Program;
+
+    fn deref(&self) -> &Self::Target {
+        &self.program
+    }
+}
+
+impl UnpremultiplyShader {
+    pub fn new(gl: &glow::Context) -> Result<Self, ShaderCompileError> {
+        let program = shader::compile(gl, COMP_VERT, UNPREMULTIPLY_FRAG)?;
+
+        Ok(Self {
+            program,
+            u_backdrop: unsafe { gl.get_uniform_location(program, "backdrop") },
+        })
+    }
+
+    /// Binds the `backdrop` sampler to `BACKDROP_TEXTURE_UNIT`.
+    #[inline]
+    pub fn set_backdrop(&self, gl: &glow::Context) {
+        unsafe { gl.uniform_1_i32(self.u_backdrop.as_ref(), BACKDROP_TEXTURE_UNIT as i32) };
+    }
+}
+
+const SCENE_FRAG: &str = include_str!("shaders/basic/scene.frag");
+
+/// Full-screen pass drawing the scene framebuffer over the target,
+/// encoding it with the sRGB transfer function if it was rendered in linear space.
+pub struct SceneShader {
+    program: glow::Program,
+    u_scene: Option<glow::UniformLocation>,
+    u_encode_srgb: Option<glow::UniformLocation>,
+}
+
+impl Deref for SceneShader {
+    type Target = glow::Program;
+
+    fn deref(&self) -> &Self::Target {
+        &self.program
+    }
+}
+
+impl SceneShader {
+    pub fn new(gl: &glow::Context) -> Result<Self, ShaderCompileError> {
+        let program = shader::compile(gl, COMP_VERT, SCENE_FRAG)?;
+
+        Ok(Self {
+            program,
+            u_scene: unsafe { gl.get_uniform_location(program, "scene") },
+            u_encode_srgb: unsafe { gl.get_uniform_location(program, "encodeSrgb") },
+        })
+    }
+
+    /// Binds the `scene` sampler to `BACKDROP_TEXTURE_UNIT`.
+    #[inline]
+    pub fn set_scene(&self, gl: &glow::Context) {
+        unsafe { gl.uniform_1_i32(self.u_scene.as_ref(), BACKDROP_TEXTURE_UNIT as i32) };
+    }
+
+    /// Sets the `encodeSrgb` uniform of the shader.
+    #[inline]
+    pub fn set_encode_srgb(&self, gl: &glow::Context, encode_srgb: bool) {
+        unsafe { gl.uniform_1_i32(self.u_encode_srgb.as_ref(), encode_srgb as i32) };
+    }
+}
+
+const BLOOM_PREFILTER_FRAG: &str = include_str!("shaders/basic/bloom-prefilter.frag");
+const BLOOM_BLUR_FRAG: &str = include_str!("shaders/basic/bloom-blur.frag");
+const BLOOM_ADD_FRAG: &str = include_str!("shaders/basic/bloom-add.frag");
+
+/// Pass keeping the emissive light above a threshold, at the resolution of the bound framebuffer.
+/// Samples the `emissive` texture on unit 0.
+pub struct BloomPrefilterShader {
+    program: glow::Program,
+    u_threshold: Option<glow::UniformLocation>,
+}
+
+impl Deref for BloomPrefilterShader {
+    type Target = glow::Program;
+
+    fn deref(&self) -> &Self::Target {
+        &self.program
+    }
+}
+
+impl BloomPrefilterShader {
+    pub fn new(gl: &glow::Context) -> Result<Self, ShaderCompileError> {
+        let program = shader::compile(gl, COMP_VERT, BLOOM_PREFILTER_FRAG)?;
+
+        Ok(Self {
+            program,
+            u_threshold: unsafe { gl.get_uniform_location(program, "threshold") },
+        })
+    }
+
+    /// Sets the `threshold` uniform of the shader.
+    #[inline]
+    pub fn set_threshold(&self, gl: &glow::Context, threshold: f32) {
+        unsafe { gl.uniform_1_f32(self.u_threshold.as_ref(), threshold) };
+    }
+}
+
+/// One direction of a separable Gaussian blur of the `source` texture on unit 0.
+pub struct BloomBlurShader {
+    program: glow::Program,
+    u_direction: Option<glow::UniformLocation>,
+}
+
+impl Deref for BloomBlurShader {
+    type Target = glow::Program;
+
+    fn deref(&self) -> &Self::Target {
+        &self.program
+    }
+}
+
+impl BloomBlurShader {
+    pub fn new(gl: &glow::Context) -> Result<Self, ShaderCompileError> {
+        let program = shader::compile(gl, COMP_VERT, BLOOM_BLUR_FRAG)?;
+
+        Ok(Self {
+            program,
+            u_direction: unsafe { gl.get_uniform_location(program, "direction") },
+        })
+    }
+
+    /// Sets the `direction` uniform of the shader, the distance between taps in UV space.
+    #[inline]
+    pub fn set_direction(&self, gl: &glow::Context, direction: Vec2) {
+        unsafe { gl.uniform_2_f32_slice(self.u_direction.as_ref(), direction.as_ref()) };
+    }
+}
+
+/// Pass adding the blurred `bloom` texture on unit 0 to the target.
+pub struct BloomAddShader {
+    program: glow::Program,
+    u_intensity: Option<glow::UniformLocation>,
+    u_encode_srgb: Option<glow::UniformLocation>,
+}
+
+impl Deref for BloomAddShader {
+    type Target = glow::Program;
+
+    fn deref(&self) -> &Self::Target {
+        &self.program
+    }
+}
+
+impl BloomAddShader {
+    pub fn new(gl: &glow::Context) -> Result<Self, ShaderCompileError> {
+        let program = shader::compile(gl, COMP_VERT, BLOOM_ADD_FRAG)?;
+
+        Ok(Self {
+            program,
+            u_intensity: unsafe { gl.get_uniform_location(program, "intensity") },
+            u_encode_srgb: unsafe { gl.get_uniform_location(program, "encodeSrgb") },
+        })
+    }
+
+    /// Sets the `intensity` uniform of the shader.
+    #[inline]
+    pub fn set_intensity(&self, gl: &glow::Context, intensity: f32) {
+        unsafe { gl.uniform_1_f32(self.u_intensity.as_ref(), intensity) };
+    }
+
+    /// Sets the `encodeSrgb` uniform of the shader.
+    #[inline]
+    pub fn set_encode_srgb(&self, gl: &glow::Context, encode_srgb: bool) {
+        unsafe { gl.uniform_1_i32(self.u_encode_srgb.as_ref(), encode_srgb as i32) };
+    }
+}
+
+const POINTS_VERT: &str = include_str!("shaders/basic/points.vert");
+const POINTS_FRAG: &str = include_str!("shaders/basic/points.frag");
+
+pub struct PointsShader {
+    program: glow::Program,
+    u_mvp: Option<glow::UniformLocation>,
+    u_point_size: Option<glow::UniformLocation>,
+    u_color: Option<glow::UniformLocation>,
+}
+
+impl Deref for PointsShader {
+    type Target = glow::Program;
+
+    fn deref(&self) -> &Self::Target {
+        &self.program
+    }
+}
+
+impl PointsShader {
+    pub fn new(gl: &glow::Context) -> Result<Self, ShaderCompileError> {
+        let program = shader::compile(gl, POINTS_VERT, POINTS_FRAG)?;
+
+        Ok(Self {
+            program,
+            u_mvp: unsafe { gl.get_uniform_location(program, "mvp") },
+            u_point_size: unsafe { gl.get_uniform_location(program, "pointSize") },
+            u_color: unsafe { gl.get_uniform_location(program, "color") },
+        })
+    }
+
+    /// Sets the `mvp` uniform of the shader.
+    #[inline]
+    pub fn set_mvp(&self, gl: &glow::Context, mvp: Mat4) {
+        unsafe { gl.uniform_matrix_4_f32_slice(self.u_mvp.as_ref(), false, mvp.as_ref()) };
+    }
+
+    /// Sets the `pointSize` uniform of the shader, in pixels.
+    #[inline]
+    pub fn set_point_size(&self, gl: &glow::Context, point_size: f32) {
+        unsafe { gl.uniform_1_f32(self.u_point_size.as_ref(), point_size) };
+    }
+
+    /// Sets the `color` uniform of the shader.
+    #[inline]
+    pub fn set_color(&self, gl: &glow::Context, color: Vec4) {
+        unsafe { gl.uniform_4_f32_slice(self.u_color.as_ref(), color.as_ref()) };
+    }
+}
@@ -8,11 +8,18 @@
 in vec2 texUVs;
 out vec4 outColor;
 
+#ifdef TEXTURE_ARRAY
+uniform sampler2DArray tex;
+uniform int layer;
+#define SAMPLE(tex) texture(tex, vec3(texUVs, float(layer)))
+#else
 uniform sampler2D tex;
+#define SAMPLE(tex) texture(tex, texUVs)
+#endif
 uniform float threshold;
 
 void main() {
-  vec4 color = texture(tex, texUVs);
+  vec4 color = SAMPLE(tex);
   if (color.a <= threshold)
     discard;
   outColor = vec4(1, 1, 1, 1);
@@ -11,18 +11,41 @@ layout(location = 0) out vec4 outAlbedo;
 layout(location = 1) out vec4 outEmissive;
 layout(location = 2) out vec4 outBump;
 
+#ifdef TEXTURE_ARRAY
+// Layers of the part's textures in the array of all the textures,
+// -1 for a part without an emissive or bump map.
+uniform sampler2DArray albedo;
+uniform int layer;
+uniform int emissiveLayer;
+uniform int bumpLayer;
+
+// Reads a missing map as black, like the texture bound for it without an array
+vec4 sampleLayer(int texLayer) {
+  if (texLayer < 0)
+    return vec4(0, 0, 0, 1);
+  return texture(albedo, vec3(texUVs, float(texLayer)));
+}
+#define SAMPLE(tex, texLayer) sampleLayer(texLayer)
+#else
 uniform sampler2D albedo;
 uniform sampler2D emissive;
 uniform sampler2D bumpmap;
+#define SAMPLE(tex, texLayer) texture(tex, texUVs)
+#endif
 
 uniform float opacity;
 uniform vec3 multColor;
 uniform vec3 screenColor;
 uniform float emissionStrength = 1;
+uniform bool premultiply;
 
 void main() {
   // Sample texture
-  vec4 texColor = texture(albedo, texUVs);
+  vec4 texColor = SAMPLE(albedo, layer);
+
+  // Straight alpha textures are premultiplied, as everything after assumes it
+  if (premultiply)
+    texColor.rgb *= texColor.a;
 
   // Screen color math
   vec3 screenOut = vec3(1.0) - ((vec3(1.0) - (texColor.xyz)) *
@@ -31,11 +54,12 @@ void main() {
   // Multiply color math + opacity application.
   outAlbedo =
       vec4(screenOut.xyz, texColor.a) * vec4(multColor.xyz, 1) * opacity;
+  outAlbedo = blendWithBackdrop(outAlbedo);
 
   // Emissive
   outEmissive =
-      vec4(texture(emissive, texUVs).xyz * emissionStrength, 1) * outAlbedo.a;
+      vec4(SAMPLE(emissive, emissiveLayer).xyz * emissionStrength, 1) * outAlbedo.a;
 
   // Bumpmap
-  outBump = vec4(texture(bumpmap, texUVs).xyz, 1) * outAlbedo.a;
+  outBump = vec4(SAMPLE(bumpmap, bumpLayer).xyz, 1) * outAlbedo.a;
 }
\ No newline at end of file
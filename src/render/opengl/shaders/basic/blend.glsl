@@ -0,0 +1,52 @@
+// Blend modes that read what was drawn below, inserted into fragment shaders after #version.
+
+// 0 when blending is done by the fixed-function pipeline, see `shader_blend_mode`
+uniform int blendMode;
+// Copy of the framebuffer being drawn to, premultiplied
+uniform sampler2D backdrop;
+
+vec3 blendHardLight(vec3 cb, vec3 cs) {
+  vec3 multiply = cb * 2.0 * cs;
+  vec3 screen = 1.0 - (1.0 - cb) * (2.0 - 2.0 * cs);
+  return mix(multiply, screen, step(0.5, cs));
+}
+
+vec3 blendSoftLight(vec3 cb, vec3 cs) {
+  vec3 d = mix(((16.0 * cb - 12.0) * cb + 4.0) * cb, sqrt(cb), step(0.25, cb));
+  vec3 darker = cb - (1.0 - 2.0 * cs) * cb * (1.0 - cb);
+  vec3 lighter = cb + (2.0 * cs - 1.0) * (d - cb);
+  return mix(darker, lighter, step(0.5, cs));
+}
+
+vec3 blendColors(vec3 cb, vec3 cs) {
+  if (blendMode == 1) // Overlay
+    return blendHardLight(cs, cb);
+  if (blendMode == 2) // SoftLight
+    return blendSoftLight(cb, cs);
+  if (blendMode == 3) // HardLight
+    return blendHardLight(cb, cs);
+  if (blendMode == 4) // Darken
+    return min(cb, cs);
+  if (blendMode == 5) // ColorBurn
+    return 1.0 - min(vec3(1.0), (1.0 - cb) / max(cs, vec3(1e-5)));
+  if (blendMode == 6) // LinearBurn
+    return max(cb + cs - 1.0, vec3(0.0));
+  if (blendMode == 7) // Difference
+    return abs(cb - cs);
+  return cs;
+}
+
+// Blends the premultiplied color of a fragment with the backdrop, keeping its alpha
+// so that it is then composited over the backdrop with normal blending.
+vec4 blendWithBackdrop(vec4 src) {
+  if (blendMode == 0)
+    return src;
+
+  vec4 dst = texelFetch(backdrop, ivec2(gl_FragCoord.xy), 0);
+  vec3 cb = dst.a > 0.0 ? dst.rgb / dst.a : vec3(0.0);
+  vec3 cs = src.a > 0.0 ? src.rgb / src.a : vec3(0.0);
+
+  // Where the backdrop is transparent, the fragment keeps its own color
+  vec3 blended = mix(cs, clamp(blendColors(cb, cs), 0.0, 1.0), dst.a);
+  return vec4(blended * src.a, src.a);
+}
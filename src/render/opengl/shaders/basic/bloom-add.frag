@@ -0,0 +1,27 @@
+#version 330
+in vec2 texUVs;
+
+layout(location = 0) out vec4 outColor;
+
+// Blurred emissive light
+uniform sampler2D bloom;
+uniform float intensity;
+// Whether the light is in linear space and must be encoded to sRGB
+uniform bool encodeSrgb;
+
+vec3 linearToSrgb(vec3 c) {
+  vec3 low = c * 12.92;
+  vec3 high = 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055;
+  return mix(high, low, vec3(lessThanEqual(c, vec3(0.0031308))));
+}
+
+void main() {
+  vec3 glow = texture(bloom, texUVs).rgb * intensity;
+  if (encodeSrgb) {
+    glow = linearToSrgb(clamp(glow, 0.0, 1.0));
+  }
+
+  // The glow covers what it lights up, so it also shows over transparent backgrounds
+  float coverage = clamp(max(glow.r, max(glow.g, glow.b)), 0.0, 1.0);
+  outColor = vec4(glow, coverage);
+}
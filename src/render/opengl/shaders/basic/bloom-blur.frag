@@ -0,0 +1,22 @@
+#version 330
+in vec2 texUVs;
+
+layout(location = 0) out vec4 outColor;
+
+uniform sampler2D source;
+// Distance between taps in UV space, along the direction of this pass
+uniform vec2 direction;
+
+// 9-tap Gaussian kernel, center then each side
+const float weights[5] =
+    float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main() {
+  vec3 sum = texture(source, texUVs).rgb * weights[0];
+  for (int i = 1; i < 5; i++) {
+    vec2 offset = direction * float(i);
+    sum += texture(source, texUVs + offset).rgb * weights[i];
+    sum += texture(source, texUVs - offset).rgb * weights[i];
+  }
+  outColor = vec4(sum, 1.0);
+}
@@ -0,0 +1,14 @@
+#version 330
+in vec2 texUVs;
+
+layout(location = 0) out vec4 outColor;
+
+// Emissive light of the frame, sampled at half resolution
+uniform sampler2D emissive;
+// Light below this is left out of the bloom
+uniform float threshold;
+
+void main() {
+  vec3 light = texture(emissive, texUVs).rgb;
+  outColor = vec4(max(light - vec3(threshold), vec3(0.0)), 1.0);
+}
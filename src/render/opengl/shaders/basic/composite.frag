@@ -30,6 +30,7 @@ void main() {
   // Multiply color math + opacity application.
   outAlbedo =
       vec4(screenOut.xyz, texColor.a) * vec4(multColor.xyz, 1) * opacity;
+  outAlbedo = blendWithBackdrop(outAlbedo);
 
   // Emissive
   outEmissive = texture(emissive, texUVs) * outAlbedo.a;
@@ -0,0 +1,10 @@
+#version 330
+out vec4 outColor;
+
+uniform vec4 color;
+
+void main() {
+  // Round points
+  if (length(gl_PointCoord - vec2(0.5)) > 0.5) discard;
+  outColor = color;
+}
@@ -0,0 +1,11 @@
+#version 330
+uniform mat4 mvp;
+uniform float pointSize;
+
+layout(location = 0) in vec2 verts;
+layout(location = 2) in vec2 deform;
+
+void main() {
+  gl_Position = mvp * vec4(verts + deform, 0, 1);
+  gl_PointSize = pointSize;
+}
@@ -0,0 +1,29 @@
+#version 330
+layout(location = 0) out vec4 outColor;
+
+// Frame drawn into the scene framebuffer, premultiplied
+uniform sampler2D scene;
+// Whether the frame is in linear space and must be encoded to sRGB
+uniform bool encodeSrgb;
+
+vec3 linearToSrgb(vec3 c) {
+  vec3 low = c * 12.92;
+  vec3 high = 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055;
+  return mix(high, low, vec3(lessThanEqual(c, vec3(0.0031308))));
+}
+
+void main() {
+  vec4 color = texelFetch(scene, ivec2(gl_FragCoord.xy), 0);
+  if (!encodeSrgb) {
+    outColor = color;
+    return;
+  }
+  if (color.a <= 0.0) {
+    outColor = vec4(0.0);
+    return;
+  }
+
+  // Encoding applies to the color itself, so it is premultiplied again afterward
+  vec3 straight = clamp(color.rgb / color.a, 0.0, 1.0);
+  outColor = vec4(linearToSrgb(straight) * color.a, color.a);
+}
@@ -0,0 +1,10 @@
+#version 330
+layout(location = 0) out vec4 outColor;
+
+// Copy of the rendered frame, premultiplied
+uniform sampler2D backdrop;
+
+void main() {
+  vec4 color = texelFetch(backdrop, ivec2(gl_FragCoord.xy), 0);
+  outColor = color.a > 0.0 ? vec4(color.rgb / color.a, color.a) : vec4(0.0);
+}
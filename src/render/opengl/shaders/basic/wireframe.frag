@@ -0,0 +1,8 @@
+#version 330
+out vec4 outColor;
+
+uniform vec4 color;
+
+void main() {
+  outColor = color;
+}
@@ -0,0 +1,8 @@
+#version 330
+uniform mat4 mvp;
+
+layout(location = 0) in vec2 verts;
+
+void main() {
+  gl_Position = mvp * vec4(verts, 0, 1);
+}
@@ -0,0 +1,174 @@
+use glow::HasContext;
+
+/// Capabilities that rendering enables or disables.
+const CAPABILITIES: [u32; 3] = [glow::BLEND, glow::STENCIL_TEST, glow::DEPTH_TEST];
+
+/// Texture units that rendering binds textures on: albedo, emissive, bumpmap and backdrop.
+const TEXTURE_UNITS: u32 = 4;
+
+/// GL objects bound by rendering.
+#[derive(Default)]
+struct BoundObjects {
+    program: Option<glow::Program>,
+    vertex_array: Option<glow::VertexArray>,
+    array_buffer: Option<glow::Buffer>,
+    draw_framebuffer: Option<glow::Framebuffer>,
+    read_framebuffer: Option<glow::Framebuffer>,
+    textures: [Option<glow::Texture>; TEXTURE_UNITS as usize],
+    /// Texture array bound on unit 0, where model textures go with `RendererConfig::texture_array`.
+    texture_array: Option<glow::Texture>,
+}
+
+impl BoundObjects {
+    #[cfg(not(target_arch = "wasm32"))]
+    unsafe fn save(gl: &glow::Context) -> Self {
+        use std::num::NonZeroU32;
+
+        let query = |parameter| NonZeroU32::new(gl.get_parameter_i32(parameter) as u32);
+
+        let mut textures = [None; TEXTURE_UNITS as usize];
+        for (unit, texture) in textures.iter_mut().enumerate() {
+            gl.active_texture(glow::TEXTURE0 + unit as u32);
+            *texture = query(glow::TEXTURE_BINDING_2D).map(glow::NativeTexture);
+        }
+        gl.active_texture(glow::TEXTURE0);
+        let texture_array = query(glow::TEXTURE_BINDING_2D_ARRAY).map(glow::NativeTexture);
+
+        Self {
+            program: query(glow::CURRENT_PROGRAM).map(glow::NativeProgram),
+            vertex_array: query(glow::VERTEX_ARRAY_BINDING).map(glow::NativeVertexArray),
+            array_buffer: query(glow::ARRAY_BUFFER_BINDING).map(glow::NativeBuffer),
+            draw_framebuffer: query(glow::DRAW_FRAMEBUFFER_BINDING).map(glow::NativeFramebuffer),
+            read_framebuffer: query(glow::READ_FRAMEBUFFER_BINDING).map(glow::NativeFramebuffer),
+            textures,
+            texture_array,
+        }
+    }
+
+    /// WebGL objects can't be looked up from the GL state, so they are unbound instead.
+    #[cfg(target_arch = "wasm32")]
+    unsafe fn save(_gl: &glow::Context) -> Self {
+        Self::default()
+    }
+
+    unsafe fn restore(&self, gl: &glow::Context) {
+        gl.active_texture(glow::TEXTURE0);
+        gl.bind_texture(glow::TEXTURE_2D_ARRAY, self.texture_array);
+        for (unit, &texture) in self.textures.iter().enumerate() {
+            gl.active_texture(glow::TEXTURE0 + unit as u32);
+            gl.bind_texture(glow::TEXTURE_2D, texture);
+        }
+
+        gl.use_program(self.program);
+        gl.bind_vertex_array(self.vertex_array);
+        gl.bind_buffer(glow::ARRAY_BUFFER, self.array_buffer);
+        gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, self.draw_framebuffer);
+        gl.bind_framebuffer(glow::READ_FRAMEBUFFER, self.read_framebuffer);
+    }
+}
+
+/// Part of the GL state that rendering changes, saved to be restored afterward
+/// for other users of the context.
+pub struct SavedGlState {
+    capabilities: [bool; CAPABILITIES.len()],
+    blend_equations: [i32; 2],
+    blend_funcs: [i32; 4],
+    stencil_func: [i32; 3],
+    stencil_ops: [i32; 3],
+    stencil_write_mask: i32,
+    stencil_clear_value: i32,
+    color_write_mask: [i32; 4],
+    clear_color: [f32; 4],
+    viewport: [i32; 4],
+    active_texture: i32,
+    objects: BoundObjects,
+}
+
+impl SavedGlState {
+    /// Queries the current state.
+    ///
+    /// # Safety
+    ///
+    /// The context must be current.
+    pub unsafe fn save(gl: &glow::Context) -> Self {
+        let get = |parameter| gl.get_parameter_i32(parameter);
+
+        // Queried before the bound textures, which changes the active unit
+        let active_texture = get(glow::ACTIVE_TEXTURE);
+
+        let mut color_write_mask = [0; 4];
+        gl.get_parameter_i32_slice(glow::COLOR_WRITEMASK, &mut color_write_mask);
+        let mut clear_color = [0.0; 4];
+        gl.get_parameter_f32_slice(glow::COLOR_CLEAR_VALUE, &mut clear_color);
+        let mut viewport = [0; 4];
+        gl.get_parameter_i32_slice(glow::VIEWPORT, &mut viewport);
+
+        Self {
+            capabilities: CAPABILITIES.map(|capability| gl.is_enabled(capability)),
+            blend_equations: [
+                get(glow::BLEND_EQUATION_RGB),
+                get(glow::BLEND_EQUATION_ALPHA),
+            ],
+            blend_funcs: [
+                get(glow::BLEND_SRC_RGB),
+                get(glow::BLEND_DST_RGB),
+                get(glow::BLEND_SRC_ALPHA),
+                get(glow::BLEND_DST_ALPHA),
+            ],
+            stencil_func: [
+                get(glow::STENCIL_FUNC),
+                get(glow::STENCIL_REF),
+                get(glow::STENCIL_VALUE_MASK),
+            ],
+            stencil_ops: [
+                get(glow::STENCIL_FAIL),
+                get(glow::STENCIL_PASS_DEPTH_FAIL),
+                get(glow::STENCIL_PASS_DEPTH_PASS),
+            ],
+            stencil_write_mask: get(glow::STENCIL_WRITEMASK),
+            stencil_clear_value: get(glow::STENCIL_CLEAR_VALUE),
+            color_write_mask,
+            clear_color,
+            viewport,
+            active_texture,
+            objects: BoundObjects::save(gl),
+        }
+    }
+
+    /// Sets the saved state back.
+    ///
+    /// # Safety
+    ///
+    /// The context must be the one the state was saved from, and be current.
+    pub unsafe fn restore(&self, gl: &glow::Context) {
+        for (&capability, &enabled) in CAPABILITIES.iter().zip(&self.capabilities) {
+            if enabled {
+                gl.enable(capability);
+            } else {
+                gl.disable(capability);
+            }
+        }
+
+        let [rgb, alpha] = self.blend_equations.map(|equation| equation as u32);
+        gl.blend_equation_separate(rgb, alpha);
+        let [src_rgb, dst_rgb, src_alpha, dst_alpha] = self.blend_funcs.map(|func| func as u32);
+        gl.blend_func_separate(src_rgb, dst_rgb, src_alpha, dst_alpha);
+
+        let [func, reference, mask] = self.stencil_func;
+        gl.stencil_func(func as u32, reference, mask as u32);
+        let [fail, depth_fail, pass] = self.stencil_ops.map(|op| op as u32);
+        gl.stencil_op(fail, depth_fail, pass);
+        gl.stencil_mask(self.stencil_write_mask as u32);
+        gl.clear_stencil(self.stencil_clear_value);
+
+        let [r, g, b, a] = self.color_write_mask.map(|mask| mask != 0);
+        gl.color_mask(r, g, b, a);
+        let [r, g, b, a] = self.clear_color;
+        gl.clear_color(r, g, b, a);
+        let [x, y, width, height] = self.viewport;
+        gl.viewport(x, y, width, height);
+
+        self.objects.restore(gl);
+        gl.active_texture(self.active_texture as u32);
+    }
+}
@@ -1,9 +1,11 @@
+use std::cell::Cell;
+
 use glow::HasContext;
 use image::{ImageBuffer, ImageError, Rgba};
 
 use crate::model::ModelTexture;
 use crate::texture::tga::TgaDecodeError;
-use crate::texture::ShallowTexture;
+use crate::texture::{ShallowTexture, TextureDecodeError};
 
 #[derive(thiserror::Error, Debug)]
 pub enum TextureError {
@@ -13,13 +15,78 @@ pub enum TextureError {
     LoadData(#[from] ImageError),
     #[error("Could not load TGA texture: {0}")]
     LoadTga(#[from] TgaDecodeError),
+    #[error("RGBA8 pixel data of {width}x{height} texture should be {expected} bytes long, got {actual}")]
+    PixelDataSize {
+        width: u32,
+        height: u32,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("There is no texture {index}, only {len} textures are loaded")]
+    NoSuchTexture { index: usize, len: usize },
+    #[error(
+        "Textures were already uploaded to a texture array, unload them before uploading others"
+    )]
+    TextureArrayFull,
+}
+
+impl From<TextureDecodeError> for TextureError {
+    fn from(value: TextureDecodeError) -> Self {
+        match value {
+            TextureDecodeError::Image(e) => TextureError::LoadData(e),
+            TextureDecodeError::Tga(e) => TextureError::LoadTga(e),
+        }
+    }
+}
+
+/// How a texture is sampled when drawn bigger or smaller than its size.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextureFilter {
+    /// Nearest pixel, without mipmaps. Keeps pixel art crisp.
+    Nearest,
+    /// Bilinear interpolation of the full-size texture.
+    #[default]
+    Linear,
+    /// Bilinear interpolation between mipmaps.
+    /// Avoids noise when the puppet is scaled down, at the cost of a third more memory.
+    Trilinear,
+}
+
+impl TextureFilter {
+    fn min_filter(self) -> u32 {
+        match self {
+            TextureFilter::Nearest => glow::NEAREST,
+            TextureFilter::Linear => glow::LINEAR,
+            TextureFilter::Trilinear => glow::LINEAR_MIPMAP_LINEAR,
+        }
+    }
+
+    fn mag_filter(self) -> u32 {
+        match self {
+            TextureFilter::Nearest => glow::NEAREST,
+            TextureFilter::Linear | TextureFilter::Trilinear => glow::LINEAR,
+        }
+    }
+
+    fn uses_mipmaps(self) -> bool {
+        self == TextureFilter::Trilinear
+    }
 }
 
 pub struct Texture {
     tex: glow::Texture,
+    /// `TEXTURE_2D`, or `TEXTURE_2D_ARRAY` for textures made with `new_array`.
+    target: u32,
     width: u32,
     height: u32,
+    layers: u32,
     bpp: u32,
+    filter: TextureFilter,
+    srgb: bool,
+    /// Whether the texture has immutable storage from `glTexStorage2D`, which can't be resized.
+    immutable: bool,
+    /// Anisotropy set with `set_anisotropy`, if any.
+    anisotropy: Cell<Option<f32>>,
 }
 
 impl Texture {
@@ -64,35 +131,187 @@ impl Texture {
         width: u32,
         height: u32,
     ) -> Result<Self, TextureError> {
-        let bpp = 8 * (pixels.len() / (width as usize * height as usize)) as u32;
+        Self::from_raw_pixels_filtered(gl, pixels, width, height, TextureFilter::default())
+    }
+
+    pub fn from_raw_pixels_filtered(
+        gl: &glow::Context,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        filter: TextureFilter,
+    ) -> Result<Self, TextureError> {
+        Self::from_raw_pixels_with(gl, pixels, width, height, filter, false)
+    }
+
+    /// Creates a texture sampled with `filter`.
+    ///
+    /// With `srgb`, the pixels are stored as `SRGB8_ALPHA8`, which the GL decodes to linear
+    /// when sampling. Filtering and mipmaps are then computed on linear values too.
+    pub fn from_raw_pixels_with(
+        gl: &glow::Context,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        filter: TextureFilter,
+        srgb: bool,
+    ) -> Result<Self, TextureError> {
+        let mut texture = Self::create(gl, glow::TEXTURE_2D, filter, srgb)?;
+        texture.replace_pixels(gl, pixels, width, height)?;
+        texture.set_filter(gl, filter);
+        Ok(texture)
+    }
+
+    /// Creates a `TEXTURE_2D_ARRAY` of `layers` empty layers of `width`x`height` RGBA pixels,
+    /// to be filled with `replace_layer`. See `from_raw_pixels_with` for `srgb`.
+    ///
+    /// Parts sampling different layers of the same array are drawn without binding textures
+    /// in between. Shaders sample it with a `sampler2DArray`.
+    pub fn new_array(
+        gl: &glow::Context,
+        width: u32,
+        height: u32,
+        layers: u32,
+        filter: TextureFilter,
+        srgb: bool,
+    ) -> Result<Self, TextureError> {
+        let mut texture = Self::create(gl, glow::TEXTURE_2D_ARRAY, filter, srgb)?;
+        unsafe {
+            gl.bind_texture(glow::TEXTURE_2D_ARRAY, Some(texture.tex));
+            gl.tex_image_3d(
+                glow::TEXTURE_2D_ARRAY,
+                0,
+                texture.internal_format() as i32,
+                width as i32,
+                height as i32,
+                layers as i32,
+                0,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                None,
+            );
+            gl.bind_texture(glow::TEXTURE_2D_ARRAY, None);
+        }
 
+        texture.width = width;
+        texture.height = height;
+        texture.bpp = 32;
+        texture.layers = layers;
+        // Mipmaps are generated once the layers are filled, see `update_mipmaps`
+        texture.set_filter(gl, filter);
+        Ok(texture)
+    }
+
+    fn create(
+        gl: &glow::Context,
+        target: u32,
+        filter: TextureFilter,
+        srgb: bool,
+    ) -> Result<Self, TextureError> {
         let tex = unsafe { gl.create_texture().map_err(TextureError::Create)? };
         unsafe {
+            gl.bind_texture(target, Some(tex));
+            gl.tex_parameter_i32(target, glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE as i32);
+            gl.tex_parameter_i32(target, glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE as i32);
+            gl.bind_texture(target, None);
+        }
+
+        Ok(Texture {
+            tex,
+            target,
+            width: 0,
+            height: 0,
+            layers: 1,
+            bpp: 0,
+            filter,
+            srgb,
+            immutable: false,
+            anisotropy: Cell::new(None),
+        })
+    }
+
+    fn internal_format(&self) -> u32 {
+        if self.srgb {
+            glow::SRGB8_ALPHA8
+        } else {
+            glow::RGBA8
+        }
+    }
+
+    /// Replaces the pixels of the texture in place, keeping the same GL texture.
+    /// The new pixels can have a different size, and are stored in the same format.
+    ///
+    /// Textures made by a `TextureUploader` can have immutable storage, in which case
+    /// a new GL texture replaces the previous one if the size changes.
+    ///
+    /// Only for 2D textures, the layers of arrays are replaced with `replace_layer`.
+    pub fn replace_pixels(
+        &mut self,
+        gl: &glow::Context,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<(), TextureError> {
+        unsafe {
+            gl.bind_texture(glow::TEXTURE_2D, Some(self.tex));
+            let result = self.store(gl, pixels, width, height, self.immutable);
+            gl.bind_texture(glow::TEXTURE_2D, None);
+            result
+        }
+    }
+
+    /// Uploads the pixels of the bound 2D texture, generating its mipmaps if its filter uses them.
+    ///
+    /// Pixels of the same size as the previous ones are copied into the existing storage.
+    /// Otherwise, with `texture_storage`, textures sampled with mipmaps get immutable storage
+    /// for all their levels, so that generating them doesn't reallocate the texture.
+    unsafe fn store(
+        &mut self,
+        gl: &glow::Context,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        texture_storage: bool,
+    ) -> Result<(), TextureError> {
+        let mut allocated = (width, height) == (self.width, self.height);
+        if !allocated && self.immutable {
+            let tex = gl.create_texture().map_err(TextureError::Create)?;
+            gl.delete_texture(self.tex);
             gl.bind_texture(glow::TEXTURE_2D, Some(tex));
-            gl.tex_parameter_i32(
-                glow::TEXTURE_2D,
-                glow::TEXTURE_MIN_FILTER,
-                glow::LINEAR as i32,
-            );
-            gl.tex_parameter_i32(
-                glow::TEXTURE_2D,
-                glow::TEXTURE_MAG_FILTER,
-                glow::LINEAR as i32,
-            );
-            gl.tex_parameter_i32(
+            self.tex = tex;
+            self.immutable = false;
+            self.set_parameters(gl);
+        }
+        if !allocated && texture_storage && self.filter.uses_mipmaps() {
+            let levels = 32 - width.max(height).leading_zeros();
+            gl.tex_storage_2d(
                 glow::TEXTURE_2D,
-                glow::TEXTURE_WRAP_S,
-                glow::CLAMP_TO_EDGE as i32,
+                levels as i32,
+                self.internal_format(),
+                width as i32,
+                height as i32,
             );
-            gl.tex_parameter_i32(
+            self.immutable = true;
+            allocated = true;
+        }
+
+        if allocated {
+            gl.tex_sub_image_2d(
                 glow::TEXTURE_2D,
-                glow::TEXTURE_WRAP_T,
-                glow::CLAMP_TO_EDGE as i32,
+                0,
+                0,
+                0,
+                width as i32,
+                height as i32,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                glow::PixelUnpackData::Slice(pixels),
             );
+        } else {
             gl.tex_image_2d(
                 glow::TEXTURE_2D,
                 0,
-                glow::RGBA8 as i32,
+                self.internal_format() as i32,
                 width as i32,
                 height as i32,
                 0,
@@ -100,15 +319,111 @@ impl Texture {
                 glow::UNSIGNED_BYTE,
                 Some(pixels),
             );
-            gl.bind_texture(glow::TEXTURE_2D, None);
+        }
+        if self.filter.uses_mipmaps() {
+            gl.generate_mipmap(glow::TEXTURE_2D);
         }
 
-        Ok(Texture {
-            tex,
-            width,
-            height,
-            bpp,
-        })
+        self.width = width;
+        self.height = height;
+        self.bpp = 8 * (pixels.len() / (width as usize * height as usize)) as u32;
+        Ok(())
+    }
+
+    /// Sets the wrapping, filter and anisotropy of the bound texture.
+    unsafe fn set_parameters(&self, gl: &glow::Context) {
+        let target = self.target;
+        gl.tex_parameter_i32(target, glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE as i32);
+        gl.tex_parameter_i32(target, glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE as i32);
+        gl.tex_parameter_i32(
+            target,
+            glow::TEXTURE_MIN_FILTER,
+            self.filter.min_filter() as i32,
+        );
+        gl.tex_parameter_i32(
+            target,
+            glow::TEXTURE_MAG_FILTER,
+            self.filter.mag_filter() as i32,
+        );
+        if let Some(anisotropy) = self.anisotropy.get() {
+            gl.tex_parameter_f32(target, glow::TEXTURE_MAX_ANISOTROPY_EXT, anisotropy);
+        }
+    }
+
+    /// Replaces the pixels of a layer of an array made with `new_array`.
+    /// They must have the size of the array. Call `update_mipmaps` once done replacing layers.
+    pub fn replace_layer(&self, gl: &glow::Context, layer: u32, pixels: &[u8]) {
+        unsafe {
+            gl.bind_texture(glow::TEXTURE_2D_ARRAY, Some(self.tex));
+            gl.tex_sub_image_3d(
+                glow::TEXTURE_2D_ARRAY,
+                0,
+                0,
+                0,
+                layer as i32,
+                self.width as i32,
+                self.height as i32,
+                1,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                glow::PixelUnpackData::Slice(pixels),
+            );
+            gl.bind_texture(glow::TEXTURE_2D_ARRAY, None);
+        }
+    }
+
+    /// Generates the mipmaps again after changing the pixels, if the filter uses them.
+    pub fn update_mipmaps(&self, gl: &glow::Context) {
+        if self.filter.uses_mipmaps() {
+            unsafe {
+                gl.bind_texture(self.target, Some(self.tex));
+                gl.generate_mipmap(self.target);
+                gl.bind_texture(self.target, None);
+            }
+        }
+    }
+
+    /// Changes how the texture is sampled, generating its mipmaps if the filter needs them.
+    pub fn set_filter(&mut self, gl: &glow::Context, filter: TextureFilter) {
+        let target = self.target;
+        unsafe {
+            gl.bind_texture(target, Some(self.tex));
+            gl.tex_parameter_i32(target, glow::TEXTURE_MIN_FILTER, filter.min_filter() as i32);
+            gl.tex_parameter_i32(target, glow::TEXTURE_MAG_FILTER, filter.mag_filter() as i32);
+            if filter.uses_mipmaps() && !self.filter.uses_mipmaps() {
+                gl.generate_mipmap(target);
+            }
+            gl.bind_texture(target, None);
+        }
+
+        self.filter = filter;
+    }
+
+    /// Sets how many samples can be taken along the texture when it is seen at an angle.
+    ///
+    /// Requires `GL_EXT_texture_filter_anisotropic`, and `anisotropy` must not exceed
+    /// the hardware maximum. 1 disables anisotropic filtering.
+    pub fn set_anisotropy(&self, gl: &glow::Context, anisotropy: f32) {
+        unsafe {
+            gl.bind_texture(self.target, Some(self.tex));
+            gl.tex_parameter_f32(self.target, glow::TEXTURE_MAX_ANISOTROPY_EXT, anisotropy);
+            gl.bind_texture(self.target, None);
+        }
+        self.anisotropy.set(Some(anisotropy));
+    }
+
+    pub fn filter(&self) -> TextureFilter {
+        self.filter
+    }
+
+    /// Whether the texture is decoded from sRGB when sampled, see `from_raw_pixels_with`.
+    pub fn is_srgb(&self) -> bool {
+        self.srgb
+    }
+
+    /// Deletes the GL texture.
+    pub fn delete(self, gl: &glow::Context) {
+        unsafe { gl.delete_texture(self.tex) };
     }
 
     pub fn bind(&self, gl: &glow::Context) {
@@ -118,12 +433,12 @@ impl Texture {
     pub fn bind_on(&self, gl: &glow::Context, slot: u32) {
         unsafe {
             gl.active_texture(glow::TEXTURE0 + slot);
-            gl.bind_texture(glow::TEXTURE_2D, Some(self.tex));
+            gl.bind_texture(self.target, Some(self.tex));
         }
     }
 
     pub fn unbind(&self, gl: &glow::Context) {
-        unsafe { gl.bind_texture(glow::TEXTURE_2D, None) };
+        unsafe { gl.bind_texture(self.target, None) };
     }
 
     pub fn width(&self) -> u32 {
@@ -137,25 +452,86 @@ impl Texture {
     pub fn bpp(&self) -> u32 {
         self.bpp
     }
+
+    /// Number of layers of an array made with `new_array`, 1 for 2D textures.
+    pub fn layers(&self) -> u32 {
+        self.layers
+    }
+
+    /// Whether the texture is an array made with `new_array`.
+    pub fn is_array(&self) -> bool {
+        self.target == glow::TEXTURE_2D_ARRAY
+    }
+}
+
+/// Uploads many 2D textures with the same settings, such as the textures of a model.
+///
+/// Each texture is bound only once, to set its parameters and upload its pixels.
+/// See `Texture::from_raw_pixels_with` for `srgb`.
+#[derive(Debug, Clone, Copy)]
+pub struct TextureUploader {
+    pub filter: TextureFilter,
+    pub srgb: bool,
+    /// Anisotropy of the textures sampled with mipmaps, if anisotropic filtering is supported.
+    pub anisotropy: Option<f32>,
+    /// Whether `glTexStorage2D` is supported (OpenGL 4.2, OpenGL ES 3.0 or `GL_ARB_texture_storage`).
+    /// Textures sampled with mipmaps then get immutable storage for all their levels up front.
+    pub texture_storage: bool,
+}
+
+impl TextureUploader {
+    /// Uploads a texture, and leaves it bound to `TEXTURE_2D` on the active texture unit.
+    pub fn upload(
+        &self,
+        gl: &glow::Context,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<Texture, TextureError> {
+        let tex = unsafe { gl.create_texture().map_err(TextureError::Create)? };
+        let mut texture = Texture {
+            tex,
+            target: glow::TEXTURE_2D,
+            width: 0,
+            height: 0,
+            layers: 1,
+            bpp: 0,
+            filter: self.filter,
+            srgb: self.srgb,
+            immutable: false,
+            anisotropy: Cell::new(self.anisotropy.filter(|_| self.filter.uses_mipmaps())),
+        };
+
+        unsafe {
+            gl.bind_texture(glow::TEXTURE_2D, Some(tex));
+            texture.set_parameters(gl);
+            if let Err(e) = texture.store(gl, pixels, width, height, self.texture_storage) {
+                texture.delete(gl);
+                return Err(e);
+            }
+        }
+        Ok(texture)
+    }
 }
 
-/// Uploads an empty texture.
+/// Uploads an empty RGBA texture, stored in `internal_format`.
 ///
 /// # Safety
 ///
-/// Make sure `ty` is a valid OpenGL number type
+/// Make sure `ty` is a valid OpenGL number type for `internal_format`
 pub unsafe fn upload_empty(
     gl: &glow::Context,
     tex: glow::Texture,
     width: u32,
     height: u32,
+    internal_format: u32,
     ty: u32,
 ) {
     gl.bind_texture(glow::TEXTURE_2D, Some(tex));
     gl.tex_image_2d(
         glow::TEXTURE_2D,
         0,
-        glow::RGBA as i32,
+        internal_format as i32,
         width as i32,
         height as i32,
         0,
@@ -22,6 +22,7 @@ pub fn buffers_for_puppet(
     let mut uniform_index_map: HashMap<InoxNodeUuid, usize> = HashMap::new();
 
     for (i, node) in (puppet.nodes.arena.iter())
+        .filter(|arena_node| !arena_node.is_removed())
         .map(|arena_node| arena_node.get())
         .filter(|node| node.is_part() || node.is_composite())
         .enumerate()
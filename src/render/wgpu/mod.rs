@@ -8,7 +8,7 @@ use crate::math::camera::Camera;
 use crate::nodes::node_data::InoxData;
 use crate::puppet::Puppet;
 use crate::render::RenderCtxKind;
-use crate::texture::decode_model_textures;
+use crate::texture::{decode_model_textures, ShallowTexture};
 use crate::{model::Model, nodes::node_data::MaskMode};
 
 use encase::ShaderType;
@@ -23,6 +23,26 @@ use self::{
     pipeline::{InoxPipeline, Uniform},
 };
 
+/// Gets the bind group of the model's texture `index`, or of the placeholder that follows
+/// the model's textures if there is no such texture.
+fn texture_bind(model_texture_binds: &[BindGroup], index: usize) -> &BindGroup {
+    &model_texture_binds[index.min(model_texture_binds.len() - 1)]
+}
+
+/// Gets the bind group of a part's emissive or bump map, the placeholder if it has none.
+///
+/// Nothing reads the emissive and bump outputs of parts, as there is no bloom pass here.
+fn map_bind(model_texture_binds: &[BindGroup], index: Option<usize>) -> &BindGroup {
+    texture_bind(model_texture_binds, index.unwrap_or(usize::MAX))
+}
+
+/// What the wgpu renderer draws frames with, see `crate::render::Renderer`.
+pub struct DrawTarget<'a> {
+    pub device: &'a Device,
+    pub queue: &'a Queue,
+    pub view: &'a TextureView,
+}
+
 pub struct Renderer {
     setup: InoxPipeline,
     composite_texture: Option<Texture>,
@@ -55,7 +75,12 @@ impl Renderer {
             ..SamplerDescriptor::default()
         });
 
-        let shalltexs = decode_model_textures(&model.textures);
+        // The placeholder comes last, for parts using textures that don't exist, see `texture_bind`
+        let shalltexs = decode_model_textures(&model.textures)
+            .into_iter()
+            .map(|shalltex| shalltex.unwrap_or_else(|_| ShallowTexture::placeholder()))
+            .chain([ShallowTexture::placeholder()])
+            .collect::<Vec<_>>();
         for shalltex in &shalltexs {
             let texture_size = wgpu::Extent3d {
                 width: shalltex.width(),
@@ -114,11 +139,32 @@ impl Renderer {
             composite_texture: None,
             model_texture_binds,
             camera: Camera::default(),
-            viewport,
+            // textures can't be zero-sized
+            viewport: viewport.max(UVec2::ONE),
         }
     }
 
+    /// Builds the buffers and draw bundles again for a puppet whose nodes were added,
+    /// removed or moved, e.g. with `Puppet::add_node`, keeping the pipeline and textures.
+    pub fn rebuild(&mut self, device: &Device, puppet: &Puppet) {
+        self.buffers = buffers_for_puppet(device, puppet, self.setup.uniform_alignment_needed);
+        self.bundles = node_bundles_for_model(
+            device,
+            &self.setup,
+            &self.buffers,
+            &self.model_texture_binds,
+            puppet,
+        );
+    }
+
+    /// Resizes the viewport.
+    ///
+    /// A zero width or height, as reported for minimized windows, is ignored:
+    /// the previous size is kept until the window is restored to a non-zero size.
     pub fn resize(&mut self, viewport: UVec2) {
+        if viewport.x == 0 || viewport.y == 0 {
+            return;
+        }
         self.viewport = viewport;
     }
 
@@ -173,9 +219,10 @@ impl Renderer {
                 todo!()
             };
 
-            render_pass.set_bind_group(1, &self.model_texture_binds[part.tex_albedo], &[]);
-            render_pass.set_bind_group(2, &self.model_texture_binds[part.tex_emissive], &[]);
-            render_pass.set_bind_group(3, &self.model_texture_binds[part.tex_bumpmap], &[]);
+            let binds = &self.model_texture_binds;
+            render_pass.set_bind_group(1, texture_bind(binds, part.tex_albedo), &[]);
+            render_pass.set_bind_group(2, map_bind(binds, part.tex_emissive), &[]);
+            render_pass.set_bind_group(3, map_bind(binds, part.tex_bumpmap), &[]);
 
             render_pass.set_bind_group(
                 0,
@@ -366,9 +413,11 @@ impl Renderer {
 
             let unif = match &node.data {
                 InoxData::Part(_) => {
+                    let node_render_ctx = &puppet.render_ctx.node_render_ctxs[&uuid];
                     let mvp = Mat4::from_scale(vec3(1.0, 1.0, 0.0))
                         * self.camera.matrix(self.viewport.as_vec2())
-                        * puppet.render_ctx.node_render_ctxs[&uuid].trans;
+                        * self.camera.zsort_translation(node_render_ctx.zsort)
+                        * node_render_ctx.trans;
 
                     Uniform {
                         opacity: 1.0,
@@ -448,3 +497,28 @@ impl Renderer {
         queue.submit(std::iter::once(encoder.finish()));
     }
 }
+
+impl crate::render::Renderer for Renderer {
+    type Target<'a> = DrawTarget<'a>;
+    type Output = ();
+
+    fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    fn viewport(&self) -> UVec2 {
+        self.viewport
+    }
+
+    fn resize(&mut self, viewport: UVec2) {
+        Renderer::resize(self, viewport);
+    }
+
+    fn draw(&mut self, puppet: &Puppet, target: DrawTarget<'_>) {
+        self.render(target.queue, target.device, puppet, target.view);
+    }
+}
@@ -6,13 +6,25 @@ use wgpu::*;
 
 use crate::nodes::node_data::BlendMode;
 
+/// Gets the fixed-function blending of a blend mode.
+///
+/// Modes that need to read the backdrop in a shader aren't supported yet, and blend normally.
 fn blend_state_for_blend_mode(mode: BlendMode) -> BlendState {
+    let normal = BlendComponent {
+        src_factor: BlendFactor::One,
+        dst_factor: BlendFactor::OneMinusSrcAlpha,
+        operation: BlendOperation::Add,
+    };
+
     let component = match mode {
-        BlendMode::Normal => BlendComponent {
-            src_factor: BlendFactor::One,
-            dst_factor: BlendFactor::OneMinusSrcAlpha,
-            operation: BlendOperation::Add,
-        },
+        BlendMode::Normal => normal,
+        BlendMode::Overlay
+        | BlendMode::SoftLight
+        | BlendMode::HardLight
+        | BlendMode::Darken
+        | BlendMode::ColorBurn
+        | BlendMode::LinearBurn
+        | BlendMode::Difference => normal,
         BlendMode::Multiply => BlendComponent {
             src_factor: BlendFactor::Dst,
             dst_factor: BlendFactor::OneMinusSrcAlpha,
@@ -39,10 +51,41 @@ fn blend_state_for_blend_mode(mode: BlendMode) -> BlendState {
             operation: BlendOperation::Add,
         },
         BlendMode::SliceFromLower => BlendComponent {
-            src_factor: BlendFactor::DstAlpha,
+            src_factor: BlendFactor::OneMinusDstAlpha,
             dst_factor: BlendFactor::OneMinusSrcAlpha,
             operation: BlendOperation::Subtract,
         },
+        // Colors are blended differently from alpha, which is composited normally
+        BlendMode::Lighten => {
+            return BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::One,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Max,
+                },
+                alpha: normal,
+            }
+        }
+        BlendMode::Subtract => {
+            return BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::One,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::ReverseSubtract,
+                },
+                alpha: normal,
+            }
+        }
+        BlendMode::Exclusion => {
+            return BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::OneMinusDst,
+                    dst_factor: BlendFactor::OneMinusSrc,
+                    operation: BlendOperation::Add,
+                },
+                alpha: normal,
+            }
+        }
     };
 
     BlendState {
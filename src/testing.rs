@@ -0,0 +1,155 @@
+//! Helpers to test that puppets render as expected, by comparing images rendered
+//! with the CPU renderer or headless OpenGL against reference images.
+
+use image::{Rgba, RgbaImage};
+
+/// Differences between two images, see `compare_images`.
+#[derive(Debug, Clone)]
+pub struct ImageDiff {
+    /// Largest difference of a channel between the two images, 255 if their sizes differ.
+    pub max_delta: u8,
+    /// Average difference of a channel between the two images, 255 if their sizes differ.
+    pub mean_delta: f64,
+    /// Number of pixels with a channel differing by more than the tolerance.
+    pub differing_pixels: usize,
+    /// Image of the first one's size showing the differing pixels in red, brighter the more
+    /// they differ, over a faded grayscale copy of the first image.
+    pub diff: RgbaImage,
+}
+
+impl ImageDiff {
+    /// Whether no pixel differs by more than the tolerance.
+    pub fn is_match(&self) -> bool {
+        self.differing_pixels == 0
+    }
+}
+
+/// Compares two images channel by channel, counting the pixels with a channel that differs
+/// by more than `tolerance`.
+///
+/// Images of different sizes don't match at all: every pixel of the first image is counted as
+/// differing by 255.
+pub fn compare_images(a: &RgbaImage, b: &RgbaImage, tolerance: u8) -> ImageDiff {
+    if a.dimensions() != b.dimensions() {
+        return ImageDiff {
+            max_delta: u8::MAX,
+            mean_delta: u8::MAX as f64,
+            differing_pixels: a.pixels().len(),
+            diff: RgbaImage::from_pixel(a.width(), a.height(), Rgba([255, 0, 0, 255])),
+        };
+    }
+
+    let mut max_delta = 0;
+    let mut total_delta = 0_u64;
+    let mut differing_pixels = 0;
+    let mut diff = RgbaImage::new(a.width(), a.height());
+    for ((pa, pb), pd) in a.pixels().zip(b.pixels()).zip(diff.pixels_mut()) {
+        let delta = (pa.0.iter().zip(pb.0))
+            .map(|(&ca, cb)| ca.abs_diff(cb))
+            .max()
+            .unwrap_or(0);
+        max_delta = max_delta.max(delta);
+        total_delta += (pa.0.iter().zip(pb.0))
+            .map(|(&ca, cb)| ca.abs_diff(cb) as u64)
+            .sum::<u64>();
+
+        *pd = if delta > tolerance {
+            differing_pixels += 1;
+            Rgba([128 + delta / 2, 0, 0, 255])
+        } else {
+            let [r, g, b, alpha] = pa.0.map(u32::from);
+            let gray = ((r + g + b) / 3 * alpha / 255 / 4) as u8;
+            Rgba([gray, gray, gray, 255])
+        };
+    }
+
+    let channels = a.as_raw().len().max(1);
+    ImageDiff {
+        max_delta,
+        mean_delta: total_delta as f64 / channels as f64,
+        differing_pixels,
+        diff,
+    }
+}
+
+/// JSON payload of a puppet with default metadata and physics, no parameters, and a root
+/// node with `children`, a comma-separated list of node objects.
+#[cfg(test)]
+pub(crate) fn puppet_json(children: &str) -> String {
+    format!(
+        r#"{{
+            "meta": {{ "name": null, "version": "1.0-alpha", "rigger": null, "artist": null,
+                "copyright": null, "licenseURL": null, "contact": null, "reference": null,
+                "preservePixels": false }},
+            "physics": {{ "pixelsPerMeter": 1000, "gravity": 9.8 }},
+            "nodes": {{
+                "uuid": 1, "name": "Root", "type": "Node", "enabled": true, "zsort": 0,
+                "lockToRoot": false,
+                "transform": {{ "trans": [0, 0, 0], "rot": [0, 0, 0], "scale": [1, 1] }},
+                "children": [{children}]
+            }},
+            "param": []
+        }}"#
+    )
+}
+
+/// JSON object of a part drawing texture 0 over `verts`, a comma-separated list of vertex
+/// coordinates, triangulated as a fan. UVs go around the corners of the texture from its top
+/// left, so a quad given from its top left corner shows the whole texture.
+/// `masks` is a comma-separated list of mask objects.
+#[cfg(test)]
+pub(crate) fn part_json(uuid: u32, verts: &str, opacity: f32, masks: &str) -> String {
+    let vertex_count = verts.split(',').count() / 2;
+    let uvs = (0..vertex_count)
+        .map(|i| ["0, 0", "1, 0", "1, 1", "0, 1"][i % 4])
+        .collect::<Vec<_>>()
+        .join(", ");
+    let indices = (1..vertex_count.saturating_sub(1))
+        .map(|i| format!("0, {i}, {}", i + 1))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        r#"{{
+            "uuid": {uuid}, "name": "Part", "type": "Part", "enabled": true, "zsort": 0,
+            "lockToRoot": false,
+            "transform": {{ "trans": [0, 0, 0], "rot": [0, 0, 0], "scale": [1, 1] }},
+            "blend_mode": "Normal", "tint": [1, 1, 1], "screenTint": [0, 0, 0],
+            "mask_threshold": 0.5, "opacity": {opacity}, "masks": [{masks}], "textures": [0],
+            "mesh": {{ "verts": [{verts}], "uvs": [{uvs}], "indices": [{indices}],
+                "origin": [0, 0] }}
+        }}"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_images() {
+        let a = RgbaImage::from_pixel(4, 2, Rgba([10, 20, 30, 255]));
+        let mut b = a.clone();
+        b.put_pixel(1, 1, Rgba([10, 20, 34, 255]));
+        b.put_pixel(2, 1, Rgba([10, 40, 30, 255]));
+
+        let diff = compare_images(&a, &b, 4);
+        assert_eq!(diff.max_delta, 20);
+        assert_eq!(diff.mean_delta, 24.0 / 32.0);
+        // Only the second pixel is beyond the tolerance
+        assert_eq!(diff.differing_pixels, 1);
+        assert!(!diff.is_match());
+        assert_eq!(diff.diff.get_pixel(2, 1).0, [138, 0, 0, 255]);
+        assert_eq!(
+            diff.diff.get_pixel(1, 1).0[0],
+            diff.diff.get_pixel(0, 0).0[0]
+        );
+
+        assert!(compare_images(&a, &b, 20).is_match());
+        assert!(compare_images(&a, &a, 0).is_match());
+
+        let smaller = RgbaImage::new(2, 2);
+        let diff = compare_images(&a, &smaller, 255);
+        assert_eq!(diff.differing_pixels, 8);
+        assert!(!diff.is_match());
+    }
+}
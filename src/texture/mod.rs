@@ -1,15 +1,31 @@
+//! Decoding of the model's textures into RGBA8 pixels.
+//!
+//! Decoded textures have their first row at the top of the image, where V = 0 in UVs,
+//! whatever the format: TGA files stored bottom to top are flipped while decoding.
+//! See `ShallowTexture::flipped_vertically` for puppets expecting the other way around.
+
+use std::collections::BTreeMap;
 use std::io;
+use std::sync::mpsc;
 
-use image::{ImageBuffer, ImageFormat, Rgba};
-use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
-use tracing::error;
+use image::imageops::{self, FilterType};
+use image::{ImageBuffer, ImageError, ImageFormat, Rgba};
+use tracing::{error, warn};
 
 use crate::model::ModelTexture;
 
-use self::tga::{read_tga, TgaImage};
+use self::tga::{read_tga, TgaDecodeError, TgaImage};
 
 pub mod tga;
 
+#[derive(Debug, thiserror::Error)]
+pub enum TextureDecodeError {
+    #[error("Could not decode texture: {0}")]
+    Image(#[from] ImageError),
+    #[error("Could not decode TGA texture: {0}")]
+    Tga(#[from] TgaDecodeError),
+}
+
 pub struct ShallowTexture {
     pixels: Vec<u8>,
     width: u32,
@@ -17,6 +33,21 @@ pub struct ShallowTexture {
 }
 
 impl ShallowTexture {
+    /// Makes a texture out of already decoded RGBA8 pixels.
+    pub fn new(pixels: Vec<u8>, width: u32, height: u32) -> Self {
+        Self {
+            pixels,
+            width,
+            height,
+        }
+    }
+
+    /// Makes a 1x1 opaque magenta texture, which stands out as a missing texture,
+    /// to stand in for a texture that couldn't be decoded or that a part uses but doesn't exist.
+    pub fn placeholder() -> Self {
+        Self::new(vec![255, 0, 255, 255], 1, 1)
+    }
+
     pub fn pixels(&self) -> &[u8] {
         &self.pixels
     }
@@ -28,6 +59,64 @@ impl ShallowTexture {
     pub fn height(&self) -> u32 {
         self.height
     }
+
+    /// Flips the texture upside down, e.g. for puppets exported by tools whose
+    /// V coordinates go up from the bottom of the textures.
+    pub fn flipped_vertically(mut self) -> Self {
+        let row_len = self.width as usize * 4;
+        if row_len > 0 && self.pixels.len() == row_len * self.height as usize {
+            let rows = self.pixels.chunks_exact(row_len).rev();
+            self.pixels = rows.flatten().copied().collect();
+        }
+        self
+    }
+
+    /// Downscales the texture so that neither of its sides exceeds `max_size`, keeping its aspect ratio.
+    ///
+    /// UVs are normalized, so meshes sampling this texture don't need any remapping.
+    pub fn downscaled(self, max_size: u32) -> Self {
+        if max_size == 0 || (self.width <= max_size && self.height <= max_size) {
+            return self;
+        }
+
+        let ratio = max_size as f32 / self.width.max(self.height) as f32;
+        let width = ((self.width as f32 * ratio) as u32).clamp(1, max_size);
+        let height = ((self.height as f32 * ratio) as u32).clamp(1, max_size);
+
+        warn!(
+            "Downscaling {}x{} texture to {}x{} to fit the maximum texture size of {}",
+            self.width, self.height, width, height, max_size
+        );
+        self.resized(width, height)
+    }
+
+    /// Resizes the texture to exactly `width`x`height`, which may change its aspect ratio.
+    ///
+    /// UVs are normalized, so meshes sampling this texture don't need any remapping.
+    pub fn resized(self, width: u32, height: u32) -> Self {
+        if self.width == width && self.height == height {
+            return self;
+        }
+
+        let (src_width, src_height) = (self.width, self.height);
+        let Some(img_buf) =
+            ImageBuffer::<Rgba<u8>, _>::from_raw(src_width, src_height, self.pixels)
+        else {
+            error!("Texture pixels do not match its {src_width}x{src_height} size, dropping it");
+            return Self {
+                pixels: vec![0; (width * height * 4) as usize],
+                width,
+                height,
+            };
+        };
+
+        Self::from(imageops::resize(
+            &img_buf,
+            width,
+            height,
+            FilterType::Triangle,
+        ))
+    }
 }
 
 impl From<TgaImage> for ShallowTexture {
@@ -50,29 +139,229 @@ impl From<ImageBuffer<Rgba<u8>, Vec<u8>>> for ShallowTexture {
     }
 }
 
-pub(crate) fn decode_model_textures(model_textures: &[ModelTexture]) -> Vec<ShallowTexture> {
-    model_textures
-        .par_iter()
-        .filter_map(|mtex| {
-            if mtex.format == ImageFormat::Tga {
-                match read_tga(&mut io::Cursor::new(&mtex.data)) {
-                    Ok(img) => Some(ShallowTexture::from(img)),
-                    Err(e) => {
-                        error!("{}", e);
-                        None
-                    }
-                }
-            } else {
-                let img_buf = image::load_from_memory_with_format(&mtex.data, mtex.format);
-
-                match img_buf {
-                    Ok(img_buf) => Some(ShallowTexture::from(img_buf.into_rgba8())),
-                    Err(e) => {
-                        error!("{}", e);
-                        None
-                    }
-                }
+/// Decodes textures in parallel.
+///
+/// Results are in the same order as `model_textures`, so that failed textures don't shift
+/// the indices of the following ones, which parts refer to their textures by.
+#[cfg(any(feature = "wgpu", feature = "cpu", test))]
+pub(crate) fn decode_model_textures(
+    model_textures: &[ModelTexture],
+) -> Vec<Result<ShallowTexture, TextureDecodeError>> {
+    let mut decoded = Vec::with_capacity(model_textures.len());
+    decode_model_textures_in_order(model_textures, |_, result| {
+        decoded.push(result);
+        Ok::<_, std::convert::Infallible>(())
+    })
+    .unwrap_or_else(|never| match never {});
+    decoded
+}
+
+/// Decodes textures in parallel, and hands them to `on_decoded` on the calling thread
+/// in the order of `model_textures`, each as soon as it and the ones before it are decoded.
+///
+/// Unlike `decode_model_textures`, the first textures can be used while the others are
+/// still being decoded. Stops at the first error returned by `on_decoded`.
+pub(crate) fn decode_model_textures_in_order<E>(
+    model_textures: &[ModelTexture],
+    mut on_decoded: impl FnMut(usize, Result<ShallowTexture, TextureDecodeError>) -> Result<(), E>,
+) -> Result<(), E> {
+    let decode = |mtex: &ModelTexture| {
+        let result = decode_texture(&mtex.data, mtex.format);
+        if let Err(e) = &result {
+            error!("{}", e);
+        }
+        result
+    };
+
+    // Spawned tasks would only run once the calling thread waits for them without threads
+    if cfg!(target_arch = "wasm32") {
+        for (i, mtex) in model_textures.iter().enumerate() {
+            on_decoded(i, decode(mtex))?;
+        }
+        return Ok(());
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    rayon::in_place_scope(|scope| {
+        for (i, mtex) in model_textures.iter().enumerate() {
+            let sender = sender.clone();
+            // The receiver is gone if `on_decoded` failed, the result isn't needed anymore
+            scope.spawn(move |_| sender.send((i, decode(mtex))).unwrap_or_default());
+        }
+        drop(sender);
+
+        let mut decoded = BTreeMap::new();
+        let mut next = 0;
+        for (i, result) in receiver {
+            decoded.insert(i, result);
+            while let Some(result) = decoded.remove(&next) {
+                on_decoded(next, result)?;
+                next += 1;
             }
-        })
-        .collect::<Vec<_>>()
+        }
+        Ok(())
+    })
+}
+
+/// Detects the format of an encoded image from its magic bytes.
+///
+/// Returns `None` for formats without a signature, such as TGA.
+pub fn sniff_format(data: &[u8]) -> Option<ImageFormat> {
+    match data {
+        [0x89, b'P', b'N', b'G', ..] => Some(ImageFormat::Png),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some(ImageFormat::WebP),
+        // ISO base media file with an AVIF brand, whatever the size of its `ftyp` box
+        [_, _, _, _, b'f', b't', b'y', b'p', b'a', b'v', b'i', b'f' | b's', ..] => {
+            Some(ImageFormat::Avif)
+        }
+        _ => image::guess_format(data).ok(),
+    }
+}
+
+/// Decodes a single encoded image into RGBA8 pixels.
+///
+/// The declared `format` is only trusted when the data has no recognizable signature,
+/// as some exporters label e.g. WebP textures as PNG.
+/// AVIF textures can only be decoded with the `avif` feature, which needs the dav1d library.
+pub(crate) fn decode_texture(
+    data: &[u8],
+    format: ImageFormat,
+) -> Result<ShallowTexture, TextureDecodeError> {
+    let format = sniff_format(data).unwrap_or(format);
+    if format == ImageFormat::Tga {
+        Ok(ShallowTexture::from(read_tga(&mut io::Cursor::new(data))?))
+    } else {
+        let img_buf = image::load_from_memory_with_format(data, format)?;
+        Ok(ShallowTexture::from(img_buf.into_rgba8()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::ImageEncoder;
+
+    use super::*;
+
+    #[test]
+    fn test_downscale_to_max_size() {
+        let shalltex = ShallowTexture {
+            pixels: vec![255; 64 * 32 * 4],
+            width: 64,
+            height: 32,
+        };
+
+        let shalltex = shalltex.downscaled(16);
+        assert_eq!((shalltex.width(), shalltex.height()), (16, 8));
+        assert_eq!(shalltex.pixels().len(), 16 * 8 * 4);
+
+        // textures that already fit are left untouched
+        let shalltex = shalltex.downscaled(16);
+        assert_eq!((shalltex.width(), shalltex.height()), (16, 8));
+    }
+
+    #[test]
+    fn test_decode_mixed_tga_and_webp() {
+        // 1x1 uncompressed BGRA TGA with its origin at the top
+        let tga = [
+            &[0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 0, 32, 0x28][..],
+            &[30, 20, 10, 255],
+        ]
+        .concat();
+
+        let mut webp = Vec::new();
+        image::codecs::webp::WebPEncoder::new_lossless(&mut webp)
+            .encode(
+                &[10, 20, 30, 255, 40, 50, 60, 255],
+                2,
+                1,
+                image::ColorType::Rgba8,
+            )
+            .unwrap();
+        assert_eq!(sniff_format(&webp), Some(ImageFormat::WebP));
+        assert_eq!(sniff_format(&tga), None);
+
+        let model_textures = [
+            ModelTexture {
+                format: ImageFormat::Tga,
+                data: tga,
+            },
+            // mislabeled, as some exporters do
+            ModelTexture {
+                format: ImageFormat::Png,
+                data: webp,
+            },
+        ];
+        let decoded = decode_model_textures(&model_textures)
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(decoded[0].pixels(), &[10, 20, 30, 255]);
+        assert_eq!(decoded[1].pixels(), &[10, 20, 30, 255, 40, 50, 60, 255]);
+    }
+
+    #[test]
+    #[cfg(feature = "avif")]
+    fn test_decode_avif() {
+        // 2x2 opaque gray AVIF, encoded at the highest quality
+        let avif = include_bytes!("../../tests/fixtures/gray.avif");
+        assert_eq!(sniff_format(avif), Some(ImageFormat::Avif));
+
+        let decoded = decode_texture(avif, ImageFormat::Png).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (2, 2));
+        for pixel in decoded.pixels().chunks(4) {
+            let close = (pixel.iter().zip([128, 128, 128, 255])).all(|(&c, e)| c.abs_diff(e) <= 4);
+            assert!(close, "{pixel:?}");
+        }
+    }
+
+    #[test]
+    fn test_textures_have_their_origin_at_the_top() {
+        // 1x2 uncompressed BGRA TGA stored bottom to top, red above blue
+        let tga = [
+            &[0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 2, 0, 32, 0x08][..],
+            &[255, 0, 0, 255, 0, 0, 255, 255],
+        ]
+        .concat();
+        let mut png = Vec::new();
+        image::codecs::png::PngEncoder::new(&mut png)
+            .write_image(
+                &[255, 0, 0, 255, 0, 0, 255, 255],
+                1,
+                2,
+                image::ColorType::Rgba8,
+            )
+            .unwrap();
+
+        let red_above_blue = [255, 0, 0, 255, 0, 0, 255, 255];
+        let tga = decode_texture(&tga, ImageFormat::Tga).unwrap();
+        let png = decode_texture(&png, ImageFormat::Png).unwrap();
+        assert_eq!(tga.pixels(), red_above_blue);
+        assert_eq!(png.pixels(), red_above_blue);
+
+        let flipped = png.flipped_vertically();
+        assert_eq!(flipped.pixels(), [0, 0, 255, 255, 255, 0, 0, 255]);
+        assert_eq!((flipped.width(), flipped.height()), (1, 2));
+    }
+
+    #[test]
+    fn test_broken_texture_keeps_indices() {
+        // 1x1 uncompressed BGRA TGA with its origin at the top
+        let tga = |bgra: [u8; 4]| ModelTexture {
+            format: ImageFormat::Tga,
+            data: [
+                &[0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 0, 32, 0x28][..],
+                &bgra,
+            ]
+            .concat(),
+        };
+        let mut broken = tga([0, 0, 0, 255]);
+        broken.data.truncate(10);
+
+        let model_textures = [tga([3, 2, 1, 255]), broken, tga([6, 5, 4, 255])];
+        let decoded = decode_model_textures(&model_textures);
+        assert_eq!(decoded.len(), 3);
+        assert_eq!(decoded[0].as_ref().unwrap().pixels(), &[1, 2, 3, 255]);
+        assert!(decoded[1].is_err());
+        assert_eq!(decoded[2].as_ref().unwrap().pixels(), &[4, 5, 6, 255]);
+    }
 }
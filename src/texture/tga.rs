@@ -230,64 +230,63 @@ pub fn read_tga<R: Read + Seek>(reader: &mut R) -> Result<TgaImage, TgaDecodeErr
 
     let channels: TgaChannels = (header.bits_pp / 8).try_into().unwrap(); // bytes per pixel
     let tchans = 4;
-    let linebuf_size = header.width * channels as u16;
-    let tline_size = header.height * tchans;
+    let linebuf_size = header.width as usize * channels as usize;
+    let tline_size = header.width as usize * tchans;
 
+    // Rows are stored bottom to top unless the origin is at the top
     let flip = !is_origin_at_top;
-    let tstride = if flip {
-        -(tline_size as i32)
-    } else {
-        tline_size as i32
+    let row_start = |y: usize| {
+        let row = if flip {
+            header.height as usize - 1 - y
+        } else {
+            y
+        };
+        row * tline_size
     };
-    let mut ti = if flip {
-        (header.height as isize - 1) * tline_size as isize
-    } else {
-        0
-    } as usize;
 
     if header.width as u64 * header.height as u64 * tchans as u64 > TGA_MAXIMUM_IMAGE_SIZE {
         return Err(TgaDecodeError::TooBig);
     }
 
-    let mut data = vec![0_u8; header.width as usize * header.height as usize * tchans as usize];
-    let mut linebuf = vec![0_u8; linebuf_size as usize];
+    let mut data = vec![0_u8; header.width as usize * header.height as usize * tchans];
+    let mut linebuf = vec![0_u8; linebuf_size];
 
     if header.id_len > 0 {
         reader.seek(io::SeekFrom::Current(header.id_len as i64))?;
     }
 
     if !is_rle {
-        for _ in 0..header.height {
+        for y in 0..header.height as usize {
             reader.read_exact(&mut linebuf)?;
-            to_rgba(channels, &linebuf, &mut data[ti..ti + tline_size as usize])?;
-            ti = ti.saturating_add_signed(tstride as isize);
+            let ti = row_start(y);
+            to_rgba(channels, &linebuf, &mut data[ti..ti + tline_size])?;
         }
     } else {
+        let channels_len = channels as usize;
         let mut pixel = [0_u8; 4];
         let mut packet_len = 0;
         let mut is_rle = false;
 
-        for _ in 0..header.height {
-            let mut wanted = linebuf_size as usize; // fill linebuf with unpacked data
+        for y in 0..header.height as usize {
+            let mut wanted = linebuf_size; // fill linebuf with unpacked data
             while wanted > 0 {
                 if packet_len == 0 {
                     let packet_head = read_u8(reader)?;
                     is_rle = packet_head & TGA_FLAG_PACKET_IS_RLE > 0;
-                    packet_len =
-                        ((packet_head & TGA_FLAG_PACKET_LEN) + 1) as usize * channels as usize;
+                    packet_len = ((packet_head & TGA_FLAG_PACKET_LEN) + 1) as usize * channels_len;
+
+                    // The repeated pixel is only stored once, even if the run spans several rows
+                    if is_rle {
+                        reader.read_exact(&mut pixel[..channels_len])?;
+                    }
                 }
 
-                let gotten = linebuf_size as usize - wanted;
+                let gotten = linebuf_size - wanted;
                 let copy_size = wanted.min(packet_len);
                 if is_rle {
-                    let channels = channels as usize;
-                    reader.read_exact(&mut pixel[..channels])?;
-
-                    let mut p = gotten;
-                    while p < gotten + copy_size {
-                        let mut place = &mut linebuf[p..p + channels];
-                        place.write_all(&pixel[..channels])?;
-                        p += channels;
+                    for place in linebuf[gotten..gotten + copy_size].chunks_exact_mut(channels_len)
+                    {
+                        place.copy_from_slice(&pixel[..channels_len]);
                     }
                 } else {
                     // raw packet
@@ -298,8 +297,8 @@ pub fn read_tga<R: Read + Seek>(reader: &mut R) -> Result<TgaImage, TgaDecodeErr
                 packet_len -= copy_size;
             }
 
-            to_rgba(channels, &linebuf, &mut data[ti..ti + tline_size as usize])?;
-            ti = ti.saturating_add_signed(tstride as isize);
+            let ti = row_start(y);
+            to_rgba(channels, &linebuf, &mut data[ti..ti + tline_size])?;
         }
     }
 
@@ -322,8 +321,7 @@ fn to_rgba(channels: TgaChannels, src: &[u8], tgt: &mut [u8]) -> io::Result<()>
 fn y_to_rgba(src: &[u8], tgt: &mut [u8]) -> io::Result<()> {
     for i in 0..src.len() {
         let (k, t) = (i, i * 4);
-        let mut tgt = &mut tgt[t..t + 3];
-        tgt.write_all(&[src[k]; 3])?;
+        (&mut tgt[t..t + 3]).write_all(&[src[k]; 3])?;
         tgt[t + 3] = 255;
     }
 
@@ -333,8 +331,7 @@ fn y_to_rgba(src: &[u8], tgt: &mut [u8]) -> io::Result<()> {
 fn ya_to_rgba(src: &[u8], tgt: &mut [u8]) -> io::Result<()> {
     for i in 0..src.len() / 2 {
         let (k, t) = (i * 2, i * 4);
-        let mut tgt = &mut tgt[t..t + 3];
-        tgt.write_all(&[src[k]; 3])?;
+        (&mut tgt[t..t + 3]).write_all(&[src[k]; 3])?;
         tgt[t + 3] = src[k + 1];
     }
 
@@ -364,3 +361,43 @@ fn bgra_to_rgba(src: &[u8], tgt: &mut [u8]) -> io::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(data_type: DataType, width: u16, height: u16, bits_pp: u8, flags: u8) -> Vec<u8> {
+        let mut header = vec![0, 0, data_type as u8, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        header.extend_from_slice(&width.to_le_bytes());
+        header.extend_from_slice(&height.to_le_bytes());
+        header.extend_from_slice(&[bits_pp, flags]);
+        header
+    }
+
+    #[test]
+    fn test_rle_run_spanning_rows() {
+        // 3x2 bottom-to-top BGR image: a run of 4 red pixels across both rows, then 2 raw pixels
+        let mut file = header(DataType::TruecolorRle, 3, 2, 24, 0);
+        file.extend_from_slice(&[0x83, 0, 0, 255]);
+        file.extend_from_slice(&[0x01, 255, 0, 0, 0, 255, 0]);
+
+        let image = read_tga(&mut io::Cursor::new(&file)).unwrap();
+        let red = [255, 0, 0, 255];
+        let blue = [0, 0, 255, 255];
+        let green = [0, 255, 0, 255];
+        // the first row of the file is the bottom one
+        let expected = [red, blue, green, red, red, red].concat();
+        assert_eq!(image.data, expected);
+    }
+
+    #[test]
+    fn test_truncated_rle_is_an_error() {
+        let mut file = header(DataType::GrayRle, 4, 4, 8, TGA_FLAG_ORIGIN_AT_TOP);
+        file.extend_from_slice(&[0x85, 7, 0x03, 1, 2]);
+
+        assert!(matches!(
+            read_tga(&mut io::Cursor::new(&file)),
+            Err(TgaDecodeError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,73 @@
+//! Fixture puppets shared by the integration tests.
+//!
+//! Each fixture is a puppet in `tests/fixtures/<name>.json`, whose textures are generated here
+//! so that the fixtures stay readable.
+
+// Each test crate only uses some of these
+#![allow(dead_code)]
+
+use std::fs;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+use image::{ImageFormat, ImageOutputFormat, Rgba, RgbaImage};
+use inox2d::model::{Model, ModelTexture};
+
+pub fn fixture_path(name: &str, extension: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+        .with_extension(extension)
+}
+
+/// Loads a fixture puppet along with its textures.
+pub fn load_fixture(name: &str) -> Model {
+    load_fixture_with(name, |json| json)
+}
+
+/// Loads a fixture puppet whose JSON is first changed by `edit`, along with its textures.
+pub fn load_fixture_with(name: &str, edit: impl FnOnce(String) -> String) -> Model {
+    let json = edit(fs::read_to_string(fixture_path(name, "json")).unwrap());
+    let mut model = Model::from_bytes(json.as_bytes()).unwrap();
+    model.textures = (fixture_textures(name).iter())
+        .map(|image| {
+            let mut data = Cursor::new(Vec::new());
+            image.write_to(&mut data, ImageOutputFormat::Png).unwrap();
+            ModelTexture {
+                format: ImageFormat::Png,
+                data: data.into_inner(),
+            }
+        })
+        .collect();
+    model
+}
+
+/// Textures of a fixture puppet, in the order its parts refer to them.
+fn fixture_textures(name: &str) -> Vec<RgbaImage> {
+    match name {
+        // Gradients with a grid of translucent texels, to see how colors and alpha are blended
+        "blend_modes" => {
+            let gradient = RgbaImage::from_fn(16, 16, |x, y| {
+                let alpha = if (x + y) % 5 == 0 { 100 } else { 255 };
+                Rgba([128, (y * 17) as u8, (x * 17) as u8, alpha])
+            });
+            vec![gradient; 3]
+        }
+        // A red disc fading out towards its edge, and an opaque blue background
+        "composited_edge" => {
+            let disc = RgbaImage::from_fn(16, 16, |x, y| {
+                let distance = (x as f32 - 7.5).hypot(y as f32 - 7.5);
+                let alpha = (1.0 - distance / 8.0).clamp(0.0, 1.0);
+                Rgba([255, 0, 0, (alpha * 255.0).round() as u8])
+            });
+            vec![disc, RgbaImage::from_pixel(4, 4, Rgba([0, 0, 255, 255]))]
+        }
+        // A gray albedo shared by all parts, and a white and a black emissive map
+        "emissive" => [[128, 128, 128, 255], [255, 255, 255, 255], [0, 0, 0, 255]]
+            .map(|color| RgbaImage::from_pixel(4, 4, Rgba(color)))
+            .to_vec(),
+        _ => [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255]]
+            .map(|color| RgbaImage::from_pixel(4, 4, Rgba(color)))
+            .to_vec(),
+    }
+}
@@ -0,0 +1,106 @@
+//! Renders the puppets in `tests/fixtures` with the CPU renderer, and compares them with
+//! the reference images next to them, or with other renders of the same puppets.
+//!
+//! After checking that a change renders correctly, run with `INOX2D_BLESS=1` to write
+//! the new reference images. Differing renders are written next to them as `*.actual.png`
+//! and `*.diff.png`.
+
+mod common;
+
+use glam::{uvec2, vec2};
+use image::RgbaImage;
+use inox2d::model::Model;
+use inox2d::render::cpu::CpuRenderer;
+use inox2d::render::Renderer;
+use inox2d::testing::compare_images;
+
+use common::{fixture_path, load_fixture, load_fixture_with};
+
+/// Difference allowed per channel, for floating point differences between platforms.
+const TOLERANCE: u8 = 2;
+
+/// Renders a fixture puppet in its rest pose, framed by the camera.
+fn render_fixture(name: &str) -> RgbaImage {
+    render_model(load_fixture(name))
+}
+
+fn render_model(mut model: Model) -> RgbaImage {
+    let mut renderer = CpuRenderer::from_model(&model, uvec2(150, 60));
+    renderer.frame_puppet(&model.puppet, 0.1);
+
+    model.puppet.begin_set_params();
+    model.puppet.end_set_params();
+    renderer.draw(&model.puppet, ())
+}
+
+fn assert_renders_as_expected(name: &str) {
+    let actual = render_fixture(name);
+    let expected_path = fixture_path(name, "png");
+    if std::env::var_os("INOX2D_BLESS").is_some() {
+        actual.save(&expected_path).unwrap();
+        return;
+    }
+
+    let expected = image::open(&expected_path).unwrap().into_rgba8();
+    let diff = compare_images(&actual, &expected, TOLERANCE);
+    if !diff.is_match() {
+        actual.save(fixture_path(name, "actual.png")).unwrap();
+        diff.diff.save(fixture_path(name, "diff.png")).unwrap();
+    }
+    assert!(
+        diff.is_match(),
+        "{name}: {} pixels differ, by up to {} (mean {:.3})",
+        diff.differing_pixels,
+        diff.max_delta,
+        diff.mean_delta
+    );
+}
+
+#[test]
+fn test_blend_modes() {
+    assert_renders_as_expected("blend_modes");
+}
+
+#[test]
+fn test_masks() {
+    assert_renders_as_expected("masks");
+}
+
+#[test]
+fn test_nested_composites() {
+    assert_renders_as_expected("nested_composites");
+}
+
+#[test]
+fn test_composited_edges() {
+    assert_renders_as_expected("composited_edge");
+
+    // Semi-transparent edges look the same drawn through a composite and drawn directly,
+    // as a plain node draws its children
+    let direct = render_model(load_fixture_with("composited_edge", |json| {
+        json.replace(r#""type": "Composite""#, r#""type": "Node""#)
+    }));
+    let diff = compare_images(&render_fixture("composited_edge"), &direct, TOLERANCE);
+    assert!(diff.is_match(), "{} pixels differ", diff.differing_pixels);
+}
+
+#[test]
+fn test_baked_pose_renders_like_the_posed_puppet() {
+    let mut model = load_fixture("posed");
+    let mut renderer = CpuRenderer::from_model(&model, uvec2(150, 60));
+    renderer.frame_puppet(&model.puppet, 0.1);
+
+    // The pose moves, reorders, fades and deforms parts inside and outside a composite
+    model.puppet.begin_set_params();
+    model.puppet.set_param("Smile", vec2(0.75, 0.5));
+    model.puppet.end_set_params();
+    let posed = renderer.render(&model.puppet);
+    assert!(!compare_images(&posed, &render_fixture("posed"), TOLERANCE).is_match());
+
+    let mut baked = model.puppet.bake_pose();
+    assert!(baked.parameters.is_empty());
+    baked.begin_set_params();
+    baked.end_set_params();
+    let diff = compare_images(&renderer.render(&baked), &posed, TOLERANCE);
+    assert!(diff.is_match(), "{} pixels differ", diff.differing_pixels);
+}
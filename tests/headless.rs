@@ -0,0 +1,186 @@
+//! Renders fixture puppets with headless OpenGL, to test what depends on the GL renderer's state.
+//!
+//! The tests are skipped when no headless GL context can be created, e.g. on machines without
+//! EGL. Mesa's software renderer is enough to run them.
+
+mod common;
+
+use glam::{uvec2, UVec2};
+use image::{imageops, RgbaImage};
+use inox2d::model::Model;
+use inox2d::nodes::node_data::{BlendMode, InoxData, Part};
+use inox2d::puppet::Puppet;
+use inox2d::render::opengl::headless::{HeadlessError, HeadlessRenderer};
+use inox2d::render::opengl::{BloomParams, RendererConfig};
+use inox2d::render::Renderer;
+use inox2d::testing::compare_images;
+
+use common::{fixture_path, load_fixture};
+
+const SIZE: UVec2 = uvec2(150, 60);
+
+/// Creates a renderer framing the model's puppet in its rest pose,
+/// or `None` if there is no way to render headless here.
+fn renderer(model: &mut Model, config: RendererConfig) -> Option<HeadlessRenderer> {
+    let mut renderer = match HeadlessRenderer::from_model(model, SIZE, config) {
+        Ok(renderer) => renderer,
+        Err(e @ (HeadlessError::Context(_) | HeadlessError::NoDevice)) => {
+            eprintln!("Skipping test: {e}");
+            return None;
+        }
+        Err(e) => panic!("{e}"),
+    };
+    renderer.renderer.frame_puppet(&model.puppet, 0.1);
+
+    model.puppet.begin_set_params();
+    model.puppet.end_set_params();
+    Some(renderer)
+}
+
+fn part_mut<'a>(puppet: &'a mut Puppet, name: &str) -> &'a mut Part {
+    let uuid = puppet.nodes.find_by_name(name).unwrap();
+    match puppet.nodes.get_node_mut(uuid).unwrap().data {
+        InoxData::Part(ref mut part) => part,
+        _ => panic!("{name} is not a part"),
+    }
+}
+
+/// Renders the puppet with composite caching, checking that it gives the same image as without.
+fn render_cached(renderer: &mut HeadlessRenderer, puppet: &Puppet) -> RgbaImage {
+    renderer.renderer.set_composite_caching(false);
+    let uncached = renderer.render(puppet);
+    renderer.renderer.set_composite_caching(true);
+    let cached = renderer.render(puppet);
+    assert!(compare_images(&cached, &uncached, 0).is_match());
+    cached
+}
+
+#[test]
+fn test_cached_composites_follow_blend_modes() {
+    let mut model = load_fixture("composite_blend_modes");
+    let Some(mut renderer) = renderer(&mut model, RendererConfig::default()) else {
+        return;
+    };
+    renderer.renderer.set_composite_caching(true);
+    let before = renderer.render(&model.puppet);
+
+    part_mut(&mut model.puppet, "Part1").draw_state.blend_mode = BlendMode::Multiply;
+    let after = renderer.render(&model.puppet);
+
+    assert!(!compare_images(&before, &after, 0).is_match());
+    assert!(compare_images(&after, &render_cached(&mut renderer, &model.puppet), 0).is_match());
+}
+
+#[test]
+fn test_cached_composites_follow_vertex_updates() {
+    let mut model = load_fixture("composite_blend_modes");
+    let Some(mut renderer) = renderer(&mut model, RendererConfig::default()) else {
+        return;
+    };
+    renderer.renderer.set_composite_caching(true);
+    let before = renderer.render(&model.puppet);
+
+    let uuid = model.puppet.nodes.find_by_name("Part0").unwrap();
+    let verts = part_mut(&mut model.puppet, "Part0").mesh.vertices.clone();
+    let verts = verts.iter().map(|vert| *vert * 0.5).collect::<Vec<_>>();
+    (renderer.renderer)
+        .update_part_vertices(&mut model.puppet, uuid, &verts)
+        .unwrap();
+    let after = renderer.render(&model.puppet);
+
+    assert!(!compare_images(&before, &after, 0).is_match());
+    assert!(compare_images(&after, &render_cached(&mut renderer, &model.puppet), 0).is_match());
+}
+
+#[test]
+fn test_reloaded_textures_are_flipped() {
+    let mut model = load_fixture("blend_modes");
+    let config = RendererConfig {
+        flip_textures_v: true,
+        ..RendererConfig::default()
+    };
+    let Some(mut renderer) = renderer(&mut model, config) else {
+        return;
+    };
+    let flipped = renderer.render(&model.puppet);
+
+    let texture = &model.textures[0];
+    (renderer.renderer)
+        .reload_texture(0, &texture.data, texture.format)
+        .unwrap();
+    assert!(compare_images(&renderer.render(&model.puppet), &flipped, 0).is_match());
+}
+
+#[test]
+fn test_composited_edges_match_reference() {
+    let mut model = load_fixture("composited_edge");
+    let Some(mut renderer) = renderer(&mut model, RendererConfig::default()) else {
+        return;
+    };
+
+    // The reference is drawn by the CPU renderer, see `golden.rs`
+    let expected = image::open(fixture_path("composited_edge", "png")).unwrap();
+    let diff = compare_images(&renderer.render(&model.puppet), &expected.into_rgba8(), 2);
+    assert!(diff.is_match(), "{} pixels differ", diff.differing_pixels);
+}
+
+#[test]
+fn test_removed_mask_sources_no_longer_mask() {
+    let mut model = load_fixture("masks");
+    let Some(mut renderer) = renderer(&mut model, RendererConfig::default()) else {
+        return;
+    };
+    let mask = model.puppet.nodes.find_by_name("Part0").unwrap();
+
+    // Hiding the mask's source and dropping the masks of the parts drawn with it
+    // should look the same as removing the source
+    model.puppet.set_node_enabled(mask, false);
+    for name in ["Part1", "Part2"] {
+        part_mut(&mut model.puppet, name).draw_state.masks.clear();
+    }
+    model.puppet.begin_set_params();
+    model.puppet.end_set_params();
+    let expected = renderer.render(&model.puppet);
+
+    let mut model = load_fixture("masks");
+    assert!(model.puppet.remove_node(mask));
+    model.puppet.begin_set_params();
+    model.puppet.end_set_params();
+    let image = renderer.render(&model.puppet);
+    assert!(compare_images(&image, &expected, 0).is_match());
+}
+
+#[test]
+fn test_bloom_only_makes_emissive_parts_glow() {
+    // With a texture array, each map is read from its own layer
+    for texture_array in [false, true] {
+        let mut model = load_fixture("emissive");
+        let config = RendererConfig {
+            texture_array,
+            ..RendererConfig::default()
+        };
+        let Some(mut renderer) = renderer(&mut model, config) else {
+            return;
+        };
+        let unlit = renderer.render(&model.puppet);
+        renderer
+            .renderer
+            .set_bloom(Some(BloomParams::default()))
+            .unwrap();
+        let lit = renderer.render(&model.puppet);
+
+        // The parts with a white, a black and no emissive map each fill a third of the image
+        let third = |image: &RgbaImage, i: u32| {
+            imageops::crop_imm(image, i * SIZE.x / 3, 0, SIZE.x / 3, SIZE.y).to_image()
+        };
+        assert!(!compare_images(&third(&lit, 0), &third(&unlit, 0), 2).is_match());
+        for i in [1, 2] {
+            let diff = compare_images(&third(&lit, i), &third(&unlit, i), 2);
+            assert!(
+                diff.is_match(),
+                "{} pixels differ with texture_array: {texture_array}",
+                diff.differing_pixels
+            );
+        }
+    }
+}